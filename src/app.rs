@@ -1,35 +1,98 @@
-use std::collections::LinkedList;
+use std::collections::{BTreeMap, LinkedList};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
 use std::time::{Duration, Instant};
 
+use ratatui::text::Line;
+
+use crate::config::{Config, Profile, TAB_WIDTHS};
 use crate::error::AppError;
 use crate::events::{AppEvent, Events};
-use crate::git::DiffAction;
-use crate::list::{ListCursor, ListScroll};
-use crate::search::Search;
-use crate::ui::Ui;
+use crate::git::{
+    git_amend, git_anchor, git_cherry_pick, git_commit, git_commit_hooks,
+    git_commit_message, git_conflicts, git_dir, git_discard, git_log,
+    git_operation_step, git_revert, git_root, git_sign_off, git_version,
+    git_worktree_changes, inherit_runner, missing_capabilities, pathspecs,
+    runner_failure, set_pathspecs, DiffAction, GitDiffOpts, HookedOutput,
+    LogFilter, Target, LOG_PAGE_SIZE,
+};
+use crate::graphics::ImageProtocol;
+use crate::script::{Script, Step, DEFAULT_SIZE};
+use crate::session::RecordedEvent;
+use crate::theme::{ColorSupport, Theme};
+use crate::ui::{render_text, Ui};
 use crate::{
     events::Key,
     stack::Stack,
     views::{
+        about::About,
         commitlog::CommitLog,
+        confirm::{describe_commits, Confirm, ConfirmAction},
+        controller::{
+            KeyResult, ViewContext, ViewController, DEFAULT_PANE_SPLIT,
+            FLASH_DURATION, MAX_PANE_SPLIT, MIN_PANE_SPLIT,
+        },
         diff::Diff,
-        stats::Stats,
+        grep::Grep,
+        input::{Input, InputAction, InputEvent},
+        popup::Popup,
+        rangediff::RangeDiff,
         statusline::{Status, StatusLine},
     },
 };
 
-pub enum View {
-    CommitLog(CommitLog),
-    Stats(Stats),
-    Diff(Diff),
-}
+/// How long a view's key hints are shown after it's opened
+const HINT_DURATION: Duration = Duration::from_secs(4);
 
 pub struct App {
-    pub views: LinkedList<View>,
+    pub views: LinkedList<Box<dyn ViewController>>,
     pub statusline: StatusLine,
     pub tab_width: u8,
     pub search: Option<String>,
+    pub confirm: Option<Confirm>,
+    pub popup: Option<Popup>,
+    pub input: Option<Input>,
+    pub message: Option<String>,
+    /// text waiting to be copied to the system clipboard
+    pub clipboard: Option<String>,
+    /// a file waiting to be opened in the user's editor, and the line to
+    /// start at
+    edit: Option<(PathBuf, u32)>,
+    /// the keys of a newly opened view, and when they were first shown
+    hint: Option<(String, Instant)>,
+    /// show key hints when views are opened
+    key_hints: bool,
     typing_search: bool,
+    /// a check for staged and unstaged changes is running
+    checking_worktree: bool,
+    /// the repo changed while the worktree was being checked
+    worktree_stale: bool,
+    /// the number of log pages being loaded in the background
+    loading_logs: usize,
+    /// diff options for new views
+    diff_opts: GitDiffOpts,
+    /// the percentage of split layouts given to the first pane
+    pub pane_split: u16,
+    /// the protocol for previewing images, if they should be previewed
+    pub images: Option<ImageProtocol>,
+    /// draw titled borders around views
+    pub borders: bool,
+    /// the built-in and installed color themes
+    pub themes: Vec<Theme>,
+    /// the theme being used, which may be previewed before it's saved
+    pub theme: usize,
+    /// the theme in the config file, to go back to if a preview is rejected
+    saved_theme: usize,
+    /// the colors the terminal can show
+    pub color_support: ColorSupport,
+    /// the named sets of settings from the config file
+    profiles: BTreeMap<String, Profile>,
+    /// the profile last switched to
+    profile: Option<String>,
     events: Events,
     should_quit: bool,
     pending_keys: Vec<Key>,
@@ -38,24 +101,79 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Result<Self, AppError> {
+    pub fn new(config: &Config) -> Result<Self, AppError> {
         let mut views = LinkedList::new();
-        let commits = CommitLog::new();
+        let scoped = config.ui.subdirectory_scope;
+        let commits = CommitLog::new(scoped);
         let status = commits.status();
-        views.push(View::CommitLog(commits));
+        views.push(Box::new(commits) as Box<dyn ViewController>);
 
-        Ok(Self {
+        let missing = missing_capabilities();
+        let message = if missing.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "Disabled (git {} is too old): {}",
+                git_version().map(|v| v.to_string()).unwrap_or_default(),
+                missing
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        };
+
+        let mut app = Self {
             views,
             should_quit: false,
             statusline: StatusLine::new(status, None),
-            tab_width: 4,
+            tab_width: config.ui.tab_width.max(1),
             pending_keys: vec![],
             pending_key_timeout: Duration::from_millis(500),
             last_key_time: Instant::now(),
             events: Events::new()?,
             search: None,
+            confirm: None,
+            popup: None,
+            input: None,
+            message,
             typing_search: false,
-        })
+            checking_worktree: false,
+            worktree_stale: false,
+            loading_logs: 0,
+            diff_opts: GitDiffOpts {
+                algorithm: config.diff.algorithm,
+                scoped,
+                submodules: config.diff.submodules,
+                ..GitDiffOpts::default()
+            },
+            pane_split: DEFAULT_PANE_SPLIT,
+            images: if config.diff.image_preview {
+                ImageProtocol::detect()
+            } else {
+                None
+            },
+            borders: config.ui.borders,
+            themes: vec![],
+            theme: 0,
+            saved_theme: 0,
+            color_support: config.ui.colors.resolve(),
+            profiles: config.profiles.clone(),
+            profile: None,
+            clipboard: None,
+            edit: None,
+            hint: None,
+            key_hints: config.ui.key_hints,
+        };
+        app.themes = Theme::load_all(&Config::themes_dir());
+        app.theme = app
+            .themes
+            .iter()
+            .position(|t| t.name == config.ui.theme)
+            .unwrap_or(0);
+        app.saved_theme = app.theme;
+        app.show_key_hints();
+        Ok(app)
     }
 
     pub fn quit(&mut self) {
@@ -86,16 +204,64 @@ impl App {
             return;
         }
 
+        self.message = None;
+
+        // any key dismisses a popup
+        if self.popup.take().is_some() {
+            return;
+        }
+
+        if let Some(mut input) = self.input.take() {
+            if matches!(key, Key::Ctrl('s'))
+                && matches!(
+                    input.action,
+                    InputAction::Commit(_) | InputAction::Amend(_)
+                )
+            {
+                match git_sign_off(&input.text) {
+                    Ok(text) => input.text = text,
+                    Err(e) => {
+                        self.message =
+                            Some(format!("Can't sign off: {}", e.first_line()))
+                    }
+                }
+                self.input = Some(input);
+                return;
+            }
+
+            match input.handle_key(&key) {
+                InputEvent::Editing => self.input = Some(input),
+                InputEvent::Submit => self.submit_input(input),
+                InputEvent::Cancel => {}
+            }
+            return;
+        }
+
+        if let Some(confirm) = self.confirm.take() {
+            match key {
+                Key::Char('y') | Key::Enter => self.run_action(confirm.action),
+                Key::Char('T')
+                    if matches!(
+                        confirm.action,
+                        ConfirmAction::SaveTheme(_)
+                    ) =>
+                {
+                    self.preview_next_theme()
+                }
+                Key::Char('n') | Key::Char('q') | Key::Escape => {
+                    // a rejected theme stops being previewed
+                    self.theme = self.saved_theme;
+                }
+                _ => self.confirm = Some(confirm),
+            }
+            return;
+        }
+
         if self.typing_search {
             match key {
                 Key::Enter => {
                     self.typing_search = false;
-                    match self.views.top_mut() {
-                        Some(View::CommitLog(v)) => v.search_next(),
-                        Some(View::Stats(v)) => v.search_next(),
-                        Some(View::Diff(v)) => v.search_next(),
-                        _ => {}
-                    }
+                    self.search_next();
                 }
 
                 Key::Char(c) => {
@@ -117,212 +283,774 @@ impl App {
                     self.typing_search = false;
                 }
 
-                Key::Ctrl('n') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.search_next(),
-                    Some(View::Stats(v)) => v.search_next(),
-                    Some(View::Diff(v)) => v.search_next(),
-                    _ => {}
-                },
-
-                Key::Ctrl('p') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.search_prev(),
-                    Some(View::Stats(v)) => v.search_prev(),
-                    Some(View::Diff(v)) => v.search_prev(),
-                    _ => {}
-                },
+                Key::Ctrl('n') => self.search_next(),
+                Key::Ctrl('p') => self.search_prev(),
                 _ => {}
             }
-        } else if !self.pending_keys.is_empty() {
-            let last_key = self.pending_keys.last().unwrap();
-            match key {
-                Key::Char('G') => match last_key {
-                    Key::Char('1') => match self.views.top_mut() {
-                        Some(View::CommitLog(v)) => {
-                            v.cursor_to_top();
-                            self.pending_keys.clear();
-                        }
+            return;
+        }
 
-                        Some(View::Diff(v)) => {
-                            v.scroll_top();
-                            self.pending_keys.clear();
-                        }
+        // searching takes precedence over the view's own use of n and N
+        let takes_text = self.views.top().is_some_and(|v| v.takes_text());
+        if self.pending_keys.is_empty() && self.search.is_some() && !takes_text
+        {
+            if let Key::Char(c @ ('n' | 'N')) = key {
+                match c {
+                    'n' => self.search_next(),
+                    _ => self.search_prev(),
+                }
+                return;
+            }
+        }
 
-                        Some(View::Stats(v)) => {
-                            v.cursor_to_top();
-                            self.pending_keys.clear();
-                        }
+        self.pending_keys.push(key);
+        let mut ctx = ViewContext::new(self.diff_opts, self.pane_split);
+        let Some(view) = self.views.top_mut() else {
+            return;
+        };
+        let watched = view.watched_path();
+        let result = view.handle_key(&self.pending_keys, &mut ctx);
 
-                        _ => self.pending_keys.clear(),
-                    },
+        match result {
+            KeyResult::Pending => {}
+            KeyResult::Handled => {
+                self.pending_keys.clear();
 
-                    _ => self.pending_keys.clear(),
-                },
+                // the view may have switched to showing a different file
+                let now_watched = view.watched_path();
+                if now_watched != watched {
+                    self.unwatch(watched);
+                    self.watch(now_watched);
+                }
 
-                _ => self.pending_keys.clear(),
+                self.apply(ctx);
             }
-        } else {
-            match key {
-                Key::Escape => self.search = None,
-                Key::Char('1') => self.pending_keys.push(key),
+            KeyResult::Ignored => {
+                // an unfinished sequence is dropped along with the key that
+                // ended it
+                let mut keys = std::mem::take(&mut self.pending_keys);
+                if keys.len() == 1 {
+                    self.handle_key(keys.remove(0));
+                }
+            }
+        }
+    }
 
-                Key::Char('l') => {
-                    if let Some(View::CommitLog(v)) = self.views.top_mut() {
-                        v.toggle_show_details();
-                    }
+    /// Handle a key that the current view didn't use
+    fn handle_key(&mut self, key: Key) {
+        match key {
+            Key::Escape => self.search = None,
+
+            Key::Char('?') => {
+                let about = About::new(self.events.watched());
+                self.views.push(Box::new(about));
+            }
+
+            Key::Char('q') => {
+                if self.views.len() > 1 {
+                    self.pop_view();
+                } else {
+                    self.quit();
                 }
+            }
+
+            Key::Char('/') => {
+                self.search = Some("".into());
+                self.typing_search = true;
+            }
 
-                Key::Char('q') => match self.views.top() {
-                    Some(View::CommitLog(_v)) => self.quit(),
-                    Some(View::Stats(_v)) => {
-                        self.views.pop();
+            Key::Char(c @ ('<' | '>')) => self.cycle_tab_width(c == '>'),
+
+            Key::Char('T') => self.preview_next_theme(),
+
+            Key::Ctrl('o') if self.profiles.is_empty() => {
+                self.message =
+                    Some("There are no profiles in the config file".into());
+            }
+
+            Key::Ctrl('o') => {
+                let names = self
+                    .profiles
+                    .keys()
+                    .map(|name| match &self.profile {
+                        Some(p) if p == name => format!("{} (in use)", name),
+                        _ => name.clone(),
+                    })
+                    .map(Line::from)
+                    .collect();
+                self.input = Some(
+                    Input::new("Profile", InputAction::Profile)
+                        .with_details(names),
+                );
+            }
+
+            Key::Char('E') => {
+                self.input = Some(
+                    Input::new("Limit to paths", InputAction::Pathspecs)
+                        .with_text(pathspecs().join(" ")),
+                );
+            }
+
+            _ => tracing::debug!("Unhandled: {}", key),
+        }
+    }
+
+    /// Switch to the next or previous of the usual tab widths, and save it in
+    /// the config file
+    fn cycle_tab_width(&mut self, forward: bool) {
+        let width = if forward {
+            TAB_WIDTHS.iter().find(|w| **w > self.tab_width)
+        } else {
+            TAB_WIDTHS.iter().rev().find(|w| **w < self.tab_width)
+        };
+        self.tab_width = match width {
+            Some(width) => *width,
+            None if forward => TAB_WIDTHS[0],
+            None => TAB_WIDTHS[TAB_WIDTHS.len() - 1],
+        };
+
+        let width = self.tab_width;
+        self.message = Some(match Config::update(|c| c.ui.tab_width = width) {
+            Ok(()) => format!("Tab width: {}", width),
+            Err(e) => {
+                format!("Tab width: {} (not saved: {})", width, e.first_line())
+            }
+        });
+    }
+
+    /// Switch to the settings of a profile from the config file, returning a
+    /// description of the switch
+    pub fn use_profile(&mut self, name: &str) -> Result<String, String> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let names: Vec<&str> =
+                self.profiles.keys().map(String::as_str).collect();
+            return Err(if names.is_empty() {
+                format!("There's no {} profile in the config file", name)
+            } else {
+                format!(
+                    "There's no {} profile (try {})",
+                    name,
+                    names.join(", ")
+                )
+            });
+        };
+        if let Some(theme) = &profile.theme {
+            self.theme = self
+                .themes
+                .iter()
+                .position(|t| &t.name == theme)
+                .ok_or_else(|| format!("There's no {} theme", theme))?;
+            self.saved_theme = self.theme;
+        }
+
+        if let Some(borders) = profile.borders {
+            self.borders = borders;
+        }
+        if let Some(key_hints) = profile.key_hints {
+            self.key_hints = key_hints;
+        }
+        if let Some(width) = profile.tab_width {
+            self.tab_width = width.max(1);
+        }
+        if let Some(split) = profile.pane_split {
+            self.pane_split = split.clamp(MIN_PANE_SPLIT, MAX_PANE_SPLIT);
+        }
+        self.diff_opts = profile.diff_opts(self.diff_opts);
+        self.broadcast(&AppEvent::UseProfile(profile));
+        self.profile = Some(name.into());
+        Ok(format!("Using the {} profile", name))
+    }
+
+    /// Draw with the next color theme, and ask whether to keep it
+    fn preview_next_theme(&mut self) {
+        self.theme = (self.theme + 1) % self.themes.len();
+        let name = self.themes[self.theme].name.clone();
+        self.confirm = Some(Confirm::new(
+            format!("Keep the {} theme? (T shows the next one)", name),
+            ConfirmAction::SaveTheme(name),
+        ));
+    }
+
+    /// Carry out the requests a view made while handling a key
+    fn apply(&mut self, ctx: ViewContext) {
+        self.diff_opts = ctx.diff_opts;
+        self.pane_split = ctx.pane_split;
+
+        if ctx.message.is_some() {
+            self.message = ctx.message;
+        }
+        if ctx.clipboard.is_some() {
+            self.clipboard = ctx.clipboard;
+        }
+        if ctx.edit.is_some() {
+            self.edit = ctx.edit;
+        }
+        if let Some(url) = ctx.open_url {
+            self.message = Some(match open_browser(&url) {
+                Ok(()) => format!("Opened {}", url),
+                Err(e) => e.first_line(),
+            });
+        }
+        if ctx.confirm.is_some() {
+            self.confirm = ctx.confirm;
+        }
+        if ctx.input.is_some() {
+            self.input = ctx.input;
+        }
+        if ctx.popup.is_some() {
+            self.popup = ctx.popup;
+        }
+
+        for event in ctx.events {
+            match event {
+                AppEvent::RepoChanged => self.check_worktree(),
+                event => self.broadcast(&event),
+            }
+        }
+
+        if let Some((filter, skip)) = ctx.load_log {
+            self.load_log(filter, skip);
+        }
+
+        if ctx.close {
+            self.pop_view();
+        }
+        if let Some(rev) = ctx.goto_commit {
+            self.goto_commit(&rev);
+        }
+
+        if let Some(view) = ctx.push {
+            self.push_view(view);
+        }
+    }
+
+    /// Send an event to every view in the stack
+    fn broadcast(&mut self, event: &AppEvent) {
+        for view in self.views.iter_mut() {
+            view.on_event(event);
+        }
+    }
+
+    /// Move the top view to the commit a revision names
+    fn goto_commit(&mut self, rev: &str) {
+        let Some(view) = self.views.top_mut() else {
+            return;
+        };
+        match view.goto_commit(rev) {
+            Ok(description) => {
+                self.message = Some(description);
+                // redraw once the commit's highlight has faded
+                self.tick_after(FLASH_DURATION);
+            }
+            Err(e) => self.message = Some(e),
+        }
+    }
+
+    fn push_view(&mut self, view: Box<dyn ViewController>) {
+        self.watch(view.watched_path());
+        self.views.push(view);
+        self.show_key_hints();
+    }
+
+    /// Show the current view's key hints for a few seconds
+    fn show_key_hints(&mut self) {
+        let hints = match self.views.top() {
+            Some(view) if self.key_hints => view.key_hints(),
+            _ => return,
+        };
+        if hints.is_empty() {
+            self.hint = None;
+            return;
+        }
+
+        let text = hints
+            .iter()
+            .map(|(key, action)| format!("{}={}", key, action))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.hint = Some((text, Instant::now()));
+        self.tick_after(HINT_DURATION);
+    }
+
+    /// Wake the app up after a while, so anything that has expired by then
+    /// is redrawn
+    fn tick_after(&self, duration: Duration) {
+        let tx = self.events.sender();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let _ = tx.send(AppEvent::Tick);
+        });
+    }
+
+    /// Return the key hints that should currently be shown
+    pub fn key_hint(&self) -> Option<&str> {
+        match &self.hint {
+            Some((text, shown)) if shown.elapsed() < HINT_DURATION => {
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    fn pop_view(&mut self) {
+        if let Some(view) = self.views.pop() {
+            self.unwatch(view.watched_path());
+        }
+        self.hint = None;
+    }
+
+    fn watch(&mut self, path: Option<PathBuf>) {
+        if let Some(p) = path {
+            if let Err(e) = self.events.watch_file(&p) {
+                tracing::warn!("Error watching {:?}: {}", p, e)
+            } else {
+                tracing::info!("Watching {:?}", p)
+            }
+        }
+    }
+
+    fn unwatch(&mut self, path: Option<PathBuf>) {
+        if let Some(p) = path {
+            if let Err(e) = self.events.unwatch_file(&p) {
+                tracing::warn!("Error unwatching {:?}: {}", p, e)
+            }
+        }
+    }
+
+    /// Run an action that the user has confirmed
+    fn run_action(&mut self, action: ConfirmAction) {
+        match action {
+            ConfirmAction::CherryPick(commits) => {
+                self.message = Some(match git_cherry_pick(&commits) {
+                    Ok(_) => {
+                        format!("Cherry-picked {}", describe_commits(&commits))
                     }
-                    Some(View::Diff(v)) => {
-                        if let Ok(p) = v.path() {
-                            if let Err(e) = self.events.unwatch_file(&p) {
-                                tracing::warn!(
-                                    "Error unwatching {:?}: {}",
-                                    v.path(),
-                                    e
-                                )
-                            }
+                    Err(e) => failure_message("Cherry-pick", e),
+                });
+            }
+
+            ConfirmAction::Revert(commit) => {
+                self.message = Some(match git_revert(&commit) {
+                    Ok(_) => format!("Reverted {}", commit.commit_ref),
+                    Err(e) => failure_message("Revert", e),
+                });
+            }
+
+            ConfirmAction::Operation(operation, step) => {
+                let (message, output) =
+                    match git_operation_step(operation, step) {
+                        Ok(output) => {
+                            (format!("{} --{} done", operation, step), output)
                         }
-                        self.views.pop();
-                    }
-                    _ => {}
-                },
+                        Err(e) => (
+                            format!("{} --{} failed", operation, step),
+                            e.to_string(),
+                        ),
+                    };
+                self.message = Some(message);
+                // git's whole report is shown, since it may say what to do
+                // next
+                if !output.is_empty() {
+                    self.popup = Some(Popup::new(
+                        format!("git {} --{}", operation, step),
+                        output_lines(&output),
+                    ));
+                }
+                self.check_worktree();
+            }
+
+            ConfirmAction::Discard(path) => {
+                self.message = Some(match git_discard(&path) {
+                    Ok(()) => format!(
+                        "Discarded changes to {} (undo with `git stash pop`)",
+                        path
+                    ),
+                    Err(e) => format!("Discard failed: {}", e.first_line()),
+                });
+            }
+
+            ConfirmAction::SaveTheme(name) => {
+                self.saved_theme = self.theme;
+                self.message =
+                    Some(match Config::update(|c| c.ui.theme = name.clone()) {
+                        Ok(()) => format!("Theme: {}", name),
+                        Err(e) => format!(
+                            "Theme: {} (not saved: {})",
+                            name,
+                            e.first_line()
+                        ),
+                    });
+                return;
+            }
+
+            ConfirmAction::CommitNoVerify(message, template) => {
+                let result = git_commit(&message, template, false);
+                self.report_hooked("Commit", result, None);
+            }
+
+            ConfirmAction::AmendNoVerify(message) => {
+                let result = git_amend(message.as_deref(), false);
+                self.report_hooked("Amend", result, None);
+            }
+
+            ConfirmAction::Amend => {
+                let message = git_commit_message("HEAD");
+                self.input = Some(
+                    Input::new(
+                        "Amended commit message",
+                        InputAction::Amend(message.clone()),
+                    )
+                    .with_text(message)
+                    .multiline(),
+                );
+                return;
+            }
+        }
+
+        self.broadcast(&AppEvent::Refresh);
+    }
+
+    /// Move to the top view's next search match, saying so if there isn't one
+    fn search_next(&mut self) {
+        if let Some(s) = self.views.top_mut().and_then(|v| v.search()) {
+            if !s.search_next() {
+                self.message = Some("No more matches".into());
+            }
+        }
+    }
+
+    /// Move to the top view's previous search match, saying so if there isn't
+    /// one
+    fn search_prev(&mut self) {
+        if let Some(s) = self.views.top_mut().and_then(|v| v.search()) {
+            if !s.search_prev() {
+                self.message = Some("No earlier matches".into());
+            }
+        }
+    }
 
-                Key::Char('/') => {
-                    self.search = Some("".into());
-                    self.typing_search = true;
+    /// Report how a commit or amend went, showing what its hooks printed; if
+    /// it failed with hooks installed, the hooks probably stopped it, so
+    /// `retry` is offered to run it again without them
+    fn report_hooked(
+        &mut self,
+        operation: &str,
+        result: Result<HookedOutput, AppError>,
+        retry: Option<ConfirmAction>,
+    ) {
+        match result {
+            Ok(out) => {
+                self.message =
+                    Some(out.summary.lines().next().unwrap_or_default().into());
+                if !out.hooks.is_empty() {
+                    self.popup = Some(Popup::new(
+                        format!("{} hooks", operation),
+                        output_lines(&out.hooks),
+                    ));
                 }
+            }
+            Err(e) => {
+                let hooks = git_commit_hooks();
+                let Some(retry) = retry.filter(|_| !hooks.is_empty()) else {
+                    self.message = Some(format!(
+                        "{} failed: {}",
+                        operation,
+                        e.first_line()
+                    ));
+                    return;
+                };
+                let output = match &e {
+                    AppError::GitFailed(output) => output.clone(),
+                    e => e.to_string(),
+                };
+                self.confirm = Some(
+                    Confirm::new(
+                        format!(
+                            "{} failed with the {} hook{} installed; retry \
+                             with --no-verify?",
+                            operation,
+                            hooks.join(" and "),
+                            if hooks.len() == 1 { "" } else { "s" }
+                        ),
+                        retry,
+                    )
+                    .with_details(output_lines(&output)),
+                );
+            }
+        }
+    }
 
-                Key::Char('G') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_to_bottom(),
-                    Some(View::Diff(v)) => v.scroll_bottom(),
-                    Some(View::Stats(v)) => v.cursor_to_bottom(),
-                    _ => {}
-                },
-
-                Key::Char('n') => {
-                    if self.search.is_some() {
-                        match self.views.top_mut() {
-                            Some(View::CommitLog(v)) => v.search_next(),
-                            Some(View::Diff(v)) => v.search_next(),
-                            Some(View::Stats(v)) => v.search_next(),
-                            _ => {}
-                        }
-                    } else if let Some(View::Diff(v)) = self.views.top_mut() {
-                        v.toggle_show_line_numbers();
-                    }
+    /// Act on the text of a submitted input
+    fn submit_input(&mut self, input: Input) {
+        match input.action {
+            InputAction::Commit(template) => {
+                if input.text.trim().is_empty() {
+                    self.message =
+                        Some("Not committing with an empty message".into());
+                    return;
+                }
+                if template
+                    .as_ref()
+                    .is_some_and(|t| t.trim() == input.text.trim())
+                {
+                    self.message =
+                        Some("Not committing with an unedited template".into());
+                    return;
                 }
 
-                Key::Char('N') => {
-                    if self.search.is_some() {
-                        match self.views.top_mut() {
-                            Some(View::CommitLog(v)) => v.search_prev(),
-                            Some(View::Diff(v)) => v.search_prev(),
-                            Some(View::Stats(v)) => v.search_next(),
-                            _ => {}
-                        }
-                    }
+                let from_template = template.is_some();
+                let result = git_commit(&input.text, from_template, true);
+                self.report_hooked(
+                    "Commit",
+                    result,
+                    Some(ConfirmAction::CommitNoVerify(
+                        input.text,
+                        from_template,
+                    )),
+                );
+                self.broadcast(&AppEvent::Refresh);
+            }
+
+            InputAction::Amend(original) => {
+                if input.text.trim().is_empty() {
+                    self.message =
+                        Some("Not amending with an empty message".into());
+                    return;
                 }
 
-                Key::Char(' ') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_mark(),
-                    Some(View::Diff(v)) => v.page_down(),
-                    _ => {}
-                },
-
-                Key::Up | Key::Char('k') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_up(),
-                    Some(View::Stats(v)) => v.cursor_up(),
-                    Some(View::Diff(v)) => v.scroll_up(),
-                    _ => {}
-                },
-
-                Key::Down | Key::Char('j') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_down(),
-                    Some(View::Stats(v)) => v.cursor_down(),
-                    Some(View::Diff(v)) => v.scroll_down(),
-                    _ => {}
-                },
-
-                Key::Ctrl('u') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_page_up(),
-                    Some(View::Stats(v)) => v.cursor_page_up(),
-                    Some(View::Diff(v)) => v.page_up(),
-                    _ => {}
-                },
-
-                Key::Ctrl('f') => match self.views.top_mut() {
-                    Some(View::CommitLog(v)) => v.cursor_page_down(),
-                    Some(View::Stats(v)) => v.cursor_page_down(),
-                    Some(View::Diff(v)) => v.page_down(),
-                    _ => {}
-                },
-
-                Key::Ctrl('c') => self.quit(),
-
-                Key::Char('d') => {
-                    if let Some(View::CommitLog(v)) = self.views.top() {
-                        let selected = v.get_selected();
-                        let marked = v.get_marked();
-                        let action = DiffAction::diff(selected, marked);
-                        self.views.push(View::Stats(Stats::new(action)));
-                    }
+                let message = if input.text.trim() == original.trim() {
+                    None
+                } else {
+                    Some(input.text)
+                };
+                let result = git_amend(message.as_deref(), true);
+                self.report_hooked(
+                    "Amend",
+                    result,
+                    Some(ConfirmAction::AmendNoVerify(message)),
+                );
+                self.broadcast(&AppEvent::Refresh);
+            }
+
+            InputAction::Anchor => {
+                let rev = match input.text.trim() {
+                    "" => "HEAD",
+                    rev => rev,
+                };
+                let Some(view) = self.views.top_mut() else {
+                    return;
+                };
+                self.message = Some(match view.anchor_to(rev) {
+                    Ok(description) => format!("Showing {}", description),
+                    Err(e) => e,
+                });
+            }
+
+            InputAction::GotoCommit => self.goto_commit(input.text.trim()),
+
+            InputAction::GrepLog => {
+                if let Some(view) = self.views.top_mut() {
+                    self.message =
+                        Some(match view.grep_log(input.text.trim()) {
+                            Ok(description) => description,
+                            Err(e) => e,
+                        });
                 }
+            }
 
-                Key::Enter => match self.views.top() {
-                    Some(View::CommitLog(v)) => {
-                        let selected = v.get_selected();
-                        let commits = DiffAction::show(selected);
-                        self.views.push(View::Stats(Stats::new(commits)));
-                    }
+            InputAction::PickaxeLog => {
+                if let Some(view) = self.views.top_mut() {
+                    // spaces can be part of the text, so it isn't trimmed
+                    self.message = Some(match view.pickaxe_log(&input.text) {
+                        Ok(description) => description,
+                        Err(e) => e,
+                    });
+                }
+            }
 
-                    Some(View::Stats(v)) => {
-                        let stat = v.current_stat().clone();
-                        let commits = v.commits().clone();
-                        self.views.push(View::Diff(Diff::new(&stat, &commits)));
-                        if let Ok(p) = stat.path() {
-                            if let Err(e) = self.events.watch_file(&p) {
-                                tracing::warn!(
-                                    "Error watching {:?}: {}",
-                                    stat.path(),
-                                    e
-                                )
-                            } else {
-                                tracing::info!("Watching {:?}", stat.path())
-                            }
-                        } else {
-                            tracing::warn!("Error watching {:?}", stat)
+            InputAction::HighlightPath => {
+                let path = input.text.trim();
+                self.message = Some(if path.is_empty() {
+                    "Not highlighting commits".into()
+                } else {
+                    format!("Highlighting the commits that changed {}", path)
+                });
+                let path = (!path.is_empty()).then(|| path.to_string());
+                self.broadcast(&AppEvent::HighlightPath(path));
+            }
+
+            InputAction::Profile => {
+                self.message =
+                    Some(match self.use_profile(input.text.trim()) {
+                        Ok(description) | Err(description) => description,
+                    });
+            }
+
+            InputAction::RangeDiff => match RangeDiff::new(&input.text) {
+                Ok(view) => self.push_view(Box::new(view)),
+                Err(e) => self.message = Some(e),
+            },
+
+            InputAction::ReleaseNotes(notes) => {
+                let file = input.text.trim();
+                self.message = Some(if file.is_empty() {
+                    self.clipboard = Some(notes);
+                    "Copied the release notes".into()
+                } else {
+                    match fs::write(file, notes) {
+                        Ok(()) => {
+                            format!("Wrote the release notes to {}", file)
                         }
+                        Err(e) => format!("Couldn't write {}: {}", file, e),
+                    }
+                });
+            }
+
+            InputAction::Pathspecs => {
+                let specs: Vec<String> =
+                    input.text.split_whitespace().map(String::from).collect();
+                let description = if specs.is_empty() {
+                    "Showing every path".into()
+                } else {
+                    format!("Limited to {}", specs.join(" "))
+                };
+                match set_pathspecs(specs) {
+                    Ok(()) => {
+                        self.message = Some(description);
+                        self.broadcast(&AppEvent::Refresh);
+                        self.check_worktree();
                     }
+                    Err(e) => self.message = Some(e.first_line()),
+                }
+            }
 
-                    _ => {}
-                },
+            InputAction::FindRemoval(path) => {
+                match Diff::find_removal(
+                    &input.text,
+                    &path,
+                    None,
+                    self.diff_opts,
+                ) {
+                    Ok(diff) => self.push_view(Box::new(diff)),
+                    Err(e) => self.message = Some(e),
+                }
+            }
 
-                _ => tracing::debug!("Unhandled: {}", key),
+            InputAction::Grep(commit) => {
+                match Grep::new(input.text.trim(), &commit, self.diff_opts) {
+                    Ok(grep) => self.push_view(Box::new(grep)),
+                    Err(e) => self.message = Some(e),
+                }
+            }
+
+            InputAction::DiffFile { commit, path } => {
+                let rev = match input.text.trim() {
+                    "" => "HEAD",
+                    rev => rev,
+                };
+                let show = DiffAction::show(Target::Ref(commit));
+                match git_anchor(&show, rev).and_then(|range| {
+                    Diff::of_file(&range, &path, self.diff_opts)
+                }) {
+                    Ok(diff) => self.push_view(Box::new(diff)),
+                    Err(e) => self.message = Some(e),
+                }
+            }
+
+            InputAction::GotoLine => {
+                let Ok(line) = input.text.trim().parse::<u32>() else {
+                    self.message =
+                        Some(format!("Not a line number: {}", input.text));
+                    return;
+                };
+                if let Some(view) = self.views.top_mut() {
+                    if !view.goto_line(line) {
+                        self.message =
+                            Some(format!("Line {} isn't in view", line));
+                    }
+                }
             }
         }
     }
 
-    pub fn start(&mut self) {
+    /// Check for staged and unstaged changes in the background; if a check is
+    /// already running, another one runs after it finishes
+    fn check_worktree(&mut self) {
+        if self.checking_worktree {
+            self.worktree_stale = true;
+            return;
+        }
+
+        self.checking_worktree = true;
+        let tx = self.events.sender();
+        let scoped = self.diff_opts.scoped;
+        thread::spawn(inherit_runner(move || {
+            let changes = git_worktree_changes(scoped);
+            if let Err(err) = tx.send(AppEvent::WorktreeChanges(changes)) {
+                tracing::error!("Error sending worktree changes: {:?}", err);
+            }
+        }));
+    }
+
+    /// Load the next page of a log in the background, following the first
+    /// `skip` commits
+    fn load_log(&mut self, filter: LogFilter, skip: usize) {
+        self.loading_logs += 1;
+        let tx = self.events.sender();
+        thread::spawn(inherit_runner(move || {
+            let commits = git_log(&filter, skip, LOG_PAGE_SIZE);
+            let event = AppEvent::LogLoaded {
+                filter,
+                skip,
+                commits,
+            };
+            if let Err(err) = tx.send(event) {
+                tracing::error!("Error sending log: {:?}", err);
+            }
+        }));
+    }
+
+    /// Press the keys of a recorded session once the app starts
+    pub fn replay(&mut self, events: Vec<RecordedEvent>) {
+        self.events.replay(events);
+    }
+
+    /// Run the app until it quits; a replayed session fails at the first git
+    /// command that wasn't recorded
+    pub fn start(&mut self) -> Result<(), String> {
         self.events.start();
 
+        // staged and unstaged changes are added to the log once they're known,
+        // and updated whenever the index or working tree changes
+        self.check_worktree();
+        let root = PathBuf::from(git_root());
+        if let Err(e) = self.events.watch_repo(&root, &git_dir()) {
+            tracing::warn!("Error watching {:?}: {}", root, e);
+        }
+
         let mut ui = Ui::new();
+        let mut result = Ok(());
 
         loop {
+            if let Some(failure) = runner_failure() {
+                result = Err(failure);
+                break;
+            }
+
             ui.update(self);
 
-            match self.events.next().unwrap() {
-                AppEvent::Input(key) => self.do_action(key),
-                AppEvent::Resize => {}
-                AppEvent::FilesChanged(_) => {
-                    if let Some(View::Diff(v)) = self.views.top_mut() {
-                        v.refresh();
-                    }
+            self.handle_event(self.events.next().unwrap());
+
+            if let Some((path, line)) = self.edit.take() {
+                self.events.pause_input();
+                ui.suspend();
+                let result = run_editor(&path, line);
+                ui.resume();
+                self.events.resume_input();
+                if let Err(e) = result {
+                    self.message = Some(e.first_line());
                 }
-            };
+            }
 
             if self.should_quit() {
                 break;
@@ -330,5 +1058,238 @@ impl App {
         }
 
         ui.stop();
+        result
+    }
+
+    /// Act on an event from a key, a watcher, or a background task
+    fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Input(key) => self.do_action(key),
+            AppEvent::Resize | AppEvent::Tick => {}
+            AppEvent::RepoChanged => self.check_worktree(),
+            event @ AppEvent::WorktreeChanges(_) => {
+                self.broadcast(&event);
+                self.checking_worktree = false;
+                if self.worktree_stale {
+                    self.worktree_stale = false;
+                    self.check_worktree();
+                }
+            }
+            event @ AppEvent::LogLoaded { .. } => {
+                self.loading_logs = self.loading_logs.saturating_sub(1);
+                self.broadcast(&event);
+            }
+            event => self.broadcast(&event),
+        };
+    }
+
+    /// Wait for the worktree check and log pages running in the background
+    /// to finish, so a script sees the same thing a user would once the
+    /// screen stopped changing
+    fn settle(&mut self) {
+        while self.checking_worktree || self.loading_logs > 0 {
+            match self.events.next() {
+                Ok(event) => self.handle_event(event),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Press a key for a script and wait for what it started; the view is
+    /// given the search being typed, like drawing the screen would
+    fn press(&mut self, key: Key) {
+        self.do_action(key);
+        if let Some(s) = self.views.top_mut().and_then(|v| v.search()) {
+            s.set_search(self.search.clone());
+        }
+        self.settle();
+    }
+
+    /// Run a script without a terminal, writing the screens and patches it
+    /// asks for; the script stops at the first step that fails
+    pub fn run_script(
+        &mut self,
+        script: &Script,
+        out: &mut impl Write,
+    ) -> Result<(), String> {
+        // nothing that depends on the terminal or the time it's run at
+        self.images = None;
+        self.key_hints = false;
+        self.hint = None;
+
+        let mut size = DEFAULT_SIZE;
+        self.check_worktree();
+        self.settle();
+
+        for (nr, step) in script {
+            let fail = |e: String| format!("line {}: {}", nr, e);
+            match step {
+                Step::Keys(keys) => {
+                    for key in keys {
+                        self.press(*key);
+                    }
+                }
+                Step::Type(text) => {
+                    for c in text.chars() {
+                        self.press(Key::Char(c));
+                    }
+                }
+                Step::Goto(rev) => {
+                    let view = self.views.top_mut().ok_or("No view is open")?;
+                    self.message = Some(view.goto_commit(rev).map_err(fail)?);
+                    self.settle();
+                }
+                Step::Search(pattern) => {
+                    let search = self
+                        .views
+                        .top_mut()
+                        .and_then(|v| v.search())
+                        .ok_or_else(|| {
+                            fail("The view can't be searched".into())
+                        })?;
+                    search.set_search(Some(pattern.clone()));
+                    if !search.search_next() {
+                        return Err(fail(format!(
+                            "No matches for {}",
+                            pattern
+                        )));
+                    }
+                    self.search = Some(pattern.clone());
+                }
+                Step::Open(path) => {
+                    let view = self.views.top_mut().ok_or("No view is open")?;
+                    view.select_file(path).map_err(fail)?;
+                    self.do_action(Key::Enter);
+                    self.settle();
+                }
+                Step::Size(width, height) => size = (*width, *height),
+                Step::Screen => write!(out, "{}", render_text(self, size))
+                    .map_err(|e| fail(e.to_string()))?,
+                Step::Patch(file) => {
+                    let view = self.views.top().ok_or("No view is open")?;
+                    let patch = view.patch(self.diff_opts).map_err(fail)?;
+                    match file {
+                        Some(file) => fs::write(file, patch).map_err(|e| {
+                            fail(format!("Couldn't write {:?}: {}", file, e))
+                        })?,
+                        None => write!(out, "{}", patch)
+                            .map_err(|e| fail(e.to_string()))?,
+                    }
+                }
+            }
+
+            // a script can't run an editor or finish after the app quits
+            if self.edit.take().is_some() {
+                return Err(fail(
+                    "An editor can't be opened by a script".into(),
+                ));
+            }
+            if self.should_quit() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Open a file in the user's editor at a line, and wait for the editor to exit
+fn run_editor(path: &Path, line: u32) -> Result<(), AppError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    // the editor may be set with arguments, like "code --wait"
+    let mut args = editor.split_whitespace();
+    let Some(program) = args.next() else {
+        return Err(AppError::EditorFailed("no editor is set".into()));
+    };
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(format!("+{}", line))
+        .arg(path)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::EditorFailed(format!("{} {}", program, status)))
+    }
+}
+
+/// Open a web page in the user's browser, without waiting for it
+fn open_browser(url: &str) -> Result<(), AppError> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // the empty argument is the title of the window start opens
+        let mut command = Command::new("cmd");
+        command.args(["/c", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Split a command's output into lines that can be shown in a popup
+fn output_lines(output: &str) -> Vec<Line<'static>> {
+    output.lines().map(|l| Line::from(l.to_string())).collect()
+}
+
+/// Describe a failed operation, which may have stopped with conflicts
+fn failure_message(operation: &str, error: AppError) -> String {
+    let conflicts = git_conflicts();
+    if conflicts.is_empty() {
+        format!("{} failed: {}", operation, error.first_line())
+    } else {
+        format!(
+            "{} stopped with conflicts in {}",
+            operation,
+            conflicts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::{
+        git::{testing::TestRepo, with_runner},
+        script,
+        time::freeze_clock,
+    };
+
+    #[test]
+    fn script_output_matches_golden_file() {
+        let repo = TestRepo::new("script");
+        repo.commit(&[("a.txt", "hello\n")], "Add a greeting", "1700000000");
+        repo.commit(&[("b.txt", "bye\n")], "Add a farewell", "1700086400");
+        repo.commit(
+            &[("a.txt", "hello world\n"), ("b.txt", "bye now\n")],
+            "Greet the world",
+            "1700172800",
+        );
+
+        let script =
+            script::parse(include_str!("../testdata/script.txt")).unwrap();
+        // this is the only test that shows ages
+        freeze_clock(DateTime::from_timestamp(1700172800, 0).unwrap());
+        let mut out = vec![];
+        with_runner(repo.runner(), || {
+            let mut app = App::new(&Config::default()).unwrap();
+            app.run_script(&script, &mut out)
+        })
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            include_str!("../testdata/script.out")
+        );
     }
 }
@@ -0,0 +1,105 @@
+use crate::git::Commit;
+
+/// The changelog's sections for the usual conventional-commit types, in the
+/// order they're listed
+const SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "Continuous integration"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+    ("revert", "Reverts"),
+];
+
+/// A commit subject in the conventional-commit form,
+/// `type(scope)!: description`, where the scope and `!` are optional
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalSubject<'a> {
+    pub kind: String,
+    pub scope: Option<&'a str>,
+    /// the `!` after the type marks a breaking change
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+impl<'a> ConventionalSubject<'a> {
+    /// Parse a subject, if it's in the conventional-commit form; types are
+    /// case-insensitive
+    pub fn parse(subject: &'a str) -> Option<ConventionalSubject<'a>> {
+        let (prefix, description) = subject.split_once(':')?;
+        let description = description.trim();
+        let (prefix, breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None),
+        };
+        if kind.is_empty()
+            || description.is_empty()
+            || !kind.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        Some(ConventionalSubject {
+            kind: kind.to_lowercase(),
+            scope: scope.map(str::trim).filter(|s| !s.is_empty()),
+            breaking,
+            description,
+        })
+    }
+}
+
+/// Return a Markdown changelog for some commits, with their subjects grouped
+/// by conventional-commit type; breaking changes are listed first, and
+/// subjects of other types or forms are listed last
+pub fn changelog(title: &str, commits: &[Commit]) -> String {
+    let mut breaking = vec![];
+    let mut sections: Vec<Vec<String>> = vec![vec![]; SECTIONS.len()];
+    let mut other = vec![];
+
+    for commit in commits {
+        let hash = &commit.commit_ref;
+        let Some(subject) = ConventionalSubject::parse(&commit.subject) else {
+            other.push(format!("- {} ({})", commit.subject, hash));
+            continue;
+        };
+        let entry = match subject.scope {
+            Some(scope) => {
+                format!("- **{}:** {} ({})", scope, subject.description, hash)
+            }
+            None => format!("- {} ({})", subject.description, hash),
+        };
+        if subject.breaking {
+            breaking.push(entry);
+        } else {
+            match SECTIONS.iter().position(|(k, _)| *k == subject.kind) {
+                Some(i) => sections[i].push(entry),
+                None => other.push(entry),
+            }
+        }
+    }
+
+    let mut text = format!("## {}\n", title);
+    let mut add_section = |heading: &str, entries: &[String]| {
+        if !entries.is_empty() {
+            text.push_str(&format!("\n### {}\n\n", heading));
+            for entry in entries {
+                text.push_str(entry);
+                text.push('\n');
+            }
+        }
+    };
+    add_section("Breaking changes", &breaking);
+    for ((_, heading), entries) in SECTIONS.iter().zip(&sections) {
+        add_section(heading, entries);
+    }
+    add_section("Other changes", &other);
+    text
+}
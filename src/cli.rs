@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use crate::git::{DiffAlgorithm, LogLimit};
+
+pub const USAGE: &str = "\
+usage: de [options] [<dir>] [-- <path>...]
+
+options:
+  --diff-algorithm=<algorithm>  myers, minimal, patience, or histogram
+  --max-count=<n>               load at most n commits into the log
+  --since=<date>                load only commits newer than date
+  --profile=<name>              start with a profile's settings
+  --record=<file>               record keys and git's output to file
+  --replay=<file>               replay a recorded session from file
+  --script=<file>               run the steps in file without a terminal
+  -h, --help                    show this message
+
+Paths after -- limit the log and diffs to the commits and files that match
+them, like they do for git log.";
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct Args {
+    /// the directory to run in
+    pub dir: Option<String>,
+    /// pathspecs that every log and diff is limited to
+    pub paths: Vec<String>,
+    /// the diff algorithm, which overrides the config file
+    pub diff_algorithm: Option<DiffAlgorithm>,
+    /// the most commits the log loads
+    pub max_count: Option<usize>,
+    /// the oldest date the log loads commits from
+    pub since: Option<String>,
+    /// the profile whose settings to start with
+    pub profile: Option<String>,
+    /// a file to record the session's keys and git output to
+    pub record: Option<PathBuf>,
+    /// a recorded session to replay, answering git commands from it
+    pub replay: Option<PathBuf>,
+    /// a script to run instead of reading keys from the terminal
+    pub script: Option<PathBuf>,
+    /// show the usage message
+    pub help: bool,
+}
+
+impl Args {
+    /// Parse command line arguments (not including the program name),
+    /// returning a description of the problem if they're invalid
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.paths.extend(args.by_ref());
+            } else if arg == "-h" || arg == "--help" {
+                parsed.help = true;
+            } else if let Some(opt) = arg.strip_prefix("--") {
+                let (name, value) = match opt.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (opt, None),
+                };
+                let mut value = || {
+                    value
+                        .clone()
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("--{} needs a value", name))
+                };
+
+                match name {
+                    "diff-algorithm" => {
+                        parsed.diff_algorithm = Some(value()?.parse()?)
+                    }
+                    "max-count" => {
+                        let value = value()?;
+                        parsed.max_count =
+                            Some(value.parse().map_err(|_| {
+                                format!(
+                                    "--max-count needs a number, not '{}'",
+                                    value
+                                )
+                            })?)
+                    }
+                    "since" => parsed.since = Some(value()?),
+                    "profile" => parsed.profile = Some(value()?),
+                    "record" => parsed.record = Some(value()?.into()),
+                    "replay" => parsed.replay = Some(value()?.into()),
+                    "script" => parsed.script = Some(value()?.into()),
+                    _ => return Err(format!("unknown option --{}", name)),
+                }
+            } else if parsed.dir.is_none() {
+                parsed.dir = Some(arg);
+            } else {
+                return Err(format!("unexpected argument '{}'", arg));
+            }
+        }
+
+        if parsed.record.is_some() && parsed.replay.is_some() {
+            return Err("--record and --replay can't be used together".into());
+        }
+
+        Ok(parsed)
+    }
+
+    /// Return the limits on how much history the log loads
+    pub fn log_limit(&self) -> LogLimit {
+        LogLimit {
+            max_count: self.max_count,
+            since: self.since.clone(),
+        }
+    }
+}
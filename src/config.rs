@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    git::{AuthorFormat, DiffAlgorithm, GitDiffOpts},
+    logging::project_directory,
+    theme::{ColorSupport, DEFAULT_THEME},
+    time::DEFAULT_DATE_FORMAT,
+};
+
+/// User settings, read from a TOML file in the config directory
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub git: GitConfig,
+    pub diff: DiffConfig,
+    pub blame: BlameConfig,
+    pub ui: UiConfig,
+    /// Named sets of settings to switch between
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings for the git processes that diff-explore runs
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Environment variables for git processes; these override the defaults
+    pub env: BTreeMap<String, String>,
+    /// Pass variables like GIT_DIR and GIT_WORK_TREE through to git rather
+    /// than letting git find the repo from the working directory
+    pub inherit_repo_env: bool,
+}
+
+/// Settings for how diffs are computed
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    /// The diff algorithm git should use
+    pub algorithm: DiffAlgorithm,
+    /// Show images in diffs of binary image files, if the terminal supports
+    /// a graphics protocol
+    pub image_preview: bool,
+    /// List the uncommitted changes inside submodules with the working
+    /// tree's changes
+    pub submodules: bool,
+}
+
+/// Settings for how lines are blamed
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BlameConfig {
+    /// Revisions that blame looks past, like commits that only reformatted
+    /// code, in addition to the ones in a repo's .git-blame-ignore-revs;
+    /// revisions that aren't in the repo are skipped
+    pub ignore_revs: Vec<String>,
+}
+
+/// Settings for how views are drawn
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Draw a border around each view, titled with what the view shows
+    pub borders: bool,
+    /// Briefly show a view's most useful keys in the status line when the
+    /// view is opened
+    pub key_hints: bool,
+    /// The number of columns a tab is expanded to in diffs
+    pub tab_width: u8,
+    /// The name of the color theme
+    pub theme: String,
+    /// The colors the terminal supports; theme colors are mapped to the
+    /// nearest ones it can show
+    pub colors: ColorSupport,
+    /// When de is started in a subdirectory of the repo, only show the
+    /// commits and changes in that subdirectory until the log's scope is
+    /// toggled
+    pub subdirectory_scope: bool,
+    /// The strftime format of the log's commit dates when they're switched
+    /// from relative to absolute
+    pub date_format: String,
+    /// How the log identifies authors: by name, or by the username of their
+    /// email
+    pub author: AuthorFormat,
+}
+
+/// A named set of settings that are switched to all at once, at startup or
+/// while de is running; settings a profile leaves out keep their values
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Show the selected commit's details below the log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<bool>,
+    /// The percentage of the log's height given to the log when the details
+    /// are shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pane_split: Option<u16>,
+    /// Open the diff of every changed file instead of the list of files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub whole_diffs: Option<bool>,
+    /// Show the log's dates instead of their ages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absolute_dates: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<AuthorFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_whitespace: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<DiffAlgorithm>,
+    /// Lines of context around changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub borders: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_hints: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab_width: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+impl Profile {
+    /// Return diff options with the profile's diff settings in place of the
+    /// ones it sets
+    pub fn diff_opts(&self, opts: GitDiffOpts) -> GitDiffOpts {
+        GitDiffOpts {
+            ignore_whitespace: self
+                .ignore_whitespace
+                .unwrap_or(opts.ignore_whitespace),
+            algorithm: self.algorithm.unwrap_or(opts.algorithm),
+            context: self.context.or(opts.context),
+            ..opts
+        }
+    }
+}
+
+/// The tab widths that can be switched between while de is running
+pub const TAB_WIDTHS: [u8; 3] = [2, 4, 8];
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            borders: false,
+            key_hints: true,
+            tab_width: 4,
+            theme: DEFAULT_THEME.into(),
+            colors: ColorSupport::Auto,
+            subdirectory_scope: false,
+            date_format: DEFAULT_DATE_FORMAT.into(),
+            author: AuthorFormat::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Return the path of the config file, which may not exist
+    pub fn path() -> PathBuf {
+        let project_name = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
+        if let Ok(path) = std::env::var(format!("{}_CONFIG", project_name)) {
+            PathBuf::from(path)
+        } else if let Some(proj_dirs) = project_directory() {
+            proj_dirs.config_dir().join("config.toml")
+        } else {
+            PathBuf::from(".").join(".config").join("config.toml")
+        }
+    }
+
+    /// Return the directory that theme files are installed in, next to the
+    /// config file
+    pub fn themes_dir() -> PathBuf {
+        let path = Config::path();
+        path.parent().unwrap_or(&path).join("themes")
+    }
+
+    /// Load the config file, or return the default config if there isn't one
+    pub fn load() -> Result<Config, AppError> {
+        let path = Config::path();
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let text = fs::read_to_string(&path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Change a setting in the config file, creating the file if it doesn't
+    /// exist; the file is rewritten, so any comments in it are lost
+    pub fn update(change: impl FnOnce(&mut Config)) -> Result<(), AppError> {
+        let mut config = Config::load()?;
+        change(&mut config);
+
+        let path = Config::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(&config)?)?;
+        Ok(())
+    }
+}
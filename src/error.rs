@@ -9,4 +9,24 @@ pub enum AppError {
 
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
+
+    #[error("Config error: {0}")]
+    ConfigError(#[from] toml::de::Error),
+
+    #[error("Config error: {0}")]
+    ConfigWriteError(#[from] toml::ser::Error),
+
+    #[error("Git error: {0}")]
+    GitFailed(String),
+
+    #[error("Editor error: {0}")]
+    EditorFailed(String),
+}
+
+impl AppError {
+    /// Return the first line of the error message, for display in the
+    /// statusline
+    pub fn first_line(&self) -> String {
+        self.to_string().lines().next().unwrap_or_default().into()
+    }
 }
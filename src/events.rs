@@ -1,8 +1,13 @@
 use std::{
     fmt::{self, Display},
     path::{Path, PathBuf},
-    sync::mpsc::{self, Receiver, Sender},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent};
@@ -11,33 +16,78 @@ use notify::{
     RecursiveMode, Watcher,
 };
 
-use crate::error::AppError;
+use crate::{
+    config::Profile,
+    error::AppError,
+    git::{path_within, same_path, Commit, LogFilter, WorktreeChanges},
+    session::{record_key, record_resize, RecordedEvent},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Key {
     Enter,
     Escape,
     Backspace,
     Up,
     Down,
+    Left,
+    Right,
+    Tab,
     Ctrl(char),
     Char(char),
     Unknown,
 }
 
-impl Display for Key {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name: String = match self {
+impl Key {
+    /// Return the key's name, like `j`, `Ctrl+n`, or `Enter`
+    pub fn name(&self) -> String {
+        match self {
             Key::Enter => "Enter".into(),
             Key::Escape => "Escape".into(),
             Key::Backspace => "Backspace".into(),
             Key::Up => "Up".into(),
             Key::Down => "Down".into(),
+            Key::Left => "Left".into(),
+            Key::Right => "Right".into(),
+            Key::Tab => "Tab".into(),
             Key::Char(char) => char.to_string(),
             Key::Ctrl(char) => format!("Ctrl+{}", char),
             Key::Unknown => "unknown".into(),
-        };
-        write!(f, "Key({})", name)
+        }
+    }
+
+    /// Return the key with the given name
+    pub fn from_name(name: &str) -> Option<Key> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(Key::Char(c));
+        }
+        if let Some(c) = name.strip_prefix("Ctrl+") {
+            return Key::from_name(c).and_then(|key| match key {
+                Key::Char(c) => Some(Key::Ctrl(c)),
+                _ => None,
+            });
+        }
+        Some(match name {
+            "Enter" => Key::Enter,
+            "Escape" => Key::Escape,
+            "Backspace" => Key::Backspace,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Tab" => Key::Tab,
+            // a space is easier to read by name
+            "Space" => Key::Char(' '),
+            "unknown" => Key::Unknown,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key({})", self.name())
     }
 }
 
@@ -73,6 +123,20 @@ impl From<event::KeyEvent> for Key {
                 ..
             } => Key::Down,
 
+            KeyEvent {
+                code: KeyCode::Left,
+                ..
+            } => Key::Left,
+
+            KeyEvent {
+                code: KeyCode::Right,
+                ..
+            } => Key::Right,
+
+            KeyEvent {
+                code: KeyCode::Tab, ..
+            } => Key::Tab,
+
             KeyEvent {
                 code: KeyCode::Char(c),
                 ..
@@ -83,16 +147,49 @@ impl From<event::KeyEvent> for Key {
     }
 }
 
+/// How often the input thread checks whether it should stop reading input
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 pub enum AppEvent {
     Input(Key),
     FilesChanged(Vec<PathBuf>),
+    WorktreeChanges(WorktreeChanges),
+    /// more commits were loaded for a log with the given filter, following
+    /// the first `skip` commits
+    LogLoaded {
+        filter: LogFilter,
+        skip: usize,
+        commits: Vec<Commit>,
+    },
+    RepoChanged,
+    /// a branch or other ref in the repo was updated
+    RefsChanged,
+    /// the app changed the repo, so views should reload what they show
+    Refresh,
+    /// logs should make the commits that changed a path stand out, or stop
+    /// if there's no path
+    HighlightPath(Option<String>),
+    /// views should switch to a profile's settings
+    UseProfile(Profile),
+    /// a timer ran out, so anything that expires should be checked
+    Tick,
     Resize,
 }
 
 pub struct Events {
     rx: Receiver<AppEvent>,
+    /// set to stop reading input, e.g. while another program uses the
+    /// terminal
+    input_paused: Arc<AtomicBool>,
+    /// set by the input thread once it has stopped reading input
+    input_idle: Arc<AtomicBool>,
     tx: Sender<AppEvent>,
     watcher: RecommendedWatcher,
+    repo_watcher: Option<RecommendedWatcher>,
+    watched: Vec<PathBuf>,
+    /// recorded keys to press and resizes to make before reading any input
+    /// from the terminal
+    replay: Vec<RecordedEvent>,
 }
 
 impl Events {
@@ -106,51 +203,183 @@ impl Events {
                     let evt = event.clone();
                     if let EventKind::Modify(ModifyKind::Data(_)) = event.kind {
                         tracing::debug!("files changed: {:?}", evt.paths);
-                        watch_tx
-                            .send(AppEvent::FilesChanged(evt.paths))
-                            .err()
-                            .map(|err| {
-                                tracing::error!(
-                                    "Error sending files changed event: {:?}",
-                                    err
-                                );
-                            });
+                        if let Err(err) =
+                            watch_tx.send(AppEvent::FilesChanged(evt.paths))
+                        {
+                            tracing::error!(
+                                "Error sending files changed event: {:?}",
+                                err
+                            );
+                        }
                     }
                 }
             },
         )?;
 
-        Ok(Events { rx, tx, watcher })
+        Ok(Events {
+            rx,
+            input_paused: Arc::new(AtomicBool::new(false)),
+            input_idle: Arc::new(AtomicBool::new(false)),
+            tx,
+            watcher,
+            repo_watcher: None,
+            watched: vec![],
+            replay: vec![],
+        })
+    }
+
+    /// Press the keys of a recorded session and make its resizes, at the
+    /// times they happened, once input starts
+    pub fn replay(&mut self, events: Vec<RecordedEvent>) {
+        self.replay = events;
     }
 
     pub fn start(&mut self) {
         let input_tx = self.tx.clone();
-        thread::spawn(move || loop {
-            if let Ok(event) = event::read() {
-                match event {
-                    Event::Key(key) => {
-                        input_tx.send(AppEvent::Input(Key::from(key))).unwrap();
-                    }
-
-                    Event::Resize(_, _) => {
-                        input_tx.send(AppEvent::Resize).unwrap();
-                    }
-
-                    _ => {}
-                }
+        let paused = self.input_paused.clone();
+        let idle = self.input_idle.clone();
+        let replay = std::mem::take(&mut self.replay);
+        thread::spawn(move || {
+            let start = Instant::now();
+            for RecordedEvent { at, event } in replay {
+                thread::sleep(at.saturating_sub(start.elapsed()));
+                input_tx.send(event).unwrap();
             }
+            read_input(input_tx, paused, idle);
         });
     }
 
+    /// Stop reading input until `resume_input` is called, waiting until the
+    /// input thread is no longer reading
+    pub fn pause_input(&self) {
+        self.input_paused.store(true, Ordering::SeqCst);
+        while !self.input_idle.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    pub fn resume_input(&self) {
+        self.input_idle.store(false, Ordering::SeqCst);
+        self.input_paused.store(false, Ordering::SeqCst);
+    }
+
     pub fn watch_file(&mut self, path: &Path) -> notify::Result<()> {
-        self.watcher.watch(path, RecursiveMode::Recursive)
+        self.watcher.watch(path, RecursiveMode::Recursive)?;
+        self.watched.push(path.to_path_buf());
+        Ok(())
     }
 
     pub fn unwatch_file(&mut self, path: &Path) -> notify::Result<()> {
+        self.watched.retain(|p| !same_path(p, path));
         self.watcher.unwatch(path)
     }
 
+    /// Watch a repo's working tree and index, sending a RepoChanged event
+    /// when either changes, and a RefsChanged event when a ref is updated
+    ///
+    /// Other changes inside the git directory are ignored.
+    pub fn watch_repo(
+        &mut self,
+        root: &Path,
+        git_dir: &Path,
+    ) -> notify::Result<()> {
+        let tx = self.tx.clone();
+        let index = git_dir.join("index");
+        let refs = git_dir.join("refs");
+        let packed_refs = git_dir.join("packed-refs");
+        let dir = git_dir.to_path_buf();
+        let mut watcher = recommended_watcher(
+            move |res: Result<notify::Event, notify::Error>| {
+                let Ok(event) = res else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                let send = |event: AppEvent| {
+                    if let Err(err) = tx.send(event) {
+                        tracing::error!("Error sending repo event: {:?}", err);
+                    }
+                };
+                if event
+                    .paths
+                    .iter()
+                    .any(|p| same_path(p, &index) || !path_within(p, &dir))
+                {
+                    send(AppEvent::RepoChanged);
+                }
+                // refs are written to lock files that are then renamed
+                if event.paths.iter().any(|p| {
+                    (path_within(p, &refs) || same_path(p, &packed_refs))
+                        && p.extension().is_none_or(|e| e != "lock")
+                }) {
+                    send(AppEvent::RefsChanged);
+                }
+            },
+        )?;
+
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        self.watched.push(root.to_path_buf());
+        if !path_within(git_dir, root) {
+            watcher.watch(git_dir, RecursiveMode::NonRecursive)?;
+            self.watched.push(git_dir.to_path_buf());
+            let refs = git_dir.join("refs");
+            watcher.watch(&refs, RecursiveMode::Recursive)?;
+            self.watched.push(refs);
+        }
+
+        self.repo_watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Return the paths currently being watched
+    pub fn watched(&self) -> &[PathBuf] {
+        &self.watched
+    }
+
+    /// Return a sender that can post events from other threads
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.tx.clone()
+    }
+
     pub fn next(&self) -> Result<AppEvent, mpsc::RecvError> {
         self.rx.recv()
     }
 }
+
+/// Read keys from the terminal and send them as events, until the app exits
+fn read_input(
+    input_tx: Sender<AppEvent>,
+    paused: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
+) {
+    loop {
+        if paused.load(Ordering::SeqCst) {
+            idle.store(true, Ordering::SeqCst);
+            thread::sleep(INPUT_POLL_INTERVAL);
+            continue;
+        }
+        idle.store(false, Ordering::SeqCst);
+
+        // input is polled so that the pause flag is checked regularly
+        if !event::poll(INPUT_POLL_INTERVAL).unwrap_or(false) {
+            continue;
+        }
+        if let Ok(event) = event::read() {
+            match event {
+                Event::Key(key) => {
+                    let key = Key::from(key);
+                    record_key(&key);
+                    input_tx.send(AppEvent::Input(key)).unwrap();
+                }
+
+                Event::Resize(width, height) => {
+                    record_resize(width, height);
+                    input_tx.send(AppEvent::Resize).unwrap();
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+
+use super::commits::GitRef;
+use crate::time::RelativeTime;
+
+/// A line of a file, and the commit that introduced it
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    pub commit: GitRef,
+    pub author_name: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// the file's path in the commit
+    pub orig_path: String,
+    /// the line's number in the file as of the commit
+    pub orig_line: u32,
+    pub text: String,
+}
+
+impl BlameLine {
+    /// Parse the output of `git blame --line-porcelain`, which describes the
+    /// commit of every line in full
+    pub fn parse_all(output: &str) -> Vec<BlameLine> {
+        let mut lines = vec![];
+        let mut current: Option<BlameLine> = None;
+
+        for line in output.lines() {
+            if let Some(text) = line.strip_prefix('\t') {
+                if let Some(mut blame) = current.take() {
+                    blame.text = text.into();
+                    lines.push(blame);
+                }
+            } else if let Some(blame) = &mut current {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                match key {
+                    "author" => blame.author_name = value.into(),
+                    "author-time" => {
+                        blame.timestamp = value
+                            .parse()
+                            .ok()
+                            .and_then(|t| DateTime::from_timestamp(t, 0))
+                    }
+                    "filename" => blame.orig_path = value.into(),
+                    _ => {}
+                }
+            } else {
+                // a line's header is its commit, its line number in the
+                // commit, and its line number in the file
+                let mut parts = line.split(' ');
+                let (Some(commit), Some(orig_line)) =
+                    (parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                current = Some(BlameLine {
+                    commit: GitRef::new(commit),
+                    author_name: "".into(),
+                    timestamp: None,
+                    orig_path: "".into(),
+                    orig_line: orig_line.parse().unwrap_or(0),
+                    text: "".into(),
+                });
+            }
+        }
+
+        lines
+    }
+
+    /// True if the line hasn't been committed yet
+    pub fn is_uncommitted(&self) -> bool {
+        self.commit.to_string().chars().all(|c| c == '0')
+    }
+
+    /// Return the commit's abbreviated hash
+    pub fn short_commit(&self) -> String {
+        self.commit.to_string().chars().take(7).collect()
+    }
+}
+
+impl RelativeTime for BlameLine {
+    fn relative_time(&self) -> String {
+        match self.timestamp {
+            Some(ts) => ts.relative_time(),
+            _ => "".into(),
+        }
+    }
+}
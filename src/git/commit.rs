@@ -1,9 +1,11 @@
-use std::num::ParseIntError;
+use std::{fmt::Display, num::ParseIntError};
 
 use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 
 use super::commits::GitRef;
-use crate::time::RelativeTime;
+use crate::time::{AbsoluteTime, RelativeTime};
 
 #[derive(Debug, Clone)]
 pub struct Decoration {
@@ -46,6 +48,51 @@ impl Decoration {
     }
 }
 
+/// What identifies a commit's author where there's only room for a few
+/// characters
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorFormat {
+    /// the author's name
+    #[default]
+    Name,
+    /// the part of the author's email before the @
+    Username,
+}
+
+impl AuthorFormat {
+    pub fn next(self) -> AuthorFormat {
+        match self {
+            AuthorFormat::Name => AuthorFormat::Username,
+            AuthorFormat::Username => AuthorFormat::Name,
+        }
+    }
+}
+
+impl Display for AuthorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthorFormat::Name => write!(f, "names"),
+            AuthorFormat::Username => write!(f, "usernames"),
+        }
+    }
+}
+
+static AUTHOR_FORMAT: OnceCell<AuthorFormat> = OnceCell::new();
+
+/// Set how logs start out showing authors; this can only be set once, before
+/// any log is loaded
+pub fn set_author_format(format: AuthorFormat) {
+    AUTHOR_FORMAT.set(format).ok();
+}
+
+/// Return how logs start out showing authors
+pub fn author_format() -> AuthorFormat {
+    AUTHOR_FORMAT.get().copied().unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub struct Commit {
     pub commit_ref: GitRef,
@@ -84,6 +131,18 @@ impl Commit {
         }
     }
 
+    /// Return the author, as the name or the username of the email; an email
+    /// without an @ is used whole
+    pub fn author(&self, format: AuthorFormat) -> &str {
+        match format {
+            AuthorFormat::Name => &self.author_name,
+            AuthorFormat::Username => self
+                .author_email
+                .split_once('@')
+                .map_or(self.author_email.as_str(), |(user, _)| user),
+        }
+    }
+
     pub fn from_log_line(line: &str) -> Commit {
         let parts: Vec<&str> = line.splitn(7, '|').collect();
         let time: Result<u64, ParseIntError> = parts[5].parse();
@@ -113,6 +172,55 @@ impl Commit {
     }
 }
 
+/// Everything shown about a commit in the log's details pane
+#[derive(Debug, Clone, Default)]
+pub struct CommitDetails {
+    /// the commit's full hash
+    pub hash: String,
+    /// each parent, with its subject
+    pub parents: Vec<(GitRef, String)>,
+    /// the author's name and email
+    pub author: String,
+    /// when the commit was authored, and how long ago that was
+    pub author_date: String,
+    /// the committer's name and email
+    pub committer: String,
+    /// when the commit was committed, and how long ago that was
+    pub commit_date: String,
+    /// the full commit message
+    pub message: String,
+}
+
+impl CommitDetails {
+    /// Parse the output of `git show` with DETAILS_FORMAT, followed by the
+    /// subjects of the parents, one per line
+    pub fn parse(output: &str, subjects: &str) -> CommitDetails {
+        let parts: Vec<&str> = output.splitn(7, '\0').collect();
+        let part = |i: usize| parts.get(i).copied().unwrap_or_default();
+        let subjects: Vec<&str> = subjects.lines().collect();
+        CommitDetails {
+            hash: part(0).into(),
+            parents: part(1)
+                .split_whitespace()
+                .enumerate()
+                .map(|(i, p)| {
+                    let subject = subjects.get(i).copied().unwrap_or_default();
+                    (GitRef::new(p), subject.to_string())
+                })
+                .collect(),
+            author: part(2).into(),
+            author_date: part(3).into(),
+            committer: part(4).into(),
+            commit_date: part(5).into(),
+            message: part(6).trim_end().into(),
+        }
+    }
+}
+
+/// The `git show` format that CommitDetails are parsed from
+pub const DETAILS_FORMAT: &str =
+    "%H%x00%p%x00%an <%ae>%x00%ad (%ar)%x00%cn <%ce>%x00%cd (%cr)%x00%B";
+
 impl RelativeTime for Commit {
     fn relative_time(&self) -> String {
         match self.timestamp {
@@ -121,3 +229,12 @@ impl RelativeTime for Commit {
         }
     }
 }
+
+impl AbsoluteTime for Commit {
+    fn absolute_time(&self) -> String {
+        match self.timestamp {
+            Some(ts) => ts.absolute_time(),
+            _ => "".into(),
+        }
+    }
+}
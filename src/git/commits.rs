@@ -81,6 +81,66 @@ impl Display for Target {
     }
 }
 
+/// A stage of a conflicted file in the index: the file as it is in the merge
+/// base, in HEAD, or in the commit being merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Base,
+    Ours,
+    Theirs,
+}
+
+impl Stage {
+    /// Return the stage's number in the index
+    pub fn number(&self) -> u8 {
+        match self {
+            Stage::Base => 1,
+            Stage::Ours => 2,
+            Stage::Theirs => 3,
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Base => "base",
+            Stage::Ours => "ours",
+            Stage::Theirs => "theirs",
+        })
+    }
+}
+
+/// Two versions of a conflicted file to compare: two of its stages, or one
+/// of them and the file in the working tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSides {
+    pub path: String,
+    pub old: Stage,
+    /// the newer stage, or None for the working tree
+    pub new: Option<Stage>,
+}
+
+/// Every comparison of a conflicted file's versions, in the order they're
+/// cycled through
+const CONFLICT_SIDES: [(Stage, Option<Stage>); 6] = [
+    (Stage::Base, Some(Stage::Ours)),
+    (Stage::Base, Some(Stage::Theirs)),
+    (Stage::Ours, Some(Stage::Theirs)),
+    (Stage::Base, None),
+    (Stage::Ours, None),
+    (Stage::Theirs, None),
+];
+
+impl Display for ConflictSides {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.new {
+            Some(new) => write!(f, "{}..{}", self.old, new),
+            None => write!(f, "{}..worktree", self.old),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum DiffType {
     Diff,
@@ -98,6 +158,16 @@ pub struct DiffAction {
     /// The anchor commit -- the currently marked commit
     pub anchor: Option<GitRef>,
     diff_type: DiffType,
+    /// the anchor and target were swapped from their usual order
+    reversed: bool,
+    /// the branch whose tip is the target, so the diff can follow the branch
+    /// when it moves
+    branch: Option<String>,
+    /// the versions of a conflicted file being compared instead of commits
+    conflict: Option<ConflictSides>,
+    /// more commits shown after the target, one after another, when several
+    /// commits are shown together
+    series: Vec<GitRef>,
 }
 
 impl DiffAction {
@@ -116,6 +186,10 @@ impl DiffAction {
             target,
             anchor,
             diff_type,
+            reversed: false,
+            branch: None,
+            conflict: None,
+            series: vec![],
         }
     }
 
@@ -125,6 +199,34 @@ impl DiffAction {
             target,
             anchor: None,
             diff_type: DiffType::Show,
+            reversed: false,
+            branch: None,
+            conflict: None,
+            series: vec![],
+        }
+    }
+
+    /// Describe a show of several commits, one after another in the given
+    /// order, like `git show` prints them; returns None if there are no
+    /// commits
+    pub fn show_series(commits: &[GitRef]) -> Option<DiffAction> {
+        let (first, rest) = commits.split_first()?;
+        Some(DiffAction {
+            series: rest.to_vec(),
+            ..DiffAction::show(Target::Ref(first.clone()))
+        })
+    }
+
+    /// The commits being shown, in order, if this is a show of several
+    /// commits; otherwise the list is empty
+    pub fn series(&self) -> Vec<GitRef> {
+        match &self.target {
+            Target::Ref(r) if !self.series.is_empty() => {
+                let mut commits = vec![r.clone()];
+                commits.extend(self.series.iter().cloned());
+                commits
+            }
+            _ => vec![],
         }
     }
 
@@ -138,6 +240,39 @@ impl DiffAction {
         DiffAction::diff(Target::Staged, None)
     }
 
+    /// Describe a comparison of two versions of a conflicted file, which are
+    /// uncommitted like the unstaged changes
+    pub fn conflict(sides: ConflictSides) -> DiffAction {
+        DiffAction {
+            conflict: Some(sides),
+            ..DiffAction::unstaged()
+        }
+    }
+
+    /// The versions of a conflicted file being compared, if this is a
+    /// comparison of them
+    pub fn conflict_sides(&self) -> Option<&ConflictSides> {
+        self.conflict.as_ref()
+    }
+
+    /// Return the next comparison of a conflicted file's versions, starting
+    /// with base..ours if this isn't a comparison of the file's versions
+    pub fn next_conflict_sides(&self, path: &str) -> DiffAction {
+        let next = match &self.conflict {
+            Some(c) if c.path == path => CONFLICT_SIDES
+                .iter()
+                .position(|s| *s == (c.old, c.new))
+                .map_or(0, |i| (i + 1) % CONFLICT_SIDES.len()),
+            _ => 0,
+        };
+        let (old, new) = CONFLICT_SIDES[next];
+        DiffAction::conflict(ConflictSides {
+            path: path.into(),
+            old,
+            new,
+        })
+    }
+
     /// This action involves the staging area
     pub fn has_staged(&self) -> bool {
         self.target == Target::Staged
@@ -147,6 +282,124 @@ impl DiffAction {
     pub fn is_show(&self) -> bool {
         self.diff_type == DiffType::Show
     }
+
+    /// True if the action compares something to the working tree
+    pub fn diffs_worktree(&self) -> bool {
+        if self.conflict.is_some() {
+            return false;
+        }
+        match self.target {
+            Target::Unstaged => true,
+            Target::Staged => false,
+            Target::Ref(_) => !self.is_show() && self.anchor.is_none(),
+        }
+    }
+
+    /// Return the next view of the uncommitted changes, going from the staged
+    /// changes to the unstaged ones to all of them since HEAD, or None if the
+    /// action isn't about uncommitted changes
+    pub fn next_worktree_side(&self) -> Option<DiffAction> {
+        if self.conflict.is_some() {
+            return None;
+        }
+        let head = GitRef::new("HEAD");
+        match (&self.anchor, &self.target) {
+            (None, Target::Staged) => Some(DiffAction::unstaged()),
+            (None, Target::Unstaged) => {
+                Some(DiffAction::diff(Target::Unstaged, Some(head)))
+            }
+            (Some(a), Target::Unstaged) if *a == head => {
+                Some(DiffAction::staged())
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the diff in the other direction, if it's between two commits
+    pub fn swapped(&self) -> Option<DiffAction> {
+        match (&self.anchor, &self.target) {
+            (Some(a), Target::Ref(t)) if !self.is_show() => Some(DiffAction {
+                target: Target::Ref(a.clone()),
+                anchor: Some(t.clone()),
+                diff_type: DiffType::Diff,
+                reversed: !self.reversed,
+                branch: None,
+                conflict: None,
+                series: vec![],
+            }),
+            _ => None,
+        }
+    }
+
+    /// Turn a show of a commit into a diff from another commit to it; returns
+    /// None if the action isn't a show of a commit
+    pub fn anchored(&self, anchor: GitRef) -> Option<DiffAction> {
+        match &self.target {
+            Target::Ref(t) if self.is_commit_show() => {
+                Some(DiffAction::diff(Target::Ref(t.clone()), Some(anchor)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Is this a show of a single commit, rather than of uncommitted changes
+    /// or several commits
+    pub fn is_commit_show(&self) -> bool {
+        self.is_show()
+            && self.series.is_empty()
+            && matches!(self.target, Target::Ref(_))
+    }
+
+    /// Follow a branch whose tip is the target commit
+    pub fn on_branch(mut self, branch: Option<String>) -> DiffAction {
+        if !self.is_show() {
+            self.branch = branch;
+        }
+        self
+    }
+
+    /// The branch the target commit was the tip of
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// Return the same diff with a new target commit, for when the target's
+    /// branch has moved
+    pub fn moved_to(&self, target: GitRef) -> DiffAction {
+        DiffAction {
+            target: Target::Ref(target),
+            ..self.clone()
+        }
+    }
+
+    /// Describe the changes being shown, like "staged changes" or
+    /// "a1b2c3..d4e5f6"
+    pub fn describe(&self) -> String {
+        let description = self.describe_sides();
+        if self.reversed {
+            format!("{} (reversed)", description)
+        } else {
+            description
+        }
+    }
+
+    fn describe_sides(&self) -> String {
+        if let Some(conflict) = &self.conflict {
+            return format!("{} of {}", conflict, conflict.path);
+        }
+        if !self.series.is_empty() {
+            return format!("commits {}", self);
+        }
+        match (&self.anchor, &self.target) {
+            (_, Target::Ref(r)) if self.is_show() => format!("commit {}", r),
+            (None, Target::Staged) => "staged changes".into(),
+            (None, Target::Unstaged) => "unstaged changes".into(),
+            (None, Target::Ref(r)) => format!("changes since {}", r),
+            (Some(a), Target::Staged) => format!("{}..index", a),
+            (Some(a), Target::Unstaged) => format!("{}..worktree", a),
+            (Some(a), Target::Ref(r)) => format!("{}..{}", a, r),
+        }
+    }
 }
 
 /// Display the commits of the diff action
@@ -156,6 +409,11 @@ impl Display for DiffAction {
             Some(h) => {
                 write!(f, "{}", h)
             }
+            None if !self.series.is_empty() => {
+                let commits: Vec<String> =
+                    self.series().iter().map(|c| c.to_string()).collect();
+                write!(f, "{}", commits.join(", "))
+            }
             None => {
                 write!(f, "{}", self.target)
             }
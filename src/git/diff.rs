@@ -1,4 +1,4 @@
-use super::commits::DiffAction;
+use std::ops::Range;
 
 #[derive(Debug, Clone)]
 struct ChunkInfo {
@@ -54,25 +54,98 @@ impl DiffLine {
     }
 }
 
+/// A row of a side-by-side diff, referring to lines of the unified diff
+#[derive(Debug, Clone)]
+pub enum SplitLine {
+    /// a header line, shown across both columns
+    Meta(usize),
+    /// an old line and a new line shown next to each other; removed and added
+    /// lines are paired in order, with a blank on one side when a change has
+    /// more lines on the other
+    Pair(Option<usize>, Option<usize>),
+}
+
+impl SplitLine {
+    /// Return the unified diff lines in the row
+    pub fn lines(&self) -> Vec<usize> {
+        match self {
+            SplitLine::Meta(i) => vec![*i],
+            SplitLine::Pair(old, new) => {
+                let mut lines: Vec<usize> = old.iter().copied().collect();
+                if new != old {
+                    lines.extend(new);
+                }
+                lines
+            }
+        }
+    }
+}
+
+/// The whitespace problems on an added line, like those `git diff --check`
+/// reports
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WhitespaceErrors {
+    /// byte ranges of the line's content with trailing whitespace, or with
+    /// indentation that mixes spaces and tabs
+    pub ranges: Vec<Range<usize>>,
+    /// the line ends with a carriage return
+    pub crlf: bool,
+}
+
+impl WhitespaceErrors {
+    fn new(content: &str, crlf: bool) -> WhitespaceErrors {
+        let mut ranges = vec![];
+        let is_blank = |c: char| c == ' ' || c == '\t';
+
+        let text_end = content.trim_end_matches(is_blank).len();
+        let indent_end =
+            content.len() - content.trim_start_matches(is_blank).len();
+        let indent = &content[..indent_end.min(text_end)];
+        if indent.contains(' ') && indent.contains('\t') {
+            ranges.push(0..indent.len());
+        }
+        if text_end < content.len() {
+            ranges.push(text_end..content.len());
+        }
+
+        WhitespaceErrors { ranges, crlf }
+    }
+}
+
+/// A single hunk within a diff
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// the header lines of the file the hunk belongs to, from `diff` up to
+    /// the first hunk
+    pub header: Range<usize>,
+    /// the lines of the hunk, starting with its `@@` line
+    pub lines: Range<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileDiff {
-    /// file path
-    pub path: String,
-    /// old path (if renamed)
-    pub old_path: String,
-    /// commit range for diff
-    pub action: DiffAction,
     /// raw diff lines
     pub lines: Vec<String>,
     /// metadata about each line
     pub line_meta: Vec<DiffLine>,
+    /// the hunks in the diff
+    pub hunks: Vec<Hunk>,
+    /// the lines of each file in the diff, starting with its `diff` line
+    pub files: Vec<Range<usize>>,
+    /// the rows of a side-by-side rendering of the diff
+    pub split: Vec<SplitLine>,
+    /// the changed parts of each removed or added line that is paired with a
+    /// line on the other side, as byte ranges of the line's content
+    pub word_changes: Vec<Vec<Range<usize>>>,
+    /// the whitespace problems on each line; only added lines have any
+    pub whitespace_errors: Vec<WhitespaceErrors>,
+    /// git found the file to be binary, so the diff has no content lines
+    pub binary: bool,
 }
 
 impl FileDiff {
-    pub fn new(text: &str, action: &DiffAction) -> FileDiff {
+    pub fn new(text: &str) -> FileDiff {
         let mut chunk_info: Option<ChunkInfo> = None;
-        let mut path: &str = "";
-        let mut old_path: &str = "";
         let lines: Vec<String> = text.lines().map(|s| s.into()).collect();
         let line_meta: Vec<DiffLine> = lines
             .iter()
@@ -95,25 +168,453 @@ impl FileDiff {
                         }
                     }
                     DiffLine::new_line(s, old, new)
-                } else if s.starts_with("---") {
-                    old_path = s[4..].into();
-                    DiffLine::new_meta(s)
-                } else if s.starts_with("+++") {
-                    path = s[4..].into();
-                    DiffLine::new_meta(s)
                 } else {
                     DiffLine::new_meta(s)
                 }
             })
             .collect();
+        let hunks = find_hunks(&lines);
+        let files = find_files(&lines);
+        let split = split_lines(&line_meta);
+        let binary = lines.iter().any(|l| {
+            l.starts_with("Binary files ") || l.starts_with("GIT binary patch")
+        });
+
+        let mut word_changes: Vec<Vec<Range<usize>>> =
+            vec![vec![]; lines.len()];
+        for row in &split {
+            if let SplitLine::Pair(Some(old), Some(new)) = row {
+                if old == new {
+                    continue;
+                }
+                if let Some((old_changes, new_changes)) =
+                    word_diff(&lines[*old][1..], &lines[*new][1..])
+                {
+                    word_changes[*old] = old_changes;
+                    word_changes[*new] = new_changes;
+                }
+            }
+        }
+
+        // lines() drops carriage returns, so they're found in the raw text
+        let whitespace_errors = text
+            .split_terminator('\n')
+            .zip(&line_meta)
+            .map(|(raw, meta)| match meta {
+                DiffLine::Add(_) => {
+                    let crlf = raw.ends_with('\r');
+                    let content = raw.strip_suffix('\r').unwrap_or(raw);
+                    WhitespaceErrors::new(&content[1..], crlf)
+                }
+                _ => WhitespaceErrors::default(),
+            })
+            .collect();
 
         FileDiff {
-            path: path.into(),
-            old_path: old_path.into(),
-            action: action.clone(),
             lines,
             line_meta,
+            hunks,
+            files,
+            split,
+            word_changes,
+            whitespace_errors,
+            binary,
+        }
+    }
+
+    /// Return the hunk containing a line, or the first hunk after it
+    pub fn hunk_at(&self, line: usize) -> Option<&Hunk> {
+        self.hunks.iter().find(|h| line < h.lines.end)
+    }
+
+    /// Build a patch of the changes in the selected lines that can be given
+    /// to `git apply`, or None if there are no changes in the selection
+    ///
+    /// Changes outside the selection are left out of the patch. If `reverse`
+    /// is true, the patch is built to be applied with `--reverse`, so the
+    /// unselected additions are kept as context rather than the unselected
+    /// removals.
+    pub fn patch(
+        &self,
+        selection: &Range<usize>,
+        reverse: bool,
+    ) -> Option<String> {
+        let mut patch: Vec<String> = vec![];
+        let mut header: Option<&Range<usize>> = None;
+
+        let hunks = self.hunks.iter().filter(|h| {
+            h.lines.start < selection.end && selection.start < h.lines.end
+        });
+
+        for hunk in hunks {
+            let mut body: Vec<String> = vec![];
+            let mut old = 0;
+            let mut new = 0;
+            let mut changed = false;
+            let mut kept = false;
+
+            for i in hunk.lines.start + 1..hunk.lines.end {
+                let line = &self.lines[i];
+                let selected = selection.contains(&i);
+                let out = match (line.chars().next(), selected) {
+                    (Some('+'), true) => {
+                        changed = true;
+                        new += 1;
+                        Some(line.clone())
+                    }
+                    (Some('-'), true) => {
+                        changed = true;
+                        old += 1;
+                        Some(line.clone())
+                    }
+                    (Some('+'), false) if reverse => {
+                        old += 1;
+                        new += 1;
+                        Some(format!(" {}", &line[1..]))
+                    }
+                    (Some('-'), false) if !reverse => {
+                        old += 1;
+                        new += 1;
+                        Some(format!(" {}", &line[1..]))
+                    }
+                    (Some('+'), false) | (Some('-'), false) => None,
+                    (Some('\\'), _) => kept.then(|| line.clone()),
+                    _ => {
+                        old += 1;
+                        new += 1;
+                        Some(line.clone())
+                    }
+                };
+                kept = out.is_some();
+                body.extend(out);
+            }
+
+            if !changed {
+                continue;
+            }
+
+            if header != Some(&hunk.header) {
+                header = Some(&hunk.header);
+                patch.extend(self.lines[hunk.header.clone()].iter().cloned());
+            }
+
+            let start = ChunkInfo::new(&self.lines[hunk.lines.start]);
+            patch.push(format!(
+                "@@ -{},{} +{},{} @@",
+                start.old, old, start.new, new
+            ));
+            patch.extend(body);
+        }
+
+        if patch.is_empty() {
+            None
+        } else {
+            Some(patch.join("\n") + "\n")
         }
     }
 }
 
+/// Arrange diff lines into side-by-side rows
+fn split_lines(line_meta: &[DiffLine]) -> Vec<SplitLine> {
+    let mut rows: Vec<SplitLine> = vec![];
+    let mut dels: Vec<usize> = vec![];
+    let mut adds: Vec<usize> = vec![];
+
+    fn pair(
+        rows: &mut Vec<SplitLine>,
+        dels: &mut Vec<usize>,
+        adds: &mut Vec<usize>,
+    ) {
+        for i in 0..dels.len().max(adds.len()) {
+            rows.push(SplitLine::Pair(
+                dels.get(i).copied(),
+                adds.get(i).copied(),
+            ));
+        }
+        dels.clear();
+        adds.clear();
+    }
+
+    for (i, meta) in line_meta.iter().enumerate() {
+        match meta {
+            DiffLine::Del(_) => {
+                // a removal after additions starts a new change
+                if !adds.is_empty() {
+                    pair(&mut rows, &mut dels, &mut adds);
+                }
+                dels.push(i);
+            }
+            DiffLine::Add(_) => adds.push(i),
+            DiffLine::Same(_) => {
+                pair(&mut rows, &mut dels, &mut adds);
+                rows.push(SplitLine::Pair(Some(i), Some(i)));
+            }
+            _ => {
+                pair(&mut rows, &mut dels, &mut adds);
+                rows.push(SplitLine::Meta(i));
+            }
+        }
+    }
+    pair(&mut rows, &mut dels, &mut adds);
+
+    rows
+}
+
+/// The largest product of two lines' word counts that will be compared word by
+/// word
+const MAX_WORD_DIFF: usize = 40_000;
+
+/// Split a line into words, runs of whitespace, and single punctuation
+/// characters, returning the byte range of each
+fn tokenize(line: &str) -> Vec<Range<usize>> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            1
+        } else if c.is_whitespace() {
+            2
+        } else {
+            0
+        }
+    };
+
+    let mut tokens: Vec<Range<usize>> = vec![];
+    let mut prev = None;
+    for (i, c) in line.char_indices() {
+        let cls = class(c);
+        match tokens.last_mut() {
+            Some(token) if cls != 0 && prev == Some(cls) => {
+                token.end = i + c.len_utf8()
+            }
+            _ => tokens.push(i..i + c.len_utf8()),
+        }
+        prev = Some(cls);
+    }
+
+    tokens
+}
+
+/// The byte ranges of the changed words in an old line and a new line
+type WordChanges = (Vec<Range<usize>>, Vec<Range<usize>>);
+
+/// Find the words that differ between two lines, returning the byte ranges
+/// of the changes in each line, or None if the lines have nothing but
+/// whitespace in common
+fn word_diff(old: &str, new: &str) -> Option<WordChanges> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    let n = old_tokens.len();
+    let m = new_tokens.len();
+
+    if n * m > MAX_WORD_DIFF {
+        return None;
+    }
+
+    let old_word = |i: usize| &old[old_tokens[i].clone()];
+    let new_word = |j: usize| &new[new_tokens[j].clone()];
+
+    // lengths of the longest common subsequences of the token suffixes
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_word(i) == new_word(j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_changed = vec![true; n];
+    let mut new_changed = vec![true; m];
+    let mut common = false;
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_word(i) == new_word(j) {
+            old_changed[i] = false;
+            new_changed[j] = false;
+            common = common || !old_word(i).trim().is_empty();
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    if !common {
+        return None;
+    }
+
+    Some((
+        changed_ranges(&old_tokens, &old_changed),
+        changed_ranges(&new_tokens, &new_changed),
+    ))
+}
+
+/// Merge adjacent changed tokens into ranges
+fn changed_ranges(
+    tokens: &[Range<usize>],
+    changed: &[bool],
+) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for (token, _) in tokens.iter().zip(changed).filter(|(_, c)| **c) {
+        match ranges.last_mut() {
+            Some(range) if range.end == token.start => range.end = token.end,
+            _ => ranges.push(token.clone()),
+        }
+    }
+    ranges
+}
+
+/// Find the files in a list of diff lines
+fn find_files(lines: &[String]) -> Vec<Range<usize>> {
+    let mut files: Vec<Range<usize>> = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("diff ") {
+            if let Some(file) = files.last_mut() {
+                file.end = i;
+            }
+            files.push(i..lines.len());
+        }
+    }
+    files
+}
+
+/// Find the hunks in a list of diff lines
+fn find_hunks(lines: &[String]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = vec![];
+    let mut header = 0..0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let is_start = line.starts_with("diff ");
+        let is_hunk = line.starts_with("@@");
+
+        if is_start || is_hunk {
+            if let Some(hunk) = hunks.last_mut() {
+                hunk.lines.end = hunk.lines.end.min(i);
+            }
+        }
+
+        if is_start {
+            header = i..i;
+        } else if is_hunk {
+            if header.is_empty() {
+                header.end = i;
+            }
+            hunks.push(Hunk {
+                header: header.clone(),
+                lines: i..lines.len(),
+            });
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{git_apply_cached, testing::TestRepo, with_runner};
+
+    const DIFF: &str = concat!(
+        "diff --git a/a.txt b/a.txt\n",
+        "index 1111111..2222222 100644\n",
+        "--- a/a.txt\n",
+        "+++ b/a.txt\n",
+        "@@ -1,4 +1,4 @@\n",
+        " one\n",
+        "-two\n",
+        "+TWO\n",
+        " three\n",
+        "-four\n",
+        "+FOUR\n",
+    );
+
+    const HEADER: &str = concat!(
+        "diff --git a/a.txt b/a.txt\n",
+        "index 1111111..2222222 100644\n",
+        "--- a/a.txt\n",
+        "+++ b/a.txt\n",
+    );
+
+    #[test]
+    fn patch_keeps_unselected_removals_as_context() {
+        let diff = FileDiff::new(DIFF);
+        assert_eq!(
+            diff.patch(&(6..8), false).unwrap(),
+            HEADER.to_string()
+                + "@@ -1,4 +1,4 @@\n one\n-two\n+TWO\n three\n four\n"
+        );
+        assert_eq!(
+            diff.patch(&(9..10), false).unwrap(),
+            HEADER.to_string() + "@@ -1,4 +1,3 @@\n one\n two\n three\n-four\n"
+        );
+    }
+
+    #[test]
+    fn reversed_patch_keeps_unselected_additions_as_context() {
+        let diff = FileDiff::new(DIFF);
+        assert_eq!(
+            diff.patch(&(6..8), true).unwrap(),
+            HEADER.to_string()
+                + "@@ -1,4 +1,4 @@\n one\n-two\n+TWO\n three\n FOUR\n"
+        );
+    }
+
+    #[test]
+    fn patch_without_changes_is_none() {
+        let diff = FileDiff::new(DIFF);
+        assert_eq!(diff.patch(&(5..6), false), None);
+        assert_eq!(diff.patch(&(0..4), false), None);
+    }
+
+    #[test]
+    fn patch_keeps_missing_newline_markers_of_kept_lines() {
+        let diff = FileDiff::new(concat!(
+            "diff --git a/a.txt b/a.txt\n",
+            "--- a/a.txt\n",
+            "+++ b/a.txt\n",
+            "@@ -1,2 +1,2 @@\n",
+            " one\n",
+            "-two\n",
+            "\\ No newline at end of file\n",
+            "+TWO\n",
+            "\\ No newline at end of file\n",
+        ));
+        assert_eq!(
+            diff.patch(&(5..6), false).unwrap(),
+            concat!(
+                "diff --git a/a.txt b/a.txt\n",
+                "--- a/a.txt\n",
+                "+++ b/a.txt\n",
+                "@@ -1,2 +1,1 @@\n",
+                " one\n",
+                "-two\n",
+                "\\ No newline at end of file\n",
+            )
+        );
+    }
+
+    #[test]
+    fn patches_stage_and_unstage_selected_lines() {
+        let repo = TestRepo::new("stage-lines");
+        repo.commit(
+            &[("a.txt", "one\ntwo\nthree\nfour\n")],
+            "Add",
+            "1700000000",
+        );
+        repo.write("a.txt", "one\nTWO\nthree\nFOUR\n");
+
+        let unstaged = FileDiff::new(&repo.git(&["diff"]));
+        let patch = unstaged.patch(&(6..8), false).unwrap();
+        with_runner(repo.runner(), || git_apply_cached(&patch, false)).unwrap();
+        assert_eq!(repo.git(&["show", ":a.txt"]), "one\nTWO\nthree\nfour\n");
+
+        let staged = FileDiff::new(&repo.git(&["diff", "--cached"]));
+        let patch = staged.patch(&(0..staged.lines.len()), true).unwrap();
+        with_runner(repo.runner(), || git_apply_cached(&patch, true)).unwrap();
+        assert_eq!(repo.git(&["diff", "--cached"]), "");
+        assert_eq!(repo.read("a.txt"), "one\nTWO\nthree\nFOUR\n");
+    }
+}
@@ -0,0 +1,139 @@
+use crate::git::{git_remote_url, git_resolve_commit, GitRef, FULL_HASH_LEN};
+
+/// The remote whose web pages are opened
+const REMOTE: &str = "origin";
+
+/// A website that hosts git repos, which has pages for commits and files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl Forge {
+    /// Guess the forge from a host name, which works for self-hosted
+    /// instances that are named after their forge, like gitlab.example.com
+    fn from_host(host: &str) -> Option<Forge> {
+        let host = host.to_lowercase();
+        if host.contains("github") {
+            Some(Forge::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Forge::GitLab)
+        } else if host.contains("bitbucket") {
+            Some(Forge::Bitbucket)
+        } else {
+            None
+        }
+    }
+}
+
+/// A repo on a forge, from the URL of a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeRepo {
+    forge: Forge,
+    /// the repo's web page, like https://github.com/owner/repo
+    base: String,
+}
+
+impl ForgeRepo {
+    /// Parse a remote URL, in any of the forms git accepts for a hosted repo:
+    /// `https://[user@]host/owner/repo.git`,
+    /// `ssh://[user@]host[:port]/owner/repo.git`, or
+    /// `[user@]host:owner/repo.git`
+    pub fn parse(url: &str) -> Option<ForgeRepo> {
+        let url = url.trim();
+        let (host, path) = match url.split_once("://") {
+            Some((_, rest)) => {
+                let (authority, path) = rest.split_once('/')?;
+                let host = authority.rsplit('@').next()?;
+                // a port is for git, not for the web page
+                let host = host.split(':').next()?;
+                (host, path)
+            }
+            None => {
+                let (authority, path) = url.split_once(':')?;
+                if authority.contains('/') {
+                    // a local path
+                    return None;
+                }
+                (authority.rsplit('@').next()?, path)
+            }
+        };
+
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        if host.is_empty() || path.is_empty() {
+            return None;
+        }
+        Some(ForgeRepo {
+            forge: Forge::from_host(host)?,
+            base: format!("https://{}/{}", host, path),
+        })
+    }
+
+    /// Return the URL of a commit's page
+    pub fn commit_url(&self, hash: &str) -> String {
+        match self.forge {
+            Forge::GitHub => format!("{}/commit/{}", self.base, hash),
+            Forge::GitLab => format!("{}/-/commit/{}", self.base, hash),
+            Forge::Bitbucket => format!("{}/commits/{}", self.base, hash),
+        }
+    }
+
+    /// Return the URL of a file as it is in a commit, scrolled to a line
+    pub fn file_url(&self, hash: &str, path: &str, line: u32) -> String {
+        let path = encode_path(path);
+        match self.forge {
+            Forge::GitHub => {
+                format!("{}/blob/{}/{}#L{}", self.base, hash, path, line)
+            }
+            Forge::GitLab => {
+                format!("{}/-/blob/{}/{}#L{}", self.base, hash, path, line)
+            }
+            Forge::Bitbucket => {
+                format!("{}/src/{}/{}#lines-{}", self.base, hash, path, line)
+            }
+        }
+    }
+}
+
+/// Percent-encode the characters of a path that can't be used in a URL as
+/// they are, leaving its separators alone
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'~'
+            | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Return the URL of a commit's page on the forge that hosts the origin
+/// remote, or of a file in the commit at a line
+pub fn web_url(
+    commit: &GitRef,
+    file: Option<(&str, u32)>,
+) -> Result<String, String> {
+    let url = git_remote_url(REMOTE)
+        .ok_or_else(|| format!("There's no {} remote", REMOTE))?;
+    let repo = ForgeRepo::parse(&url).ok_or_else(|| {
+        format!("{} isn't on GitHub, GitLab, or Bitbucket", url)
+    })?;
+    // forges need the full hash to find a commit reliably
+    let hash = git_resolve_commit(&commit.to_string(), FULL_HASH_LEN)
+        .ok_or_else(|| format!("{} isn't a commit", commit))?;
+    Ok(match file {
+        Some((path, line)) => repo.file_url(&hash.to_string(), path, line),
+        None => repo.commit_url(&hash.to_string()),
+    })
+}
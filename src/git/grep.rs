@@ -0,0 +1,42 @@
+/// A line of a file found by `git grep`, either a match or context around one
+#[derive(Debug, Clone)]
+pub struct GrepLine {
+    /// the file's path from the repo root
+    pub path: String,
+    pub line: u32,
+    pub text: String,
+    pub is_match: bool,
+}
+
+impl GrepLine {
+    /// Parse the output of `git grep --null --line-number` for a commit; with
+    /// `--column`, only matches have a column, which is how they're told
+    /// apart from context lines
+    pub fn parse_all(
+        output: &str,
+        commit: &str,
+        columns: bool,
+    ) -> Vec<GrepLine> {
+        let prefix = format!("{}:", commit);
+        output
+            .lines()
+            .filter_map(|line| {
+                let line = line.strip_prefix(&prefix).unwrap_or(line);
+                let parts: Vec<&str> = line.splitn(4, '\0').collect();
+                let (path, number, text, is_match) = match parts[..] {
+                    [path, number, _, text] if columns => {
+                        (path, number, text, true)
+                    }
+                    [path, number, text] => (path, number, text, !columns),
+                    _ => return None,
+                };
+                Some(GrepLine {
+                    path: path.into(),
+                    line: number.parse().ok()?,
+                    text: text.into(),
+                    is_match,
+                })
+            })
+            .collect()
+    }
+}
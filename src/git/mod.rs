@@ -1,11 +1,34 @@
+mod blame;
 mod commit;
 mod commits;
 mod diff;
+mod forge;
+mod grep;
+mod path;
+mod rangediff;
+mod runner;
+#[cfg(test)]
+pub mod testing;
 mod util;
+mod version;
 mod stat;
 
-pub use commit::Commit;
+pub use blame::BlameLine;
+pub use commit::{
+    author_format, set_author_format, AuthorFormat, Commit, CommitDetails,
+};
 pub use commits::{DiffAction, GitRef, Target};
-pub use diff::{DiffLine, FileDiff};
+pub use diff::{DiffLine, FileDiff, SplitLine, WhitespaceErrors};
+pub use forge::web_url;
+pub use grep::GrepLine;
+pub use path::{path_within, same_path};
+pub use rangediff::{RangeDiffPair, RangeDiffStatus};
+pub use runner::{
+    inherit_runner, init_runner, runner_failure, set_runner, RecordingRunner,
+    ReplayRunner, SystemRunner,
+};
+#[cfg(test)]
+pub use runner::with_runner;
 pub use util::*;
+pub use version::*;
 pub use stat::Stat;
@@ -0,0 +1,191 @@
+use std::{
+    borrow::Cow,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// True if paths on this system are compared the way Windows compares them:
+/// either separator may be used, and case doesn't matter
+pub const WINDOWS_PATHS: bool = cfg!(windows);
+
+/// Remove the verbatim prefix that canonicalizing adds to Windows paths, as in
+/// `\\?\C:\repo` or `\\?\UNC\server\share\repo`, since git and many other
+/// programs don't understand it
+pub fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        Cow::Owned(format!(r"\\{}", rest))
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        Cow::Borrowed(rest)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Canonicalize a path, leaving off any verbatim prefix
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let path = path.canonicalize()?;
+    Ok(match path.to_str() {
+        Some(text) => PathBuf::from(strip_verbatim(text).as_ref()),
+        None => path,
+    })
+}
+
+/// Join a path that git printed, relative to the repo root and separated with
+/// `/`, onto a directory
+pub fn join_git_path(dir: &Path, path: &str) -> PathBuf {
+    let mut joined = dir.to_path_buf();
+    joined.extend(path.split('/').filter(|part| !part.is_empty()));
+    joined
+}
+
+/// Turn a pathspec relative to a directory, given as its path from the repo
+/// root ending with `/`, into one relative to the repo root, so that it means
+/// the same thing to git commands run anywhere in the repo
+///
+/// Pathspecs that use magic, like `:(glob)*.rs`, are left alone.
+pub fn top_pathspec(prefix: &str, spec: &str) -> String {
+    if spec.starts_with(':') {
+        return spec.into();
+    }
+    let mut parts: Vec<&str> = vec![];
+    for part in prefix.split('/').chain(spec.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    let dir = if spec.ends_with('/') && !parts.is_empty() {
+        "/"
+    } else {
+        ""
+    };
+    format!(":(top){}{}", parts.join("/"), dir)
+}
+
+/// Return a form of a path that can be compared with other paths: with `/`
+/// separators and no trailing separator, and for Windows paths, without a
+/// verbatim prefix and in lowercase
+pub fn comparable_path(path: &Path, windows: bool) -> String {
+    let text = path.to_string_lossy();
+    if !windows {
+        return match text.trim_end_matches('/') {
+            "" => text.into(),
+            trimmed => trimmed.into(),
+        };
+    }
+    let text = strip_verbatim(&text).replace('\\', "/").to_lowercase();
+    match text.trim_end_matches('/') {
+        "" => text,
+        trimmed => trimmed.into(),
+    }
+}
+
+/// True if two paths name the same file, going by their text
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    comparable_path(a, WINDOWS_PATHS) == comparable_path(b, WINDOWS_PATHS)
+}
+
+/// True if a path is a directory or is inside it, going by their text
+pub fn path_within(path: &Path, dir: &Path) -> bool {
+    comparable_within(
+        &comparable_path(path, WINDOWS_PATHS),
+        &comparable_path(dir, WINDOWS_PATHS),
+    )
+}
+
+/// path_within for paths that comparable_path has already normalized
+fn comparable_within(path: &str, dir: &str) -> bool {
+    path == dir
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/') || dir.ends_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_verbatim_removes_disk_and_unc_prefixes() {
+        assert_eq!(strip_verbatim(r"\\?\C:\repo"), r"C:\repo");
+        assert_eq!(strip_verbatim(r"\\?\C:\"), r"C:\");
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\repo"),
+            r"\\server\share\repo"
+        );
+        assert_eq!(strip_verbatim(r"\\server\share"), r"\\server\share");
+        assert_eq!(strip_verbatim("/home/ann/repo"), "/home/ann/repo");
+    }
+
+    #[test]
+    fn comparable_path_normalizes_windows_paths() {
+        let compare = |text: &str| comparable_path(Path::new(text), true);
+        assert_eq!(compare(r"\\?\C:\Repo\Src\"), "c:/repo/src");
+        assert_eq!(compare(r"c:\repo\src"), compare(r"C:\REPO\SRC"));
+        assert_eq!(compare(r"C:\repo/src\main.rs"), "c:/repo/src/main.rs");
+        assert_eq!(compare(r"\\?\C:\"), "c:");
+    }
+
+    #[test]
+    fn comparable_path_keeps_unix_paths_as_they_are() {
+        let compare = |text: &str| comparable_path(Path::new(text), false);
+        assert_eq!(compare("/repo/Src/"), "/repo/Src");
+        assert_eq!(compare(r"/repo/a\b"), r"/repo/a\b");
+        assert_eq!(compare("/"), "/");
+    }
+
+    #[test]
+    fn top_pathspec_resolves_against_the_prefix() {
+        assert_eq!(top_pathspec("src/", "main.rs"), ":(top)src/main.rs");
+        assert_eq!(top_pathspec("src/git/", "../app.rs"), ":(top)src/app.rs");
+        assert_eq!(top_pathspec("src/", "./views/"), ":(top)src/views/");
+        assert_eq!(top_pathspec("src/", ".."), ":(top)");
+        assert_eq!(top_pathspec("", "README.md"), ":(top)README.md");
+        assert_eq!(top_pathspec("src/", ":(glob)*.rs"), ":(glob)*.rs");
+    }
+
+    #[test]
+    fn join_git_path_splits_on_slashes() {
+        let joined = join_git_path(Path::new("/repo"), "src/git/path.rs");
+        assert_eq!(
+            joined,
+            Path::new("/repo").join("src").join("git").join("path.rs")
+        );
+        assert_eq!(
+            join_git_path(Path::new("/repo"), "dir/"),
+            Path::new("/repo/dir")
+        );
+    }
+
+    #[test]
+    fn same_path_ignores_trailing_separators() {
+        assert!(same_path(Path::new("/a/b/"), Path::new("/a/b")));
+        assert!(!same_path(Path::new("/a/b"), Path::new("/a/bc")));
+    }
+
+    #[test]
+    fn path_within_matches_whole_components() {
+        assert!(path_within(Path::new("/a/b"), Path::new("/a/b")));
+        assert!(path_within(Path::new("/a/b/c"), Path::new("/a/b/")));
+        assert!(path_within(Path::new("/a/b"), Path::new("/")));
+        assert!(!path_within(Path::new("/a/bc"), Path::new("/a/b")));
+        assert!(!path_within(Path::new("/a"), Path::new("/a/b")));
+    }
+
+    #[test]
+    fn path_within_handles_windows_paths() {
+        let within = |path: &str, dir: &str| {
+            comparable_within(
+                &comparable_path(Path::new(path), true),
+                &comparable_path(Path::new(dir), true),
+            )
+        };
+        assert!(within(r"C:\Repo\src\main.rs", r"\\?\c:\repo"));
+        assert!(within(r"c:\repo", r"C:\REPO\"));
+        assert!(!within(r"C:\a\bc", r"C:\a\b"));
+        assert!(!within(r"D:\repo\src", r"C:\repo"));
+    }
+}
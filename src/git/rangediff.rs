@@ -0,0 +1,101 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::commits::GitRef;
+
+/// A pair's line, whose numbers are right-aligned, so pairs before the 10th
+/// start with spaces when a range has 10 or more commits
+static PAIR_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?:\d+|-):\s+(\S+) ([=!<>]) ",
+        r"\s*(?:\d+|-):\s+(\S+) ?(.*)$"
+    ))
+    .unwrap()
+});
+
+/// How a commit in the old range of a range-diff compares to the new range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeDiffStatus {
+    /// both commits make the same change
+    Same,
+    /// the commits correspond, but their changes differ
+    Changed,
+    /// the commit is only in the old range
+    Removed,
+    /// the commit is only in the new range
+    Added,
+}
+
+impl RangeDiffStatus {
+    /// Return the symbol `git range-diff` marks the status with
+    pub fn symbol(&self) -> char {
+        match self {
+            RangeDiffStatus::Same => '=',
+            RangeDiffStatus::Changed => '!',
+            RangeDiffStatus::Removed => '<',
+            RangeDiffStatus::Added => '>',
+        }
+    }
+}
+
+/// A commit of the old range of a range-diff paired with the corresponding
+/// commit of the new range; added and removed commits have no partner
+#[derive(Debug, Clone)]
+pub struct RangeDiffPair {
+    pub status: RangeDiffStatus,
+    pub old: Option<GitRef>,
+    pub new: Option<GitRef>,
+    pub subject: String,
+    /// the diff of the two commits' patches, for changed commits
+    pub diff: Vec<String>,
+}
+
+impl RangeDiffPair {
+    /// Parse the output of `git range-diff --no-color`, where each pair's
+    /// line looks like `1:  abc1234 ! 1:  def5678 subject` and is followed by
+    /// the indented diff of the commits' patches
+    pub fn parse_all(output: &str) -> Vec<RangeDiffPair> {
+        let mut pairs: Vec<RangeDiffPair> = vec![];
+        for line in output.lines() {
+            if let Some(pair) = RangeDiffPair::parse(line) {
+                pairs.push(pair);
+            } else if let Some(pair) = pairs.last_mut() {
+                // a pair's diff is indented by 4 spaces
+                if line.is_empty() {
+                    pair.diff.push(String::new());
+                } else if let Some(line) = line.strip_prefix("    ") {
+                    pair.diff.push(line.into());
+                }
+            }
+        }
+        for pair in pairs.iter_mut() {
+            while pair.diff.last().is_some_and(|l| l.trim().is_empty()) {
+                pair.diff.pop();
+            }
+        }
+        pairs
+    }
+
+    fn parse(line: &str) -> Option<RangeDiffPair> {
+        let caps = PAIR_LINE.captures(line)?;
+        let field = |i: usize| caps.get(i).map_or("", |m| m.as_str());
+        let (old, new) = (field(1), field(3));
+        let status = match field(2) {
+            "=" => RangeDiffStatus::Same,
+            "!" => RangeDiffStatus::Changed,
+            "<" => RangeDiffStatus::Removed,
+            ">" => RangeDiffStatus::Added,
+            _ => return None,
+        };
+        // a commit that's missing from a range is shown as dashes
+        let commit =
+            |hash: &str| (!hash.starts_with('-')).then(|| GitRef::new(hash));
+        Some(RangeDiffPair {
+            status,
+            old: commit(old),
+            new: commit(new),
+            subject: field(4).into(),
+            diff: vec![],
+        })
+    }
+}
@@ -0,0 +1,753 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ffi::OsStr,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+    thread,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::{config::GitConfig, string::base64_encode, time::frozen_clock};
+
+/// The output of a git invocation
+#[derive(Debug, Clone, Default)]
+pub struct GitOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A git invocation that has been built but not yet run
+#[derive(Debug, Clone, Default)]
+pub struct GitCommand {
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    stdin: Option<String>,
+    base64: bool,
+}
+
+impl GitCommand {
+    pub fn new() -> GitCommand {
+        GitCommand::default()
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut GitCommand {
+        self.args.push(arg.as_ref().to_string_lossy().into());
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut GitCommand {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set an environment variable for the command
+    pub fn env(
+        &mut self,
+        key: &str,
+        value: impl AsRef<OsStr>,
+    ) -> &mut GitCommand {
+        let value = value.as_ref().to_string_lossy().into();
+        self.env.push((key.into(), value));
+        self
+    }
+
+    /// Provide text to be written to the command's standard input
+    pub fn stdin(&mut self, input: impl Into<String>) -> &mut GitCommand {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// Return the command's standard output base64 encoded, for output that
+    /// isn't text, like the contents of a blob
+    pub fn base64_stdout(&mut self) -> &mut GitCommand {
+        self.base64 = true;
+        self
+    }
+
+    /// Run the command with the active runner
+    pub fn output(&self) -> io::Result<GitOutput> {
+        #[cfg(test)]
+        if let Some(runner) = TEST_RUNNER.with(|r| r.borrow().clone()) {
+            return runner.run(self);
+        }
+        runner().run(self)
+    }
+}
+
+/// Executes git commands
+///
+/// This is the boundary between building a git command and running it, which
+/// allows git's behavior to be recorded or replaced.
+pub trait GitRunner: Send + Sync {
+    fn run(&self, cmd: &GitCommand) -> io::Result<GitOutput>;
+
+    /// Return why the runner can't answer commands any more, if it can't
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Environment variables that keep git's output predictable, regardless of the
+/// user's environment
+const DEFAULT_ENV: [(&str, &str); 5] = [
+    ("GIT_PAGER", "cat"),
+    ("PAGER", "cat"),
+    // commands that would open an editor, like `rebase --continue`, keep the
+    // message git prepared instead
+    ("GIT_EDITOR", ":"),
+    ("LC_ALL", "C"),
+    ("GIT_TERMINAL_PROMPT", "0"),
+];
+
+/// Config that keeps git's output predictable, passed with `-c` so config the
+/// user sets in the environment still applies
+const DEFAULT_CONFIG: [&str; 1] = ["color.ui=never"];
+
+/// Environment variables that point git at a repo other than the one
+/// containing the working directory
+const REPO_ENV: [&str; 4] = [
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_INDEX_FILE",
+    "GIT_NAMESPACE",
+];
+
+/// Runs commands with the installed git
+pub struct SystemRunner {
+    env: Vec<(String, String)>,
+    inherit_repo_env: bool,
+    /// where commands that don't name a directory run, instead of the
+    /// current one
+    dir: Option<PathBuf>,
+}
+
+impl SystemRunner {
+    pub fn new(config: &GitConfig) -> SystemRunner {
+        let mut env: BTreeMap<String, String> = DEFAULT_ENV
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        env.extend(config.env.clone());
+
+        SystemRunner {
+            env: env.into_iter().collect(),
+            inherit_repo_env: config.inherit_repo_env,
+            dir: None,
+        }
+    }
+
+    /// Run commands that don't name a directory in this one
+    #[cfg(test)]
+    pub fn in_dir(mut self, dir: impl Into<PathBuf>) -> SystemRunner {
+        self.dir = Some(dir.into());
+        self
+    }
+}
+
+impl GitRunner for SystemRunner {
+    fn run(&self, cmd: &GitCommand) -> io::Result<GitOutput> {
+        let mut command = Command::new("git");
+        for config in DEFAULT_CONFIG {
+            command.arg("-c").arg(config);
+        }
+        command.args(&cmd.args);
+
+        if !self.inherit_repo_env {
+            REPO_ENV.iter().for_each(|k| {
+                command.env_remove(k);
+            });
+        }
+        if let Some(now) = frozen_clock() {
+            // git takes the current time from this, which keeps the relative
+            // dates in its output in step
+            command.env("GIT_TEST_DATE_NOW", now.timestamp().to_string());
+        }
+        command.envs(self.env.iter().map(|(k, v)| (k, v)));
+        command.envs(cmd.env.iter().map(|(k, v)| (k, v)));
+
+        if let Some(dir) = cmd.cwd.as_ref().or(self.dir.as_ref()) {
+            command.current_dir(dir);
+        }
+
+        let output = match &cmd.stdin {
+            Some(input) => {
+                let mut child = command
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                let stdin = child.stdin.take();
+                // git can fill the output pipe before it has read all of its
+                // input, so the input is written while the output is read;
+                // the pipe closes when the writer is done with it
+                let (written, output) = thread::scope(|s| {
+                    let writer = s.spawn(move || match stdin {
+                        Some(mut stdin) => stdin.write_all(input.as_bytes()),
+                        None => Ok(()),
+                    });
+                    let output = child.wait_with_output();
+                    (writer.join().unwrap(), output)
+                });
+                let output = output?;
+                // git may exit without reading everything, which its status
+                // reports
+                match written {
+                    Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                        return Err(e)
+                    }
+                    _ => output,
+                }
+            }
+            None => command.output()?,
+        };
+
+        Ok(GitOutput {
+            success: output.status.success(),
+            stdout: if cmd.base64 {
+                base64_encode(&output.stdout)
+            } else {
+                String::from_utf8_lossy(&output.stdout).into()
+            },
+            stderr: String::from_utf8_lossy(&output.stderr).into(),
+        })
+    }
+}
+
+/// What a recorded output is looked up by: a command's arguments, the
+/// directory it ran in, its own environment, and a hash of its input
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Invocation {
+    args: Vec<String>,
+    cwd: Option<String>,
+    env: Vec<String>,
+    stdin: Option<String>,
+}
+
+impl Invocation {
+    fn of(cmd: &GitCommand) -> Invocation {
+        Invocation {
+            args: cmd.args.clone(),
+            cwd: cmd.cwd.as_ref().map(|d| d.to_string_lossy().into()),
+            env: cmd
+                .env
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect(),
+            stdin: cmd.stdin.as_deref().map(input_hash),
+        }
+    }
+}
+
+/// Return a hash of a command's input that's the same in every build, unlike
+/// the standard library's hashers; this is 64-bit FNV-1a
+fn input_hash(input: &str) -> String {
+    let hash = input.bytes().fold(0xcbf29ce484222325_u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Runs commands with another runner, writing each invocation and its output
+/// to a log that a ReplayRunner can read
+pub struct RecordingRunner {
+    inner: Box<dyn GitRunner>,
+    log: Mutex<Box<dyn Write + Send>>,
+}
+
+impl RecordingRunner {
+    pub fn new(
+        inner: Box<dyn GitRunner>,
+        log: Box<dyn Write + Send>,
+    ) -> RecordingRunner {
+        RecordingRunner {
+            inner,
+            log: Mutex::new(log),
+        }
+    }
+}
+
+impl GitRunner for RecordingRunner {
+    fn run(&self, cmd: &GitCommand) -> io::Result<GitOutput> {
+        let output = self.inner.run(cmd)?;
+
+        let invocation = Invocation::of(cmd);
+        let mut record = format!(
+            "$ {}\n",
+            invocation
+                .args
+                .iter()
+                .map(|a| escape(a))
+                .collect::<Vec<_>>()
+                .join("\t")
+        );
+        if let Some(cwd) = &invocation.cwd {
+            record.push_str(&format!("@ {}\n", escape(cwd)));
+        }
+        for var in &invocation.env {
+            record.push_str(&format!("= {}\n", escape(var)));
+        }
+        if let Some(hash) = &invocation.stdin {
+            record.push_str(&format!("< {}\n", hash));
+        }
+        record.push_str(&format!("? {}\n", output.success));
+        // outputs are escaped like arguments, so they're replayed exactly
+        record.push_str(&format!("1 {}\n", escape(&output.stdout)));
+        record.push_str(&format!("2 {}\n", escape(&output.stderr)));
+
+        let mut log = self.log.lock().unwrap();
+        log.write_all(record.as_bytes())?;
+        log.flush()?;
+
+        Ok(output)
+    }
+}
+
+/// Answers commands from a log written by a RecordingRunner, without running
+/// git
+///
+/// Outputs for the same arguments, directory, and input are returned in the
+/// order they were recorded; the last one is repeated once the others have
+/// been used.
+pub struct ReplayRunner {
+    outputs: Mutex<HashMap<Invocation, VecDeque<GitOutput>>>,
+    /// the recorded commands that haven't been run yet
+    unplayed: Mutex<HashSet<Invocation>>,
+    /// the first command that was run without having been recorded
+    missing: Mutex<Option<String>>,
+}
+
+impl ReplayRunner {
+    pub fn new(log: impl BufRead) -> io::Result<ReplayRunner> {
+        let mut outputs: HashMap<Invocation, VecDeque<GitOutput>> =
+            HashMap::new();
+        let mut current: Option<(Invocation, GitOutput)> = None;
+
+        for line in log.lines() {
+            let line = line?;
+            let (kind, value) = line.split_at(line.len().min(2));
+            match kind {
+                "$ " => {
+                    if let Some((invocation, output)) = current.take() {
+                        outputs
+                            .entry(invocation)
+                            .or_default()
+                            .push_back(output);
+                    }
+                    let invocation = Invocation {
+                        args: value.split('\t').map(unescape).collect(),
+                        ..Invocation::default()
+                    };
+                    current = Some((invocation, GitOutput::default()));
+                }
+                "@ " => {
+                    if let Some((invocation, _)) = &mut current {
+                        invocation.cwd = Some(unescape(value));
+                    }
+                }
+                "= " => {
+                    if let Some((invocation, _)) = &mut current {
+                        invocation.env.push(unescape(value));
+                    }
+                }
+                "< " => {
+                    if let Some((invocation, _)) = &mut current {
+                        invocation.stdin = Some(value.into());
+                    }
+                }
+                "? " => {
+                    if let Some((_, output)) = &mut current {
+                        output.success = value == "true";
+                    }
+                }
+                "1 " => {
+                    if let Some((_, output)) = &mut current {
+                        output.stdout = unescape(value);
+                    }
+                }
+                "2 " => {
+                    if let Some((_, output)) = &mut current {
+                        output.stderr = unescape(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((invocation, output)) = current.take() {
+            outputs.entry(invocation).or_default().push_back(output);
+        }
+
+        Ok(ReplayRunner {
+            unplayed: Mutex::new(outputs.keys().cloned().collect()),
+            outputs: Mutex::new(outputs),
+            missing: Mutex::new(None),
+        })
+    }
+}
+
+impl GitRunner for ReplayRunner {
+    fn run(&self, cmd: &GitCommand) -> io::Result<GitOutput> {
+        let invocation = Invocation::of(cmd);
+        self.unplayed.lock().unwrap().remove(&invocation);
+        let mut outputs = self.outputs.lock().unwrap();
+        match outputs.get_mut(&invocation) {
+            Some(queue) if queue.len() > 1 => Ok(queue.pop_front().unwrap()),
+            Some(queue) if !queue.is_empty() => Ok(queue[0].clone()),
+            // the command fails like git would, and the replay is stopped
+            // once the command's caller has dealt with that
+            _ => {
+                let args = cmd.args.join(" ");
+                let message = if outputs.keys().any(|i| i.args == cmd.args) {
+                    format!(
+                        "The session recorded git {} with other input or in \
+                         another directory",
+                        args
+                    )
+                } else {
+                    format!(
+                        "The session has no recorded output for git {}",
+                        args
+                    )
+                };
+                self.missing
+                    .lock()
+                    .unwrap()
+                    .get_or_insert_with(|| message.clone());
+                Ok(GitOutput {
+                    success: false,
+                    stdout: String::new(),
+                    stderr: message,
+                })
+            }
+        }
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.missing.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl ReplayRunner {
+    /// Return the recorded commands that were never run, so a test can check
+    /// that the same commands were run as when they were recorded
+    pub fn unplayed(&self) -> Vec<String> {
+        let mut unplayed: Vec<String> = self
+            .unplayed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|invocation| invocation.args.join(" "))
+            .collect();
+        unplayed.sort();
+        unplayed
+    }
+}
+
+static RUNNER: OnceCell<Box<dyn GitRunner>> = OnceCell::new();
+
+/// Replace the runner used for all git commands; this must happen before the
+/// first command runs
+pub fn set_runner(runner: Box<dyn GitRunner>) -> Result<(), io::Error> {
+    RUNNER.set(runner).map_err(|_| {
+        io::Error::new(io::ErrorKind::AlreadyExists, "git runner already set")
+    })
+}
+
+/// Configure the git runner from the config; recording and replaying
+/// sessions set their own runners with the `--record` and `--replay` flags
+pub fn init_runner(config: &GitConfig) -> Result<(), io::Error> {
+    set_runner(Box::new(SystemRunner::new(config)))
+}
+
+/// Return why git commands can't be answered any more, like a replayed
+/// session running a command that wasn't recorded
+pub fn runner_failure() -> Option<String> {
+    runner().failure()
+}
+
+fn runner() -> &'static dyn GitRunner {
+    RUNNER
+        .get_or_init(|| Box::new(SystemRunner::new(&GitConfig::default())))
+        .as_ref()
+}
+
+/// A runner that's shared with the test that installed it
+#[cfg(test)]
+type SharedRunner = std::sync::Arc<dyn GitRunner>;
+
+#[cfg(test)]
+thread_local! {
+    static TEST_RUNNER: std::cell::RefCell<Option<SharedRunner>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Wrap a function that's going to run on another thread so that its git
+/// commands go to the same runner as this thread's
+pub fn inherit_runner<T>(
+    f: impl FnOnce() -> T + Send,
+) -> impl FnOnce() -> T + Send {
+    #[cfg(test)]
+    {
+        let runner = TEST_RUNNER.with(|r| r.borrow().clone());
+        move || match runner {
+            Some(runner) => with_runner(runner, f),
+            None => f(),
+        }
+    }
+    #[cfg(not(test))]
+    f
+}
+
+/// Run a function with git commands on this thread going to a runner, so a
+/// test can replace git without affecting other tests
+#[cfg(test)]
+pub fn with_runner<T>(runner: SharedRunner, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<SharedRunner>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            TEST_RUNNER.with(|r| *r.borrow_mut() = self.0.take());
+        }
+    }
+
+    let previous = TEST_RUNNER.with(|r| r.borrow_mut().replace(runner));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// A runner for tests that expects an exact sequence of commands, answering
+/// each with a canned output; any other command fails the test
+#[cfg(test)]
+#[derive(Default)]
+pub struct ExpectRunner {
+    expected: Mutex<VecDeque<(Vec<String>, GitOutput)>>,
+}
+
+#[cfg(test)]
+impl ExpectRunner {
+    pub fn new() -> ExpectRunner {
+        ExpectRunner::default()
+    }
+
+    /// Expect a command to be run next, and have it succeed with some output
+    pub fn expect(self, args: &[&str], stdout: &str) -> ExpectRunner {
+        self.expected.lock().unwrap().push_back((
+            args.iter().map(|a| a.to_string()).collect(),
+            GitOutput {
+                success: true,
+                stdout: stdout.into(),
+                stderr: String::new(),
+            },
+        ));
+        self
+    }
+
+    /// Expect a command to be run next, and have it fail with an error
+    pub fn expect_failure(self, args: &[&str], stderr: &str) -> ExpectRunner {
+        self.expected.lock().unwrap().push_back((
+            args.iter().map(|a| a.to_string()).collect(),
+            GitOutput {
+                success: false,
+                stdout: String::new(),
+                stderr: stderr.into(),
+            },
+        ));
+        self
+    }
+
+    /// Fail the test if an expected command wasn't run
+    pub fn assert_done(&self) {
+        let expected = self.expected.lock().unwrap();
+        if let Some((args, _)) = expected.front() {
+            panic!("git {} wasn't run", args.join(" "));
+        }
+    }
+}
+
+#[cfg(test)]
+impl GitRunner for ExpectRunner {
+    fn run(&self, cmd: &GitCommand) -> io::Result<GitOutput> {
+        let mut expected = self.expected.lock().unwrap();
+        match expected.pop_front() {
+            Some((args, output)) => {
+                assert_eq!(cmd.args, args, "unexpected git command");
+                Ok(output)
+            }
+            None => panic!("unexpected git command: {}", cmd.args.join(" ")),
+        }
+    }
+}
+
+fn escape(arg: &str) -> String {
+    arg.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(arg: &str) -> String {
+    let mut out = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::git::testing::TestRepo;
+
+    /// A log that can still be read after a RecordingRunner takes it
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn command(args: &[&str]) -> GitCommand {
+        let mut cmd = GitCommand::new();
+        for arg in args {
+            cmd.arg(arg);
+        }
+        cmd
+    }
+
+    #[test]
+    fn expect_runner_answers_commands_on_this_thread() {
+        let runner = Arc::new(
+            ExpectRunner::new()
+                .expect(&["rev-parse", "HEAD"], "abc1234\n")
+                .expect_failure(&["show", "nope"], "fatal: bad revision\n"),
+        );
+        with_runner(runner.clone(), || {
+            let head = command(&["rev-parse", "HEAD"]).output().unwrap();
+            assert!(head.success);
+            assert_eq!(head.stdout, "abc1234\n");
+
+            let show = command(&["show", "nope"]).output().unwrap();
+            assert!(!show.success);
+            assert_eq!(show.stderr, "fatal: bad revision\n");
+        });
+        runner.assert_done();
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected git command")]
+    fn expect_runner_rejects_other_commands() {
+        let runner = Arc::new(ExpectRunner::new().expect(&["status"], ""));
+        with_runner(runner, || command(&["log"]).output().ok());
+    }
+
+    #[test]
+    fn replay_returns_what_was_recorded() {
+        let log = SharedLog::default();
+        let inner = ExpectRunner::new()
+            .expect(&["log", "--format=%s\tx"], "one\n\ntwo\r\n")
+            .expect(&["status"], "clean\n");
+        let recorder =
+            RecordingRunner::new(Box::new(inner), Box::new(log.clone()));
+        recorder.run(&command(&["log", "--format=%s\tx"])).unwrap();
+        recorder.run(&command(&["status"])).unwrap();
+
+        let recorded = log.0.lock().unwrap().clone();
+        let replay = ReplayRunner::new(recorded.as_slice()).unwrap();
+        assert_eq!(replay.unplayed(), vec!["log --format=%s\tx", "status"]);
+
+        let output = replay.run(&command(&["log", "--format=%s\tx"])).unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout, "one\n\ntwo\r\n");
+        assert_eq!(replay.unplayed(), vec!["status"]);
+
+        assert_eq!(replay.failure(), None);
+        let output = replay.run(&command(&["diff"])).unwrap();
+        assert!(!output.success);
+        assert_eq!(
+            replay.failure().as_deref(),
+            Some("The session has no recorded output for git diff")
+        );
+    }
+
+    #[test]
+    fn replay_matches_input_and_directory() {
+        let log = SharedLog::default();
+        let inner = ExpectRunner::new()
+            .expect(&["apply", "--cached"], "first\n")
+            .expect(&["apply", "--cached"], "second\n");
+        let recorder =
+            RecordingRunner::new(Box::new(inner), Box::new(log.clone()));
+        let mut first = command(&["apply", "--cached"]);
+        first.current_dir("/work/a").stdin("patch one\n");
+        let mut second = command(&["apply", "--cached"]);
+        second.current_dir("/work/b\tc").stdin("patch two\n");
+        recorder.run(&first).unwrap();
+        recorder.run(&second).unwrap();
+
+        let recorded = log.0.lock().unwrap().clone();
+        let replay = ReplayRunner::new(recorded.as_slice()).unwrap();
+        assert_eq!(replay.run(&second).unwrap().stdout, "second\n");
+        assert_eq!(replay.run(&first).unwrap().stdout, "first\n");
+        assert_eq!(replay.failure(), None);
+
+        let mut other = command(&["apply", "--cached"]);
+        other.current_dir("/work/a").stdin("patch three\n");
+        assert!(!replay.run(&other).unwrap().success);
+        assert_eq!(
+            replay.failure().as_deref(),
+            Some(
+                "The session recorded git apply --cached with other input \
+                 or in another directory"
+            )
+        );
+    }
+
+    #[test]
+    fn system_runner_pipes_large_input_and_output() {
+        let repo = TestRepo::new("large-input");
+        let names: Vec<String> = (0..200_000)
+            .map(|i| format!("HEAD:missing-{}", i))
+            .collect();
+        let mut cmd = command(&["cat-file", "--batch-check"]);
+        cmd.current_dir(repo.path()).stdin(names.join("\n") + "\n");
+
+        let output =
+            SystemRunner::new(&GitConfig::default()).run(&cmd).unwrap();
+        assert!(output.success, "{}", output.stderr);
+        assert_eq!(output.stdout.lines().count(), names.len());
+        assert_eq!(
+            output.stdout.lines().last(),
+            Some("HEAD:missing-199999 missing")
+        );
+    }
+
+    #[test]
+    fn escaped_args_round_trip() {
+        let arg = "a\tb\nc\\d\r";
+        assert_eq!(unescape(&escape(arg)), arg);
+    }
+}
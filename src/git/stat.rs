@@ -1,4 +1,6 @@
-use std::path::{PathBuf, Path};
+use std::path::PathBuf;
+
+use super::{worktree_file, GitRef};
 
 #[derive(Debug, Clone)]
 pub struct Stat {
@@ -10,13 +12,25 @@ pub struct Stat {
     pub path: String,
     /// Original path of the modified file (if renamed)
     pub old_path: String,
+    /// Whether git considers the file binary, in which case it has no line
+    /// counts
+    pub binary: bool,
+    /// Number of hunks in the file's diff, if they've been counted
+    pub hunks: Option<u32>,
+    /// A fingerprint of the lines the change added and deleted, which files
+    /// changed the same way share, if it's been taken
+    pub change_id: Option<u64>,
+    /// The commit that made the change, when the stats are of several
+    /// commits shown together
+    pub commit: Option<GitRef>,
 }
 
 impl Stat {
     pub fn new(stat_line: &str) -> Stat {
         let parts: Vec<&str> = stat_line.split('\t').collect();
-        let adds: u32 = parts[0].parse().unwrap();
-        let deletes: u32 = parts[1].parse().unwrap();
+        let binary = parts[0] == "-";
+        let adds: u32 = parts[0].parse().unwrap_or(0);
+        let deletes: u32 = parts[1].parse().unwrap_or(0);
         let (path, old_path) = if parts[2].contains(" => ") {
             let path_parts: Vec<&str> = parts[2].split(" => ").collect();
             (path_parts[0].into(), path_parts[1].into())
@@ -29,12 +43,30 @@ impl Stat {
             deletes,
             path,
             old_path,
+            binary,
+            hunks: None,
+            change_id: None,
+            commit: None,
         }
     }
 
-    pub fn path(&self) -> Result<PathBuf, std::io::Error> {
-        let repo_root = crate::git::git_root();
-        let repo_path = format!("{}/{}", repo_root, self.path);
-        Path::new(&repo_path).canonicalize()
+    /// Return the path of the file in the working tree, where a renamed file
+    /// has its new name
+    ///
+    /// The path is resolved from the repo root, so it's found wherever in the
+    /// repo the app was started, and the file doesn't have to exist.
+    pub fn worktree_path(&self) -> PathBuf {
+        worktree_file(self.new_path())
+    }
+
+    /// Return the file's path after the change, which is its new name if it
+    /// was renamed
+    pub fn new_path(&self) -> &str {
+        // for renames, git reports the old path first
+        if self.old_path.is_empty() {
+            &self.path
+        } else {
+            &self.old_path
+        }
     }
 }
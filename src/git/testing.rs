@@ -0,0 +1,154 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use super::{runner::GitRunner, SystemRunner};
+use crate::config::GitConfig;
+
+/// The environment that keeps the user's own git config out of tests, and
+/// gives the commits they make a fixed author and committer
+const ISOLATED_ENV: [(&str, &str); 6] = [
+    ("GIT_CONFIG_GLOBAL", "/dev/null"),
+    ("GIT_CONFIG_NOSYSTEM", "1"),
+    ("GIT_AUTHOR_NAME", "Ann Dev"),
+    ("GIT_AUTHOR_EMAIL", "ann@example.com"),
+    ("GIT_COMMITTER_NAME", "Ann Dev"),
+    ("GIT_COMMITTER_EMAIL", "ann@example.com"),
+];
+
+/// A repo for a test, in a directory of its own that's removed when the repo
+/// is dropped
+pub struct TestRepo {
+    pub path: PathBuf,
+}
+
+impl TestRepo {
+    /// Create an empty repo on a `main` branch; the name keeps the repos of
+    /// tests that run at the same time apart
+    pub fn new(name: &str) -> TestRepo {
+        let path = env::temp_dir().join(format!(
+            "de-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let repo = TestRepo { path };
+        // `init -b` needs git 2.28
+        repo.git(&["init", "-q"]);
+        repo.git(&["symbolic-ref", "HEAD", "refs/heads/main"]);
+        repo
+    }
+
+    /// Run git in the repo, ignoring the user's config, and return what it
+    /// printed; the test fails if git does
+    pub fn git(&self, args: &[&str]) -> String {
+        self.git_at(args, "1700000000")
+    }
+
+    /// Run git with a fixed author and committer, at a time given in seconds
+    /// since the epoch, so the commits it makes always have the same hashes
+    pub fn git_at(&self, args: &[&str], date: &str) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.path)
+            .envs(ISOLATED_ENV)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Write a file in the working tree
+    pub fn write(&self, path: &str, text: &str) {
+        fs::write(self.path.join(path), text).unwrap();
+    }
+
+    /// Read a file in the working tree
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.path.join(path)).unwrap()
+    }
+
+    /// Write some files and commit them at a time, returning the commit's
+    /// hash
+    pub fn commit(
+        &self,
+        files: &[(&str, &str)],
+        message: &str,
+        date: &str,
+    ) -> String {
+        for (path, text) in files {
+            self.write(path, text);
+        }
+        self.git_at(&["add", "."], date);
+        self.git_at(&["commit", "-q", "-m", message], date);
+        self.git(&["rev-parse", "HEAD"]).trim().into()
+    }
+
+    /// Fill an empty repo with empty commits a minute apart, starting at a
+    /// time given in seconds since the epoch; this is much faster than
+    /// committing them one at a time
+    pub fn empty_commits(&self, count: u64, date: u64) {
+        let mut stream = String::new();
+        for i in 1..=count {
+            let message = format!("Commit {}", i);
+            stream.push_str(&format!(
+                "commit refs/heads/main\n\
+                 committer Ann Dev <ann@example.com> {} +0000\n\
+                 data {}\n{}\n",
+                date + i * 60,
+                message.len(),
+                message
+            ));
+        }
+        let mut child = Command::new("git")
+            .args(["fast-import", "--quiet"])
+            .current_dir(&self.path)
+            .envs(ISOLATED_ENV)
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stream.as_bytes())
+            .unwrap();
+        assert!(child.wait().unwrap().success(), "git fast-import failed");
+    }
+
+    /// Return a runner for de's git commands that runs them in the repo,
+    /// ignoring the user's config
+    pub fn runner(&self) -> Arc<dyn GitRunner> {
+        let config = GitConfig {
+            env: ISOLATED_ENV
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            ..GitConfig::default()
+        };
+        Arc::new(SystemRunner::new(&config).in_dir(&self.path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -1,10 +1,31 @@
-use std::process::Command;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::RwLock,
+    thread,
+};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, string::base64_encode};
 
 use super::{
-    commit::Commit,
-    commits::{GitRef, Target},
+    blame::BlameLine,
+    commit::{Commit, CommitDetails, DETAILS_FORMAT},
+    commits::{ConflictSides, GitRef, Stage, Target},
     diff::FileDiff,
+    grep::GrepLine,
+    path::{canonicalize, join_git_path, path_within, top_pathspec},
+    rangediff::RangeDiffPair,
+    runner::{inherit_runner, GitCommand},
     stat::Stat,
+    version::{has_capability, Capability},
     DiffAction,
 };
 
@@ -14,104 +35,700 @@ pub trait Stdout {
     fn stdout_str(&mut self) -> String;
 }
 
-impl Stdout for Command {
+impl Stdout for GitCommand {
     fn stdout_str(&mut self) -> String {
-        let output =
-            self.output().expect("output of command should be a string");
-        let out_str = String::from_utf8(output.stdout)
-            .expect("output should be a UTF8 string");
-        out_str.trim().into()
+        let output = self.output().expect("git command should run");
+        output.stdout.trim().into()
     }
 }
 
 /// Return the absolute root directory of the current repo
 pub fn is_git_repo() -> bool {
-    let output = Command::new("git")
+    let output = GitCommand::new()
         .arg("rev-parse")
         .arg("--show-toplevel")
         .output();
     match output {
         Err(_) => false,
-        Ok(output) => output.status.success(),
+        Ok(output) => output.success,
     }
 }
 
+/// Return when the newest commit in the repo was committed
+pub fn git_newest_commit_time() -> Option<DateTime<Utc>> {
+    let time = GitCommand::new()
+        .arg("log")
+        .arg("--all")
+        .arg("--max-count=1")
+        .arg("--format=%ct")
+        .stdout_str();
+    DateTime::from_timestamp(time.parse().ok()?, 0)
+}
+
 /// Return the absolute root directory of the current repo
 pub fn git_root() -> String {
     // TODO: make this a lazy initialized static
-    Command::new("git")
+    GitCommand::new()
         .arg("rev-parse")
         .arg("--show-toplevel")
         .stdout_str()
 }
 
+/// Return the directory de was started in, relative to the repo root and
+/// ending with `/`, or an empty string if it was started at the root
+pub fn git_prefix() -> &'static str {
+    static PREFIX: OnceCell<String> = OnceCell::new();
+    PREFIX.get_or_init(|| {
+        GitCommand::new()
+            .arg("rev-parse")
+            .arg("--show-prefix")
+            .stdout_str()
+    })
+}
+
+static PATHSPECS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Limit every log and diff to the given pathspecs, which are relative to
+/// the directory de was started in, or to nothing if there are none; returns
+/// git's complaint if a pathspec isn't valid
+pub fn set_pathspecs(specs: Vec<String>) -> Result<(), AppError> {
+    if !specs.is_empty() {
+        let cmd = &mut GitCommand::new();
+        cmd.arg("log").arg("--max-count=0").arg("--");
+        for spec in &specs {
+            cmd.arg(top_pathspec(git_prefix(), spec));
+        }
+        git_exec(cmd)?;
+    }
+    *PATHSPECS.write().unwrap() = specs;
+    Ok(())
+}
+
+/// Return the pathspecs every log and diff is limited to, as they were given
+pub fn pathspecs() -> Vec<String> {
+    PATHSPECS.read().unwrap().clone()
+}
+
+/// Limits on how much history any log loads, for repos whose full history
+/// isn't needed
+#[derive(Debug, Clone, Default)]
+pub struct LogLimit {
+    /// the most commits a log shows
+    pub max_count: Option<usize>,
+    /// only show commits newer than this date, in any form git understands
+    pub since: Option<String>,
+}
+
+impl LogLimit {
+    pub fn is_empty(&self) -> bool {
+        self.max_count.is_none() && self.since.is_none()
+    }
+}
+
+impl Display for LogLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(count) = self.max_count {
+            parts.push(format!("last {}", count));
+        }
+        if let Some(since) = &self.since {
+            parts.push(format!("since {}", since));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+static LOG_LIMIT: OnceCell<LogLimit> = OnceCell::new();
+
+/// Limit how much history every log loads; this can only be set once, before
+/// any log is loaded
+pub fn set_log_limit(limit: LogLimit) {
+    LOG_LIMIT.set(limit).ok();
+}
+
+/// Return the limits on how much history every log loads
+pub fn log_limit() -> &'static LogLimit {
+    LOG_LIMIT.get_or_init(LogLimit::default)
+}
+
+/// Return the commits on any branch that changed a file or directory, given
+/// relative to the repo root
+pub fn git_commits_touching(path: &str) -> HashSet<GitRef> {
+    GitCommand::new()
+        .current_dir(git_root())
+        .arg("log")
+        .arg("--all")
+        .arg("--format=%h")
+        .arg("--")
+        .arg(path)
+        .stdout_str()
+        .lines()
+        .map(GitRef::new)
+        .collect()
+}
+
+/// Limit a command to the pathspecs de was given, or if there aren't any and
+/// `scoped` is set, to the directory de was started in; this must be the last
+/// thing added to the command
+fn add_scope(command: &mut GitCommand, scoped: bool) {
+    let specs = pathspecs();
+    if !specs.is_empty() {
+        command.arg("--");
+        for spec in &specs {
+            command.arg(top_pathspec(git_prefix(), spec));
+        }
+    } else if scoped && !git_prefix().is_empty() {
+        command.arg("--").arg(format!(":(top){}", git_prefix()));
+    }
+}
+
+/// Return the absolute path of the current repo's git directory
+pub fn git_dir() -> PathBuf {
+    let dir = PathBuf::from(
+        GitCommand::new()
+            .arg("rev-parse")
+            .arg("--git-dir")
+            .stdout_str(),
+    );
+    canonicalize(&dir).unwrap_or(dir)
+}
+
+/// Return the location of a file in the working tree, from its path relative
+/// to the repo root as git prints it
+pub fn worktree_file(path: &str) -> PathBuf {
+    join_git_path(Path::new(&git_root()), path)
+}
+
+/// Return the URL of a remote, or None if there's no such remote
+pub fn git_remote_url(remote: &str) -> Option<String> {
+    let output = GitCommand::new()
+        .arg("remote")
+        .arg("get-url")
+        .arg(remote)
+        .output()
+        .ok()?;
+    output.success.then(|| output.stdout.trim().to_string())
+}
+
+/// The kind of a named ref
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    Branch,
+    RemoteBranch,
+    Tag,
+}
+
+/// A branch or tag, by its short name, like `main` or `origin/main`
+#[derive(Debug, Clone)]
+pub struct NamedRef {
+    pub name: String,
+    /// the full name, like `refs/heads/main`
+    pub refname: String,
+    pub kind: RefKind,
+}
+
+/// Return every local branch, remote branch, and tag, sorted by name within
+/// each kind
+pub fn git_refs() -> Vec<NamedRef> {
+    GitCommand::new()
+        .arg("for-each-ref")
+        .arg("--format=%(refname)")
+        .arg("refs/heads")
+        .arg("refs/remotes")
+        .arg("refs/tags")
+        .stdout_str()
+        .lines()
+        .filter_map(|refname| {
+            let (kind, name) =
+                if let Some(n) = refname.strip_prefix("refs/heads/") {
+                    (RefKind::Branch, n)
+                } else if let Some(n) = refname.strip_prefix("refs/remotes/") {
+                    // a remote's HEAD only names one of its other branches
+                    if n.ends_with("/HEAD") {
+                        return None;
+                    }
+                    (RefKind::RemoteBranch, n)
+                } else {
+                    (RefKind::Tag, refname.strip_prefix("refs/tags/")?)
+                };
+            Some(NamedRef {
+                name: name.into(),
+                refname: refname.into(),
+                kind,
+            })
+        })
+        .collect()
+}
+
 /// Return the commit hash of the current branch head
 pub fn git_id() -> String {
-    Command::new("git")
+    GitCommand::new().arg("rev-parse").arg("HEAD").stdout_str()
+}
+
+/// True if one commit is an ancestor of another
+pub fn git_is_ancestor(ancestor: &GitRef, descendant: &GitRef) -> bool {
+    GitCommand::new()
+        .arg("merge-base")
+        .arg("--is-ancestor")
+        .arg(ancestor)
+        .arg(descendant)
+        .output()
+        .is_ok_and(|o| o.success)
+}
+
+/// Return the best common ancestor of two commits, abbreviated
+pub fn git_merge_base(a: &GitRef, b: &GitRef) -> Option<GitRef> {
+    let output = GitCommand::new()
+        .arg("merge-base")
+        .arg(a)
+        .arg(b)
+        .output()
+        .ok()?;
+    if !output.success {
+        return None;
+    }
+    let base = GitCommand::new()
         .arg("rev-parse")
-        .arg("HEAD")
+        .arg("--short")
+        .arg(output.stdout.trim())
+        .stdout_str();
+    Some(GitRef::new(base))
+}
+
+/// A commit in a range, with the patch-id of its changes, which commits that
+/// make the same change share
+#[derive(Debug, Clone)]
+pub struct PatchIdCommit {
+    pub commit: GitRef,
+    pub subject: String,
+    /// merges and commits without changes have no patch-id
+    pub patch_id: Option<String>,
+}
+
+/// Return the commits that are in `tip` but not in `base`, newest first,
+/// with their patch-ids
+pub fn git_patch_ids(base: &GitRef, tip: &GitRef) -> Vec<PatchIdCommit> {
+    let range = format!("{}..{}", base, tip);
+    let log = GitCommand::new()
+        .arg("log")
+        .arg("--format=%H %h %s")
+        .arg(&range)
+        .stdout_str();
+
+    // patch-id reads the commits' diffs, and prints each one's id and hash
+    let patches = GitCommand::new()
+        .arg("log")
+        .arg("-p")
+        .arg("--no-merges")
+        .arg("--format=commit %H")
+        .arg(&range)
+        .output()
+        .map(|o| o.stdout)
+        .unwrap_or_default();
+    let ids: HashMap<String, String> = GitCommand::new()
+        .arg("patch-id")
+        .arg("--stable")
+        .stdin(patches)
         .stdout_str()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(id, hash)| (hash.to_string(), id.to_string()))
+        .collect();
+
+    log.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let hash = parts.next()?;
+            let short = parts.next()?;
+            Some(PatchIdCommit {
+                commit: GitRef::new(short),
+                subject: parts.next().unwrap_or_default().into(),
+                patch_id: ids.get(hash).cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Compare two ranges of commits with `git range-diff`, pairing each commit
+/// in the old range with its counterpart in the new one; the ranges are given
+/// like git takes them, as `old...new`, `base old new`, or two `base..tip`
+/// ranges
+pub fn git_range_diff(ranges: &[&str]) -> Result<Vec<RangeDiffPair>, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.arg("range-diff").arg("--no-color");
+    for range in ranges {
+        cmd.arg(*range);
+    }
+    let output = cmd.output()?;
+    if output.success {
+        Ok(RangeDiffPair::parse_all(&output.stdout))
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// Return the commit a diff's target branch points to now, if the branch has
+/// moved since the diff was made
+pub fn git_branch_moved(action: &DiffAction) -> Option<GitRef> {
+    let (Some(branch), Target::Ref(target)) = (action.branch(), &action.target)
+    else {
+        return None;
+    };
+    let output = GitCommand::new()
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("--short={}", target.len()))
+        .arg(format!("{}^{{commit}}", branch))
+        .output()
+        .ok()?;
+    let tip = GitRef::new(output.stdout.trim());
+    (output.success && tip != *target).then_some(tip)
+}
+
+/// The length of a full commit hash; with `git_resolve_commit`, a prefix this
+/// long is enough to identify a commit even in repos that use longer hashes
+pub const FULL_HASH_LEN: usize = 40;
+
+/// Return the abbreviated hash of the commit a revision names, like a branch
+/// or `HEAD~2`, or None if it doesn't name a commit
+pub fn git_resolve_commit(rev: &str, len: usize) -> Option<GitRef> {
+    let output = GitCommand::new()
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("--short={}", len))
+        .arg(format!("{}^{{commit}}", rev))
+        .output()
+        .ok()?;
+    output.success.then(|| GitRef::new(output.stdout.trim()))
+}
+
+/// Turn a show of a commit into a diff to it from another revision, or
+/// describe why that isn't possible
+pub fn git_anchor(
+    action: &DiffAction,
+    rev: &str,
+) -> Result<DiffAction, String> {
+    let Target::Ref(target) = &action.target else {
+        return Err(NOT_ANCHORABLE.into());
+    };
+    let anchor = git_resolve_commit(rev, target.len())
+        .ok_or_else(|| format!("Unknown commit: {}", rev))?;
+    action.anchored(anchor).ok_or_else(|| NOT_ANCHORABLE.into())
 }
 
-/// Return a git commit log for the current repo
-pub fn git_log() -> Vec<Commit> {
-    let output = Command::new("git")
+const NOT_ANCHORABLE: &str =
+    "Only a single commit can be diffed against another";
+
+/// The number of commits a log loads at a time
+pub const LOG_PAGE_SIZE: usize = 1000;
+
+/// Limits on which commits a log shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// only show commits that changed this path
+    pub path: Option<String>,
+    /// only show commits whose messages match this pattern
+    pub grep: Option<String>,
+    /// only show commits whose changes add or remove some text
+    pub pickaxe: Option<Pickaxe>,
+    /// only show commits that changed the directory de was started in, when
+    /// there's no path
+    pub scoped: bool,
+    /// show only merges, or no merges
+    pub merges: MergeFilter,
+}
+
+impl LogFilter {
+    /// True if the filter only limits the log to the directory de was
+    /// started in, which the staged and unstaged changes are limited to too
+    pub fn is_scope_only(&self) -> bool {
+        self.path.is_none()
+            && self.grep.is_none()
+            && self.pickaxe.is_none()
+            && self.merges == MergeFilter::All
+    }
+}
+
+/// What a log's commits must change to be shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pickaxe {
+    /// text whose number of appearances in a file the commit changes, like
+    /// `git log -S`
+    Text(String),
+    /// a regex that the commit's added or removed lines match, like
+    /// `git log -G`
+    Regex(String),
+}
+
+impl Pickaxe {
+    /// Parse the text of a pickaxe search, which is a regex if it starts with
+    /// `r/`, like other searches
+    pub fn parse(text: &str) -> Pickaxe {
+        match text.strip_prefix("r/") {
+            Some(regex) => Pickaxe::Regex(regex.into()),
+            None => Pickaxe::Text(text.into()),
+        }
+    }
+
+    /// Return the git log option that finds the commits, like `-S`
+    pub fn option(&self) -> &'static str {
+        match self {
+            Pickaxe::Text(_) => "-S",
+            Pickaxe::Regex(_) => "-G",
+        }
+    }
+
+    /// Return the text or regex, without the option
+    pub fn pattern(&self) -> &str {
+        match self {
+            Pickaxe::Text(text) | Pickaxe::Regex(text) => text,
+        }
+    }
+}
+
+/// Which commits a log shows, by whether they're merges
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeFilter {
+    #[default]
+    All,
+    MergesOnly,
+    NoMerges,
+}
+
+impl MergeFilter {
+    /// Return the filter after this one, cycling back to the first
+    pub fn next(self) -> MergeFilter {
+        match self {
+            MergeFilter::All => MergeFilter::MergesOnly,
+            MergeFilter::MergesOnly => MergeFilter::NoMerges,
+            MergeFilter::NoMerges => MergeFilter::All,
+        }
+    }
+}
+
+impl Display for MergeFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MergeFilter::All => "all commits",
+            MergeFilter::MergesOnly => "merges only",
+            MergeFilter::NoMerges => "no merges",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The log format that Commits are parsed from:
+/// commit|parents|decoration|author_name|author_email|timestamp|subject
+const LOG_FORMAT: &str = "--pretty=format:%h|%p|%d|%aN|%aE|%at|%s";
+
+/// Return a git commit log for the current repo, or only the commits that
+/// pass a filter, starting after the first `skip` commits and returning at
+/// most `count`
+///
+/// The log doesn't include pseudo-commits for staged and unstaged changes;
+/// those come from [`git_worktree_changes`].
+pub fn git_log(filter: &LogFilter, skip: usize, count: usize) -> Vec<Commit> {
+    let limit = log_limit();
+    let count = match limit.max_count {
+        Some(max) => count.min(max.saturating_sub(skip)),
+        None => count,
+    };
+    if count == 0 {
+        return vec![];
+    }
+
+    let command = &mut GitCommand::new();
+    command
         .arg("log")
         .arg("--all")
+        .arg(format!("--skip={}", skip))
+        .arg(format!("--max-count={}", count))
         .arg("--date=iso8601-strict")
         .arg("--decorate")
-        // commit|decoration|author_name|author_email|timestamp|subject
-        .arg("--pretty=format:%h|%p|%d|%aN|%aE|%at|%s")
-        .stdout_str();
-    let mut log = output
+        .arg(LOG_FORMAT)
+        // when the log is limited to some paths, parents are rewritten to
+        // the nearest commits that changed them, so the graph connects the
+        // commits that are shown
+        .arg("--parents");
+    add_log_filter(command, filter);
+    let output = command.stdout_str();
+    output
         .lines()
         .map(Commit::from_log_line)
-        .collect::<Vec<Commit>>();
+        .collect::<Vec<Commit>>()
+}
+
+/// Return whether a commit passes a log's filter, without walking the history
+/// to find it
+pub fn git_log_includes(filter: &LogFilter, commit: &str) -> bool {
+    let command = &mut GitCommand::new();
+    command
+        .arg("log")
+        .arg("--max-count=1")
+        .arg("--format=%H")
+        .arg(format!("{}^!", commit));
+    add_log_filter(command, filter);
+    !command.stdout_str().is_empty()
+}
 
-    let hash_len = if let Some(c) = log.first() {
-        c.commit_ref.len()
+/// Add the options that limit a log to the commits that pass a filter, which
+/// must be the last options
+fn add_log_filter(command: &mut GitCommand, filter: &LogFilter) {
+    if let Some(since) = &log_limit().since {
+        command.arg(format!("--since={}", since));
+    }
+    if let Some(grep) = &filter.grep {
+        command
+            .arg("--regexp-ignore-case")
+            .arg(format!("--grep={}", grep));
+    }
+    if let Some(pickaxe) = &filter.pickaxe {
+        command.arg(format!("{}{}", pickaxe.option(), pickaxe.pattern()));
+    }
+    match filter.merges {
+        MergeFilter::All => {}
+        MergeFilter::MergesOnly => {
+            command.arg("--merges");
+        }
+        MergeFilter::NoMerges => {
+            command.arg("--no-merges");
+        }
+    }
+    if let Some(path) = &filter.path {
+        command.current_dir(git_root()).arg("--").arg(path);
     } else {
-        6
+        add_scope(command, filter.scoped);
+    }
+}
+
+/// Return the commits in a diff's range, newest first, leaving out merges
+pub fn git_range_log(range: &DiffAction) -> Vec<Commit> {
+    let (Some(anchor), Target::Ref(target)) = (&range.anchor, &range.target)
+    else {
+        return vec![];
     };
-    let head = String::from(&git_id()[..hash_len]);
+    GitCommand::new()
+        .arg("log")
+        .arg("--no-merges")
+        .arg("--decorate")
+        .arg(LOG_FORMAT)
+        .arg(format!("{}..{}", anchor, target))
+        .stdout_str()
+        .lines()
+        .map(Commit::from_log_line)
+        .collect()
+}
 
-    if has_changes(&DiffAction::staged()) {
-        log.insert(
-            0,
-            Commit::new(
-                GitRef::staged(hash_len),
-                vec![GitRef::new(head.clone())],
+/// The staged and unstaged changes in the repo
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeChanges {
+    /// The commit hash of HEAD
+    pub head: String,
+    /// The diff summary of the staged changes, if there are any
+    pub staged: Option<String>,
+    /// The diff summary of the unstaged changes, if there are any
+    pub unstaged: Option<String>,
+    /// The operation that stopped partway, if there is one
+    pub operation: Option<Operation>,
+}
+
+impl WorktreeChanges {
+    /// Return pseudo-commits for the changes, with hashes of the given length
+    pub fn commits(&self, hash_len: usize) -> Vec<Commit> {
+        let head = &self.head[..hash_len.min(self.head.len())];
+        let mut commits = vec![];
+
+        if self.unstaged.is_some() {
+            commits.push(Commit::new(
+                GitRef::unstaged(hash_len),
+                vec![GitRef::new(head)],
                 "",
                 "".into(),
                 "".into(),
                 None,
-                "Staged changes".into(),
-            ),
-        );
-    }
+                "Unstaged changes".into(),
+            ));
+        }
 
-    if has_changes(&DiffAction::unstaged()) {
-        log.insert(
-            0,
-            Commit::new(
-                GitRef::unstaged(hash_len),
+        if self.staged.is_some() {
+            commits.push(Commit::new(
+                GitRef::staged(hash_len),
                 vec![GitRef::new(head)],
                 "",
                 "".into(),
                 "".into(),
                 None,
-                "Unstaged changes".into(),
-            ),
-        );
+                "Staged changes".into(),
+            ));
+        }
+
+        commits
     }
 
-    log
+    /// Return a compact summary of the changes represented by a pseudo-commit,
+    /// like "+123 −45 in 7 files"
+    pub fn summary(&self, commit: &GitRef) -> Option<String> {
+        let shortstat = if commit.is_staged() {
+            self.staged.as_ref()
+        } else if commit.is_unstaged() {
+            self.unstaged.as_ref()
+        } else {
+            None
+        }?;
+
+        let count = |kind: &str| {
+            shortstat
+                .split(", ")
+                .find(|part| part.contains(kind))
+                .and_then(|part| part.split_whitespace().next())
+                .unwrap_or("0")
+        };
+        let files = count("changed");
+
+        Some(format!(
+            "+{} −{} in {} {}",
+            count("insertion"),
+            count("deletion"),
+            files,
+            if files == "1" { "file" } else { "files" }
+        ))
+    }
+}
+
+/// Check the repo for staged and unstaged changes, or only those in the
+/// directory de was started in if `scoped` is set, running the git probes
+/// concurrently
+pub fn git_worktree_changes(scoped: bool) -> WorktreeChanges {
+    let summary = |action: DiffAction| {
+        let summary = git_summary(&action, scoped);
+        if summary.is_empty() {
+            None
+        } else {
+            Some(summary)
+        }
+    };
+
+    thread::scope(|s| {
+        let head = s.spawn(inherit_runner(git_id));
+        let staged = s.spawn(inherit_runner(|| summary(DiffAction::staged())));
+        let unstaged =
+            s.spawn(inherit_runner(|| summary(DiffAction::unstaged())));
+
+        WorktreeChanges {
+            head: head.join().unwrap_or_default(),
+            staged: staged.join().unwrap_or_default(),
+            unstaged: unstaged.join().unwrap_or_default(),
+            operation: git_operation(),
+        }
+    })
 }
 
 /// Return the diff summary stats between two commits or between a commit and
 /// the index or working tree
-fn git_summary(commits: &DiffAction) -> String {
-    let cmd = &mut Command::new("git");
+fn git_summary(commits: &DiffAction, scoped: bool) -> String {
+    let cmd = &mut GitCommand::new();
     cmd.arg("diff").arg("--shortstat");
 
     if commits.has_staged() {
@@ -126,35 +743,320 @@ fn git_summary(commits: &DiffAction) -> String {
         cmd.arg(h);
     }
 
+    add_scope(cmd, scoped);
+
     cmd.stdout_str()
 }
 
-/// Return a git commit log message for the given commit
-pub fn git_log_message(commit: &GitRef) -> String {
-    Command::new("git")
+/// Return the details of a commit, for the log's details pane
+pub fn git_commit_details(commit: &GitRef) -> CommitDetails {
+    let output = GitCommand::new()
         .arg("show")
-        .arg("--shortstat")
+        .arg("--no-patch")
+        .arg("--date=iso")
+        .arg(format!("--format={}", DETAILS_FORMAT))
         .arg(commit)
-        .stdout_str()
+        .stdout_str();
+    let parents: Vec<&str> = output
+        .split('\0')
+        .nth(1)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    let subjects = if parents.is_empty() {
+        String::new()
+    } else {
+        let cmd = &mut GitCommand::new();
+        cmd.arg("show").arg("--no-patch").arg("--format=%s");
+        for parent in parents {
+            cmd.arg(parent);
+        }
+        cmd.stdout_str()
+    };
+    CommitDetails::parse(&output, &subjects)
 }
 
-#[derive(Default)]
+/// An algorithm git can use to compute diffs
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    Myers,
+    Minimal,
+    #[default]
+    Patience,
+    Histogram,
+}
+
+impl DiffAlgorithm {
+    pub const ALL: [DiffAlgorithm; 4] = [
+        DiffAlgorithm::Myers,
+        DiffAlgorithm::Minimal,
+        DiffAlgorithm::Patience,
+        DiffAlgorithm::Histogram,
+    ];
+
+    /// Return the algorithm that follows this one, for cycling through them
+    pub fn next(self) -> DiffAlgorithm {
+        let i = DiffAlgorithm::ALL.iter().position(|a| *a == self).unwrap();
+        DiffAlgorithm::ALL[(i + 1) % DiffAlgorithm::ALL.len()]
+    }
+}
+
+impl Display for DiffAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DiffAlgorithm::Myers => "myers",
+            DiffAlgorithm::Minimal => "minimal",
+            DiffAlgorithm::Patience => "patience",
+            DiffAlgorithm::Histogram => "histogram",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DiffAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiffAlgorithm::ALL
+            .into_iter()
+            .find(|a| a.to_string() == s)
+            .ok_or_else(|| format!("unknown diff algorithm '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct GitDiffOpts {
-    ignore_whitespace: bool,
+    pub ignore_whitespace: bool,
+    pub algorithm: DiffAlgorithm,
+    /// lines of context around changes, or None for git's default
+    pub context: Option<u32>,
+    /// only diff the files in the directory de was started in
+    pub scoped: bool,
+    /// diffs of the working tree include the uncommitted changes inside
+    /// submodules, in place of the submodules' own entries
+    pub submodules: bool,
 }
 
+/// The number of context lines git shows by default
+pub const DEFAULT_CONTEXT: u32 = 3;
+
 /// Return file diff stats between two commits, or for a particular commit
 /// (between that commit and its parent)
 pub fn git_diff_stat(
     action: &DiffAction,
     opts: Option<GitDiffOpts>,
 ) -> Vec<Stat> {
-    let opts = match opts {
-        Some(o) => o,
-        _ => GitDiffOpts::default(),
-    };
+    // each commit of a series is read on its own, so its stats can say
+    // which commit they're from
+    let series = action.series();
+    if !series.is_empty() {
+        return series
+            .into_iter()
+            .flat_map(|commit| {
+                let show = DiffAction::show(Target::Ref(commit.clone()));
+                git_diff_stat(&show, opts).into_iter().map(move |s| Stat {
+                    commit: Some(commit.clone()),
+                    ..s
+                })
+            })
+            .collect();
+    }
 
-    let cmd = &mut Command::new("git");
+    let opts = opts.unwrap_or_default();
+    let cmd = &mut stat_command(action, &opts, &["--numstat"]);
+
+    // comparing a stage to the working tree lists the conflicted file as
+    // unmerged before its actual changes
+    let unmerged = action
+        .conflict_sides()
+        .map_or(0, |c| c.new.is_none() as usize);
+    let mut stats: Vec<Stat> = cmd
+        .stdout_str()
+        .lines()
+        .filter(|x| !x.is_empty())
+        .skip(unmerged)
+        .map(Stat::new)
+        .collect();
+
+    // a submodule's files are listed after the repo's own, in the same order
+    // as their diffs
+    for submodule in diffed_submodules(action, &opts) {
+        let cmd = &mut submodule_command(&submodule, &opts, &["--numstat"]);
+        stats.extend(cmd.stdout_str().lines().filter(|x| !x.is_empty()).map(
+            |line| {
+                let stat = Stat::new(line);
+                let prefix = |path: String| {
+                    if path.is_empty() {
+                        path
+                    } else {
+                        format!("{}/{}", submodule, path)
+                    }
+                };
+                Stat {
+                    path: prefix(stat.path),
+                    old_path: prefix(stat.old_path),
+                    ..stat
+                }
+            },
+        ));
+    }
+    stats
+}
+
+/// Return the checked out submodules of the repo, by their paths from the
+/// repo root
+pub fn git_submodules() -> Vec<String> {
+    GitCommand::new()
+        .current_dir(git_root())
+        .arg("ls-files")
+        .arg("--stage")
+        .stdout_str()
+        .lines()
+        // submodules are the entries with the gitlink mode
+        .filter_map(|line| line.strip_prefix("160000 "))
+        .filter_map(|line| line.split_once('\t').map(|(_, path)| path))
+        .filter(|path| worktree_file(path).join(".git").exists())
+        .map(String::from)
+        .collect()
+}
+
+/// Return the submodules whose uncommitted changes are part of a diff: with
+/// `opts.submodules`, a diff of the working tree includes the submodules in
+/// its scope, unless it's limited to some paths
+fn diffed_submodules(action: &DiffAction, opts: &GitDiffOpts) -> Vec<String> {
+    if !opts.submodules || !action.diffs_worktree() || !pathspecs().is_empty() {
+        return vec![];
+    }
+    let mut submodules = git_submodules();
+    if opts.scoped && !git_prefix().is_empty() {
+        submodules
+            .retain(|s| path_within(Path::new(s), Path::new(git_prefix())));
+    }
+    submodules
+}
+
+/// Return the submodule a file from a diff is in, if the diff includes the
+/// submodules' changes, with the file's path within the submodule
+fn file_submodule<'a>(
+    path: &'a str,
+    action: &DiffAction,
+    opts: &GitDiffOpts,
+) -> Option<(String, &'a str)> {
+    diffed_submodules(action, opts)
+        .into_iter()
+        .find_map(|submodule| {
+            let inner = path.strip_prefix(&submodule)?.strip_prefix('/')?;
+            Some((submodule, inner))
+        })
+}
+
+/// Start a command that diffs a submodule's uncommitted changes in some
+/// format; patches name the files by their paths from the repo root, like
+/// the repo's own files
+fn submodule_command(
+    submodule: &str,
+    opts: &GitDiffOpts,
+    format: &[&str],
+) -> GitCommand {
+    let mut cmd = GitCommand::new();
+    cmd.current_dir(worktree_file(submodule))
+        .arg("diff")
+        .arg("HEAD")
+        .arg(format!("--src-prefix=a/{}/", submodule))
+        .arg(format!("--dst-prefix=b/{}/", submodule));
+    for arg in format {
+        cmd.arg(arg);
+    }
+    cmd.arg(format!("--find-renames={}", RENAME_THRESHOLD));
+    cmd.arg(format!("--diff-algorithm={}", opts.algorithm));
+    if opts.ignore_whitespace {
+        cmd.arg("-w");
+    }
+    cmd
+}
+
+/// The hunks of a file's diff and the lines they change, read by
+/// `git_scan_changes`
+#[derive(Default)]
+struct FileChanges {
+    hunks: u32,
+    /// the added and deleted lines, without their line numbers
+    lines: DefaultHasher,
+}
+
+/// Count the hunks in the diff of each file in a set of stats that
+/// `git_diff_stat` returned for the same diff action and options, and take
+/// a fingerprint of each file's changed lines, so files changed the same way
+/// can be found
+pub fn git_scan_changes(
+    stats: &mut [Stat],
+    action: &DiffAction,
+    opts: Option<GitDiffOpts>,
+) {
+    let opts = opts.unwrap_or_default();
+    let context = format!("-U{}", opts.context.unwrap_or(DEFAULT_CONTEXT));
+    let mut output =
+        stat_command(action, &opts, &["-p", &context]).stdout_str();
+    for submodule in diffed_submodules(action, &opts) {
+        output.push('\n');
+        output.push_str(
+            &submodule_command(&submodule, &opts, &["-p", &context])
+                .stdout_str(),
+        );
+    }
+
+    // the files are diffed in the same order their stats are listed
+    let mut counts: Vec<FileChanges> = vec![];
+    let mut in_hunk = false;
+    for line in output.lines() {
+        if line.starts_with("diff ") {
+            counts.push(FileChanges::default());
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            if let Some(changes) = counts.last_mut() {
+                changes.hunks += 1;
+            }
+            in_hunk = true;
+        } else if in_hunk && (line.starts_with('+') || line.starts_with('-')) {
+            if let Some(changes) = counts.last_mut() {
+                // changes that only differ in spacing are the same change
+                let words: Vec<&str> = line[1..].split_whitespace().collect();
+                (&line[..1], words).hash(&mut changes.lines);
+            }
+        }
+    }
+    if counts.len() != stats.len() {
+        tracing::warn!(
+            "found {} diffs for {} stats of {}",
+            counts.len(),
+            stats.len(),
+            action
+        );
+        return;
+    }
+    for (stat, changes) in stats.iter_mut().zip(counts) {
+        stat.hunks = Some(changes.hunks);
+        // binary files have no lines to compare, and a renamed file is only
+        // changed the same way as other renamed files
+        if !stat.binary {
+            let mut id = changes.lines;
+            stat.old_path.is_empty().hash(&mut id);
+            stat.change_id = Some(id.finish());
+        }
+    }
+}
+
+/// Start a command that lists the changes to each file in a diff action in
+/// some format, like `--numstat`
+fn stat_command(
+    action: &DiffAction,
+    opts: &GitDiffOpts,
+    format: &[&str],
+) -> GitCommand {
+    let mut cmd = GitCommand::new();
 
     if action.is_show() {
         if action.target == Target::Staged || action.target == Target::Unstaged
@@ -171,25 +1073,27 @@ pub fn git_diff_stat(
         cmd.arg("--cached");
     }
 
-    cmd.arg("--numstat");
+    for arg in format {
+        cmd.arg(arg);
+    }
     cmd.arg(format!("--find-renames={}", RENAME_THRESHOLD));
+    cmd.arg(format!("--diff-algorithm={}", opts.algorithm));
 
     if opts.ignore_whitespace {
         cmd.arg("-w");
     }
+    if opts.submodules && action.diffs_worktree() {
+        // a dirty submodule's changes are listed in place of its entry
+        cmd.arg("--ignore-submodules=dirty");
+    }
 
-    match &action.target {
-        Target::Staged | Target::Unstaged => {}
-        Target::Ref(h) => {
-            cmd.arg(h);
-        }
+    add_range(&mut cmd, action);
+
+    if action.conflict_sides().is_none() {
+        add_scope(&mut cmd, opts.scoped);
     }
 
-    cmd.stdout_str()
-        .lines()
-        .filter(|x| !x.is_empty())
-        .map(Stat::new)
-        .collect()
+    cmd
 }
 
 /// Return a diff for a specific file between two commits
@@ -199,12 +1103,105 @@ pub fn git_diff_file(
     action: &DiffAction,
     opts: Option<GitDiffOpts>,
 ) -> FileDiff {
-    let opts = match opts {
-        Some(o) => o,
-        _ => GitDiffOpts::default(),
+    git_diff(Some((path, old_path)), action, opts)
+}
+
+/// Return the diff of every file changed by a diff action, one after another
+/// like `git show` prints them
+pub fn git_diff_all(
+    action: &DiffAction,
+    opts: Option<GitDiffOpts>,
+) -> FileDiff {
+    git_diff(None, action, opts)
+}
+
+/// Diff one file and its old path, or every file if none is given
+fn git_diff(
+    paths: Option<(&str, &str)>,
+    action: &DiffAction,
+    opts: Option<GitDiffOpts>,
+) -> FileDiff {
+    FileDiff::new(git_patch(paths, action, opts).trim())
+}
+
+/// Return the patch of one file and its old path, or of every file if none
+/// is given, as git prints it
+pub fn git_patch(
+    paths: Option<(&str, &str)>,
+    action: &DiffAction,
+    opts: Option<GitDiffOpts>,
+) -> String {
+    let opts = opts.unwrap_or_default();
+    let context = opts.context.map(|c| format!("-U{}", c));
+    let format = match &context {
+        Some(context) => vec!["-p", context.as_str()],
+        None => vec!["-p"],
     };
 
-    let command = &mut Command::new("git");
+    // a file in a submodule is diffed by the submodule's repo
+    if let Some((path, old_path)) = paths {
+        if let Some((submodule, path)) = file_submodule(path, action, &opts) {
+            let command = &mut submodule_command(&submodule, &opts, &format);
+            command.arg("--").arg(path);
+            if let Some((_, old_path)) = file_submodule(old_path, action, &opts)
+            {
+                command.arg(old_path);
+            }
+            return command.output().expect("git command should run").stdout;
+        }
+    }
+
+    let command = &mut diff_command(action);
+    command
+        .arg(format!("--diff-algorithm={}", opts.algorithm))
+        .arg("--format=")
+        .arg(format!("--find-renames={}", RENAME_THRESHOLD));
+    for arg in &format {
+        command.arg(arg);
+    }
+
+    if opts.ignore_whitespace {
+        command.arg("-w");
+    }
+
+    match paths {
+        Some((path, old_path)) => {
+            add_file_range(command, path, old_path, action)
+        }
+        None => {
+            if opts.submodules && action.diffs_worktree() {
+                command.arg("--ignore-submodules=dirty");
+            }
+            add_range(command, action);
+            add_scope(command, opts.scoped);
+        }
+    }
+
+    // a patch's last line ends with a newline, and may be blank context
+    let mut output = command.output().expect("git command should run").stdout;
+    if paths.is_none() {
+        for submodule in diffed_submodules(action, &opts) {
+            let command = &mut submodule_command(&submodule, &opts, &format);
+            output.push_str(
+                &command.output().expect("git command should run").stdout,
+            );
+        }
+    }
+    if action.conflict_sides().is_some() {
+        // git notes that the file is unmerged before diffing a stage of it
+        // with the working tree
+        output = output
+            .split_inclusive('\n')
+            .filter(|l| !l.starts_with("* Unmerged path "))
+            .collect();
+    }
+    tracing::debug!("got {} lines of output", output.lines().count());
+    output
+}
+
+/// Start a command that compares the two sides of a diff action
+fn diff_command(action: &DiffAction) -> GitCommand {
+    let mut command = GitCommand::new();
     command.current_dir(git_root());
 
     if action.is_show() {
@@ -223,13 +1220,43 @@ pub fn git_diff_file(
     }
 
     command
-        .arg("--patience")
-        .arg("--format=")
-        .arg(format!("--find-renames={}", RENAME_THRESHOLD))
-        .arg("-p");
+}
 
-    if opts.ignore_whitespace {
-        command.arg("-w");
+/// Add the commits of a diff action and the path of a file to a command
+/// started by `diff_command`
+fn add_file_range(
+    command: &mut GitCommand,
+    path: &str,
+    old_path: &str,
+    action: &DiffAction,
+) {
+    add_range(command, action);
+    if action.conflict_sides().is_some() {
+        // the conflicted file's path was added with its versions
+        return;
+    }
+
+    command.arg("--").arg(path);
+
+    if !old_path.is_empty() {
+        command.arg(old_path);
+    }
+}
+
+/// Add the commits of a diff action to a command started by `diff_command`
+fn add_range(command: &mut GitCommand, action: &DiffAction) {
+    if let Some(conflict) = action.conflict_sides() {
+        add_conflict_sides(command, conflict);
+        return;
+    }
+
+    let series = action.series();
+    if !series.is_empty() {
+        // git shows each commit in turn
+        for commit in &series {
+            command.arg(commit);
+        }
+        return;
     }
 
     if let Some(h) = &action.anchor {
@@ -239,19 +1266,851 @@ pub fn git_diff_file(
     if let Target::Ref(h) = &action.target {
         command.arg(h);
     }
+}
 
-    command.arg("--").arg(path);
+/// Add the versions of a conflicted file to a diff command: two stages are
+/// compared as blobs, which can't be followed by a path, and a stage is
+/// compared to the working tree with its flag, like `--ours`
+fn add_conflict_sides(command: &mut GitCommand, conflict: &ConflictSides) {
+    let stage = |s: Stage| format!(":{}:{}", s.number(), conflict.path);
+    match conflict.new {
+        Some(new) => {
+            command.arg(stage(conflict.old)).arg(stage(new));
+        }
+        None => {
+            command
+                .arg(format!("--{}", conflict.old))
+                .arg("--")
+                .arg(&conflict.path);
+        }
+    }
+}
 
-    if !old_path.is_empty() {
-        command.arg(old_path);
+/// One side of a file diff: a blob in the object database, or the file in the
+/// working tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileVersion {
+    Blob(String),
+    Worktree(String),
+}
+
+impl FileVersion {
+    /// Return the size of the file in bytes
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            FileVersion::Blob(id) => GitCommand::new()
+                .arg("cat-file")
+                .arg("-s")
+                .arg(id)
+                .stdout_str()
+                .parse()
+                .ok(),
+            FileVersion::Worktree(path) => {
+                fs::metadata(worktree_file(path)).ok().map(|m| m.len())
+            }
+        }
+    }
+
+    /// Return the contents of the file, base64 encoded
+    pub fn base64(&self) -> Option<String> {
+        match self {
+            FileVersion::Blob(id) => {
+                let output = GitCommand::new()
+                    .arg("cat-file")
+                    .arg("blob")
+                    .arg(id)
+                    .base64_stdout()
+                    .output()
+                    .ok()?;
+                output.success.then_some(output.stdout)
+            }
+            FileVersion::Worktree(path) => fs::read(worktree_file(path))
+                .ok()
+                .map(|bytes| base64_encode(&bytes)),
+        }
     }
 
+    /// Return the contents of the file as text
+    pub fn text(&self) -> Option<String> {
+        match self {
+            FileVersion::Blob(id) => {
+                let output = GitCommand::new()
+                    .arg("cat-file")
+                    .arg("blob")
+                    .arg(id)
+                    .output()
+                    .ok()?;
+                output.success.then_some(output.stdout)
+            }
+            FileVersion::Worktree(path) => fs::read(worktree_file(path))
+                .ok()
+                .map(|bytes| String::from_utf8_lossy(&bytes).into()),
+        }
+    }
+
+    /// Return a value that identifies the contents of the file
+    pub fn id(&self) -> String {
+        match self {
+            FileVersion::Blob(id) => id.clone(),
+            FileVersion::Worktree(path) => {
+                let modified = fs::metadata(worktree_file(path))
+                    .and_then(|m| m.modified())
+                    .ok();
+                format!("{}@{:?}", path, modified)
+            }
+        }
+    }
+}
+
+/// Return the old and new versions of a file changed by a diff action; a side
+/// is None if the file doesn't exist there
+pub fn git_file_versions(
+    path: &str,
+    old_path: &str,
+    action: &DiffAction,
+) -> (Option<FileVersion>, Option<FileVersion>) {
+    let command = &mut diff_command(action);
+    command.arg("--format=").arg("--raw").arg("--no-abbrev");
+    add_file_range(command, path, old_path, action);
+
+    // a raw line looks like ":<old mode> <new mode> <old id> <new id> <status>"
     let output = command.stdout_str();
-    tracing::debug!("got {} lines of output", output.lines().count());
-    FileDiff::new(&output, action)
+    let Some(fields) = output
+        .lines()
+        // a conflicted file is also listed as unmerged, with no versions
+        .find(|l| {
+            l.starts_with(':')
+                && !l.starts_with("::")
+                && !l.split('\t').next().is_some_and(|f| f.ends_with('U'))
+        })
+        .and_then(|l| l.split('\t').next())
+        .map(|l| l[1..].split(' ').collect::<Vec<_>>())
+    else {
+        return (None, None);
+    };
+    if fields.len() < 4 {
+        return (None, None);
+    }
+
+    let version = |mode: &str, id: &str| {
+        if mode.chars().all(|c| c == '0') {
+            None
+        } else if id.chars().all(|c| c == '0') {
+            Some(FileVersion::Worktree(path.into()))
+        } else {
+            Some(FileVersion::Blob(id.into()))
+        }
+    };
+
+    (version(fields[0], fields[2]), version(fields[1], fields[3]))
+}
+
+/// Run a git command that modifies the repo, returning its output or the
+/// error that git reported
+fn git_exec(cmd: &mut GitCommand) -> Result<String, AppError> {
+    let output = cmd.output()?;
+    if output.success {
+        Ok(output.stdout.trim().into())
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// Cherry-pick commits onto the current branch, oldest first; merges are
+/// picked relative to their first parent, one commit at a time, since older
+/// versions of git refuse `--mainline` for commits that aren't merges
+pub fn git_cherry_pick(commits: &[Commit]) -> Result<String, AppError> {
+    if commits.iter().all(|c| c.parent_refs.len() < 2) {
+        let cmd = &mut GitCommand::new();
+        cmd.arg("cherry-pick");
+        for commit in commits {
+            cmd.arg(&commit.commit_ref);
+        }
+        return git_exec(cmd);
+    }
+
+    let mut output = String::new();
+    for commit in commits {
+        let cmd = &mut GitCommand::new();
+        cmd.arg("cherry-pick");
+        if commit.parent_refs.len() > 1 {
+            cmd.arg("--mainline=1");
+        }
+        output = git_exec(cmd.arg(&commit.commit_ref))?;
+    }
+    Ok(output)
+}
+
+/// Revert a commit on the current branch; merges are reverted relative to
+/// their first parent
+pub fn git_revert(commit: &Commit) -> Result<String, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.arg("revert").arg("--no-edit");
+    if commit.parent_refs.len() > 1 {
+        cmd.arg("--mainline=1");
+    }
+    git_exec(cmd.arg(&commit.commit_ref))
+}
+
+/// The hooks that can stop a commit, which `--no-verify` skips
+const VERIFY_HOOKS: [&str; 2] = ["pre-commit", "commit-msg"];
+
+/// What a command that runs hooks printed
+#[derive(Debug, Clone, Default)]
+pub struct HookedOutput {
+    /// git's own report, like the new commit's summary
+    pub summary: String,
+    /// what the hooks printed, which git sends to stderr
+    pub hooks: String,
+}
+
+/// Run a git command that may run hooks; if it fails, the error has
+/// everything the hooks printed
+fn git_exec_hooked(cmd: &mut GitCommand) -> Result<HookedOutput, AppError> {
+    let output = cmd.output()?;
+    if output.success {
+        Ok(HookedOutput {
+            summary: output.stdout.trim().into(),
+            hooks: output.stderr.trim().into(),
+        })
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// Return the names of the installed hooks that can stop a commit
+pub fn git_commit_hooks() -> Vec<&'static str> {
+    VERIFY_HOOKS
+        .into_iter()
+        .filter(|hook| {
+            // core.hooksPath is followed when finding a hook's path
+            let path = GitCommand::new()
+                .arg("rev-parse")
+                .arg("--git-path")
+                .arg(format!("hooks/{}", hook))
+                .stdout_str();
+            fs::metadata(path).is_ok_and(|m| m.is_file())
+        })
+        .collect()
+}
+
+/// Commit the staged changes with the given message; a message that was
+/// written from a template has its comment lines removed, like it would in
+/// the editor `git commit` opens, and the pre-commit and commit-msg hooks
+/// are skipped unless `verify` is set
+pub fn git_commit(
+    message: &str,
+    template: bool,
+    verify: bool,
+) -> Result<HookedOutput, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.arg("commit");
+    if template {
+        cmd.arg("--cleanup=strip");
+    }
+    if !verify {
+        cmd.arg("--no-verify");
+    }
+    git_exec_hooked(cmd.arg("-m").arg(message))
+}
+
+/// Return the value of a git config setting, with a leading `~` expanded if
+/// it's a path
+fn git_config(key: &str, path: bool) -> Option<String> {
+    let cmd = &mut GitCommand::new();
+    cmd.arg("config");
+    if path {
+        cmd.arg("--path");
+    }
+    let output = cmd.arg(key).output().ok()?;
+    output.success.then(|| output.stdout.trim().to_string())
+}
+
+/// Return the text of the commit message template set by commit.template
+pub fn git_commit_template() -> Option<String> {
+    let path = git_config("commit.template", true)?;
+    fs::read_to_string(path).ok()
+}
+
+/// Return whether commit.verbose asks for the staged changes to be shown
+/// while a commit message is written
+pub fn git_commit_verbose() -> bool {
+    git_config("commit.verbose", false).is_some_and(|value| {
+        match value.to_lowercase().as_str() {
+            "" | "true" | "yes" | "on" => true,
+            value => value.parse::<i32>().is_ok_and(|n| n > 0),
+        }
+    })
+}
+
+/// Return the diff of the staged changes, as `git commit --verbose` shows it
+pub fn git_staged_diff() -> String {
+    GitCommand::new()
+        .arg("diff")
+        .arg("--cached")
+        .arg("--no-color")
+        .arg("--no-ext-diff")
+        .stdout_str()
+}
+
+/// Add a Signed-off-by trailer with the user's name and email to a commit
+/// message, unless the message already has the same one
+pub fn git_sign_off(message: &str) -> Result<String, AppError> {
+    let ident =
+        git_exec(GitCommand::new().arg("var").arg("GIT_COMMITTER_IDENT"))?;
+    // the ident ends with a timestamp, after the email
+    let Some((name, _)) = ident.rsplit_once('>') else {
+        return Err(AppError::GitFailed("no user name or email".into()));
+    };
+    git_exec(
+        GitCommand::new()
+            .arg("interpret-trailers")
+            .arg("--if-exists")
+            .arg("addIfDifferent")
+            .arg("--trailer")
+            .arg(format!("Signed-off-by: {}>", name))
+            .stdin(message),
+    )
+}
+
+/// Amend the HEAD commit with the staged changes, replacing its message if
+/// one is given; the pre-commit and commit-msg hooks are skipped unless
+/// `verify` is set
+pub fn git_amend(
+    message: Option<&str>,
+    verify: bool,
+) -> Result<HookedOutput, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.arg("commit").arg("--amend");
+    if !verify {
+        cmd.arg("--no-verify");
+    }
+    match message {
+        Some(m) => cmd.arg("-m").arg(m),
+        None => cmd.arg("--no-edit"),
+    };
+    git_exec_hooked(cmd)
+}
+
+/// Return the commit that introduced each line of a file, as the file is in
+/// a commit, or in the working tree if no commit is given
+pub fn git_blame(
+    path: &str,
+    commit: Option<&GitRef>,
+    ignore_revs: bool,
+) -> Result<Vec<BlameLine>, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.current_dir(git_root())
+        .arg("blame")
+        .arg("--line-porcelain");
+    if ignore_revs {
+        for arg in blame_ignore_args() {
+            cmd.arg(arg);
+        }
+    }
+    if let Some(commit) = commit {
+        cmd.arg(commit);
+    }
+    cmd.arg("--").arg(path);
+
+    // the output isn't trimmed, since that could change the last line
+    let output = cmd.output()?;
+    if output.success {
+        Ok(BlameLine::parse_all(&output.stdout))
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// The file in the root of a repo that lists revisions for blame to look
+/// past, by convention
+const BLAME_IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+static BLAME_IGNORE_REVS: OnceCell<Vec<String>> = OnceCell::new();
+
+/// Set the revisions from the config that blame looks past; this can only be
+/// set once
+pub fn set_blame_ignore_revs(revs: Vec<String>) {
+    BLAME_IGNORE_REVS.set(revs).ok();
+}
+
+/// Return the arguments that make blame look past the revisions in the
+/// repo's .git-blame-ignore-revs and the configured revisions that are in the
+/// repo, which git fails on if they aren't
+fn blame_ignore_args() -> &'static [String] {
+    static ARGS: OnceCell<Vec<String>> = OnceCell::new();
+    ARGS.get_or_init(|| {
+        if !has_capability(Capability::BlameIgnoreRevs) {
+            return vec![];
+        }
+        let mut args = vec![];
+        if Path::new(&git_root()).join(BLAME_IGNORE_REVS_FILE).exists() {
+            args.push("--ignore-revs-file".into());
+            args.push(BLAME_IGNORE_REVS_FILE.into());
+        }
+        for rev in BLAME_IGNORE_REVS.get().into_iter().flatten() {
+            match git_resolve_commit(rev, FULL_HASH_LEN) {
+                Some(commit) => {
+                    args.push("--ignore-rev".into());
+                    args.push(commit.to_string());
+                }
+                None => tracing::warn!("can't ignore unknown revision {}", rev),
+            }
+        }
+        args
+    })
 }
 
-/// Return true if there are changes across a range
-fn has_changes(commits: &DiffAction) -> bool {
-    !git_summary(commits).is_empty()
+/// Return true if blame can look past some revisions, from the repo's
+/// .git-blame-ignore-revs or the config
+pub fn git_blame_ignores_revs() -> bool {
+    !blame_ignore_args().is_empty()
+}
+
+/// Return the path of every file in a commit, relative to the repo root
+pub fn git_ls_tree(commit: &GitRef) -> Result<Vec<String>, AppError> {
+    let output = git_exec(
+        GitCommand::new()
+            .arg("ls-tree")
+            .arg("-r")
+            .arg("-z")
+            .arg("--name-only")
+            .arg("--full-tree")
+            .arg(commit),
+    )?;
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Return the contents of a file as it is in a commit
+pub fn git_show_file(commit: &GitRef, path: &str) -> Result<String, AppError> {
+    // the output isn't trimmed, since that could change the last line
+    let output = GitCommand::new()
+        .arg("show")
+        .arg(format!("{}:{}", commit, path))
+        .output()?;
+    if output.success {
+        Ok(output.stdout)
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// Search the files in a commit for lines that match a pattern, returning
+/// them with a few lines of context around each, if git can tell the context
+/// from the matches
+pub fn git_grep(
+    pattern: &str,
+    commit: &GitRef,
+) -> Result<Vec<GrepLine>, AppError> {
+    let columns = has_capability(Capability::GrepColumn);
+    let cmd = &mut GitCommand::new();
+    cmd.current_dir(git_root())
+        .arg("grep")
+        .arg("--null")
+        .arg("--line-number")
+        .arg("--full-name")
+        .arg("-I");
+    if columns {
+        cmd.arg("--column")
+            .arg(format!("--context={}", GREP_CONTEXT));
+    }
+    cmd.arg("-e").arg(pattern).arg(commit);
+    add_scope(cmd, false);
+
+    // git grep fails without an error message when nothing matches
+    let output = cmd.output()?;
+    if output.success || output.stderr.trim().is_empty() {
+        Ok(GrepLine::parse_all(
+            &output.stdout,
+            &commit.to_string(),
+            columns,
+        ))
+    } else {
+        Err(AppError::GitFailed(output.stderr.trim().into()))
+    }
+}
+
+/// The number of lines shown before and after each grep match
+const GREP_CONTEXT: u32 = 2;
+
+/// Find the newest commit that removed some text from a file, looking at the
+/// history of a commit, or of HEAD if no commit is given
+pub fn git_find_removal(
+    text: &str,
+    path: &str,
+    commit: Option<&GitRef>,
+) -> Option<GitRef> {
+    let cmd = &mut GitCommand::new();
+    cmd.current_dir(git_root())
+        .arg("log")
+        .arg(format!("-S{}", text))
+        .arg("--patch")
+        .arg("--unified=0")
+        .arg("--no-ext-diff")
+        .arg("--format=%x00%H");
+    match commit {
+        Some(commit) => cmd.arg(commit),
+        None => cmd.arg("HEAD"),
+    };
+    cmd.arg("--").arg(path);
+
+    // git -S finds the commits that changed how many times the text appears,
+    // which includes the ones that added it; a removal deletes more lines
+    // with the text than it adds
+    let output = cmd.stdout_str();
+    output.split('\0').find_map(|entry| {
+        let (hash, patch) = entry.split_once('\n').unwrap_or((entry, ""));
+        // a file's `---` and `+++` lines come before its first hunk, while a
+        // removed line can start with `--` too
+        let mut in_hunk = false;
+        let removed = patch.lines().fold(0, |count, line| {
+            if line.starts_with("diff ") {
+                in_hunk = false;
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+            }
+            if !in_hunk || !line.contains(text) {
+                count
+            } else if line.starts_with('-') {
+                count + 1
+            } else if line.starts_with('+') {
+                count - 1
+            } else {
+                count
+            }
+        });
+        (!hash.is_empty() && removed > 0).then(|| GitRef::new(hash.trim()))
+    })
+}
+
+/// Return the full commit message of a commit
+pub fn git_commit_message(commit: &str) -> String {
+    GitCommand::new()
+        .arg("log")
+        .arg("-1")
+        .arg("--format=%B")
+        .arg(commit)
+        .stdout_str()
+}
+
+/// A merge, rebase, cherry-pick, or revert that stopped partway, usually
+/// because of conflicts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+}
+
+impl Operation {
+    /// True if the operation can skip the commit it stopped at
+    pub fn can_skip(&self) -> bool {
+        *self != Operation::Merge
+    }
+}
+
+/// Operations are named by the git commands that run them
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Merge => "merge",
+            Operation::Rebase => "rebase",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+        })
+    }
+}
+
+/// A way to get out of an operation that stopped partway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationStep {
+    /// carry on after the conflicts have been resolved
+    Continue,
+    /// drop the commit the operation stopped at and carry on
+    Skip,
+    /// go back to where things were before the operation
+    Abort,
+}
+
+/// Steps are named by their flags
+impl Display for OperationStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OperationStep::Continue => "continue",
+            OperationStep::Skip => "skip",
+            OperationStep::Abort => "abort",
+        })
+    }
+}
+
+/// Return the operation that stopped partway in the repo, if there is one,
+/// from the state git keeps in the git directory
+pub fn git_operation() -> Option<Operation> {
+    let dir = git_dir();
+    // a rebase also picks commits, so it's checked for first
+    if dir.join("rebase-merge").is_dir() || dir.join("rebase-apply").is_dir() {
+        Some(Operation::Rebase)
+    } else if dir.join("MERGE_HEAD").is_file() {
+        Some(Operation::Merge)
+    } else if dir.join("CHERRY_PICK_HEAD").is_file() {
+        Some(Operation::CherryPick)
+    } else if dir.join("REVERT_HEAD").is_file() {
+        Some(Operation::Revert)
+    } else {
+        None
+    }
+}
+
+/// Continue, skip, or abort an operation that stopped partway, returning
+/// everything git printed, which is the error if the step failed
+pub fn git_operation_step(
+    operation: Operation,
+    step: OperationStep,
+) -> Result<String, AppError> {
+    let output = GitCommand::new()
+        .current_dir(git_root())
+        .arg(operation.to_string())
+        .arg(format!("--{}", step))
+        .output()?;
+    // git reports progress on stderr, even when the step succeeds
+    let text = [output.stdout.trim(), output.stderr.trim()]
+        .iter()
+        .filter(|t| !t.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if output.success {
+        Ok(text)
+    } else {
+        Err(AppError::GitFailed(text))
+    }
+}
+
+/// Return the paths that have unresolved merge conflicts
+pub fn git_conflicts() -> Vec<String> {
+    GitCommand::new()
+        .arg("diff")
+        .arg("--name-only")
+        .arg("--diff-filter=U")
+        .stdout_str()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+/// Apply a patch to the index, or remove it from the index if `reverse` is
+/// true
+pub fn git_apply_cached(
+    patch: &str,
+    reverse: bool,
+) -> Result<String, AppError> {
+    let cmd = &mut GitCommand::new();
+    cmd.current_dir(git_root()).arg("apply").arg("--cached");
+    if reverse {
+        cmd.arg("--reverse");
+    }
+    git_exec(cmd.arg("-").stdin(patch))
+}
+
+/// Discard the unstaged changes to a file, stashing them rather than throwing
+/// them away
+pub fn git_discard(path: &str) -> Result<(), AppError> {
+    let root = git_root();
+    git_stash_unstaged(&root, path)?;
+    git_exec(
+        GitCommand::new()
+            .current_dir(&root)
+            .arg("checkout")
+            .arg("--")
+            .arg(path),
+    )?;
+    Ok(())
+}
+
+/// Stash the unstaged changes to a file
+///
+/// `stash push --keep-index` would stash the staged changes too, and applying
+/// them again later would conflict with the ones left in the index, so the
+/// stash is built by hand on top of a commit of the index.
+fn git_stash_unstaged(root: &str, path: &str) -> Result<(), AppError> {
+    let git = |args: &[&str]| {
+        let cmd = &mut GitCommand::new();
+        cmd.current_dir(root);
+        for arg in args {
+            cmd.arg(arg);
+        }
+        git_exec(cmd)
+    };
+    let message = format!("discarded changes to {}", path);
+
+    // a stash is a commit of the working tree whose parents are the commit
+    // its changes are relative to and a commit of the index; here both hold
+    // the index, and need different messages to be different commits
+    let index_tree = git(&["write-tree"])?;
+    let base = git(&[
+        "commit-tree",
+        &index_tree,
+        "-p",
+        "HEAD",
+        "-m",
+        "staged changes",
+    ])?;
+    let index = git(&["commit-tree", &index_tree, "-p", &base, "-m", "index"])?;
+
+    // the file's working tree version is added to a copy of the index, so
+    // the real one is left alone
+    let temp_index = git_dir().join("de-discard-index");
+    let worktree_tree = (|| {
+        let temp = |args: &[&str]| {
+            let cmd = &mut GitCommand::new();
+            cmd.current_dir(root).env("GIT_INDEX_FILE", &temp_index);
+            for arg in args {
+                cmd.arg(arg);
+            }
+            git_exec(cmd)
+        };
+        temp(&["read-tree", &index_tree])?;
+        temp(&["add", "--", path])?;
+        temp(&["write-tree"])
+    })();
+    let _ = fs::remove_file(&temp_index);
+
+    let worktree = git(&[
+        "commit-tree",
+        &worktree_tree?,
+        "-p",
+        &base,
+        "-p",
+        &index,
+        "-m",
+        &message,
+    ])?;
+    git(&["stash", "store", "-m", &message, &worktree])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::git::{
+        runner::{with_runner, ExpectRunner},
+        testing::TestRepo,
+        RangeDiffStatus,
+    };
+
+    #[test]
+    fn range_diff_pairs_right_aligned_commits() {
+        let output = concat!(
+            " 9:  1a8b97e <  -:  ------- drop this\n",
+            "10:  25cfebd ! 10:  11dc409 change that\n",
+            "    @@ f\n",
+            "    -+old\n",
+            "    ++new\n",
+            " -:  ------- > 11:  3185834 add another\n",
+        );
+        let runner = Arc::new(
+            ExpectRunner::new()
+                .expect(&["range-diff", "--no-color", "old...new"], output),
+        );
+        let pairs = with_runner(runner.clone(), || {
+            git_range_diff(&["old...new"]).unwrap()
+        });
+        runner.assert_done();
+
+        let statuses: Vec<RangeDiffStatus> =
+            pairs.iter().map(|p| p.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                RangeDiffStatus::Removed,
+                RangeDiffStatus::Changed,
+                RangeDiffStatus::Added
+            ]
+        );
+        assert_eq!(pairs[0].new, None);
+        assert_eq!(pairs[1].subject, "change that");
+        assert_eq!(pairs[1].diff, ["@@ f", "-+old", "++new"]);
+        assert_eq!(pairs[2].new, Some(GitRef::new("3185834")));
+    }
+
+    #[test]
+    fn range_diff_reports_git_errors() {
+        let runner = Arc::new(ExpectRunner::new().expect_failure(
+            &["range-diff", "--no-color", "a...b"],
+            "fatal: ambiguous argument 'a...b'\n",
+        ));
+        let result = with_runner(runner, || git_range_diff(&["a...b"]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn find_removal_counts_removed_lines_that_start_with_dashes() {
+        let output = concat!(
+            "\0bbbbbbb\n\n",
+            "diff --git a/opts b/opts\n",
+            "--- a/opts\n",
+            "+++ b/opts\n",
+            "@@ -1 +0,0 @@\n",
+            "---verbose\n",
+            "\0aaaaaaa\n\n",
+            "diff --git a/opts b/opts\n",
+            "--- /dev/null\n",
+            "+++ b/opts\n",
+            "@@ -0,0 +1 @@\n",
+            "+--verbose\n",
+        );
+        let runner = Arc::new(
+            ExpectRunner::new()
+                .expect(&["rev-parse", "--show-toplevel"], "/repo\n")
+                .expect(
+                    &[
+                        "log",
+                        "-S--verbose",
+                        "--patch",
+                        "--unified=0",
+                        "--no-ext-diff",
+                        "--format=%x00%H",
+                        "HEAD",
+                        "--",
+                        "opts",
+                    ],
+                    output,
+                ),
+        );
+        let commit = with_runner(runner.clone(), || {
+            git_find_removal("--verbose", "opts", None)
+        });
+        runner.assert_done();
+        assert_eq!(commit, Some(GitRef::new("bbbbbbb")));
+    }
+
+    #[test]
+    fn discard_stashes_only_unstaged_changes() {
+        let repo = TestRepo::new("discard");
+        repo.commit(&[("a.txt", "one\ntwo\nthree\n")], "Add", "1700000000");
+        repo.write("a.txt", "one\nTWO\nthree\n");
+        repo.git(&["add", "a.txt"]);
+        repo.write("a.txt", "one\nTWO\nTHREE\n");
+
+        with_runner(repo.runner(), || git_discard("a.txt")).unwrap();
+        assert_eq!(repo.read("a.txt"), "one\nTWO\nthree\n");
+        assert_eq!(
+            repo.git(&["diff", "--cached", "--stat"]).lines().count(),
+            2
+        );
+        assert!(repo
+            .git(&["stash", "list"])
+            .contains("discarded changes to a.txt"));
+
+        repo.git(&["stash", "pop"]);
+        assert_eq!(repo.read("a.txt"), "one\nTWO\nTHREE\n");
+        assert_eq!(repo.git(&["show", ":a.txt"]), "one\nTWO\nthree\n");
+    }
 }
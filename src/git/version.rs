@@ -0,0 +1,117 @@
+use std::fmt::{self, Display, Formatter};
+
+use once_cell::sync::Lazy;
+
+use super::runner::GitCommand;
+
+/// The oldest git that supports every flag diff-explore relies on
+pub const MIN_GIT_VERSION: GitVersion = GitVersion::new(2, 2, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl GitVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> GitVersion {
+        GitVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse the output of `git --version`, e.g.
+    /// "git version 2.39.3 (Apple Git-145)"
+    pub fn parse(version: &str) -> Option<GitVersion> {
+        let number = version
+            .split_whitespace()
+            .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+        let mut parts = number
+            .split('.')
+            .map(|p| p.parse::<u32>().ok())
+            .take_while(|p| p.is_some())
+            .flatten();
+
+        Some(GitVersion::new(
+            parts.next()?,
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+        ))
+    }
+}
+
+impl Display for GitVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// An optional git feature that diff-explore can use when it's available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// `diff --color-moved`
+    ColorMoved,
+    /// `git range-diff`
+    RangeDiff,
+    /// `grep --column`
+    GrepColumn,
+    /// `blame --ignore-rev`
+    BlameIgnoreRevs,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [
+        Capability::ColorMoved,
+        Capability::RangeDiff,
+        Capability::GrepColumn,
+        Capability::BlameIgnoreRevs,
+    ];
+
+    /// The first git version that provides the capability
+    pub fn min_version(&self) -> GitVersion {
+        match self {
+            Capability::ColorMoved => GitVersion::new(2, 15, 0),
+            Capability::RangeDiff => GitVersion::new(2, 19, 0),
+            Capability::GrepColumn => GitVersion::new(2, 19, 0),
+            Capability::BlameIgnoreRevs => GitVersion::new(2, 23, 0),
+        }
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::ColorMoved => "moved line detection",
+            Capability::RangeDiff => "range-diff",
+            Capability::GrepColumn => "context around grep matches",
+            Capability::BlameIgnoreRevs => "ignoring revisions in blame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+static GIT_VERSION: Lazy<Option<GitVersion>> = Lazy::new(|| {
+    let output = GitCommand::new().arg("--version").output().ok()?;
+    GitVersion::parse(&output.stdout)
+});
+
+/// Return the version of the installed git, if it could be determined
+pub fn git_version() -> Option<GitVersion> {
+    *GIT_VERSION
+}
+
+/// Return true if the installed git supports a capability
+pub fn has_capability(capability: Capability) -> bool {
+    git_version().is_some_and(|v| v >= capability.min_version())
+}
+
+/// Return the capabilities the installed git lacks
+pub fn missing_capabilities() -> Vec<Capability> {
+    Capability::ALL
+        .into_iter()
+        .filter(|c| !has_capability(*c))
+        .collect()
+}
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, fmt};
+
 use crate::git::{Commit, GitRef};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,14 +24,44 @@ pub struct CommitCell {
 
     /// how the cell relates to the next row
     pub track: Track,
+
+    /// the most recent commit drawn in this cell's lane
+    pub last: Option<GitRef>,
+
+    /// the branch name associated with this cell's lane, if known
+    pub label: Option<String>,
 }
 
 impl CommitCell {
-    fn new(parent: Option<&GitRef>, related: GitRef, track: Track) -> CommitCell {
+    fn new(
+        parent: Option<&GitRef>,
+        related: GitRef,
+        track: Track,
+    ) -> CommitCell {
         CommitCell {
             parent: parent.cloned(),
             related,
             track,
+            last: None,
+            label: None,
+        }
+    }
+}
+
+/// A point where a branch diverged from or merged into another lane
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchPoint {
+    /// the first commit of a branch that forked from the named lane
+    Fork(String),
+    /// a merge commit that merged the named lane
+    Merge(String),
+}
+
+impl fmt::Display for BranchPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BranchPoint::Fork(name) => write!(f, "↘ forked from {}", name),
+            BranchPoint::Merge(name) => write!(f, "↗ merged {}", name),
         }
     }
 }
@@ -37,11 +69,22 @@ impl CommitCell {
 #[derive(Debug, Clone)]
 pub struct CommitRow {
     pub tracks: Vec<CommitCell>,
+    pub branch_points: Vec<BranchPoint>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CommitGraph {
     pub graph: Vec<CommitRow>,
+    /// the row of each commit in the graph
+    rows: HashMap<GitRef, usize>,
+    /// the branch name of HEAD, whose lane is considered the main line
+    head_label: Option<String>,
+    /// the label of the lane each commit's node was drawn in
+    node_labels: Vec<Option<String>>,
+    /// the forks found while building each row, with the rows that show them;
+    /// they're kept by the row that found them so that rebuilding rows drops
+    /// the forks those rows found
+    forks: Vec<Vec<(usize, BranchPoint)>>,
 }
 
 // Drawing rules
@@ -85,159 +128,409 @@ pub struct CommitGraph {
 
 impl CommitGraph {
     pub fn new(commits: &[Commit]) -> CommitGraph {
-        // use a vector of open branches to preserve order
-        let mut tracks: Vec<CommitCell> = vec![];
-        let mut prev_tracks: Vec<CommitCell> = vec![];
+        let mut graph = CommitGraph::default();
+        graph.extend(commits);
+        graph
+    }
 
-        CommitGraph {
-            graph: commits
+    /// Add rows for commits appended to the log the graph was built for,
+    /// rebuilding only the rows whose lanes the new commits change; returns
+    /// the first row that was built
+    ///
+    /// The graph is rebuilt from the start if the commits don't start with
+    /// the ones it was built for.
+    pub fn extend(&mut self, commits: &[Commit]) -> usize {
+        let old_len = self.graph.len();
+        let head_label = commits.iter().find_map(|c| c.decoration.head.clone());
+        let is_prefix = commits.len() >= old_len
+            && head_label == self.head_label
+            && commits[..old_len]
                 .iter()
-                .map(|c| {
-                    // used to walk through parent commits
-                    let mut parent_hash_iter = c.parent_refs.iter();
+                .enumerate()
+                .all(|(i, c)| self.rows.get(&c.commit_ref) == Some(&i));
+        if !is_prefix {
+            *self = CommitGraph {
+                head_label,
+                ..CommitGraph::default()
+            };
+        }
+        let old_len = self.graph.len();
 
-                    // initialize the current row of tracks with all the
-                    // non-None tracks from the previous row
-                    let temp_tracks: Vec<CommitCell> = tracks
-                        .iter()
-                        .filter(|t| t.parent.is_some()).cloned()
-                        .collect();
-
-                    tracks = vec![];
-
-                    let mut offset = 0;
-
-                    // add connections to the tracks in the previous row if
-                    // things have shifted
-                    (0..temp_tracks.len()).for_each(|i| {
-                        let pi = i + offset;
-
-                        if let Some(prev_track) = prev_tracks.get(pi) {
-                            // there's a track corresponding to this one in the
-                            // previous tracks list
-
-                            let parent = &temp_tracks[i].parent;
-
-                            if prev_track.parent == *parent {
-                                // the parent in the previous track is the same
-                                // as this track -- it's a continue
-                                tracks.push(CommitCell {
-                                    track: Track::Continue,
-                                    ..prev_track.clone()
-                                });
-                            } else if let Some(x) =
-                                prev_tracks.iter().skip(pi).position(|p| {
-                                    p.parent.is_some() && p.parent == *parent
-                                })
-                            {
-                                // this track's parent is in a later track (x)
-                                // in the previous tracks list; mark any
-                                // tracks between i and x as continuations of
-                                // x's parent, adding new tracks as necessary
-
-                                // the search started at pi
-                                let x = x + pi;
-
-                                // push a continue cell to parent
-                                tracks.push(CommitCell::new(
-                                    parent.as_ref(),
-                                    parent.clone().unwrap(),
-                                    Track::ContinueRight,
-                                ));
+        for (i, c) in commits.iter().enumerate().skip(old_len) {
+            self.rows.insert(c.commit_ref.clone(), i);
+        }
 
-                                // push some connector cells to get to the
-                                // target track
-                                for _ in pi + 1..x {
-                                    tracks.push(CommitCell::new(
-                                        None,
-                                        parent.clone().unwrap(),
-                                        Track::ContinueRight,
-                                    ));
-                                }
-
-                                // push a connector cell to the target track
-                                tracks.push(CommitCell::new(
-                                    None,
-                                    parent.clone().unwrap(),
-                                    Track::ContinueUp,
-                                ));
+        // a row is built again if one of its parents was left out of the log
+        // before, since the parent gets a lane now
+        let start = commits[..old_len]
+            .iter()
+            .position(|c| {
+                c.parent_refs
+                    .iter()
+                    .any(|p| self.rows.get(p).is_some_and(|r| *r >= old_len))
+            })
+            .unwrap_or(old_len);
+        self.graph.truncate(start);
+        self.node_labels.truncate(start);
+        self.forks.truncate(start);
 
-                                offset += x - i;
-                            }
-                        }
-                    });
+        // the lanes carried into each row are the ones of the row before it
+        let mut tracks: Vec<CommitCell> =
+            self.graph.last().map_or(vec![], |r| r.tracks.clone());
+        let mut prev_tracks: Vec<CommitCell> = tracks.clone();
+
+        for c in &commits[start..] {
+            let mut forks: Vec<(usize, BranchPoint)> = vec![];
+            let mut node_label: Option<String> = None;
 
-                    if let Some(x) = tracks
+            // used to walk through parent commits; parents that aren't in
+            // the log, like those a filtered log leaves out, don't get
+            // lanes, so the lanes end instead of running to the bottom
+            let mut parent_hash_iter =
+                c.parent_refs.iter().filter(|p| self.rows.contains_key(*p));
+            let name = lane_label(c);
+
+            // initialize the current row of tracks with all the
+            // non-None tracks from the previous row
+            let temp_tracks: Vec<CommitCell> = tracks
+                .iter()
+                .filter(|t| t.parent.is_some())
+                .cloned()
+                .collect();
+
+            tracks = vec![];
+
+            let mut offset = 0;
+
+            // add connections to the tracks in the previous row if
+            // things have shifted
+            (0..temp_tracks.len()).for_each(|i| {
+                let pi = i + offset;
+
+                if let Some(prev_track) = prev_tracks.get(pi) {
+                    // there's a track corresponding to this one in the
+                    // previous tracks list
+
+                    let parent = &temp_tracks[i].parent;
+
+                    if prev_track.parent == *parent {
+                        // the parent in the previous track is the same
+                        // as this track -- it's a continue
+                        tracks.push(CommitCell {
+                            track: Track::Continue,
+                            ..prev_track.clone()
+                        });
+                    } else if let Some(x) = prev_tracks
                         .iter()
-                        .position(|t| t.parent == Some(c.commit_ref.clone()))
+                        .skip(pi)
+                        .position(|p| p.parent.is_some() && p.parent == *parent)
                     {
-                        // this commit's hash is in the track list, so its node
-                        // will be inserted into the track list at the commit
-                        // hash's first occurrence
-
-                        // the hash of this commit's first parent (if it has
-                        // parents) will become the ancestor hash of this
-                        // commit's track
-                        if let Some(parent_hash) = parent_hash_iter.next() {
-                            tracks[x].parent = Some(parent_hash.clone());
-                            tracks[x].related =
-                                tracks[x].parent.clone().unwrap();
-                            tracks[x].track = Track::Node;
-                        }
+                        // this track's parent is in a later track (x)
+                        // in the previous tracks list; mark any
+                        // tracks between i and x as continuations of
+                        // x's parent, adding new tracks as necessary
 
-                        // clear out any other instances of this commit's hash
-                        // in the track list
-                        for y in x + 1..tracks.len() {
-                            if tracks[y].parent == Some(c.commit_ref.clone()) {
-                                tracks[y].related = c.commit_ref.clone();
-                                tracks[y].parent = None;
-
-                                if tracks[y].track == Track::ContinueRight {
-                                    // this is a continuation cell added during
-                                    // dead track removal -- find the end of the
-                                    // continuation run and replace that with a
-                                    // Branch
-                                    if let Some(t) =
-                                        tracks.iter().skip(y + 1).position(
-                                            |t| t.track == Track::ContinueUp,
-                                        )
-                                    {
-                                        tracks[y + 1 + t].track = Track::Branch;
-                                    }
-                                } else {
-                                    tracks[y].track = Track::Branch;
-                                }
-                            }
-                        }
-                    } else {
-                        // this commit's hash isn't in the tracks list -- create
-                        // a new track for it
-                        let hash = parent_hash_iter.next();
-                        if let Some(hash) = hash {
+                        // the search started at pi
+                        let x = x + pi;
+
+                        // push a continue cell to parent
+                        tracks.push(CommitCell {
+                            last: temp_tracks[i].last.clone(),
+                            label: temp_tracks[i].label.clone(),
+                            ..CommitCell::new(
+                                parent.as_ref(),
+                                parent.clone().unwrap(),
+                                Track::ContinueRight,
+                            )
+                        });
+
+                        // push some connector cells to get to the
+                        // target track
+                        for _ in pi + 1..x {
                             tracks.push(CommitCell::new(
-                                Some(hash),
-                                hash.clone(),
-                                Track::Node,
+                                None,
+                                parent.clone().unwrap(),
+                                Track::ContinueRight,
                             ));
                         }
-                    }
 
-                    // create tracks for all this commit's remaining parents
-                    for p in parent_hash_iter {
+                        // push a connector cell to the target track
                         tracks.push(CommitCell::new(
-                            Some(p),
-                            p.clone(),
-                            Track::Merge,
+                            None,
+                            parent.clone().unwrap(),
+                            Track::ContinueUp,
+                        ));
+
+                        offset += x - i;
+                    }
+                }
+            });
+
+            if let Some(x) = tracks
+                .iter()
+                .position(|t| t.parent == Some(c.commit_ref.clone()))
+            {
+                // this commit's hash is in the track list, so its node
+                // will be inserted into the track list at the commit
+                // hash's first occurrence
+
+                // the hash of this commit's first parent (if it has
+                // parents) will become the ancestor hash of this
+                // commit's track
+                let prev_last = tracks[x].last.clone();
+                if let Some(parent_hash) = parent_hash_iter.next() {
+                    tracks[x].parent = Some(parent_hash.clone());
+                    tracks[x].related = tracks[x].parent.clone().unwrap();
+                    tracks[x].track = Track::Node;
+                    tracks[x].last = Some(c.commit_ref.clone());
+                    if name.is_some() {
+                        tracks[x].label.clone_from(&name);
+                    }
+                } else {
+                    // the lane ends at this commit
+                    tracks[x].parent = None;
+                    tracks[x].related = c.commit_ref.clone();
+                    tracks[x].track = Track::Node;
+                    tracks[x].last = Some(c.commit_ref.clone());
+                }
+
+                // the lane of the checked out branch is the main line;
+                // if it collapses here, the lane it collapses into is
+                // the one that forked
+                let head_y = (x + 1..tracks.len()).find(|y| {
+                    tracks[*y].parent == Some(c.commit_ref.clone())
+                        && self.head_label.is_some()
+                        && tracks[*y].label == self.head_label
+                });
+                if let Some(y) = head_y {
+                    if let Some(i) =
+                        fork_start(commits, &self.rows, &prev_last, c)
+                    {
+                        forks.push((
+                            i,
+                            BranchPoint::Fork(self.head_label.clone().unwrap()),
                         ));
                     }
+                    tracks[x].label = tracks[y].label.clone();
+                }
 
-                    prev_tracks.clone_from(&tracks);
+                node_label.clone_from(&tracks[x].label);
+                let fork_label = tracks[x]
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| c.commit_ref.to_string());
 
-                    CommitRow {
-                        tracks: tracks.clone(),
+                // clear out any other instances of this commit's hash
+                // in the track list
+                for y in x + 1..tracks.len() {
+                    if tracks[y].parent == Some(c.commit_ref.clone()) {
+                        // the most recent commit in a collapsing lane
+                        // is the first commit of a branch that forked
+                        // from this commit
+                        if let Some(i) =
+                            fork_start(commits, &self.rows, &tracks[y].last, c)
+                        {
+                            if head_y != Some(y) {
+                                forks.push((
+                                    i,
+                                    BranchPoint::Fork(fork_label.clone()),
+                                ));
+                            }
+                        }
+
+                        tracks[y].related = c.commit_ref.clone();
+                        tracks[y].parent = None;
+
+                        if tracks[y].track == Track::ContinueRight {
+                            // this is a continuation cell added during
+                            // dead track removal -- find the end of the
+                            // continuation run and replace that with a
+                            // Branch
+                            if let Some(t) = tracks
+                                .iter()
+                                .skip(y + 1)
+                                .position(|t| t.track == Track::ContinueUp)
+                            {
+                                tracks[y + 1 + t].track = Track::Branch;
+                            }
+                        } else {
+                            tracks[y].track = Track::Branch;
+                        }
                     }
-                })
-                .collect::<Vec<CommitRow>>(),
+                }
+            } else {
+                // this commit's hash isn't in the tracks list -- create
+                // a new track for it
+                // a commit without parents in the log gets a lane that
+                // ends with it
+                let hash = parent_hash_iter.next();
+                tracks.push(CommitCell {
+                    last: Some(c.commit_ref.clone()),
+                    label: name.clone(),
+                    ..CommitCell::new(
+                        hash,
+                        hash.unwrap_or(&c.commit_ref).clone(),
+                        Track::Node,
+                    )
+                });
+                node_label = name;
+            }
+
+            // create tracks for all this commit's remaining parents
+            for p in parent_hash_iter {
+                tracks.push(CommitCell {
+                    last: Some(c.commit_ref.clone()),
+                    ..CommitCell::new(Some(p), p.clone(), Track::Merge)
+                });
+            }
+
+            prev_tracks.clone_from(&tracks);
+
+            self.graph.push(CommitRow {
+                tracks: tracks.clone(),
+                branch_points: vec![],
+            });
+            self.node_labels.push(node_label);
+            self.forks.push(forks);
+        }
+
+        self.update_branch_points(commits);
+        start
+    }
+
+    /// Gather the branch points of every row, from the forks found while
+    /// building the rows and the merges of the commits
+    fn update_branch_points(&mut self, commits: &[Commit]) {
+        for row in self.graph.iter_mut() {
+            row.branch_points.clear();
+        }
+        for forks in &self.forks {
+            for (row, point) in forks {
+                self.graph[*row].branch_points.push(point.clone());
+            }
+        }
+
+        // a merge is labeled with the lane its merged parent was drawn in
+        for (row, c) in commits.iter().enumerate() {
+            if let Some(merged) = c.parent_refs.get(1) {
+                if let Some(i) = self.rows.get(merged) {
+                    let label = self.node_labels[*i]
+                        .clone()
+                        .unwrap_or(merged.to_string());
+                    self.graph[row]
+                        .branch_points
+                        .push(BranchPoint::Merge(label));
+                }
+            }
         }
     }
 }
+
+/// Return the row of `last` if it is the first commit of a branch that forked
+/// from `fork_point`
+fn fork_start(
+    commits: &[Commit],
+    rows: &HashMap<GitRef, usize>,
+    last: &Option<GitRef>,
+    fork_point: &Commit,
+) -> Option<usize> {
+    let row = *rows.get(last.as_ref()?)?;
+    if commits[row].parent_refs.first() == Some(&fork_point.commit_ref) {
+        Some(row)
+    } else {
+        None
+    }
+}
+
+/// Return the branch name a commit gives to its lane, if it has one
+fn lane_label(commit: &Commit) -> Option<String> {
+    let deco = &commit.decoration;
+    deco.head
+        .clone()
+        .or_else(|| deco.branches.first().cloned())
+        .or_else(|| deco.refs.first().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(hash: &str, parents: &[&str]) -> Commit {
+        Commit::new(
+            hash.into(),
+            GitRef::from_strs(parents),
+            "",
+            "Ann Dev".into(),
+            "ann@example.com".into(),
+            None,
+            hash.into(),
+        )
+    }
+
+    /// The parts of a graph that are drawn, in a form that can be compared
+    fn shape(graph: &CommitGraph) -> Vec<String> {
+        graph
+            .graph
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .tracks
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{:?} {} {:?} {:?} {:?}",
+                            c.track,
+                            c.related,
+                            c.parent.as_ref().map(|p| p.to_string()),
+                            c.last.as_ref().map(|l| l.to_string()),
+                            c.label
+                        )
+                    })
+                    .collect();
+                format!("{} | {:?}", cells.join(", "), row.branch_points)
+            })
+            .collect()
+    }
+
+    /// A log with a merge of a side branch, newest first
+    fn log() -> Vec<Commit> {
+        vec![
+            commit("f", &["e"]),
+            commit("e", &["d", "c"]),
+            commit("d", &["b"]),
+            commit("c", &["b"]),
+            commit("b", &["a"]),
+            commit("a", &[]),
+        ]
+    }
+
+    #[test]
+    fn extend_appends_rows_for_a_new_page() {
+        let commits = log();
+        // e's parents are on the next page, so e gets new lanes
+        let mut graph = CommitGraph::new(&commits[..2]);
+        assert_eq!(graph.extend(&commits), 1);
+        assert_eq!(shape(&graph), shape(&CommitGraph::new(&commits)));
+
+        // nothing is rebuilt when there's nothing new
+        assert_eq!(graph.extend(&commits), commits.len());
+        assert_eq!(shape(&graph), shape(&CommitGraph::new(&commits)));
+    }
+
+    #[test]
+    fn extend_rebuilds_rows_whose_parents_were_missing() {
+        let commits = log();
+        // d and c lead to b, which the first page leaves out
+        let mut graph = CommitGraph::new(&commits[..4]);
+        assert_eq!(graph.extend(&commits), 2);
+        assert_eq!(shape(&graph), shape(&CommitGraph::new(&commits)));
+    }
+
+    #[test]
+    fn extend_starts_over_for_a_different_log() {
+        let commits = log();
+        let mut graph = CommitGraph::new(&commits[..3]);
+        assert_eq!(graph.extend(&commits[1..]), 0);
+        assert_eq!(shape(&graph), shape(&CommitGraph::new(&commits[1..])));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+};
+
+use crossterm::{cursor::MoveTo, queue};
+use ratatui::layout::Rect;
+
+use crate::string::base64_decode;
+
+/// The usual size of a terminal cell in pixels, used to estimate how many
+/// cells an image covers
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+
+/// The largest piece of image data kitty accepts in one escape sequence
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// An image format that may be shown inline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// Return the format of an image file, going by its extension
+    pub fn from_path(path: &str) -> Option<ImageFormat> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "gif" => Some(ImageFormat::Gif),
+            "webp" => Some(ImageFormat::Webp),
+            "bmp" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Gif => "GIF",
+            ImageFormat::Webp => "WebP",
+            ImageFormat::Bmp => "BMP",
+        }
+    }
+}
+
+/// Describe the type of a file, going by its extension
+pub fn file_type(path: &str) -> String {
+    if let Some(format) = ImageFormat::from_path(path) {
+        return format!("{} image", format.name());
+    }
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{} file", ext.to_uppercase()),
+        None => "binary file".into(),
+    }
+}
+
+/// An image to be drawn over part of the screen
+#[derive(Debug, Clone)]
+pub struct InlineImage {
+    /// where the image should be drawn
+    pub area: Rect,
+    /// identifies the image's contents
+    pub id: String,
+    /// the image file, base64 encoded
+    pub data: String,
+}
+
+/// A protocol that terminals use to show images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// kitty's graphics protocol
+    Kitty,
+    /// iTerm2's inline images protocol, which some other terminals also
+    /// support
+    Iterm,
+}
+
+impl ImageProtocol {
+    /// Return the image protocol the terminal supports, if it's one de knows
+    pub fn detect() -> Option<ImageProtocol> {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        if var("TERM").contains("kitty") || !var("KITTY_WINDOW_ID").is_empty() {
+            Some(ImageProtocol::Kitty)
+        } else if matches!(
+            var("TERM_PROGRAM").as_str(),
+            "iTerm.app" | "WezTerm"
+        ) || var("LC_TERMINAL") == "iTerm2"
+        {
+            Some(ImageProtocol::Iterm)
+        } else {
+            None
+        }
+    }
+
+    /// True if the protocol can show images in the given format
+    pub fn supports(&self, format: ImageFormat) -> bool {
+        match self {
+            // kitty decodes PNGs itself; other formats would have to be
+            // converted to pixels first
+            ImageProtocol::Kitty => format == ImageFormat::Png,
+            ImageProtocol::Iterm => true,
+        }
+    }
+
+    /// Draw an image, scaled to fit its area
+    pub fn show(
+        &self,
+        out: &mut impl Write,
+        image: &InlineImage,
+    ) -> io::Result<()> {
+        let area = image.area;
+        queue!(out, MoveTo(area.x, area.y))?;
+
+        match self {
+            ImageProtocol::Kitty => {
+                let (cols, rows) = fit_cells(image);
+                let chunks: Vec<&[u8]> =
+                    image.data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = (i + 1 < chunks.len()) as u8;
+                    // q=2 keeps kitty from sending replies, which would be
+                    // read as key presses
+                    let control = if i == 0 {
+                        format!(
+                            "a=T,f=100,q=2,c={},r={},m={}",
+                            cols, rows, more
+                        )
+                    } else {
+                        format!("m={}", more)
+                    };
+                    write!(out, "\x1b_G{};", control)?;
+                    out.write_all(chunk)?;
+                    write!(out, "\x1b\\")?;
+                }
+            }
+            ImageProtocol::Iterm => {
+                write!(
+                    out,
+                    "\x1b]1337;File=inline=1;width={};height={};\
+                     preserveAspectRatio=1:{}\x07",
+                    area.width, area.height, image.data
+                )?;
+            }
+        }
+
+        out.flush()
+    }
+
+    /// Remove any images that have been drawn; returns false if the images are
+    /// part of the text, so the screen has to be redrawn to remove them
+    pub fn clear(&self, out: &mut impl Write) -> io::Result<bool> {
+        match self {
+            ImageProtocol::Kitty => {
+                write!(out, "\x1b_Ga=d,q=2\x1b\\")?;
+                out.flush()?;
+                Ok(true)
+            }
+            ImageProtocol::Iterm => Ok(false),
+        }
+    }
+}
+
+/// Return the number of columns and rows an image should cover to fit its
+/// area without being stretched
+fn fit_cells(image: &InlineImage) -> (u16, u16) {
+    let area = image.area;
+    let Some((width, height)) = png_size(&image.data) else {
+        return (area.width, area.height);
+    };
+
+    let cols = width.div_ceil(CELL_WIDTH).max(1) as f64;
+    let rows = height.div_ceil(CELL_HEIGHT).max(1) as f64;
+    let scale = (area.width as f64 / cols)
+        .min(area.height as f64 / rows)
+        .min(1.0);
+
+    (
+        ((cols * scale).round() as u16).max(1),
+        ((rows * scale).round() as u16).max(1),
+    )
+}
+
+/// Return the width and height of a base64 encoded PNG, from its header
+fn png_size(data: &str) -> Option<(u32, u32)> {
+    // the signature and the start of the IHDR chunk take 16 bytes, followed
+    // by the width and height as big-endian u32s
+    let header = base64_decode(data.get(..32)?);
+    if header.get(..8)? != b"\x89PNG\r\n\x1a\n" {
+        return None;
+    }
+    let width = u32::from_be_bytes(header.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(header.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
@@ -1,12 +1,23 @@
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
 
 use directories::ProjectDirs;
+use once_cell::sync::OnceCell;
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{
     self, layer::SubscriberExt, util::SubscriberInitExt, Layer,
 };
 
-fn project_directory() -> Option<ProjectDirs> {
+/// The most messages written to the log file in a second, unless the
+/// DE_LOGRATE environment variable sets another limit
+const DEFAULT_LOG_RATE: u32 = 200;
+
+pub fn project_directory() -> Option<ProjectDirs> {
     ProjectDirs::from("com", "jasoncheatham", env!("CARGO_PKG_NAME"))
 }
 
@@ -20,28 +31,160 @@ pub fn get_data_dir(data_folder: Option<PathBuf>) -> PathBuf {
     }
 }
 
-pub fn initialize_logging() -> Result<(), std::io::Error> {
+/// Return the path of the log file, in the data directory
+pub fn log_file_path() -> PathBuf {
     let project_name = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
     let data_folder = std::env::var(format!("{}_DATA", project_name))
         .ok()
         .map(PathBuf::from);
-    let log_env = format!("{}_LOGLEVEL", project_name);
     let log_file = format!("{}.log", env!("CARGO_PKG_NAME"));
 
-    let directory = get_data_dir(data_folder);
-    std::fs::create_dir_all(directory.clone())?;
+    get_data_dir(data_folder).join(log_file)
+}
+
+/// The messages logged in the current second
+struct RateWindow {
+    start: Instant,
+    written: u32,
+    dropped: u64,
+}
+
+impl RateWindow {
+    fn new() -> RateWindow {
+        RateWindow {
+            start: Instant::now(),
+            written: 0,
+            dropped: 0,
+        }
+    }
+}
+
+/// A log file that drops the messages past a limit in each second, so a
+/// loop that logs on every pass can't grow the file without end or slow de
+/// down; the log says how many were dropped once the second is over, or
+/// when it's flushed
+struct RateLimitedFile {
+    file: Mutex<File>,
+    limit: u32,
+    window: Mutex<RateWindow>,
+}
+
+impl RateLimitedFile {
+    fn new(file: File, limit: u32) -> RateLimitedFile {
+        RateLimitedFile {
+            file: Mutex::new(file),
+            limit,
+            window: Mutex::new(RateWindow::new()),
+        }
+    }
+
+    fn lock_file(&self) -> MutexGuard<'_, File> {
+        // a thread that panicked while logging leaves the file usable
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_window(&self) -> MutexGuard<'_, RateWindow> {
+        self.window.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Note how many messages were dropped since the last note
+    fn write_dropped(&self, window: &mut RateWindow) {
+        if window.dropped > 0 {
+            writeln!(
+                self.lock_file(),
+                "... dropped {} log messages",
+                window.dropped
+            )
+            .ok();
+            window.dropped = 0;
+        }
+    }
+
+    fn flush(&self) {
+        self.write_dropped(&mut self.lock_window());
+        self.lock_file().flush().ok();
+    }
+
+    // this is called once for each message
+    fn make_writer(&self) -> LogWriter<'_> {
+        let mut window = self.lock_window();
+        if window.start.elapsed() >= Duration::from_secs(1) {
+            self.write_dropped(&mut window);
+            *window = RateWindow::new();
+        }
+
+        if window.written >= self.limit {
+            window.dropped += 1;
+            LogWriter::Dropped
+        } else {
+            window.written += 1;
+            LogWriter::File(self.lock_file())
+        }
+    }
+}
+
+/// The log file, which outlives the subscriber writing to it so it can be
+/// flushed when de exits
+static LOG_FILE: OnceCell<RateLimitedFile> = OnceCell::new();
+
+/// Flushes the log file when it's dropped, at the end of `main`
+pub struct LogGuard;
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        if let Some(file) = LOG_FILE.get() {
+            file.flush();
+        }
+    }
+}
+
+/// Where a single log message is written
+enum LogWriter<'a> {
+    File(MutexGuard<'a, File>),
+    Dropped,
+}
+
+impl Write for LogWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            LogWriter::File(file) => file.write(buf),
+            LogWriter::Dropped => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            LogWriter::File(file) => file.flush(),
+            LogWriter::Dropped => Ok(()),
+        }
+    }
+}
+
+pub fn initialize_logging() -> Result<LogGuard, std::io::Error> {
+    let project_name = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
+    let log_env = format!("{}_LOGLEVEL", project_name);
+
+    let log_path = log_file_path();
+    if let Some(directory) = log_path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
 
-    let log_path = directory.join(log_file);
     let log_file = std::fs::File::create(log_path.clone())?;
+    let log_rate = std::env::var(format!("{}_LOGRATE", project_name))
+        .ok()
+        .and_then(|rate| rate.parse().ok())
+        .unwrap_or(DEFAULT_LOG_RATE);
     let log_setting = std::env::var("RUST_LOG")
         .or_else(|_| std::env::var(log_env))
         .unwrap_or_else(|_| format!("{}=info", env!("CARGO_CRATE_NAME")));
 
     std::env::set_var("RUST_LOG", log_setting);
+    let log_file =
+        LOG_FILE.get_or_init(|| RateLimitedFile::new(log_file, log_rate));
     let file_subscriber = tracing_subscriber::fmt::layer()
         .with_file(true)
         .with_line_number(true)
-        .with_writer(log_file)
+        .with_writer(|| log_file.make_writer())
         .with_target(false)
         .with_ansi(false)
         .with_filter(tracing_subscriber::filter::EnvFilter::from_default_env());
@@ -49,7 +192,7 @@ pub fn initialize_logging() -> Result<(), std::io::Error> {
         .with(file_subscriber)
         .with(ErrorLayer::default())
         .init();
-    Ok(())
+    Ok(LogGuard)
 }
 
 /// Similar to the `std::dbg!` macro, but generates `tracing` events rather
@@ -1,47 +1,154 @@
 mod app;
+mod changelog;
+mod cli;
+mod config;
 mod error;
 mod events;
 mod git;
 mod graph;
+mod graphics;
 mod list;
 mod logging;
+mod script;
 mod search;
+mod session;
 mod stack;
 mod string;
+mod theme;
 mod time;
 mod ui;
 mod views;
 
 use app::App;
+use cli::{Args, USAGE};
+use config::Config;
 use error::AppError;
-use git::is_git_repo;
+use git::{git_version, is_git_repo, missing_capabilities, MIN_GIT_VERSION};
 use std::{
     env::{self, set_current_dir},
-    process::exit,
+    process::ExitCode,
 };
 
-fn main() -> Result<(), AppError> {
-    logging::initialize_logging()?;
+// exit codes are returned rather than exiting early, so that the log guard
+// is dropped and writes its last messages
+fn main() -> Result<ExitCode, AppError> {
+    let _log = logging::initialize_logging()?;
 
-    // Process command line arg
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        set_current_dir(args[1].clone())?;
+    // Process command line args
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            println!("{}\n\n{}", err, USAGE);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut config = Config::load()?;
+    if let Some(algorithm) = args.diff_algorithm {
+        config.diff.algorithm = algorithm;
+    }
+    let script = match &args.script {
+        Some(path) => match script::load(path) {
+            Ok(script) => Some(script),
+            Err(err) => {
+                println!("{}", err);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        None => None,
+    };
+
+    let mut replay = vec![];
+    if let Some(path) = &args.record {
+        session::record(path, &config.git)?;
+    } else if let Some(path) = &args.replay {
+        replay = session::replay(path)?;
+    } else {
+        git::init_runner(&config.git)?;
+    }
+
+    if let Some(dir) = &args.dir {
+        set_current_dir(dir)?;
+    }
+
+    // Verify that git is new enough
+    match git_version() {
+        Some(version) if version < MIN_GIT_VERSION => {
+            println!(
+                "git {} is too old; at least {} is required",
+                version, MIN_GIT_VERSION
+            );
+            return Ok(ExitCode::FAILURE);
+        }
+        None => {
+            println!("Unable to determine the git version; is git installed?");
+            return Ok(ExitCode::FAILURE);
+        }
+        _ => {}
+    }
+
+    for capability in missing_capabilities() {
+        tracing::warn!(
+            "git {} does not support {}; it will be disabled",
+            git_version().unwrap(),
+            capability
+        );
     }
 
     // Verify that we are in a git repo
     if !is_git_repo() {
         println!("Not a git repo");
-        exit(1);
+        return Ok(ExitCode::FAILURE);
+    }
+
+    git::set_log_limit(args.log_limit());
+    git::set_blame_ignore_revs(config.blame.ignore_revs.clone());
+    time::set_date_format(config.ui.date_format.clone());
+    git::set_author_format(config.ui.author);
+
+    if let Err(err) = git::set_pathspecs(args.paths) {
+        println!("{}", err.first_line());
+        return Ok(ExitCode::FAILURE);
+    }
+
+    // ages in a script's output are measured from the newest commit, so the
+    // output only changes with the repo
+    if script.is_some() {
+        if let Some(newest) = git::git_newest_commit_time() {
+            time::freeze_clock(newest);
+        }
     }
 
     // Initialize the app
-    let mut app = App::new()?;
+    let mut app = App::new(&config)?;
+    if let Some(name) = &args.profile {
+        if let Err(err) = app.use_profile(name) {
+            println!("{}", err);
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    app.replay(replay);
+
+    if let Some(script) = script {
+        tracing::info!("Running script");
+        if let Err(err) = app.run_script(&script, &mut std::io::stdout()) {
+            eprintln!("{}", err);
+            return Ok(ExitCode::FAILURE);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
 
     tracing::info!("Starting app");
 
     // Run the app
-    app.start();
+    if let Err(err) = app.start() {
+        eprintln!("{}", err);
+        return Ok(ExitCode::FAILURE);
+    }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,115 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::events::Key;
+
+/// The size of the screen a script draws, until it sets one
+pub const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Something a script does, in place of a user at a terminal
+#[derive(Debug)]
+pub enum Step {
+    /// press keys, named like they are in a recorded session
+    Keys(Vec<Key>),
+    /// type text, one character at a time
+    Type(String),
+    /// move the log to the commit a revision names
+    Goto(String),
+    /// search the top view and move to the first match
+    Search(String),
+    /// select a changed file in a list of files and open its diff
+    Open(String),
+    /// set the size of the screen
+    Size(u16, u16),
+    /// print the screen
+    Screen,
+    /// write the top view's changes as a patch to a file, or print them
+    Patch(Option<PathBuf>),
+}
+
+/// A script's steps, with the lines they're on
+pub type Script = Vec<(usize, Step)>;
+
+/// Read a script from a file, or from stdin if the path is `-`; patch files
+/// are relative to the directory de was started in
+pub fn load(path: &Path) -> Result<Script, String> {
+    let text = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+    .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+
+    let cwd = env::current_dir().unwrap_or_default();
+    let mut script = parse(&text)?;
+    for (_, step) in script.iter_mut() {
+        if let Step::Patch(Some(file)) = step {
+            *file = cwd.join(&file);
+        }
+    }
+    Ok(script)
+}
+
+/// Parse a script, with one step per line; blank lines and lines starting
+/// with `#` are skipped
+pub fn parse(text: &str) -> Result<Script, String> {
+    let mut script = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let nr = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        let needs_arg = |what: &str| {
+            if arg.is_empty() {
+                Err(format!("line {}: {} needs {}", nr, name, what))
+            } else {
+                Ok(arg.to_string())
+            }
+        };
+
+        let step = match name {
+            "key" => Step::Keys(
+                needs_arg("a key")?
+                    .split_whitespace()
+                    .map(|k| {
+                        Key::from_name(k).ok_or_else(|| {
+                            format!("line {}: unknown key '{}'", nr, k)
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            "type" => Step::Type(needs_arg("text")?),
+            "goto" => Step::Goto(needs_arg("a revision")?),
+            "search" => Step::Search(needs_arg("a pattern")?),
+            "open" => Step::Open(needs_arg("a path")?),
+            "size" => {
+                let size = arg
+                    .split_once(['x', ' '])
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|(w, h)| *w > 0 && *h > 0);
+                match size {
+                    Some((w, h)) => Step::Size(w, h),
+                    None => {
+                        return Err(format!(
+                        "line {}: size needs a width and height, like 80x24",
+                        nr
+                    ))
+                    }
+                }
+            }
+            "screen" => Step::Screen,
+            "patch" => Step::Patch(match arg {
+                "" | "-" => None,
+                file => Some(file.into()),
+            }),
+            _ => return Err(format!("line {}: unknown step '{}'", nr, name)),
+        };
+        script.push((nr, step));
+    }
+    Ok(script)
+}
@@ -1,29 +1,124 @@
+use std::ops::Range;
+
+use regex::Regex;
+
 use crate::list::{ListInfo, ListScroll};
 
+/// A search query, matched as plain text, or as a regular expression if it
+/// starts with `r/`
+#[derive(Debug, Clone)]
+pub enum SearchPattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl SearchPattern {
+    /// Parse a query, returning a description of the problem if it's an
+    /// invalid regular expression
+    pub fn new(query: &str) -> Result<SearchPattern, String> {
+        match query.strip_prefix("r/") {
+            Some(pattern) => {
+                Regex::new(pattern).map(SearchPattern::Regex).map_err(|e| {
+                    // regex errors quote the pattern over several lines,
+                    // ending with the actual problem
+                    let msg = e.to_string();
+                    let problem = msg.lines().last().unwrap_or_default();
+                    problem.trim_start_matches("error: ").into()
+                })
+            }
+            None => Ok(SearchPattern::Text(query.into())),
+        }
+    }
+
+    /// True if the pattern matches nothing, because it's empty
+    pub fn is_empty(&self) -> bool {
+        match self {
+            SearchPattern::Text(text) => text.is_empty(),
+            SearchPattern::Regex(re) => re.as_str().is_empty(),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            SearchPattern::Text(query) => text.contains(query.as_str()),
+            SearchPattern::Regex(re) => re.is_match(text),
+        }
+    }
+
+    /// Return the byte ranges of the non-empty matches in a text
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            SearchPattern::Text(query) if query.is_empty() => vec![],
+            SearchPattern::Text(query) => text
+                .match_indices(query.as_str())
+                .map(|(i, m)| i..i + m.len())
+                .collect(),
+            SearchPattern::Regex(re) => re
+                .find_iter(text)
+                .map(|m| m.range())
+                .filter(|r| !r.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Patterns are equal if they were parsed from the same query
+impl PartialEq for SearchPattern {
+    fn eq(&self, other: &SearchPattern) -> bool {
+        match (self, other) {
+            (SearchPattern::Text(a), SearchPattern::Text(b)) => a == b,
+            (SearchPattern::Regex(a), SearchPattern::Regex(b)) => {
+                a.as_str() == b.as_str()
+            }
+            _ => false,
+        }
+    }
+}
+
 pub trait Search: ListInfo + ListScroll {
     fn set_search(&mut self, search: Option<String>);
     fn get_search(&self) -> Option<String>;
     fn is_match(&self, index: usize) -> bool;
 
-    fn search_next(&mut self) {
+    /// Move to the next match, returning false if there isn't one
+    fn search_next(&mut self) -> bool {
         if self.get_search().is_some() {
             for i in self.list_pos() + 1..self.list_count() {
                 if self.is_match(i) {
                     self.scroll_to(i);
-                    break;
+                    return true;
                 }
             }
         }
+        false
     }
 
-    fn search_prev(&mut self) {
+    /// Move to the previous match, returning false if there isn't one
+    fn search_prev(&mut self) -> bool {
         if self.get_search().is_some() && self.list_pos() > 0 {
             for i in (0..self.list_pos()).rev() {
                 if self.is_match(i) {
                     self.scroll_to(i);
-                    break;
+                    return true;
                 }
             }
         }
+        false
+    }
+
+    /// Describe where the list is among the search's matches, like
+    /// "match 3/17", or return None if nothing is being searched for
+    fn match_status(&self) -> Option<String> {
+        if self.get_search().unwrap_or_default().is_empty() {
+            return None;
+        }
+        let matches: Vec<usize> = (0..self.list_count())
+            .filter(|i| self.is_match(*i))
+            .collect();
+        Some(match matches.binary_search(&self.list_pos()) {
+            _ if matches.is_empty() => "no matches".into(),
+            Ok(i) => format!("match {}/{}", i + 1, matches.len()),
+            Err(_) => format!("{} matches", matches.len()),
+        })
     }
 }
@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::{
+    config::GitConfig,
+    events::{AppEvent, Key},
+    git::{set_runner, RecordingRunner, ReplayRunner, SystemRunner},
+};
+
+/// A file that a session's keys and git's output are written to
+///
+/// Keys are written between the git invocations they led to, as
+/// `k <milliseconds> <key>` lines, and resizes of the terminal as
+/// `r <milliseconds> <width>x<height>` lines, which a ReplayRunner skips.
+#[derive(Clone)]
+struct SessionLog {
+    file: Arc<Mutex<File>>,
+    start: Instant,
+}
+
+impl Write for SessionLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+static RECORDING: OnceCell<SessionLog> = OnceCell::new();
+
+/// A key pressed or a resize during a recorded session, and when it happened
+pub struct RecordedEvent {
+    pub at: Duration,
+    pub event: AppEvent,
+}
+
+/// Record every key and git invocation to a file; this must happen before
+/// the first git command runs
+pub fn record(path: &Path, config: &GitConfig) -> io::Result<()> {
+    let log = SessionLog {
+        file: Arc::new(Mutex::new(File::create(path)?)),
+        start: Instant::now(),
+    };
+    let system = Box::new(SystemRunner::new(config));
+    set_runner(Box::new(RecordingRunner::new(
+        system,
+        Box::new(log.clone()),
+    )))?;
+    RECORDING.set(log).ok();
+    Ok(())
+}
+
+/// Add a key to the recording, if there is one
+pub fn record_key(key: &Key) {
+    record_event('k', &key.name());
+}
+
+/// Add a resize of the terminal to the recording, if there is one
+pub fn record_resize(width: u16, height: u16) {
+    record_event('r', &format!("{}x{}", width, height));
+}
+
+fn record_event(kind: char, value: &str) {
+    if let Some(log) = RECORDING.get() {
+        let record =
+            format!("{} {} {}\n", kind, log.start.elapsed().as_millis(), value);
+        let mut file = log.file.lock().unwrap();
+        if let Err(err) = file.write_all(record.as_bytes()) {
+            tracing::error!("Error recording event: {:?}", err);
+        }
+    }
+}
+
+/// Answer git commands from a recorded session instead of running git,
+/// returning the session's keys and resizes so they can happen again
+pub fn replay(path: &Path) -> io::Result<Vec<RecordedEvent>> {
+    set_runner(Box::new(ReplayRunner::new(BufReader::new(File::open(
+        path,
+    )?))?))?;
+
+    let mut events = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let Some((kind, record)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some((millis, value)) = record.split_once(' ') else {
+            continue;
+        };
+        // the app redraws at the terminal's size, so a resize is replayed
+        // as a redraw at the time it happened
+        let event = match kind {
+            "k" => Key::from_name(value).map(AppEvent::Input),
+            "r" => Some(AppEvent::Resize),
+            _ => None,
+        };
+        if let (Ok(millis), Some(event)) = (millis.parse(), event) {
+            events.push(RecordedEvent {
+                at: Duration::from_millis(millis),
+                event,
+            });
+        }
+    }
+    Ok(events)
+}
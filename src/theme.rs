@@ -0,0 +1,259 @@
+use std::{collections::BTreeMap, env, fs, path::Path, str::FromStr};
+
+use ratatui::{buffer::Buffer, style::Color};
+use serde::{Deserialize, Serialize};
+
+/// The name of the theme that leaves the terminal's colors alone
+pub const DEFAULT_THEME: &str = "default";
+
+/// Colors that replace the terminal's indexed colors, which de draws with
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    colors: BTreeMap<u8, Color>,
+}
+
+/// A theme file, which maps color indexes to colors like "#268bd2" or "blue"
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    colors: BTreeMap<String, String>,
+}
+
+impl Theme {
+    fn new(name: &str, colors: &[(u8, u32)]) -> Theme {
+        Theme {
+            name: name.into(),
+            colors: colors
+                .iter()
+                .map(|(i, rgb)| (*i, Color::from_u32(*rgb)))
+                .collect(),
+        }
+    }
+
+    /// Return the built-in themes, followed by any in the themes directory,
+    /// sorted by name
+    pub fn load_all(dir: &Path) -> Vec<Theme> {
+        let mut themes = vec![
+            Theme::new(DEFAULT_THEME, &[]),
+            Theme::new(
+                "gruvbox",
+                &[
+                    (0, 0x282828),
+                    (1, 0xfb4934),
+                    (2, 0xb8bb26),
+                    (3, 0xfabd2f),
+                    (4, 0x83a598),
+                    (5, 0xd3869b),
+                    (6, 0x8ec07c),
+                    (7, 0xa89984),
+                    (8, 0x504945),
+                    (15, 0xebdbb2),
+                ],
+            ),
+            Theme::new(
+                "solarized",
+                &[
+                    (0, 0x073642),
+                    (1, 0xdc322f),
+                    (2, 0x859900),
+                    (3, 0xb58900),
+                    (4, 0x268bd2),
+                    (5, 0xd33682),
+                    (6, 0x2aa198),
+                    (7, 0x93a1a1),
+                    (8, 0x586e75),
+                    (15, 0xfdf6e3),
+                ],
+            ),
+        ];
+
+        let mut installed: Vec<Theme> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| Theme::load(&entry.path()))
+            .filter(|t| themes.iter().all(|b| b.name != t.name))
+            .collect();
+        installed.sort_by(|a, b| a.name.cmp(&b.name));
+        themes.extend(installed);
+        themes
+    }
+
+    /// Read a theme from a TOML file, named after the file; returns None if
+    /// the file isn't a valid theme
+    fn load(path: &Path) -> Option<Theme> {
+        if path.extension()? != "toml" {
+            return None;
+        }
+        let name = path.file_stem()?.to_str()?;
+        let text = fs::read_to_string(path).ok()?;
+        let file: ThemeFile = match toml::from_str(&text) {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::warn!("Invalid theme {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        let colors = file
+            .colors
+            .iter()
+            .filter_map(|(i, color)| {
+                Some((i.parse().ok()?, Color::from_str(color).ok()?))
+            })
+            .collect();
+        Some(Theme {
+            name: name.into(),
+            colors,
+        })
+    }
+
+    /// Recolor a drawn frame, using only the colors the terminal supports
+    pub fn apply(&self, buf: &mut Buffer, support: ColorSupport) {
+        if self.colors.is_empty() && support == ColorSupport::TrueColor {
+            return;
+        }
+        let recolor = |color: Color| {
+            let color = match color {
+                Color::Indexed(i) => {
+                    self.colors.get(&i).copied().unwrap_or(color)
+                }
+                color => color,
+            };
+            support.fit(color)
+        };
+        for cell in buf.content.iter_mut() {
+            cell.fg = recolor(cell.fg);
+            cell.bg = recolor(cell.bg);
+        }
+    }
+}
+
+/// The colors a terminal can show
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSupport {
+    /// work it out from the environment
+    #[default]
+    Auto,
+    /// any RGB color
+    TrueColor,
+    /// the 256-color palette
+    #[serde(rename = "256")]
+    Palette256,
+    /// only the 16 basic colors
+    #[serde(rename = "16")]
+    Basic,
+}
+
+/// The standard xterm values of the 16 basic colors
+const BASIC_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each component in the 6x6x6 color cube of the 256-color
+/// palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Resolve `Auto` to what the terminal supports, going by COLORTERM and
+    /// TERM
+    pub fn resolve(self) -> ColorSupport {
+        if self != ColorSupport::Auto {
+            return self;
+        }
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let colorterm = var("COLORTERM");
+        let term = var("TERM");
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") || term.contains("kitty") {
+            ColorSupport::Palette256
+        } else {
+            ColorSupport::Basic
+        }
+    }
+
+    /// Return the nearest color the terminal can show
+    fn fit(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::Palette256, Color::Rgb(r, g, b)) => {
+                Color::Indexed(nearest_256((r, g, b)))
+            }
+            (ColorSupport::Basic, Color::Rgb(r, g, b)) => {
+                Color::Indexed(nearest_basic((r, g, b)))
+            }
+            (ColorSupport::Basic, Color::Indexed(i)) if i >= 16 => {
+                Color::Indexed(nearest_basic(palette_rgb(i)))
+            }
+            _ => color,
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Return the index of the basic color nearest to an RGB color
+fn nearest_basic(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8)
+        .min_by_key(|i| distance(rgb, BASIC_COLORS[*i as usize]))
+        .unwrap_or(0)
+}
+
+/// Return the index of the color in the 256-color palette's color cube or
+/// gray ramp nearest to an RGB color
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let gray = (232..=255u8)
+        .min_by_key(|i| distance(rgb, palette_rgb(*i)))
+        .unwrap_or(232);
+    if distance(rgb, palette_rgb(gray)) < distance(rgb, palette_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Return the usual RGB value of a color in the 256-color palette
+fn palette_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
@@ -1,4 +1,47 @@
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Local, Timelike, Utc,
+};
+use once_cell::sync::OnceCell;
+
+/// The strftime format of absolute dates, like 2024-05-01 13:37
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+static DATE_FORMAT: OnceCell<String> = OnceCell::new();
+
+static CLOCK: OnceCell<DateTime<Utc>> = OnceCell::new();
+
+/// Stop the clock at a time, so ages are measured from it instead of from
+/// the current time; this can only be set once, before any age is shown
+pub fn freeze_clock(now: DateTime<Utc>) {
+    CLOCK.set(now).ok();
+}
+
+/// Return the time the clock was frozen at, if it was
+pub fn frozen_clock() -> Option<DateTime<Utc>> {
+    CLOCK.get().copied()
+}
+
+/// Return the current time, or the time the clock was frozen at
+fn now() -> DateTime<Utc> {
+    CLOCK.get().copied().unwrap_or_else(Utc::now)
+}
+
+/// Set the format of absolute dates; this can only be set once, before any
+/// date is shown, and an invalid format is replaced with the default one
+pub fn set_date_format(format: String) {
+    let format = if StrftimeItems::new(&format).any(|i| i == Item::Error) {
+        tracing::warn!(
+            "Invalid date format '{}', using '{}'",
+            format,
+            DEFAULT_DATE_FORMAT
+        );
+        DEFAULT_DATE_FORMAT.into()
+    } else {
+        format
+    };
+    DATE_FORMAT.set(format).ok();
+}
 
 pub trait RelativeTime {
     /// Return a string describing the relative time between this timestamp and
@@ -8,7 +51,7 @@ pub trait RelativeTime {
 
 impl RelativeTime for DateTime<Utc> {
     fn relative_time(&self) -> String {
-        let now = Utc::now().naive_utc();
+        let now = now().naive_utc();
         if self.year() != now.year() {
             format!("{}Y", now.year() - self.year())
         } else if self.month() != now.month() {
@@ -24,3 +67,19 @@ impl RelativeTime for DateTime<Utc> {
         }
     }
 }
+
+pub trait AbsoluteTime {
+    /// Return this timestamp in the local time zone, in the configured date
+    /// format
+    fn absolute_time(&self) -> String;
+}
+
+impl AbsoluteTime for DateTime<Utc> {
+    fn absolute_time(&self) -> String {
+        let format = DATE_FORMAT
+            .get()
+            .map(|f| f.as_str())
+            .unwrap_or(DEFAULT_DATE_FORMAT);
+        self.with_timezone(&Local).format(format).to_string()
+    }
+}
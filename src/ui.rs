@@ -7,27 +7,53 @@ use crossterm::{
     },
 };
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{CrosstermBackend, TestBackend},
     layout::{Constraint, Direction, Layout, Rect},
-    style::Style,
+    style::{Color, Modifier, Style},
     text::Span,
     Frame, Terminal,
 };
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Write};
 
 use crate::{
-    app::{App, View},
-    list::ListInfo,
-    search::Search,
+    app::App,
+    git::pathspecs,
+    graphics::ImageProtocol,
+    search::SearchPattern,
     stack::Stack,
+    string::base64_encode,
     views::{
-        commitlog::CommitsView,
-        diff::{DiffView, DiffViewOpts},
-        stats::StatsView,
-        statusline::{Status, StatusLineView},
+        confirm::ConfirmView,
+        controller::RenderOpts,
+        input::InputView,
+        popup::PopupView,
+        statusline::{StatusLineView, StatusSegment},
     },
 };
 
+/// Copy text to the system clipboard with an OSC 52 escape sequence, which
+/// terminals support even when de is running on a remote host
+fn copy_to_clipboard(out: &mut impl Write, text: &str) -> io::Result<()> {
+    write!(out, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+    out.flush()
+}
+
+/// Draw the UI on a screen of a given width and height that isn't shown
+/// anywhere, and return its text, one line per row
+pub fn render_text(app: &mut App, (width, height): (u16, u16)) -> String {
+    let mut term = Terminal::new(TestBackend::new(width, height)).unwrap();
+    term.draw(|f| draw(f, app)).unwrap();
+
+    let buf = term.backend().buffer();
+    let mut text = String::new();
+    for y in 0..height {
+        let row: String = (0..width).map(|x| buf.get(x, y).symbol()).collect();
+        text.push_str(row.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
 /// Draw the UI
 fn draw(f: &mut Frame, app: &mut App) {
     let constraints =
@@ -41,54 +67,60 @@ fn draw(f: &mut Frame, app: &mut App) {
 
     let content_rect = parts[0];
     let search = app.entering_search();
+    let hint = app.key_hint().map(String::from);
 
-    match app.views.top_mut() {
-        Some(View::CommitLog(v)) => {
-            if let Some(s) = search {
-                app.statusline.set_status(search_status(s));
-            } else {
-                app.statusline.set_status(v.status());
-            }
-
-            app.statusline.set_location(v.list_pos(), v.list_count());
-            v.set_search(app.search.clone());
-            f.render_widget(CommitsView::new(v), content_rect);
-        }
+    if let Some(v) = app.views.top_mut() {
+        let matches = v.search().and_then(|s| {
+            s.set_search(app.search.clone());
+            s.match_status()
+        });
 
-        Some(View::Stats(v)) => {
-            if let Some(s) = search {
-                app.statusline.set_status(search_status(s));
-            } else {
-                app.statusline.set_status(v.status());
+        if let Some(s) = search {
+            let mut status = search_status(s);
+            status.extend(matches.map(StatusSegment::position));
+            app.statusline.set_status(status);
+        } else {
+            let mut status = v.status();
+            if !pathspecs().is_empty() {
+                status.push(StatusSegment::mode(pathspecs().join(" ")));
+            }
+            status.extend(matches.map(StatusSegment::position));
+            if let Some(hint) = hint {
+                status.push(StatusSegment::hint(hint));
             }
+            app.statusline.set_status(status);
+        }
 
-            app.statusline.set_location(v.list_pos(), v.list_count());
-            v.set_search(app.search.clone());
-            f.render_widget(StatsView::new(v), content_rect);
+        match v.location() {
+            Some((pos, count)) => app.statusline.set_location(pos, count),
+            None => app.statusline.clear_location(),
         }
 
-        Some(View::Diff(v)) => {
-            if let Some(s) = search {
-                app.statusline.set_status(search_status(s));
-            } else {
-                app.statusline.set_status(v.status());
-            }
+        let opts = RenderOpts {
+            tab_width: app.tab_width,
+            pane_split: app.pane_split,
+            images: app.images,
+            borders: app.borders,
+        };
+        v.render(f, content_rect, &opts);
+    }
 
-            app.statusline.set_location(v.list_pos(), v.list_count());
-            v.set_search(app.search.clone());
+    if let Some(m) = &app.message {
+        app.statusline
+            .set_status(vec![StatusSegment::message(m.clone())]);
+    }
 
-            let w = DiffView::new(
-                v,
-                Some(DiffViewOpts {
-                    tab_width: app.tab_width,
-                }),
-            );
+    if let Some(p) = &app.popup {
+        f.render_widget(PopupView::new(p), content_rect);
+    }
 
-            f.render_widget(w, content_rect);
-        }
+    if let Some(i) = &app.input {
+        f.render_widget(InputView::new(i), content_rect);
+    }
 
-        _ => {}
-    };
+    if let Some(c) = &app.confirm {
+        f.render_widget(ConfirmView::new(c), content_rect);
+    }
 
     f.render_widget(
         StatusLineView::new(&app.statusline),
@@ -99,6 +131,22 @@ fn draw(f: &mut Frame, app: &mut App) {
             height: 1,
         },
     );
+
+    if let Some(theme) = app.themes.get(app.theme) {
+        theme.apply(f.buffer_mut(), app.color_support);
+    }
+}
+
+/// The style of a search match; the current match, which n and N move to,
+/// stands out from the others; both are reversed so they still show on a
+/// list's highlighted row, which has its own background
+pub fn match_style(current: bool) -> Style {
+    let style = Style::default().add_modifier(Modifier::REVERSED);
+    if current {
+        style.fg(Color::Indexed(3)).add_modifier(Modifier::BOLD)
+    } else {
+        style
+    }
 }
 
 pub fn highlight_spans<'a>(
@@ -153,12 +201,58 @@ pub fn highlight_spans<'a>(
     }
 }
 
-fn search_status(query: String) -> String {
-    format!("/{}", query)
+/// Restyle the parts of a list of spans that match a search pattern
+pub fn highlight_matches<'a>(
+    spans: Vec<Span<'a>>,
+    pattern: &SearchPattern,
+    hl_style: Style,
+) -> Vec<Span<'a>> {
+    let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+    let matches = pattern.find(&text);
+    if matches.is_empty() {
+        return spans;
+    }
+
+    let mut styles: Vec<Style> = vec![];
+    for span in &spans {
+        styles.extend(std::iter::repeat_n(span.style, span.content.len()));
+    }
+    for m in matches {
+        styles[m].fill(hl_style);
+    }
+
+    let mut new_spans: Vec<Span> = vec![];
+    let mut start = 0;
+    for i in 1..=text.len() {
+        if i == text.len() || styles[i] != styles[start] {
+            new_spans
+                .push(Span::styled(text[start..i].to_string(), styles[start]));
+            start = i;
+        }
+    }
+    new_spans
+}
+
+/// Return a rect of the given size centered in `area`
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn search_status(query: String) -> Vec<StatusSegment> {
+    vec![StatusSegment::target(format!("/{}", query))]
 }
 
 pub struct Ui {
     term: Terminal<CrosstermBackend<Stdout>>,
+    /// the area and id of the image that has been drawn
+    image: Option<(Rect, String)>,
 }
 
 impl Ui {
@@ -175,14 +269,55 @@ impl Ui {
         let backend = CrosstermBackend::new(stdout);
         let term = Terminal::new(backend).unwrap();
 
-        Ui { term }
+        Ui { term, image: None }
     }
 
     pub fn update(&mut self, app: &mut App) {
         self.term.draw(|f| draw(f, app)).unwrap();
+        if let Some(text) = app.clipboard.take() {
+            if let Err(e) = copy_to_clipboard(self.term.backend_mut(), &text) {
+                tracing::warn!("Error copying to the clipboard: {}", e);
+            }
+        }
+        if let Some(protocol) = app.images {
+            self.update_image(app, protocol);
+        }
     }
 
-    pub fn stop(&mut self) {
+    /// Draw the current view's image, if it has one, replacing any image that
+    /// was drawn before
+    fn update_image(&mut self, app: &mut App, protocol: ImageProtocol) {
+        let image = app
+            .views
+            .top()
+            .and_then(|v| v.inline_image())
+            .map(|i| (i.area, i.id.clone()));
+        if image == self.image {
+            return;
+        }
+
+        if self.image.is_some() {
+            match protocol.clear(self.term.backend_mut()) {
+                Ok(true) => {}
+                // the image is part of the text, so everything is redrawn
+                Ok(false) => {
+                    self.term.clear().unwrap();
+                    self.term.draw(|f| draw(f, app)).unwrap();
+                }
+                Err(e) => tracing::warn!("Error clearing images: {}", e),
+            }
+        }
+
+        if let Some(image) = app.views.top().and_then(|v| v.inline_image()) {
+            if let Err(e) = protocol.show(self.term.backend_mut(), image) {
+                tracing::warn!("Error showing image: {}", e);
+            }
+        }
+        self.image = image;
+    }
+
+    /// Give the terminal back to the shell, e.g. to run an editor in it
+    pub fn suspend(&mut self) {
         disable_raw_mode().unwrap();
         execute!(
             self.term.backend_mut(),
@@ -192,4 +327,27 @@ impl Ui {
         .unwrap();
         self.term.show_cursor().unwrap();
     }
+
+    /// Take the terminal back after `suspend`, redrawing everything
+    pub fn resume(&mut self) {
+        enable_raw_mode().unwrap();
+        execute!(
+            self.term.backend_mut(),
+            EnterAlternateScreen,
+            EnableMouseCapture
+        )
+        .unwrap();
+        self.term.hide_cursor().unwrap();
+        self.term.clear().unwrap();
+        self.image = None;
+    }
+
+    pub fn stop(&mut self) {
+        if let (Some(_), Some(protocol)) =
+            (&self.image, ImageProtocol::detect())
+        {
+            let _ = protocol.clear(self.term.backend_mut());
+        }
+        self.suspend();
+    }
 }
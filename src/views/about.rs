@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+    Frame,
+};
+
+use crate::{
+    config::Config,
+    events::Key,
+    git::{git_root, git_version, missing_capabilities},
+    logging::log_file_path,
+    views::{
+        controller::{KeyResult, RenderOpts, ViewContext, ViewController},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// Diagnostic information about the app and its environment
+#[derive(Debug, Clone)]
+pub struct About {
+    entries: Vec<(&'static str, String)>,
+}
+
+impl About {
+    pub fn new(watched: &[PathBuf]) -> About {
+        let missing = missing_capabilities();
+        let watched = if watched.is_empty() {
+            "nothing".into()
+        } else {
+            watched
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        About {
+            entries: vec![
+                ("Version", env!("CARGO_PKG_VERSION").into()),
+                (
+                    "Git version",
+                    git_version()
+                        .map(|v| v.to_string())
+                        .unwrap_or("unknown".into()),
+                ),
+                (
+                    "Git features",
+                    if missing.is_empty() {
+                        "all supported".into()
+                    } else {
+                        format!(
+                            "missing {}",
+                            missing
+                                .iter()
+                                .map(|c| c.to_string())
+                                .collect::<Vec<String>>()
+                                .join(", ")
+                        )
+                    },
+                ),
+                ("Repo root", git_root()),
+                ("Config file", {
+                    let path = Config::path();
+                    if path.exists() {
+                        path.display().to_string()
+                    } else {
+                        format!("{} (not found)", path.display())
+                    }
+                }),
+                ("Log file", log_file_path().display().to_string()),
+                ("Watching", watched),
+            ],
+        }
+    }
+}
+
+impl Status for About {
+    fn status(&self) -> Vec<StatusSegment> {
+        vec![StatusSegment::target("About")]
+    }
+}
+
+impl ViewController for About {
+    fn handle_key(
+        &mut self,
+        keys: &[Key],
+        _ctx: &mut ViewContext,
+    ) -> KeyResult {
+        match keys {
+            // the about view is already open
+            [Key::Char('?')] => KeyResult::Handled,
+            _ => KeyResult::Ignored,
+        }
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, _opts: &RenderOpts) {
+        f.render_widget(AboutView::new(self), area);
+    }
+}
+
+/// The Widget used to render About
+pub struct AboutView<'a> {
+    about: &'a About,
+}
+
+impl<'a> AboutView<'a> {
+    pub fn new(about: &'a About) -> AboutView<'a> {
+        AboutView { about }
+    }
+}
+
+impl<'a> Widget for AboutView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let label_width = self
+            .about
+            .entries
+            .iter()
+            .map(|(l, _)| l.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![
+            Line::styled(
+                env!("CARGO_PKG_NAME"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Line::from(""),
+        ];
+        lines.extend(self.about.entries.iter().map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>width$}  ", label, width = label_width),
+                    Style::default().fg(Color::Indexed(4)),
+                ),
+                Span::from(value.clone()),
+            ])
+        }));
+
+        Paragraph::new(lines).render(area, buf);
+    }
+}
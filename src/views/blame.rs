@@ -0,0 +1,317 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_blame, git_blame_ignores_revs, BlameLine, GitDiffOpts, GitRef},
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    search::Search,
+    string::Ellipses,
+    time::RelativeTime,
+    ui::{highlight_spans, match_style},
+    views::{
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        diff::Diff,
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// The widest an author's name is shown
+const AUTHOR_WIDTH: usize = 16;
+
+/// The lines of a file, each with the commit that introduced it
+#[derive(Debug, Clone)]
+pub struct Blame {
+    list: ListData,
+    path: String,
+    /// the commit the file is blamed as of, or None for the working tree
+    commit: Option<GitRef>,
+    lines: Vec<BlameLine>,
+    search: Option<String>,
+    opts: GitDiffOpts,
+    /// look past the revisions listed in .git-blame-ignore-revs and the
+    /// config, like commits that only reformatted code
+    ignore_revs: bool,
+}
+
+impl Blame {
+    /// Blame a file as it is in a commit, or in the working tree if there's
+    /// no commit, with the cursor on a line
+    pub fn new(
+        path: &str,
+        commit: Option<&GitRef>,
+        line: u32,
+        opts: GitDiffOpts,
+    ) -> Result<Blame, String> {
+        let lines = git_blame(path, commit, true)
+            .map_err(|e| e.first_line().to_string())?;
+        let mut blame = Blame {
+            list: ListData::new(),
+            path: path.into(),
+            commit: commit.cloned(),
+            lines,
+            search: None,
+            opts,
+            ignore_revs: true,
+        };
+        blame.cursor_to(line.saturating_sub(1) as usize);
+        Ok(blame)
+    }
+
+    /// Switch between looking past the ignored revisions and blaming every
+    /// revision, keeping the cursor on the same line; returns whether
+    /// revisions are ignored now
+    fn toggle_ignore_revs(&mut self) -> Result<bool, String> {
+        if !git_blame_ignores_revs() {
+            return Err("No revisions are set to be ignored".into());
+        }
+        let ignore_revs = !self.ignore_revs;
+        self.lines = git_blame(&self.path, self.commit.as_ref(), ignore_revs)
+            .map_err(|e| e.first_line().to_string())?;
+        self.ignore_revs = ignore_revs;
+        let cursor = self.cursor().min(self.lines.len().saturating_sub(1));
+        self.cursor_to(cursor);
+        Ok(ignore_revs)
+    }
+
+    /// Open the diff of the commit that introduced the line at the cursor,
+    /// scrolled to the line
+    fn open_commit(&self, ctx: &mut ViewContext) {
+        let Some(line) = self.lines.get(self.cursor()) else {
+            return;
+        };
+        if line.is_uncommitted() {
+            ctx.message("The line hasn't been committed");
+            return;
+        }
+
+        // the line's number in the commit is its number on the new side of
+        // the commit's diff
+        match Diff::of_commit(&line.commit, &line.orig_path, self.opts) {
+            Ok(mut diff) => {
+                diff.goto_line(line.orig_line);
+                ctx.push(diff);
+            }
+            Err(e) => ctx.message(e),
+        }
+    }
+}
+
+impl ListInfo for Blame {
+    fn list_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for Blame {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for Blame {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for Blame {
+    fn status(&self) -> Vec<StatusSegment> {
+        let mut status = vec![StatusSegment::target(match &self.commit {
+            Some(commit) => format!("{} at {}", self.path, commit),
+            None => self.path.clone(),
+        })];
+        if let Some(line) = self.lines.get(self.cursor()) {
+            if !line.is_uncommitted() {
+                status.push(StatusSegment::mode(line.short_commit()));
+            }
+        }
+        if self.ignore_revs && git_blame_ignores_revs() {
+            status.push(StatusSegment::mode("ignoring revisions"));
+        }
+        status
+    }
+}
+
+impl Search for Blame {
+    fn set_search(&mut self, search: Option<String>) {
+        self.search = search;
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    fn is_match(&self, idx: usize) -> bool {
+        match &self.search {
+            Some(search) => {
+                let line = &self.lines[idx];
+                line.text.contains(search)
+                    || line.author_name.contains(search)
+                    || line.commit.contains(search)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for Blame {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => self.open_commit(ctx),
+            [Key::Char('i')] => match self.toggle_ignore_revs() {
+                Ok(true) => ctx.message("Ignoring the listed revisions"),
+                Ok(false) => ctx.message("Blaming every revision"),
+                Err(e) => ctx.message(e),
+            },
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("Blame — {}", self.path));
+        let mut view = BlameView::new(self, opts.tab_width);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter", "commit diff"), ("/", "search"), ("q", "back")]
+    }
+}
+
+/// The Widget used to render a Blame
+pub struct BlameView<'a> {
+    blame: &'a mut Blame,
+    block: Option<Block<'a>>,
+    tab_width: u8,
+}
+
+impl<'a> BlameView<'a> {
+    pub fn new(blame: &'a mut Blame, tab_width: u8) -> BlameView<'a> {
+        BlameView {
+            blame,
+            block: None,
+            tab_width,
+        }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> BlameView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for BlameView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.blame.list.height = area.height as usize;
+
+        let author_width = self
+            .blame
+            .lines
+            .iter()
+            .map(|l| l.author_name.len())
+            .max()
+            .unwrap_or(0)
+            .min(AUTHOR_WIDTH);
+        let number_width = self.blame.lines.len().to_string().len();
+        let tab = " ".repeat(self.tab_width as usize);
+
+        let cursor = self.blame.cursor();
+        let items: Vec<ListItem> = self
+            .blame
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let (commit, author) = if line.is_uncommitted() {
+                    ("       ".into(), "".into())
+                } else {
+                    (line.short_commit(), line.author_name.clone())
+                };
+                let mut spans = vec![
+                    Span::styled(
+                        commit,
+                        Style::default().fg(Color::Indexed(5)),
+                    ),
+                    Span::from(" "),
+                    Span::styled(
+                        format!(
+                            "{:width$}",
+                            author.ellipses(author_width),
+                            width = author_width
+                        ),
+                        Style::default().fg(Color::Indexed(2)),
+                    ),
+                    Span::from(" "),
+                    Span::styled(
+                        format!("{:>3}", line.relative_time()),
+                        Style::default().fg(Color::Indexed(4)),
+                    ),
+                    Span::styled(
+                        format!(" {:>width$} ", i + 1, width = number_width),
+                        Style::default().fg(Color::Indexed(8)),
+                    ),
+                    Span::from(line.text.replace('\t', &tab)),
+                ];
+
+                if let Some(search) = &self.blame.search {
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
+                }
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.blame.list_state_mut());
+    }
+}
@@ -1,4 +1,8 @@
-use std::{cmp::min, collections::HashMap};
+use std::{
+    cmp::{max, min},
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 
 use once_cell::sync::Lazy;
 use ratatui::{
@@ -10,19 +14,48 @@ use ratatui::{
         Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget,
         Widget,
     },
+    Frame,
 };
 use regex::Regex;
 
 use crate::graph::CommitGraph;
 use crate::{
-    git::{git_log, git_log_message, Commit, GitRef, Target},
+    changelog::changelog,
+    config::Profile,
+    events::{AppEvent, Key},
+    git::{
+        author_format, git_commit_details, git_commit_template,
+        git_commit_verbose, git_commits_touching, git_diff_stat, git_id,
+        git_is_ancestor, git_log, git_log_includes, git_merge_base, git_patch,
+        git_prefix, git_range_log, git_resolve_commit, git_staged_diff,
+        git_worktree_changes, log_limit, pathspecs, web_url, AuthorFormat,
+        Commit, CommitDetails, DiffAction, GitDiffOpts, GitRef, LogFilter,
+        MergeFilter, OperationStep, Pickaxe, Stat, Target, WorktreeChanges,
+        FULL_HASH_LEN, LOG_PAGE_SIZE,
+    },
     graph::{CommitRow, Track},
     list::{ListCursor, ListData, ListInfo, ListScroll},
     search::Search,
     string::Ellipses,
-    time::RelativeTime,
-    ui::highlight_spans,
-    views::statusline::Status,
+    time::{AbsoluteTime, RelativeTime},
+    ui::{highlight_spans, match_style},
+    views::{
+        confirm::{describe_commits, Confirm, ConfirmAction},
+        controller::{
+            handle_cursor_keys, handle_scroll_keys, Focus, KeyResult,
+            RenderOpts, ViewContext, ViewController, DEFAULT_PANE_SPLIT,
+            FLASH_DURATION,
+        },
+        diff::Diff,
+        finder::FileFinder,
+        input::{Input, InputAction},
+        patchids::PatchIds,
+        popup::Popup,
+        refs::RefPicker,
+        stats::Stats,
+        statusline::{Status, StatusSegment},
+        tree::Tree,
+    },
 };
 
 /// Formatted fields that are used for searching and rendering
@@ -35,14 +68,40 @@ struct CommitFields {
     refs: Vec<String>,
     head: Option<String>,
     subject: String,
+    /// the change summary shown for staged and unstaged changes in place of
+    /// the age and author
+    summary: Option<String>,
+}
+
+/// How the age and author columns show each commit
+#[derive(Debug, Clone, Copy, Default)]
+struct ColumnStyle {
+    /// show commit dates rather than how long ago they were made
+    absolute_dates: bool,
+    author: AuthorFormat,
+}
+
+impl ColumnStyle {
+    /// Return a commit's age, like 3D, or its date, like 2024-05-01 13:37
+    fn age(&self, c: &Commit) -> String {
+        if self.absolute_dates {
+            c.absolute_time()
+        } else {
+            c.relative_time()
+        }
+    }
 }
 
 impl CommitFields {
-    fn new(c: &Commit) -> CommitFields {
+    fn new(
+        c: &Commit,
+        worktree: &WorktreeChanges,
+        style: ColumnStyle,
+    ) -> CommitFields {
         let deco = &c.decoration;
         CommitFields {
-            age: c.relative_time(),
-            author: c.author_name.clone(),
+            age: style.age(c),
+            author: c.author(style.author).into(),
             hash: c.commit_ref.clone(),
             branches: deco
                 .branches
@@ -53,6 +112,7 @@ impl CommitFields {
             tags: deco.tags.iter().map(|b| format!("<{}>", b)).collect(),
             refs: deco.refs.iter().map(|b| format!("<{}>", b)).collect(),
             subject: c.subject.clone(),
+            summary: worktree.summary(&c.commit_ref),
         }
     }
 
@@ -66,6 +126,280 @@ impl CommitFields {
             || (self.head.is_some()
                 && self.head.as_ref().unwrap().contains(query))
             || self.subject.contains(query)
+            || self.summary.as_ref().is_some_and(|s| s.contains(query))
+    }
+}
+
+/// The widths of the age and author columns, which are measured when the log
+/// is loaded rather than when it's drawn
+#[derive(Debug, Clone, Default)]
+struct ColumnWidths {
+    age: usize,
+    author: usize,
+}
+
+impl ColumnWidths {
+    fn new(
+        commits: &[Commit],
+        worktree: &WorktreeChanges,
+        style: ColumnStyle,
+    ) -> ColumnWidths {
+        let age = commits
+            .iter()
+            .map(|c| style.age(c).chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut author = min(
+            20,
+            commits
+                .iter()
+                .map(|c| c.author(style.author).chars().count())
+                .max()
+                .unwrap_or(0),
+        );
+
+        // summaries span the age and author columns, which are widened to fit
+        if let Some(len) = commits
+            .iter()
+            .filter_map(|c| worktree.summary(&c.commit_ref))
+            .map(|s| s.chars().count())
+            .max()
+        {
+            author = max(author, len.saturating_sub(age + 1));
+        }
+
+        ColumnWidths { age, author }
+    }
+
+    /// Widen the columns to fit some more commits, which have no summaries
+    fn widen(&mut self, commits: &[Commit], style: ColumnStyle) {
+        let more =
+            ColumnWidths::new(commits, &WorktreeChanges::default(), style);
+        self.age = max(self.age, more.age);
+        self.author = max(self.author, more.author);
+    }
+}
+
+/// How a commit reached by parent or child navigation is related to the
+/// commit navigation started from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Relation {
+    Parent,
+    Child,
+}
+
+/// A step of parent or child navigation, which is continued by repeating it
+/// from the commit it reached
+#[derive(Debug, Clone)]
+struct Hop {
+    relation: Relation,
+    origin: usize,
+    target: usize,
+    count: usize,
+}
+
+/// The details of the selected commit, shown below the log
+#[derive(Debug, Clone, Default)]
+struct Details {
+    /// the commit the details belong to
+    commit: Option<GitRef>,
+    details: CommitDetails,
+    /// the files the commit changed
+    stats: Vec<Stat>,
+    /// the parent link that Enter follows when the details have the focus
+    link: usize,
+    line_count: usize,
+    offset: usize,
+    height: usize,
+}
+
+impl Details {
+    /// Load a commit's details, unless they're already loaded
+    fn load(&mut self, commit: &GitRef) {
+        if self.commit.as_ref() == Some(commit) {
+            return;
+        }
+        // the staged and unstaged pseudo-commits only have changed files
+        let range = if commit.is_staged() {
+            self.details = CommitDetails::default();
+            DiffAction::staged()
+        } else if commit.is_unstaged() {
+            self.details = CommitDetails::default();
+            DiffAction::unstaged()
+        } else {
+            self.details = git_commit_details(commit);
+            DiffAction::show(Target::Ref(commit.clone()))
+        };
+        self.stats = git_diff_stat(&range, None);
+        self.commit = Some(commit.clone());
+        self.line_count = self.lines(false).len();
+        self.offset = 0;
+        self.link = 0;
+    }
+
+    /// Return the parent that the selected link points to
+    fn selected_parent(&self) -> Option<&GitRef> {
+        self.details.parents.get(self.link).map(|(p, _)| p)
+    }
+
+    /// Select the next or previous parent link, returning false if there's
+    /// only one
+    fn cycle_link(&mut self, forward: bool) -> bool {
+        let count = self.details.parents.len();
+        if count < 2 {
+            return false;
+        }
+        self.link = if forward {
+            (self.link + 1) % count
+        } else {
+            (self.link + count - 1) % count
+        };
+        true
+    }
+
+    /// Render the details; the selected parent link is highlighted if the
+    /// details have the focus
+    fn lines(&self, focused: bool) -> Vec<Line<'static>> {
+        let label = |text: &str| {
+            Span::styled(
+                format!("{:<11}", text),
+                Style::default().add_modifier(Modifier::BOLD),
+            )
+        };
+        let mut lines = vec![];
+
+        match &self.commit {
+            Some(c) if c.is_staged() || c.is_unstaged() => {
+                lines.push(Line::from(Span::styled(
+                    if c.is_staged() {
+                        "Staged changes"
+                    } else {
+                        "Unstaged changes"
+                    },
+                    Style::default().add_modifier(Modifier::BOLD),
+                )));
+            }
+            _ => {
+                let details = &self.details;
+                lines.push(Line::from(vec![
+                    label("Commit:"),
+                    Span::styled(
+                        details.hash.clone(),
+                        Style::default().fg(Color::Indexed(3)),
+                    ),
+                ]));
+                for (i, (parent, subject)) in details.parents.iter().enumerate()
+                {
+                    let mut style = Style::default().fg(Color::Indexed(5));
+                    if focused && i == self.link {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    lines.push(Line::from(vec![
+                        label("Parent:"),
+                        Span::styled(parent.to_string(), style),
+                        Span::from(format!(" {}", subject)),
+                    ]));
+                }
+                for (name, who, date) in [
+                    ("Author:", &details.author, &details.author_date),
+                    ("Committer:", &details.committer, &details.commit_date),
+                ] {
+                    lines.push(Line::from(vec![
+                        label(name),
+                        Span::styled(
+                            who.clone(),
+                            Style::default().fg(Color::Indexed(2)),
+                        ),
+                        Span::styled(
+                            format!("  {}", date),
+                            Style::default().fg(Color::Indexed(4)),
+                        ),
+                    ]));
+                }
+                lines.push(Line::default());
+                for line in details.message.lines() {
+                    lines.push(Line::from(format!("    {}", line)));
+                }
+            }
+        }
+
+        if !self.stats.is_empty() {
+            let adds: u32 = self.stats.iter().map(|s| s.adds).sum();
+            let deletes: u32 = self.stats.iter().map(|s| s.deletes).sum();
+            let count_width = |count: fn(&Stat) -> u32| {
+                self.stats
+                    .iter()
+                    .map(|s| count(s).to_string().len())
+                    .max()
+                    .unwrap_or(0)
+                    + 1
+            };
+            let adds_width = count_width(|s| s.adds);
+            let dels_width = count_width(|s| s.deletes);
+
+            lines.push(Line::default());
+            lines.push(Line::from(vec![
+                Span::from(format!(
+                    "{} file{} changed, ",
+                    self.stats.len(),
+                    if self.stats.len() == 1 { "" } else { "s" }
+                )),
+                Span::styled(
+                    format!("+{}", adds),
+                    Style::default().fg(Color::Indexed(2)),
+                ),
+                Span::from(" "),
+                Span::styled(
+                    format!("−{}", deletes),
+                    Style::default().fg(Color::Indexed(1)),
+                ),
+            ]));
+            for stat in &self.stats {
+                let (adds, deletes) = if stat.binary {
+                    ("-".to_string(), "-".to_string())
+                } else {
+                    (format!("+{}", stat.adds), format!("−{}", stat.deletes))
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{:>width$}", adds, width = adds_width),
+                        Style::default().fg(Color::Indexed(2)),
+                    ),
+                    Span::from(" "),
+                    Span::styled(
+                        format!("{:>width$}", deletes, width = dels_width),
+                        Style::default().fg(Color::Indexed(1)),
+                    ),
+                    Span::from(format!(" {}", stat.path)),
+                ]));
+            }
+        }
+
+        lines
+    }
+}
+
+impl ListInfo for Details {
+    fn list_count(&self) -> usize {
+        self.line_count
+    }
+
+    fn list_pos(&self) -> usize {
+        self.offset
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.offset = pos;
+    }
+}
+
+impl ListScroll for Details {
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn scroll_bottom(&mut self) {
+        self.scroll_to(self.line_count.saturating_sub(self.height));
     }
 }
 
@@ -74,23 +408,123 @@ pub struct CommitLog {
     list: ListData,
     commits: Vec<Commit>,
     mark: Option<usize>,
+    /// commits picked to be shown together, in the order they were picked
+    picked: Vec<GitRef>,
     graph: CommitGraph,
+    /// the rendered graph of the rows at the top of the log, which grows as
+    /// rows further down are drawn
+    graph_spans: Vec<Vec<Span<'static>>>,
+    /// the color of each lane in the graph, assigned in the order lanes are
+    /// first drawn
+    graph_colors: HashMap<GitRef, Color>,
+    /// the row of each commit in the log
+    index: HashMap<GitRef, usize>,
+    /// the rows of each row's children, nearest first
+    children: Vec<Vec<usize>>,
+    hop: Option<Hop>,
+    widths: ColumnWidths,
+    style: ColumnStyle,
     query: Option<String>,
     show_details: bool,
+    details: Details,
+    /// whether keys go to the log or the details
+    focus: Focus,
+    worktree: WorktreeChanges,
+    /// open diffs of all the changed files at once, rather than a list of the
+    /// files
+    whole_diffs: bool,
+    /// limits on which commits are shown
+    filter: LogFilter,
+    /// whether every commit that passes the filter has been loaded
+    complete: bool,
+    /// whether more commits are being loaded in the background
+    loading: bool,
+    /// the commit that was just jumped to, and when, so it can be
+    /// highlighted for a moment
+    flash: Option<(GitRef, Instant)>,
+    /// a path whose commits stand out from the rest, and the commits that
+    /// changed it
+    touching: Option<(String, HashSet<GitRef>)>,
 }
 
+/// How close the cursor gets to the last loaded commit before more are
+/// loaded
+const LOAD_MARGIN: usize = 200;
+
 impl CommitLog {
-    pub fn new() -> CommitLog {
-        let commits = git_log();
+    /// Create a log of every commit, or only those that changed the
+    /// directory de was started in if `scoped` is set
+    pub fn new(scoped: bool) -> CommitLog {
+        CommitLog::create(LogFilter {
+            scoped,
+            ..LogFilter::default()
+        })
+    }
+
+    /// Create a log of the commits that changed a file, with the cursor on
+    /// the given commit if it's one of them; returns None if no commits
+    /// changed the file
+    pub fn for_file(path: &str, commit: Option<&GitRef>) -> Option<CommitLog> {
+        let mut log = CommitLog::create(LogFilter {
+            path: Some(path.into()),
+            ..LogFilter::default()
+        });
+        if log.commits.is_empty() {
+            return None;
+        }
+        if let Some(row) = commit.and_then(|c| log.commit_row(c)) {
+            log.cursor_to(row);
+        }
+        Some(log)
+    }
+
+    fn create(filter: LogFilter) -> CommitLog {
+        let commits = git_log(&filter, 0, LOG_PAGE_SIZE);
+        let complete = commits.len() < LOG_PAGE_SIZE;
         let graph = CommitGraph::new(&commits);
+        let index = commit_index(&commits);
+        let children = commit_children(&commits, &index);
+        let style = ColumnStyle {
+            author: author_format(),
+            ..ColumnStyle::default()
+        };
+        let widths =
+            ColumnWidths::new(&commits, &WorktreeChanges::default(), style);
 
         CommitLog {
             list: ListData::new(),
             mark: None,
+            picked: vec![],
             commits,
             graph,
+            graph_spans: vec![],
+            graph_colors: HashMap::new(),
+            index,
+            children,
+            hop: None,
+            widths,
+            style,
             query: None,
             show_details: false,
+            details: Details::default(),
+            focus: Focus::First,
+            worktree: WorktreeChanges::default(),
+            whole_diffs: false,
+            filter,
+            complete,
+            loading: false,
+            flash: None,
+            touching: None,
+        }
+    }
+
+    /// Open a view of the changes in a range: the list of changed files, or
+    /// the diff of all of them
+    fn open_range(&self, range: DiffAction, ctx: &mut ViewContext) {
+        if self.whole_diffs {
+            ctx.push(Diff::all_files(&range, ctx.diff_opts));
+        } else {
+            ctx.push(Stats::new(range, ctx.diff_opts));
         }
     }
 
@@ -106,6 +540,37 @@ impl CommitLog {
         }
     }
 
+    /// Add the selected commit to the commits shown together, or take it out
+    /// if it's already there; returns false for uncommitted changes, which
+    /// can't be picked
+    pub fn toggle_pick(&mut self) -> bool {
+        let Target::Ref(commit) = self.get_selected() else {
+            return false;
+        };
+        match self.picked.iter().position(|p| *p == commit) {
+            Some(i) => {
+                self.picked.remove(i);
+            }
+            None => self.picked.push(commit),
+        }
+        true
+    }
+
+    /// Return a show of the picked commits, oldest first so that later
+    /// changes build on earlier ones, or None if no commits are picked
+    pub fn get_picked(&self) -> Option<DiffAction> {
+        let mut picked: Vec<(usize, &GitRef)> = self
+            .picked
+            .iter()
+            .filter_map(|p| self.commit_row(p).map(|row| (row, p)))
+            .collect();
+        // the log lists newer commits first
+        picked.sort_by_key(|(row, _)| std::cmp::Reverse(*row));
+        let commits: Vec<GitRef> =
+            picked.into_iter().map(|(_, p)| p.clone()).collect();
+        DiffAction::show_series(&commits)
+    }
+
     pub fn get_selected(&self) -> Target {
         let r = &self.commits[self.cursor()].commit_ref;
         if r.is_staged() {
@@ -117,12 +582,598 @@ impl CommitLog {
         }
     }
 
+    pub fn get_selected_commit(&self) -> &Commit {
+        &self.commits[self.cursor()]
+    }
+
     pub fn get_marked(&self) -> Option<GitRef> {
         self.mark.map(|m| self.commits[m].commit_ref.clone())
     }
 
+    /// Return a diff between the marked and selected commits, from the older
+    /// one to the newer one, or between the selected commit and the working
+    /// tree if nothing is marked
+    pub fn get_range(&self) -> DiffAction {
+        let selected = self.get_selected();
+        let Some(marked) = self.get_marked() else {
+            return self.follow_branch(DiffAction::diff(selected, None));
+        };
+
+        let marked_target = if marked.is_staged() {
+            Some(Target::Staged)
+        } else if marked.is_unstaged() {
+            Some(Target::Unstaged)
+        } else {
+            None
+        };
+        let range = match (selected, marked_target) {
+            // staged and unstaged changes are always the newer side
+            (Target::Ref(r), Some(target)) => DiffAction::diff(target, Some(r)),
+            (selected, Some(_)) => DiffAction::diff(selected, None),
+            (Target::Ref(r), None)
+                if r != marked && git_is_ancestor(&r, &marked) =>
+            {
+                DiffAction::diff(Target::Ref(marked), Some(r))
+            }
+            (selected, None) => DiffAction::diff(selected, Some(marked)),
+        };
+        self.follow_branch(range)
+    }
+
+    /// Have a diff follow the branch at its target commit, if there is one
+    fn follow_branch(&self, range: DiffAction) -> DiffAction {
+        let Target::Ref(target) = &range.target else {
+            return range;
+        };
+        let branch = self
+            .commits
+            .iter()
+            .find(|c| c.commit_ref == *target)
+            .and_then(|c| {
+                let deco = &c.decoration;
+                deco.head
+                    .iter()
+                    .chain(&deco.branches)
+                    .chain(deco.refs.iter().filter(|r| !r.ends_with("/HEAD")))
+                    .next()
+                    .cloned()
+            });
+        range.on_branch(branch)
+    }
+
+    /// Draft release notes for the commits from the marked commit to the
+    /// selected one, returning them with a description of the range
+    fn release_notes(&self) -> Result<(String, String), String> {
+        let range = self.get_range();
+        let (Some(anchor), Target::Ref(target)) =
+            (&range.anchor, &range.target)
+        else {
+            return Err(
+                "Mark a commit to draft release notes for the commits since it"
+                    .into(),
+            );
+        };
+        let commits = git_range_log(&range);
+        if commits.is_empty() {
+            return Err("There are no commits after the marked one".into());
+        }
+        let range_name = format!(
+            "{}..{}",
+            anchor,
+            range
+                .branch()
+                .map_or_else(|| target.to_string(), String::from)
+        );
+        Ok((
+            changelog(&range_name, &commits),
+            format!(
+                "{} commit{} in {}",
+                commits.len(),
+                if commits.len() == 1 { "" } else { "s" },
+                range_name
+            ),
+        ))
+    }
+
+    /// Return diffs from the merge base of the marked and selected commits to
+    /// each of them, if they're on different branches
+    pub fn get_branch_ranges(&self) -> Option<(DiffAction, DiffAction)> {
+        let (Target::Ref(selected), Some(marked)) =
+            (self.get_selected(), self.get_marked())
+        else {
+            return None;
+        };
+        if marked.is_staged() || marked.is_unstaged() {
+            return None;
+        }
+        if git_is_ancestor(&marked, &selected)
+            || git_is_ancestor(&selected, &marked)
+        {
+            return None;
+        }
+
+        let base = git_merge_base(&marked, &selected)?;
+        Some((
+            self.follow_branch(DiffAction::diff(
+                Target::Ref(marked),
+                Some(base.clone()),
+            )),
+            self.follow_branch(DiffAction::diff(
+                Target::Ref(selected),
+                Some(base),
+            )),
+        ))
+    }
+
+    /// Return the selected commit, or the commits listed from the marked one
+    /// to the selected one including both ends, oldest first; staged and
+    /// unstaged changes can't be among them
+    pub fn get_selected_commits(&self) -> Option<Vec<Commit>> {
+        let cursor = self.cursor();
+        let (newer, older) = match self.mark {
+            Some(m) => (m.min(cursor), m.max(cursor)),
+            None => (cursor, cursor),
+        };
+        let commits = self.commits.get(newer..=older)?;
+        if commits
+            .iter()
+            .any(|c| c.commit_ref.is_staged() || c.commit_ref.is_unstaged())
+        {
+            return None;
+        }
+        Some(commits.iter().rev().cloned().collect())
+    }
+
+    /// Reload the commit log, e.g. after an action that created commits
+    ///
+    /// As many commits as were loaded before are loaded again.
+    pub fn refresh(&mut self) {
+        let count = max(self.loaded_count(), LOG_PAGE_SIZE);
+        let commits = git_log(&self.filter, 0, count);
+        self.complete = commits.len() < count;
+        self.loading = false;
+        self.set_commits(commits);
+    }
+
+    /// Return the number of real commits in the log, which doesn't include
+    /// the pseudo-commits for staged and unstaged changes
+    fn loaded_count(&self) -> usize {
+        self.commits
+            .iter()
+            .filter(|c| {
+                !c.commit_ref.is_staged() && !c.commit_ref.is_unstaged()
+            })
+            .count()
+    }
+
+    /// Ask for the next page of commits if the cursor is near the last one
+    /// and there may be more
+    fn load_more(&mut self, ctx: &mut ViewContext) {
+        if self.complete
+            || self.loading
+            || self.cursor() + LOAD_MARGIN < self.commits.len()
+        {
+            return;
+        }
+        self.loading = true;
+        ctx.load_log = Some((self.filter.clone(), self.loaded_count()));
+    }
+
+    /// Add commits loaded in the background to the end of the log
+    fn append_commits(&mut self, commits: &[Commit]) {
+        self.loading = false;
+        self.complete = commits.len() < LOG_PAGE_SIZE;
+        self.commits.extend_from_slice(commits);
+        // rebuilds the graph and indexes, keeping the pseudo-commits; only
+        // the new page can make the columns wider
+        self.splice_worktree_changes(&self.worktree.clone());
+        self.widths.widen(commits, self.style);
+    }
+
+    /// Move the cursor to the commit a revision names, loading more of the
+    /// log until the commit is found; returns the commit's hash
+    pub fn goto_rev(&mut self, rev: &str) -> Result<GitRef, String> {
+        let full = git_resolve_commit(rev, FULL_HASH_LEN)
+            .ok_or_else(|| format!("{} isn't a commit", rev))?
+            .to_string();
+        // a commit the log leaves out would only be missed after loading the
+        // whole log
+        let in_log = self.commits.iter().any(|c| {
+            !c.commit_ref.is_staged()
+                && !c.commit_ref.is_unstaged()
+                && full.starts_with(&c.commit_ref.to_string())
+        });
+        if !in_log && !self.complete && !git_log_includes(&self.filter, &full) {
+            return Err(format!("{} isn't in the log", rev));
+        }
+        loop {
+            let row = self.commits.iter().position(|c| {
+                let r = &c.commit_ref;
+                !r.is_staged()
+                    && !r.is_unstaged()
+                    && full.starts_with(&r.to_string())
+            });
+            if let Some(row) = row {
+                self.hop = None;
+                self.cursor_to(row);
+                let commit = self.commits[row].commit_ref.clone();
+                self.flash = Some((commit.clone(), Instant::now()));
+                return Ok(commit);
+            }
+            if self.complete {
+                return Err(format!("{} isn't in the log", rev));
+            }
+            let commits =
+                git_log(&self.filter, self.loaded_count(), LOG_PAGE_SIZE);
+            self.append_commits(&commits);
+        }
+    }
+
+    /// Show only the commits whose messages match a pattern, or every commit
+    /// if there's no pattern; returns the number of commits shown
+    pub fn set_grep(&mut self, grep: Option<String>) -> Result<usize, String> {
+        self.set_filter(LogFilter {
+            grep,
+            ..self.filter.clone()
+        })
+    }
+
+    /// Show only the commits that add or remove some text, or every commit if
+    /// there's no pickaxe; returns the number of commits shown
+    pub fn set_pickaxe(
+        &mut self,
+        pickaxe: Option<Pickaxe>,
+    ) -> Result<usize, String> {
+        self.set_filter(LogFilter {
+            pickaxe,
+            ..self.filter.clone()
+        })
+    }
+
+    /// Describe how many commits a filter matched
+    fn describe_matches(&self, count: usize) -> String {
+        format!(
+            "{}{} commit{} match{}",
+            if self.complete { "" } else { "At least " },
+            count,
+            if count == 1 { "" } else { "s" },
+            if count == 1 { "es" } else { "" },
+        )
+    }
+
+    /// Switch to the next of showing every commit, only merges, and no
+    /// merges, skipping any that would leave the log empty; returns the
+    /// filter that's now used
+    pub fn cycle_merges(&mut self) -> Result<MergeFilter, String> {
+        let mut merges = self.filter.merges.next();
+        while merges != self.filter.merges {
+            let filter = LogFilter {
+                merges,
+                ..self.filter.clone()
+            };
+            if self.set_filter(filter).is_ok() {
+                return Ok(merges);
+            }
+            merges = merges.next();
+        }
+        Err("No other commits match".into())
+    }
+
+    /// Show only the commits that pass a filter, keeping the current filter
+    /// if none do; returns the number of commits shown
+    fn set_filter(&mut self, filter: LogFilter) -> Result<usize, String> {
+        let commits = git_log(&filter, 0, LOG_PAGE_SIZE);
+        if commits.is_empty() {
+            return Err("No commits match".into());
+        }
+        self.filter = filter;
+        self.complete = commits.len() < LOG_PAGE_SIZE;
+        self.loading = false;
+        self.set_commits(commits);
+        Ok(self.commits.len())
+    }
+
+    /// Switch between showing the commits that changed the directory de was
+    /// started in and every commit
+    pub fn toggle_scope(&mut self) -> Result<(), &'static str> {
+        if !pathspecs().is_empty() {
+            return Err("The log is limited to the paths de was given");
+        }
+        if git_prefix().is_empty() {
+            return Err("de was started at the top of the repo");
+        }
+        self.filter.scoped = !self.filter.scoped;
+        self.refresh();
+        Ok(())
+    }
+
+    /// Switch between showing how long ago commits were made and their dates
+    pub fn toggle_dates(&mut self) {
+        self.style.absolute_dates = !self.style.absolute_dates;
+        self.widths =
+            ColumnWidths::new(&self.commits, &self.worktree, self.style);
+    }
+
+    /// Switch between showing authors' names and the usernames of their
+    /// emails, returning the new format
+    pub fn toggle_author_format(&mut self) -> AuthorFormat {
+        self.style.author = self.style.author.next();
+        self.widths =
+            ColumnWidths::new(&self.commits, &self.worktree, self.style);
+        self.style.author
+    }
+
+    /// Switch to a profile's log settings, keeping the ones it doesn't set
+    fn use_profile(&mut self, profile: &Profile) {
+        if profile.details.is_some_and(|d| d != self.show_details) {
+            self.toggle_show_details();
+        }
+        if let Some(whole_diffs) = profile.whole_diffs {
+            self.whole_diffs = whole_diffs;
+        }
+        if let Some(absolute) = profile.absolute_dates {
+            self.style.absolute_dates = absolute;
+        }
+        if let Some(author) = profile.author {
+            self.style.author = author;
+        }
+        self.widths =
+            ColumnWidths::new(&self.commits, &self.worktree, self.style);
+    }
+
+    /// Replace the commits in the log, keeping the cursor on the same commit
+    /// if it's still there
+    fn set_commits(&mut self, commits: Vec<Commit>) {
+        let selected = self
+            .commits
+            .get(self.cursor())
+            .map(|c| c.commit_ref.clone());
+
+        self.commits = commits;
+        self.mark = None;
+        // the graph is built again, since the commits' branches may have moved
+        self.graph = CommitGraph::default();
+        self.set_worktree_changes(&git_worktree_changes(self.filter.scoped));
+
+        // commits that were just made may have changed the highlighted path
+        if let Some((path, _)) = self.touching.take() {
+            self.set_touching(Some(path));
+        }
+
+        // picked commits stay picked if they're still in the log
+        let picked = std::mem::take(&mut self.picked);
+        self.picked = picked
+            .into_iter()
+            .filter(|p| self.commit_row(p).is_some())
+            .collect();
+
+        // stay on the selected commit if it's still in the log
+        let cursor = selected.and_then(|r| self.commit_row(&r)).unwrap_or(min(
+            self.cursor(),
+            self.commits.len().saturating_sub(1),
+        ));
+        self.cursor_to(cursor);
+    }
+
+    /// Make the commits that changed a path stand out, or stop if there's no
+    /// path
+    pub fn set_touching(&mut self, path: Option<String>) {
+        self.touching = path.map(|path| {
+            let commits = git_commits_touching(&path);
+            (path, commits)
+        });
+    }
+
+    /// Return the row of a commit in the log
+    pub fn commit_row(&self, commit: &GitRef) -> Option<usize> {
+        self.index.get(commit).copied()
+    }
+
+    /// Return the rows of a commit's parents that are in the log
+    fn parent_rows(&self, row: usize) -> Vec<usize> {
+        self.commits[row]
+            .parent_refs
+            .iter()
+            .filter_map(|p| self.commit_row(p))
+            .collect()
+    }
+
+    /// Move the cursor to the selected commit's first parent, returning false
+    /// if the parent isn't in the log
+    pub fn cursor_to_parent(&mut self) -> bool {
+        self.hop = None;
+        let first_parent = self.commits[self.cursor()]
+            .parent_refs
+            .first()
+            .and_then(|p| self.commit_row(p));
+        match first_parent {
+            Some(row) => {
+                self.cursor_to(row);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the cursor to the next parent of the commit that parent
+    /// navigation started from, returning false if it has no parents in the
+    /// log
+    pub fn cycle_parents(&mut self) -> bool {
+        self.cycle(Relation::Parent)
+    }
+
+    /// Move the cursor to the next child of the commit that child navigation
+    /// started from, returning false if it has no children in the log
+    pub fn cycle_children(&mut self) -> bool {
+        self.cycle(Relation::Child)
+    }
+
+    fn cycle(&mut self, relation: Relation) -> bool {
+        let cursor = self.cursor();
+        let (origin, count) = match &self.hop {
+            Some(hop) if hop.relation == relation && hop.target == cursor => {
+                (hop.origin, hop.count)
+            }
+            _ => (cursor, 0),
+        };
+
+        let rows = match relation {
+            Relation::Parent => self.parent_rows(origin),
+            Relation::Child => self.children[origin].clone(),
+        };
+        if rows.is_empty() {
+            return false;
+        }
+
+        let target = rows[count % rows.len()];
+        self.cursor_to(target);
+        self.hop = Some(Hop {
+            relation,
+            origin,
+            target,
+            count: count + 1,
+        });
+        true
+    }
+
+    /// Replace the pseudo-commits for staged and unstaged changes at the top
+    /// of the log, keeping the cursor and mark on the same commits
+    pub fn set_worktree_changes(&mut self, changes: &WorktreeChanges) {
+        self.splice_worktree_changes(changes);
+        self.widths =
+            ColumnWidths::new(&self.commits, &self.worktree, self.style);
+    }
+
+    /// Replace the pseudo-commits and rebuild the rows after them, leaving
+    /// the column widths to the caller
+    fn splice_worktree_changes(&mut self, changes: &WorktreeChanges) {
+        let old_count = self
+            .commits
+            .iter()
+            .take_while(|c| {
+                c.commit_ref.is_staged() || c.commit_ref.is_unstaged()
+            })
+            .count();
+        let hash_len = self
+            .commits
+            .get(old_count)
+            .map_or(7, |c| c.commit_ref.len());
+        // a filtered log only has real commits, since the staged and
+        // unstaged changes may not pass the filter
+        let pseudo = if self.filter.is_scope_only() {
+            changes.commits(hash_len)
+        } else {
+            vec![]
+        };
+        let new_count = pseudo.len();
+        let marked_pseudo = self
+            .mark
+            .filter(|m| *m < old_count)
+            .map(|m| self.commits[m].commit_ref.clone());
+
+        self.commits.splice(..old_count, pseudo);
+        self.worktree = changes.clone();
+        // only the rows that changed are built again, like the rows of
+        // commits that were appended to the log
+        let start = self.graph.extend(&self.commits);
+        self.graph_spans.truncate(start);
+        if start == 0 {
+            self.graph_colors.clear();
+        }
+        self.index = commit_index(&self.commits);
+        self.children = commit_children(&self.commits, &self.index);
+        self.hop = None;
+        // the staged and unstaged messages may have changed
+        self.details = Details::default();
+
+        let shift = |i: usize| {
+            if i < old_count {
+                min(i, new_count.saturating_sub(1))
+            } else {
+                i + new_count - old_count
+            }
+        };
+        let cursor = shift(self.cursor());
+        self.cursor_to(cursor);
+        self.mark = match marked_pseudo {
+            // a mark on the staged or unstaged changes stays with them, and
+            // goes away when they do
+            Some(marked) => self.commits[..new_count]
+                .iter()
+                .position(|c| c.commit_ref == marked),
+            None => self.mark.map(shift),
+        };
+    }
+
+    /// Render the graph for the rows above `end` that haven't been rendered
+    /// since they were built; rows are rendered from the top, since lane
+    /// colors are assigned in the order lanes are first drawn
+    fn update_graph_spans(&mut self, end: usize) {
+        let end = min(end, self.graph.graph.len());
+        for row in self.graph_spans.len()..end {
+            let spans =
+                draw_graph(&self.graph.graph[row], &mut self.graph_colors);
+            self.graph_spans.push(spans);
+        }
+    }
+
     pub fn toggle_show_details(&mut self) {
         self.show_details = !self.show_details;
+        self.focus = Focus::First;
+    }
+
+    /// Describe each lane in the graph row under the cursor, drawn in the
+    /// lane's color
+    pub fn lane_info(&self) -> Vec<Line<'static>> {
+        let cursor = self.cursor();
+
+        // the lanes have the colors they were drawn with
+        let mut colors = self.graph_colors.clone();
+        for row in
+            &self.graph.graph[min(self.graph_spans.len(), cursor)..cursor]
+        {
+            draw_graph(row, &mut colors);
+        }
+
+        let row = &self.graph.graph[cursor];
+        row.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let kind = match cell.track {
+                    Track::Node => "commit",
+                    Track::Continue => "continue",
+                    Track::ContinueRight | Track::ContinueUp => "shift",
+                    Track::Branch => "branch",
+                    Track::Merge => "merge",
+                };
+                let subject = self
+                    .commits
+                    .iter()
+                    .find(|c| c.commit_ref == cell.related)
+                    .map(|c| c.subject.ellipses(40))
+                    .unwrap_or_default();
+
+                let mut spans = vec![
+                    draw_cell(&cell.related, VLINE_CHAR, &mut colors),
+                    Span::from(format!(" {:>2} {:<8} ", i + 1, kind)),
+                    Span::styled(
+                        cell.related.to_string(),
+                        Style::default().fg(Color::Indexed(5)),
+                    ),
+                    Span::from(" "),
+                ];
+                if let Some(label) = &cell.label {
+                    spans.push(Span::styled(
+                        format!("[{}] ", label),
+                        Style::default().fg(Color::Indexed(6)),
+                    ));
+                }
+                spans.push(Span::from(subject));
+
+                Line::from(spans)
+            })
+            .collect()
     }
 }
 
@@ -161,17 +1212,69 @@ impl ListCursor for CommitLog {
 }
 
 impl Status for CommitLog {
-    fn status(&self) -> String {
+    fn status(&self) -> Vec<StatusSegment> {
         let marked = self.get_marked();
         let selected = self.get_selected();
-        match marked {
-            Some(m) => {
-                format!("{}..{}", m, selected)
-            }
-            _ => {
-                format!("{}", selected)
-            }
+        let mut status = match marked {
+            _ if !self.picked.is_empty() => vec![
+                StatusSegment::target(format!(
+                    "{} picked commit{}",
+                    self.picked.len(),
+                    if self.picked.len() == 1 { "" } else { "s" }
+                )),
+                StatusSegment::hint("Enter shows the picked commits"),
+            ],
+            Some(m) => vec![
+                StatusSegment::target(format!("{}..{}", m, selected)),
+                StatusSegment::hint("Enter shows the marked range"),
+            ],
+            _ => vec![StatusSegment::target(format!("{}", selected))],
+        };
+        if self.focus == Focus::Second {
+            status.push(StatusSegment::mode("details"));
+        }
+        if self.whole_diffs {
+            status.push(StatusSegment::mode("whole diffs"));
+        }
+        if let Some((path, _)) = &self.touching {
+            status.push(StatusSegment::mode(format!("highlighting {}", path)));
+        }
+        if let Some(path) = &self.filter.path {
+            status.push(StatusSegment::mode(path));
+        } else if self.filter.scoped
+            && !git_prefix().is_empty()
+            && pathspecs().is_empty()
+        {
+            status.push(StatusSegment::mode(git_prefix()));
+        }
+        if let Some(grep) = &self.filter.grep {
+            status.push(StatusSegment::mode(format!("grep {}", grep)));
+        }
+        if let Some(pickaxe) = &self.filter.pickaxe {
+            status.push(StatusSegment::mode(format!(
+                "{} {}",
+                pickaxe.option(),
+                pickaxe.pattern()
+            )));
+        }
+        if self.filter.merges != MergeFilter::All {
+            status.push(StatusSegment::mode(self.filter.merges.to_string()));
         }
+        if !log_limit().is_empty() {
+            status.push(StatusSegment::mode(log_limit().to_string()));
+        }
+        if let Some(operation) = self.worktree.operation {
+            status.push(StatusSegment::mode(format!(
+                "{} in progress",
+                operation
+            )));
+            status.push(StatusSegment::hint(if operation.can_skip() {
+                "Xc continues, Xs skips, Xa aborts"
+            } else {
+                "Xc continues, Xa aborts"
+            }));
+        }
+        status
     }
 }
 
@@ -186,18 +1289,476 @@ impl Search for CommitLog {
 
     fn is_match(&self, idx: usize) -> bool {
         match &self.query {
-            Some(query) => {
-                CommitFields::new(&self.commits[idx]).contains(query)
-            }
+            Some(query) => CommitFields::new(
+                &self.commits[idx],
+                &self.worktree,
+                self.style,
+            )
+            .contains(query),
             _ => false,
         }
     }
 }
 
+impl ViewController for CommitLog {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Char('l')] => self.toggle_show_details(),
+
+            [Key::Char('X')] if self.worktree.operation.is_some() => {
+                return KeyResult::Pending
+            }
+            [Key::Char('X'), Key::Char(c @ ('c' | 's' | 'a'))] => {
+                let Some(operation) = self.worktree.operation else {
+                    return KeyResult::Ignored;
+                };
+                let step = match c {
+                    'c' => OperationStep::Continue,
+                    's' => OperationStep::Skip,
+                    _ => OperationStep::Abort,
+                };
+                if step == OperationStep::Skip && !operation.can_skip() {
+                    ctx.message(format!("A {} can't be skipped", operation));
+                } else {
+                    ctx.confirm = Some(Confirm::new(
+                        format!("Run git {} --{}?", operation, step),
+                        ConfirmAction::Operation(operation, step),
+                    ));
+                }
+            }
+
+            [Key::Char(':')] => {
+                ctx.input =
+                    Some(Input::new("Go to commit", InputAction::GotoCommit));
+            }
+
+            // once there's no search for Escape to clear, it clears the
+            // pickaxe
+            [Key::Escape]
+                if self.query.is_none() && self.filter.pickaxe.is_some() =>
+            {
+                match self.set_pickaxe(None) {
+                    Ok(_) => ctx.message("Showing all commits"),
+                    Err(e) => ctx.message(e),
+                }
+            }
+
+            [Key::Tab] if self.show_details => self.focus.toggle(),
+
+            [Key::Char(c @ ('+' | '-' | '='))] if self.show_details => {
+                match c {
+                    '+' => ctx.resize_panes(5),
+                    '-' => ctx.resize_panes(-5),
+                    _ => ctx.pane_split = DEFAULT_PANE_SPLIT,
+                }
+                ctx.message(format!("Log pane: {}%", ctx.pane_split));
+            }
+
+            [Key::Char(' ')] => self.cursor_mark(),
+
+            [Key::Char('v')] => {
+                if !self.toggle_pick() {
+                    ctx.message("Only commits can be picked")
+                }
+            }
+
+            [Key::Char('V')] => {
+                if self.picked.is_empty() {
+                    ctx.message("No commits are picked")
+                } else {
+                    self.picked.clear();
+                    ctx.message("Unpicked all the commits")
+                }
+            }
+
+            [Key::Char('R')] => match self.get_selected() {
+                Target::Ref(r) => {
+                    ctx.confirm = Some(Confirm::new(
+                        format!("Revert {}?", r),
+                        ConfirmAction::Revert(Box::new(
+                            self.get_selected_commit().clone(),
+                        )),
+                    ))
+                }
+                _ => ctx.message("Only commits can be reverted"),
+            },
+
+            [Key::Char('c')] => {
+                if self.get_selected() == Target::Staged {
+                    let template = git_commit_template();
+                    let mut input = Input::new(
+                        "Commit message",
+                        InputAction::Commit(template.clone()),
+                    )
+                    .with_text(template.unwrap_or_default())
+                    .multiline();
+                    if git_commit_verbose() {
+                        input =
+                            input.with_details(diff_lines(&git_staged_diff()));
+                    }
+                    ctx.input = Some(input);
+                } else {
+                    ctx.message("Select the staged changes to commit them");
+                }
+            }
+
+            [Key::Char('A')] => match self.get_selected() {
+                Target::Ref(r) if !git_id().starts_with(&r.to_string()) => {
+                    ctx.message("Only the HEAD commit can be amended")
+                }
+                Target::Unstaged => {
+                    ctx.message("Stage changes to amend them into HEAD")
+                }
+                _ => {
+                    ctx.confirm = Some(Confirm::new(
+                        "Amend HEAD with the staged changes?",
+                        ConfirmAction::Amend,
+                    ))
+                }
+            },
+
+            [Key::Char('C')] => match self.get_selected_commits() {
+                Some(commits) => {
+                    ctx.confirm = Some(Confirm::new(
+                        format!("Cherry-pick {}?", describe_commits(&commits)),
+                        ConfirmAction::CherryPick(commits),
+                    ))
+                }
+                None => ctx.message("Only commits can be cherry-picked"),
+            },
+
+            [Key::Char('J')] => {
+                if !self.cursor_to_parent() {
+                    ctx.message("The parent commit isn't in the log");
+                }
+            }
+
+            [Key::Char('p')] => {
+                if !self.cycle_parents() {
+                    ctx.message("No parent commits are in the log");
+                }
+            }
+
+            [Key::Char('K')] => {
+                if !self.cycle_children() {
+                    ctx.message("No child commits are in the log");
+                }
+            }
+
+            [Key::Char('i')] => {
+                ctx.popup = Some(Popup::new("Lanes", self.lane_info()));
+            }
+
+            [Key::Char('d')] => self.open_range(self.get_range(), ctx),
+
+            [Key::Char('g')] => {
+                // the working tree can't be searched as of a commit, so its
+                // pseudo-commits search HEAD
+                let commit = match self.get_selected() {
+                    Target::Ref(commit) => commit,
+                    _ => GitRef::new("HEAD"),
+                };
+                ctx.input = Some(Input::new(
+                    format!("Grep {}", commit),
+                    InputAction::Grep(commit),
+                ));
+            }
+
+            [Key::Char('f')] => {
+                let commit = match self.get_selected() {
+                    Target::Ref(commit) => commit,
+                    _ => GitRef::new("HEAD"),
+                };
+                match FileFinder::new(&commit, ctx.diff_opts) {
+                    Ok(finder) => ctx.push(finder),
+                    Err(e) => ctx.message(e),
+                }
+            }
+
+            [Key::Char('b')] => match RefPicker::new() {
+                Ok(picker) => ctx.push(picker),
+                Err(e) => ctx.message(e),
+            },
+
+            [Key::Char('t')] => match self.get_selected() {
+                Target::Ref(commit) => {
+                    match Tree::new(&commit, ctx.diff_opts) {
+                        Ok(tree) => ctx.push(tree),
+                        Err(e) => ctx.message(e),
+                    }
+                }
+                _ => ctx.message("Only a commit's files can be browsed"),
+            },
+
+            [Key::Char('H')] => {
+                let path = self
+                    .touching
+                    .as_ref()
+                    .map(|(path, _)| path.clone())
+                    .unwrap_or_default();
+                ctx.input = Some(
+                    Input::new(
+                        "Highlight commits that changed",
+                        InputAction::HighlightPath,
+                    )
+                    .with_text(path),
+                );
+            }
+
+            [Key::Char('F')] => {
+                let grep = self.filter.grep.clone().unwrap_or_default();
+                ctx.input = Some(
+                    Input::new("Filter by message", InputAction::GrepLog)
+                        .with_text(grep),
+                );
+            }
+
+            [Key::Char('S')] => {
+                let text = match &self.filter.pickaxe {
+                    Some(Pickaxe::Regex(regex)) => format!("r/{}", regex),
+                    Some(Pickaxe::Text(text)) => text.clone(),
+                    None => String::new(),
+                };
+                ctx.input = Some(
+                    Input::new(
+                        "Filter by added or removed text",
+                        InputAction::PickaxeLog,
+                    )
+                    .with_text(text),
+                );
+            }
+
+            [Key::Char('P')] if self.filter.path.is_none() => {
+                match self.toggle_scope() {
+                    Ok(()) => {
+                        // new views follow the log's scope
+                        ctx.diff_opts.scoped = self.filter.scoped;
+                        ctx.message(if self.filter.scoped {
+                            format!("Showing changes in {}", git_prefix())
+                        } else {
+                            "Showing changes in the whole repo".into()
+                        });
+                    }
+                    Err(e) => ctx.message(e),
+                }
+            }
+
+            [Key::Char('M')] => ctx.message(match self.cycle_merges() {
+                Ok(merges) => format!("Showing {}", merges),
+                Err(e) => e,
+            }),
+
+            [Key::Char('o')] => {
+                self.whole_diffs = !self.whole_diffs;
+                ctx.message(if self.whole_diffs {
+                    "Showing all the changed files in one diff"
+                } else {
+                    "Showing the list of changed files"
+                });
+            }
+
+            [Key::Char('Y')] => self.toggle_dates(),
+
+            [Key::Char('W')] => match self.release_notes() {
+                Ok((notes, description)) => {
+                    ctx.input = Some(
+                        Input::new(
+                            "Write release notes to",
+                            InputAction::ReleaseNotes(notes),
+                        )
+                        .with_details(vec![
+                            Line::from(description),
+                            Line::from("Leave empty to copy them instead"),
+                        ]),
+                    )
+                }
+                Err(e) => ctx.message(e),
+            },
+
+            [Key::Char('I')] => {
+                // a marked commit and the selected one are most likely a
+                // branch before and after a rebase
+                let ranges = match (self.get_marked(), self.get_selected()) {
+                    (Some(marked), Target::Ref(selected)) => {
+                        format!("{}...{}", marked, selected)
+                    }
+                    _ => String::new(),
+                };
+                ctx.input = Some(
+                    Input::new("Range-diff", InputAction::RangeDiff)
+                        .with_text(ranges)
+                        .with_details(vec![
+                            Line::from("old...new"),
+                            Line::from("base old new"),
+                            Line::from("old-base..old new-base..new"),
+                        ]),
+                );
+            }
+
+            [Key::Char('a')] => {
+                let format = self.toggle_author_format();
+                ctx.message(format!("Showing authors' {}", format));
+            }
+
+            [Key::Char('O')] => match self.get_selected() {
+                Target::Ref(commit) => match web_url(&commit, None) {
+                    Ok(url) => ctx.open_url = Some(url),
+                    Err(e) => ctx.message(e),
+                },
+                _ => ctx.message("Uncommitted changes aren't on the remote"),
+            },
+
+            [Key::Char('m')] => match self.get_branch_ranges() {
+                Some((marked, selected)) => ctx.push(
+                    Stats::new(marked, ctx.diff_opts).other_side(selected),
+                ),
+                None => ctx.message(
+                    "Mark a commit on a different branch to compare the \
+                     branches",
+                ),
+            },
+
+            [Key::Char('D')] => match self.get_branch_ranges() {
+                Some((marked, selected)) => {
+                    ctx.push(PatchIds::new(&marked, &selected))
+                }
+                None => ctx.message(
+                    "Mark a commit on a different branch to find the changes \
+                     both branches made",
+                ),
+            },
+
+            [Key::Char(c @ (']' | '['))] if self.focus == Focus::Second => {
+                if !self.details.cycle_link(*c == ']') {
+                    ctx.message("The commit doesn't have other parents");
+                }
+            }
+
+            [Key::Enter] if self.focus == Focus::Second => {
+                match self.details.selected_parent().cloned() {
+                    Some(parent) => match self.commit_row(&parent) {
+                        Some(row) => self.cursor_to(row),
+                        None => {
+                            ctx.message(format!("{} isn't in the log", parent))
+                        }
+                    },
+                    None => ctx.message("The commit has no parents"),
+                }
+            }
+
+            [Key::Enter] => match self.get_picked() {
+                Some(picked) => self.open_range(picked, ctx),
+                None => {
+                    self.open_range(DiffAction::show(self.get_selected()), ctx)
+                }
+            },
+
+            _ if self.focus == Focus::Second => {
+                return handle_scroll_keys(&mut self.details, keys)
+            }
+
+            _ => {
+                let result = handle_cursor_keys(self, keys);
+                self.load_more(ctx);
+                return result;
+            }
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let mut view = CommitsView::new(self).pane_split(opts.pane_split);
+        if let Some(block) =
+            opts.block(format!("Commits — {}", view.commits.commits.len()))
+        {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::WorktreeChanges(changes) => {
+                self.set_worktree_changes(changes)
+            }
+            AppEvent::Refresh => self.refresh(),
+            AppEvent::HighlightPath(path) => self.set_touching(path.clone()),
+            AppEvent::UseProfile(profile) => self.use_profile(profile),
+            AppEvent::LogLoaded {
+                filter,
+                skip,
+                commits,
+            } if self.loading
+                && *filter == self.filter
+                && *skip == self.loaded_count() =>
+            {
+                self.append_commits(commits)
+            }
+            _ => {}
+        }
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn goto_commit(&mut self, rev: &str) -> Result<String, String> {
+        let commit = self.goto_rev(rev)?;
+        Ok(format!("Moved to {}", commit))
+    }
+
+    fn grep_log(&mut self, pattern: &str) -> Result<String, String> {
+        if pattern.is_empty() {
+            self.set_grep(None)?;
+            return Ok("Showing all commits".into());
+        }
+        let count = self.set_grep(Some(pattern.into()))?;
+        Ok(self.describe_matches(count))
+    }
+
+    fn pickaxe_log(&mut self, text: &str) -> Result<String, String> {
+        if text.is_empty() {
+            self.set_pickaxe(None)?;
+            return Ok("Showing all commits".into());
+        }
+        let count = self.set_pickaxe(Some(Pickaxe::parse(text)))?;
+        Ok(self.describe_matches(count))
+    }
+
+    fn patch(&self, opts: GitDiffOpts) -> Result<String, String> {
+        if self.commits.is_empty() {
+            return Err("There are no commits".into());
+        }
+        // the changes Enter would list, or the range from the mark
+        let range = match (self.get_picked(), self.mark) {
+            (Some(picked), _) => picked,
+            (None, Some(_)) => self.get_range(),
+            (None, None) => DiffAction::show(self.get_selected()),
+        };
+        Ok(git_patch(None, &range, Some(opts)))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("enter", "files"),
+            ("space", "mark"),
+            ("d", "diff"),
+            ("l", "details"),
+            ("/", "search"),
+            ("?", "help"),
+        ]
+    }
+}
+
 /// The Widget used to render Commits
 pub struct CommitsView<'a> {
     commits: &'a mut CommitLog,
     block: Option<Block<'a>>,
+    pane_split: u16,
 }
 
 impl<'a> CommitsView<'a> {
@@ -205,8 +1766,22 @@ impl<'a> CommitsView<'a> {
         CommitsView {
             commits,
             block: None,
+            pane_split: DEFAULT_PANE_SPLIT,
         }
     }
+
+    /// Draw the log inside a block
+    pub fn block(mut self, block: Block<'a>) -> CommitsView<'a> {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set the percentage of the height given to the log when the details
+    /// pane is shown
+    pub fn pane_split(mut self, percent: u16) -> CommitsView<'a> {
+        self.pane_split = percent;
+        self
+    }
 }
 
 const SPACE_CHAR: &str = " ";
@@ -242,11 +1817,59 @@ fn draw_cell<'a>(
     Span::styled(char, Style::default().fg(get_commit_color(hash, colors)))
 }
 
+/// Map each commit to its row in a log
+fn commit_index(commits: &[Commit]) -> HashMap<GitRef, usize> {
+    commits
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.commit_ref.clone(), i))
+        .collect()
+}
+
+/// List the rows of each commit's children in a log, nearest first
+fn commit_children(
+    commits: &[Commit],
+    index: &HashMap<GitRef, usize>,
+) -> Vec<Vec<usize>> {
+    let mut children: Vec<Vec<usize>> = vec![vec![]; commits.len()];
+    for (row, c) in commits.iter().enumerate().rev() {
+        for parent in c.parent_refs.iter().filter_map(|p| index.get(p)) {
+            children[*parent].push(row);
+        }
+    }
+    children
+}
+
+/// Color the lines of a diff, to be shown below a commit message while it's
+/// written
+fn diff_lines(diff: &str) -> Vec<Line<'static>> {
+    diff.lines()
+        .map(|line| {
+            let color = if line.starts_with("diff ")
+                || line.starts_with("index ")
+                || line.starts_with("+++")
+                || line.starts_with("---")
+            {
+                Color::Indexed(8)
+            } else if line.starts_with("@@") {
+                Color::Indexed(6)
+            } else if line.starts_with('+') {
+                Color::Indexed(2)
+            } else if line.starts_with('-') {
+                Color::Indexed(1)
+            } else {
+                Color::Reset
+            };
+            Line::styled(line.replace('\t', "    "), Style::default().fg(color))
+        })
+        .collect()
+}
+
 /// Render the graph for a row
-fn draw_graph<'a>(
-    node: CommitRow,
+fn draw_graph(
+    node: &CommitRow,
     colors: &mut HashMap<GitRef, Color>,
-) -> Vec<Span<'a>> {
+) -> Vec<Span<'static>> {
     let mut graph: Vec<Span> = vec![];
 
     // set to the commit hash of the target when a horizontal line should be
@@ -428,9 +2051,11 @@ static COMMIT_RE: Lazy<Regex> =
 
 impl<'a> Widget for CommitsView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let mut colors: HashMap<GitRef, Color> = HashMap::new();
         let constraints: Vec<Constraint> = if self.commits.show_details {
-            vec![Constraint::Percentage(50), Constraint::Percentage(50)]
+            vec![
+                Constraint::Percentage(self.pane_split),
+                Constraint::Percentage(100 - self.pane_split),
+            ]
         } else {
             vec![Constraint::Percentage(100)]
         };
@@ -439,35 +2064,52 @@ impl<'a> Widget for CommitsView<'a> {
             .constraints(constraints)
             .split(area);
 
-        self.commits.list.height = layout[0].height as usize;
+        // the log gets a border to show whether it has the focus when the
+        // details are also shown
+        let focus = self.commits.focus;
+        let block = match self.block {
+            Some(b) if self.commits.show_details => {
+                Some(b.border_style(focus.border_style(Focus::First)))
+            }
+            None if self.commits.show_details => Some(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(focus.border_style(Focus::First)),
+            ),
+            block => block,
+        };
+        let list_area =
+            block.as_ref().map_or(layout[0], |b| b.inner(layout[0]));
+        let height = list_area.height as usize;
+        self.commits.list.height = height;
+
+        // only the rows in view are built, so a long log draws as quickly as
+        // a short one; the list scrolls just enough to show the cursor, like
+        // a List does
+        let cursor = self.commits.cursor();
+        let mut top = self.commits.list_state().offset();
+        if cursor < top {
+            top = cursor;
+        } else if cursor >= top + height {
+            top = cursor + 1 - max(height, 1);
+        }
+        let visible = top..min(top + height, self.commits.commits.len());
+        self.commits.update_graph_spans(visible.end);
 
-        let rows = self
-            .commits
-            .commits
+        let rows = self.commits.commits[visible.clone()]
             .iter()
-            .map(CommitFields::new)
+            .map(|c| {
+                CommitFields::new(c, &self.commits.worktree, self.commits.style)
+            })
             .collect::<Vec<CommitFields>>();
 
-        let author_width = min(
-            20,
-            rows.iter()
-                .max_by(|x, y| x.author.len().cmp(&y.author.len()))
-                .unwrap()
-                .author
-                .len(),
-        );
-
-        let time_width = rows
-            .iter()
-            .max_by(|x, y| x.age.len().cmp(&y.age.len()))
-            .unwrap()
-            .age
-            .len();
+        let author_width = self.commits.widths.author;
+        let time_width = self.commits.widths.age;
 
         let items: Vec<ListItem> = rows
             .iter()
-            .enumerate()
-            .map(|(i, f)| {
+            .zip(visible)
+            .map(|(f, i)| {
                 let age = &f.age;
                 let author = format!(
                     "{:width$}",
@@ -476,33 +2118,51 @@ impl<'a> Widget for CommitsView<'a> {
                 );
 
                 // draw the graph
-                let graph = draw_graph(
-                    self.commits.graph.graph[i].clone(),
-                    &mut colors,
-                );
+                let row = &self.commits.graph.graph[i];
 
+                // picked commits have their hashes highlighted
+                let hash_style = if self
+                    .commits
+                    .picked
+                    .contains(&self.commits.commits[i].commit_ref)
+                {
+                    Style::default().fg(Color::Indexed(0)).bg(Color::Indexed(5))
+                } else {
+                    Style::default().fg(Color::Indexed(5))
+                };
                 let mut spans: Vec<Span> = vec![
                     // commit hash
-                    Span::styled(
-                        format!("{}", f.hash),
-                        Style::default().fg(Color::Indexed(5)),
-                    ),
-                    Span::from(" "),
-                    // age
-                    Span::styled(
-                        format!("{:>width$}", age, width = time_width),
-                        Style::default().fg(Color::Indexed(4)),
-                    ),
-                    Span::from(" "),
-                    // author
-                    Span::styled(
-                        author,
-                        Style::default().fg(Color::Indexed(2)),
-                    ),
+                    Span::styled(format!("{}", f.hash), hash_style),
                     Span::from(" "),
                 ];
 
-                spans.extend(graph);
+                if let Some(summary) = &f.summary {
+                    spans.push(Span::styled(
+                        format!(
+                            "{:width$}",
+                            summary,
+                            width = time_width + 1 + author_width
+                        ),
+                        Style::default().fg(Color::Indexed(3)),
+                    ));
+                } else {
+                    spans.extend([
+                        // age
+                        Span::styled(
+                            format!("{:>width$}", age, width = time_width),
+                            Style::default().fg(Color::Indexed(4)),
+                        ),
+                        Span::from(" "),
+                        // author
+                        Span::styled(
+                            author,
+                            Style::default().fg(Color::Indexed(2)),
+                        ),
+                    ]);
+                }
+                spans.push(Span::from(" "));
+
+                spans.extend(self.commits.graph_spans[i].iter().cloned());
                 spans.push(Span::from(" "));
 
                 // subject
@@ -536,6 +2196,15 @@ impl<'a> Widget for CommitsView<'a> {
                     ));
                     spans.push(Span::from(" "));
                 });
+                row.branch_points.iter().for_each(|p| {
+                    spans.push(Span::styled(
+                        p.to_string(),
+                        Style::default()
+                            .fg(Color::Indexed(8))
+                            .add_modifier(Modifier::ITALIC),
+                    ));
+                    spans.push(Span::from(" "));
+                });
 
                 if COMMIT_RE.is_match(&f.subject) {
                     let mut subj_type = f.subject.clone();
@@ -551,47 +2220,223 @@ impl<'a> Widget for CommitsView<'a> {
                 }
 
                 if let Some(search) = &self.commits.query {
-                    spans = highlight_spans(
-                        spans.clone(),
-                        search,
-                        Style::default().add_modifier(Modifier::REVERSED),
-                    )
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
                 }
 
-                let mut item = ListItem::new(Line::from(spans));
+                let mut style = Style::default();
+
+                // the commits that changed the highlighted path stand out,
+                // and the others fade
+                if let Some((_, touching)) = &self.commits.touching {
+                    let commit = &self.commits.commits[i].commit_ref;
+                    if touching.contains(commit) {
+                        style = style.add_modifier(Modifier::BOLD);
+                    } else if f.summary.is_none() {
+                        style = style.add_modifier(Modifier::DIM);
+                    }
+                }
 
                 if let Some(m) = self.commits.mark {
                     if m == i {
-                        item =
-                            item.style(Style::default().bg(Color::Indexed(8)));
+                        style = style.bg(Color::Indexed(8));
                     }
                 }
 
-                item
+                ListItem::new(Line::from(spans)).style(style)
             })
             .collect();
 
-        let mut list = List::new(items)
-            .highlight_style(Style::default().bg(Color::Indexed(0)));
+        // the commit that was just jumped to stands out for a moment
+        let flashing = self.commits.flash.as_ref().is_some_and(|(r, at)| {
+            at.elapsed() < FLASH_DURATION
+                && self.commits.commits.get(cursor).map(|c| &c.commit_ref)
+                    == Some(r)
+        });
+        let highlight = if flashing {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().bg(Color::Indexed(0))
+        };
+        let mut list = List::new(items).highlight_style(highlight);
 
-        if let Some(b) = self.block {
+        if let Some(b) = block {
             list = list.block(b);
         }
 
-        StatefulWidget::render(
-            list,
-            layout[0],
-            buf,
-            self.commits.list_state_mut(),
-        );
+        let mut state = ListState::default().with_selected(Some(cursor - top));
+        StatefulWidget::render(list, layout[0], buf, &mut state);
+        *self.commits.list_state_mut().offset_mut() = top;
 
         if self.commits.show_details {
             let commit =
                 &self.commits.commits[self.commits.cursor()].commit_ref;
-            let message = git_log_message(commit);
-            let log = Paragraph::new(message)
-                .block(Block::default().borders(Borders::ALL));
+            let details = &mut self.commits.details;
+            details.load(commit);
+            details.height = layout[1].height.saturating_sub(2) as usize;
+            let log = Paragraph::new(details.lines(focus == Focus::Second))
+                .scroll((details.offset as u16, 0))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(focus.border_style(Focus::Second)),
+                );
             log.render(layout[1], buf);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{testing::TestRepo, with_runner};
+
+    fn commit(hash: &str, parents: &[&str]) -> Commit {
+        Commit::new(
+            hash.into(),
+            GitRef::from_strs(parents),
+            "",
+            "Ann Dev".into(),
+            "ann@example.com".into(),
+            None,
+            hash.into(),
+        )
+    }
+
+    #[test]
+    fn children_are_listed_nearest_first() {
+        let commits = vec![
+            commit("e", &["d", "c"]),
+            commit("d", &["b"]),
+            commit("c", &["b"]),
+            commit("b", &["a"]),
+            commit("a", &[]),
+        ];
+        let index = commit_index(&commits);
+        assert_eq!(index[&GitRef::new("c")], 2);
+        assert_eq!(index.len(), commits.len());
+
+        let children = commit_children(&commits, &index);
+        assert_eq!(
+            children,
+            vec![vec![], vec![0], vec![0], vec![2, 1], vec![3]]
+        );
+    }
+
+    #[test]
+    fn parent_and_child_keys_cycle_through_relatives() {
+        let repo = TestRepo::new("relatives");
+        repo.commit(&[("a.txt", "a\n")], "a", "1700000000");
+        repo.commit(&[("a.txt", "b\n")], "b", "1700000060");
+        repo.git(&["branch", "side"]);
+        repo.commit(&[("a.txt", "d\n")], "d", "1700000120");
+        repo.git(&["checkout", "-q", "side"]);
+        repo.commit(&[("b.txt", "c\n")], "c", "1700000180");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.git_at(&["merge", "-q", "-m", "e", "side"], "1700000240");
+
+        with_runner(repo.runner(), || {
+            let mut log = CommitLog::new(false);
+            let row = |log: &CommitLog, subject: &str| {
+                log.commits
+                    .iter()
+                    .position(|c| c.subject == subject)
+                    .unwrap()
+            };
+            let subject =
+                |log: &CommitLog| log.commits[log.cursor()].subject.clone();
+
+            log.cursor_to(row(&log, "e"));
+            assert!(log.cursor_to_parent());
+            assert_eq!(subject(&log), "d");
+
+            log.cursor_to(row(&log, "e"));
+            assert!(log.cycle_parents());
+            assert_eq!(subject(&log), "d");
+            assert!(log.cycle_parents());
+            assert_eq!(subject(&log), "c");
+            assert!(log.cycle_parents());
+            assert_eq!(subject(&log), "d");
+
+            log.cursor_to(row(&log, "b"));
+            assert!(log.cycle_children());
+            let first = subject(&log);
+            assert!(log.cycle_children());
+            let second = subject(&log);
+            assert_eq!(
+                HashSet::from([first.as_str(), second.as_str()]),
+                HashSet::from(["c", "d"])
+            );
+            assert!(log.cycle_children());
+            assert_eq!(subject(&log), first);
+
+            log.cursor_to(row(&log, "e"));
+            assert!(!log.cycle_children());
+            log.cursor_to(row(&log, "a"));
+            assert!(!log.cursor_to_parent());
+        });
+    }
+
+    #[test]
+    fn log_loads_pages_as_the_cursor_nears_the_end() {
+        let repo = TestRepo::new("paged-log");
+        let total = LOG_PAGE_SIZE + 150;
+        repo.empty_commits(total as u64, 1700000000);
+
+        with_runner(repo.runner(), || {
+            let mut log = CommitLog::new(false);
+            assert_eq!(log.commits.len(), LOG_PAGE_SIZE);
+            assert!(!log.complete);
+
+            let mut ctx = ViewContext::new(GitDiffOpts::default(), 50);
+            log.load_more(&mut ctx);
+            assert!(
+                ctx.load_log.is_none(),
+                "loaded with the cursor at the top"
+            );
+
+            log.cursor_to(LOG_PAGE_SIZE - LOAD_MARGIN);
+            log.load_more(&mut ctx);
+            let (filter, skip) = ctx.load_log.take().unwrap();
+            assert_eq!(skip, LOG_PAGE_SIZE);
+            log.load_more(&mut ctx);
+            assert!(ctx.load_log.is_none(), "loaded a page twice");
+
+            let commits = git_log(&filter, skip, LOG_PAGE_SIZE);
+            // a page for an older filter or position is ignored
+            log.on_event(&AppEvent::LogLoaded {
+                filter: filter.clone(),
+                skip: 0,
+                commits: commits.clone(),
+            });
+            assert_eq!(log.commits.len(), LOG_PAGE_SIZE);
+
+            log.on_event(&AppEvent::LogLoaded {
+                filter,
+                skip,
+                commits,
+            });
+            assert_eq!(log.commits.len(), total);
+            assert!(log.complete);
+            assert_eq!(log.commits[total - 1].subject, "Commit 1");
+
+            log.cursor_to(total - 1);
+            log.load_more(&mut ctx);
+            assert!(ctx.load_log.is_none(), "loaded past the end");
+        });
+    }
+
+    #[test]
+    fn goto_rev_loads_pages_until_it_finds_the_commit() {
+        let repo = TestRepo::new("goto-paged-log");
+        repo.empty_commits(LOG_PAGE_SIZE as u64 + 10, 1700000000);
+
+        with_runner(repo.runner(), || {
+            let mut log = CommitLog::new(false);
+            let root = repo.git(&["rev-list", "--max-parents=0", "HEAD"]);
+            log.goto_rev(root.trim()).unwrap();
+            assert_eq!(log.cursor(), LOG_PAGE_SIZE + 9);
+            assert!(log.complete);
+        });
+    }
+}
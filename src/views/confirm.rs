@@ -0,0 +1,148 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    git::{Commit, Operation, OperationStep},
+    ui::centered_rect,
+};
+
+/// An action that must be confirmed before it runs
+#[derive(Debug, Clone)]
+pub enum ConfirmAction {
+    /// Cherry-pick commits onto the current branch, oldest first
+    CherryPick(Vec<Commit>),
+    /// Revert a commit on the current branch
+    Revert(Box<Commit>),
+    /// Amend the HEAD commit with the staged changes
+    Amend,
+    /// Commit the staged changes without running the hooks that stopped the
+    /// commit; whether the message was written from a template is included
+    CommitNoVerify(String, bool),
+    /// Amend the HEAD commit without running the hooks that stopped the
+    /// amend, replacing its message if one is given
+    AmendNoVerify(Option<String>),
+    /// Continue, skip, or abort an operation that stopped partway
+    Operation(Operation, OperationStep),
+    /// Discard the unstaged changes to a file
+    Discard(String),
+    /// Keep the color theme being previewed, saving it in the config
+    SaveTheme(String),
+}
+
+/// Name the commits an action applies to: a single commit's hash, or how
+/// many commits there are
+pub fn describe_commits(commits: &[Commit]) -> String {
+    match commits {
+        [commit] => commit.commit_ref.to_string(),
+        _ => format!("{} commits", commits.len()),
+    }
+}
+
+/// A yes/no question about a pending action
+#[derive(Debug, Clone)]
+pub struct Confirm {
+    pub prompt: String,
+    pub action: ConfirmAction,
+    /// lines shown below the prompt, like the output of a failed command
+    details: Vec<Line<'static>>,
+}
+
+impl Confirm {
+    pub fn new(prompt: impl Into<String>, action: ConfirmAction) -> Confirm {
+        Confirm {
+            prompt: prompt.into(),
+            action,
+            details: vec![],
+        }
+    }
+
+    /// Show lines below the prompt, like why an action is being asked about
+    pub fn with_details(mut self, details: Vec<Line<'static>>) -> Confirm {
+        self.details = details;
+        self
+    }
+}
+
+/// The Widget used to render a Confirm dialog
+pub struct ConfirmView<'a> {
+    confirm: &'a Confirm,
+}
+
+impl<'a> ConfirmView<'a> {
+    pub fn new(confirm: &'a Confirm) -> ConfirmView<'a> {
+        ConfirmView { confirm }
+    }
+}
+
+impl<'a> Widget for ConfirmView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let details_width = self
+            .confirm
+            .details
+            .iter()
+            .map(|l| l.width())
+            .max()
+            .unwrap_or(0);
+        let width = (self.confirm.prompt.len().max(details_width) as u16 + 6)
+            .clamp(30, 78);
+        let mut text = vec![Line::from(self.confirm.prompt.clone())];
+        if !self.confirm.details.is_empty() {
+            // only the end of long details is shown, since that's where
+            // commands usually say what went wrong
+            let inner = width.saturating_sub(4) as usize;
+            let mut room = area.height.saturating_sub(6) as usize;
+            let mut details: Vec<Line> = vec![];
+            for line in self.confirm.details.iter().rev() {
+                let rows = line.width().max(1).div_ceil(inner);
+                if rows > room {
+                    break;
+                }
+                room -= rows;
+                details.push(line.clone().alignment(Alignment::Left));
+            }
+            details.reverse();
+            text.push(Line::from(""));
+            text.extend(details);
+        }
+        let height = text
+            .iter()
+            .map(|l| l.width().max(1).div_ceil(width as usize - 4) as u16)
+            .sum::<u16>()
+            + 4;
+        let rect = centered_rect(width, height.min(area.height), area);
+
+        text.extend(vec![
+            Line::from(""),
+            Line::from(vec![
+                Span::styled(
+                    "y",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::from("es / "),
+                Span::styled(
+                    "n",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::from("o"),
+            ]),
+        ]);
+
+        let dialog = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .padding(Padding::horizontal(1))
+                    .border_style(Style::default().fg(Color::Indexed(3))),
+            );
+
+        Clear.render(rect, buf);
+        dialog.render(rect, buf);
+    }
+}
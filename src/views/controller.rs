@@ -0,0 +1,284 @@
+use std::{path::PathBuf, time::Duration};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders},
+    Frame,
+};
+
+use crate::{
+    events::{AppEvent, Key},
+    git::{GitDiffOpts, LogFilter},
+    graphics::{ImageProtocol, InlineImage},
+    list::{ListCursor, ListScroll},
+    search::Search,
+    views::{confirm::Confirm, input::Input, popup::Popup, statusline::Status},
+};
+
+/// How a view responded to a key sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyResult {
+    /// the view acted on the keys
+    Handled,
+    /// the keys are the start of a longer sequence the view understands
+    Pending,
+    /// the view doesn't use the keys
+    Ignored,
+}
+
+/// The share of a split layout's height given to its first pane, as a
+/// percentage
+pub const DEFAULT_PANE_SPLIT: u16 = 50;
+pub const MIN_PANE_SPLIT: u16 = 10;
+pub const MAX_PANE_SPLIT: u16 = 90;
+
+/// How long a row that was jumped to stays highlighted
+pub const FLASH_DURATION: Duration = Duration::from_millis(800);
+
+/// The pane of a two-pane layout that keys are sent to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Focus {
+    #[default]
+    First,
+    Second,
+}
+
+impl Focus {
+    /// Move the focus to the other pane
+    pub fn toggle(&mut self) {
+        *self = match self {
+            Focus::First => Focus::Second,
+            Focus::Second => Focus::First,
+        };
+    }
+
+    /// The style of a pane's border, which is highlighted if the pane has
+    /// the focus
+    pub fn border_style(&self, pane: Focus) -> Style {
+        if *self == pane {
+            Style::default().fg(Color::Indexed(6))
+        } else {
+            Style::default().fg(Color::Indexed(8))
+        }
+    }
+}
+
+/// Options that affect how views are rendered
+pub struct RenderOpts {
+    pub tab_width: u8,
+    /// the percentage of a split layout given to its first pane
+    pub pane_split: u16,
+    /// the protocol for previewing images, if they should be previewed
+    pub images: Option<ImageProtocol>,
+    /// draw titled borders around views
+    pub borders: bool,
+}
+
+impl RenderOpts {
+    /// Return a border with the given title, if views should have borders
+    pub fn block(&self, title: impl Into<String>) -> Option<Block<'static>> {
+        self.borders.then(|| {
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {} ", title.into()))
+        })
+    }
+}
+
+/// The requests a view makes of the app while it handles a key
+#[derive(Default)]
+pub struct ViewContext {
+    /// the diff options for new views, which a view may update
+    pub diff_opts: GitDiffOpts,
+    /// the split of multi-pane layouts, which a view may update
+    pub pane_split: u16,
+    pub message: Option<String>,
+    pub confirm: Option<Confirm>,
+    pub input: Option<Input>,
+    pub popup: Option<Popup>,
+    /// a view to push onto the stack
+    pub push: Option<Box<dyn ViewController>>,
+    /// close the view
+    pub close: bool,
+    /// events to send to every view in the stack; a RepoChanged event has
+    /// the app check the working tree in the background instead
+    pub events: Vec<AppEvent>,
+    /// text to copy to the system clipboard
+    pub clipboard: Option<String>,
+    /// a file to open in the user's editor, and the line to start at
+    pub edit: Option<(PathBuf, u32)>,
+    /// a revision for the view below this one to move to, after this view
+    /// closes
+    pub goto_commit: Option<String>,
+    /// a web page to open in the user's browser
+    pub open_url: Option<String>,
+    /// more of a log to load in the background: its filter, and how many
+    /// commits it has
+    pub load_log: Option<(LogFilter, usize)>,
+}
+
+impl ViewContext {
+    pub fn new(diff_opts: GitDiffOpts, pane_split: u16) -> ViewContext {
+        ViewContext {
+            diff_opts,
+            pane_split,
+            ..ViewContext::default()
+        }
+    }
+
+    /// Grow or shrink the first pane of split layouts by `delta` percent
+    pub fn resize_panes(&mut self, delta: i16) {
+        self.pane_split = self
+            .pane_split
+            .saturating_add_signed(delta)
+            .clamp(MIN_PANE_SPLIT, MAX_PANE_SPLIT);
+    }
+
+    pub fn message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    pub fn push(&mut self, view: impl ViewController + 'static) {
+        self.push = Some(Box::new(view));
+    }
+}
+
+/// A view that can be shown in the app's view stack
+pub trait ViewController: Status {
+    /// Handle a key sequence; `keys` holds any keys the view said were
+    /// pending, followed by the key that was just pressed
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult;
+
+    /// Draw the view in the given area
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts);
+
+    /// React to something that happened outside the view
+    fn on_event(&mut self, _event: &AppEvent) {}
+
+    /// Return the view's searchable list, if it has one
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        None
+    }
+
+    /// Return the position in the view and the length of its list, if it
+    /// has one
+    fn location(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Return a file that should be watched for changes while the view is
+    /// open
+    fn watched_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Return the view's most useful keys and what they do, to remind users
+    /// when the view is opened
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    /// Scroll to a line of the file the view shows, returning false if the
+    /// view doesn't show that line
+    fn goto_line(&mut self, _line: u32) -> bool {
+        false
+    }
+
+    /// Diff the commit the view shows from another commit instead of from
+    /// its parent, returning a description of the new diff
+    fn anchor_to(&mut self, _rev: &str) -> Result<String, String> {
+        Err("This view doesn't show a single commit".into())
+    }
+
+    /// Move to the commit a revision names, returning a description of where
+    /// the view moved to
+    fn goto_commit(&mut self, _rev: &str) -> Result<String, String> {
+        Err("Only the commit log can go to a commit".into())
+    }
+
+    /// Show only the commits whose messages match a pattern, or every commit
+    /// if the pattern is empty, returning a description of the result
+    fn grep_log(&mut self, _pattern: &str) -> Result<String, String> {
+        Err("Only the commit log can be filtered".into())
+    }
+
+    /// Show only the commits that add or remove some text, or whose changed
+    /// lines match a regex if the text starts with `r/`, or every commit if
+    /// the text is empty, returning a description of the result
+    fn pickaxe_log(&mut self, _text: &str) -> Result<String, String> {
+        Err("Only the commit log can be filtered".into())
+    }
+
+    /// Move to a changed file, by its path from the repo root
+    fn select_file(&mut self, _path: &str) -> Result<(), String> {
+        Err("Only a list of files can select a file".into())
+    }
+
+    /// Return the changes the view shows as a patch; views that don't have
+    /// diff options of their own use `opts`
+    fn patch(&self, _opts: GitDiffOpts) -> Result<String, String> {
+        Err("This view doesn't show any changes".into())
+    }
+
+    /// True if the view uses typed characters as text, so the app shouldn't
+    /// take any of them for itself
+    fn takes_text(&self) -> bool {
+        false
+    }
+
+    /// Return an image to draw over the view
+    fn inline_image(&self) -> Option<&InlineImage> {
+        None
+    }
+}
+
+/// Return the count typed as the digits of a key sequence, like the 12 of
+/// 12G
+pub fn key_count(keys: &[Key]) -> Option<u32> {
+    if matches!(keys.first(), None | Some(Key::Char('0'))) {
+        return None;
+    }
+    keys.iter()
+        .map(|k| match k {
+            Key::Char(c) => c.to_digit(10),
+            _ => None,
+        })
+        .try_fold(0u32, |count, d| count.checked_mul(10)?.checked_add(d?))
+}
+
+/// Move the cursor of a list for the common navigation keys
+pub fn handle_cursor_keys(
+    list: &mut impl ListCursor,
+    keys: &[Key],
+) -> KeyResult {
+    match keys {
+        [Key::Char('1')] => return KeyResult::Pending,
+        [Key::Char('1'), Key::Char('G')] => list.cursor_to_top(),
+        [Key::Char('G')] => list.cursor_to_bottom(),
+        [Key::Up | Key::Char('k')] => list.cursor_up(),
+        [Key::Down | Key::Char('j')] => list.cursor_down(),
+        [Key::Ctrl('u')] => list.cursor_page_up(),
+        [Key::Ctrl('f')] => list.cursor_page_down(),
+        _ => return KeyResult::Ignored,
+    }
+    KeyResult::Handled
+}
+
+/// Scroll a list for the common navigation keys
+pub fn handle_scroll_keys(
+    list: &mut impl ListScroll,
+    keys: &[Key],
+) -> KeyResult {
+    match keys {
+        [Key::Char('1')] => return KeyResult::Pending,
+        [Key::Char('1'), Key::Char('G')] => list.scroll_top(),
+        [Key::Char('G')] => list.scroll_bottom(),
+        [Key::Up | Key::Char('k')] => list.scroll_up(),
+        [Key::Down | Key::Char('j')] => list.scroll_down(),
+        [Key::Ctrl('u')] => list.page_up(),
+        [Key::Ctrl('f') | Key::Char(' ')] => list.page_down(),
+        _ => return KeyResult::Ignored,
+    }
+    KeyResult::Handled
+}
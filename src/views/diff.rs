@@ -1,210 +1,2625 @@
-use std::{cmp::max, path::PathBuf};
+use std::{
+    cmp::{max, min, Ordering},
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::PathBuf,
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+    Frame,
+};
+
+use crate::{
+    error::AppError,
+    events::{AppEvent, Key},
+    git::{
+        git_anchor, git_apply_cached, git_branch_moved, git_conflicts,
+        git_diff_all, git_diff_file, git_diff_stat, git_file_versions,
+        git_find_removal, git_patch, same_path, web_url, DiffAction,
+        DiffAlgorithm, DiffLine, FileDiff, FileVersion, GitDiffOpts, GitRef,
+        SplitLine, Stat, Target, WhitespaceErrors, DEFAULT_CONTEXT,
+    },
+    graphics::{file_type, ImageFormat, ImageProtocol, InlineImage},
+    list::{ListInfo, ListScroll},
+    search::{Search, SearchPattern},
+    ui::{highlight_matches, match_style},
+    views::{
+        blame::Blame,
+        commitlog::CommitLog,
+        controller::{
+            handle_scroll_keys, key_count, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        input::{Input, InputAction},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// A range of lines selected in a diff, from the line where the selection was
+/// started to the line it has been extended to
+#[derive(Debug, Clone)]
+struct Selection {
+    anchor: usize,
+    cursor: usize,
+}
+
+/// The number of columns the view scrolls sideways at a time
+const HSCROLL_STEP: usize = 8;
+
+/// Files with more diff lines than this start out collapsed when all files are
+/// shown
+const LARGE_FILE_LINES: usize = 500;
+
+/// The most rendered rows kept around; the cache is emptied when it grows past
+/// this, so jumping around a large diff doesn't keep every row in memory
+const MAX_CACHED_ROWS: usize = 2000;
+
+/// Stands in for the file at the top of the view when a diff of all files has
+/// no files left, e.g. after all the changes were staged
+static NO_STAT: Stat = Stat {
+    adds: 0,
+    deletes: 0,
+    path: String::new(),
+    old_path: String::new(),
+    binary: false,
+    hunks: None,
+    change_id: None,
+    commit: None,
+};
+
+/// What a row of the view shows
+#[derive(Debug, Clone)]
+enum Row {
+    /// a line of the unified diff
+    Line(usize),
+    /// a row of the side-by-side diff
+    Split(usize),
+    /// a collapsed hunk, by index
+    Fold(usize),
+    /// a collapsed file of a diff of all files, by index
+    FileFold(usize),
+    /// a line of the full file, by index
+    File(usize),
+}
+
+/// How a line of the full file was changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Added,
+    Changed,
+    /// lines were removed before this one
+    Removed,
+}
+
+impl Marker {
+    fn span(marker: Option<Marker>) -> Span<'static> {
+        match marker {
+            Some(Marker::Added) => {
+                Span::styled("+", Style::default().fg(Color::Indexed(2)))
+            }
+            Some(Marker::Changed) => {
+                Span::styled("~", Style::default().fg(Color::Indexed(3)))
+            }
+            Some(Marker::Removed) => {
+                Span::styled("_", Style::default().fg(Color::Indexed(1)))
+            }
+            None => Span::from(" "),
+        }
+    }
+}
+
+/// The order the hunks of a diff of all files are shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HunkOrder {
+    /// file by file, as git lists them
+    #[default]
+    Diff,
+    /// the hunks that change the most lines first
+    Size,
+    /// the hunks that add the most for what they delete first, so pure
+    /// deletions come last
+    Additions,
+}
+
+impl HunkOrder {
+    pub fn next(self) -> HunkOrder {
+        match self {
+            HunkOrder::Diff => HunkOrder::Size,
+            HunkOrder::Size => HunkOrder::Additions,
+            HunkOrder::Additions => HunkOrder::Diff,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HunkOrder::Diff => "as diffed",
+            HunkOrder::Size => "largest first",
+            HunkOrder::Additions => "additions first",
+        }
+    }
+
+    /// Compare two hunks by their added and deleted lines; hunks that come
+    /// first are less
+    fn compare(self, a: (usize, usize), b: (usize, usize)) -> Ordering {
+        let size = |(adds, dels): (usize, usize)| adds + dels;
+        match self {
+            HunkOrder::Diff => Ordering::Equal,
+            HunkOrder::Size => size(b).cmp(&size(a)),
+            // compare the fractions of added lines without dividing
+            HunkOrder::Additions => (b.0 * size(a))
+                .cmp(&(a.0 * size(b)))
+                .then(size(b).cmp(&size(a))),
+        }
+    }
+}
+
+/// Rows that have already been rendered, which are reused until the diff or
+/// the way it's rendered changes
+#[derive(Debug, Clone, Default)]
+struct RowCache {
+    /// the settings the rows were rendered with
+    renderer: Option<(LineRenderer, usize)>,
+    rows: HashMap<usize, Vec<Line<'static>>>,
+}
+
+impl RowCache {
+    /// Return a rendered row, if it was rendered with the same settings
+    fn get(
+        &mut self,
+        row: usize,
+        renderer: &LineRenderer,
+        width: usize,
+    ) -> Option<Vec<Line<'static>>> {
+        if self.renderer.as_ref() != Some(&(renderer.clone(), width)) {
+            self.renderer = Some((renderer.clone(), width));
+            self.rows.clear();
+            return None;
+        }
+        self.rows.get(&row).cloned()
+    }
+
+    fn insert(&mut self, row: usize, lines: Vec<Line<'static>>) {
+        if self.rows.len() >= MAX_CACHED_ROWS {
+            self.rows.clear();
+        }
+        self.rows.insert(row, lines);
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+}
+
+/// The complete file as it is after the change, with the changed lines marked
+#[derive(Debug, Clone)]
+struct FullFile {
+    lines: Vec<String>,
+    markers: Vec<Option<Marker>>,
+    /// for each line, the first unified diff line at or after it
+    diff_lines: Vec<usize>,
+}
+
+impl FullFile {
+    fn new(text: &str, diff: &FileDiff) -> FullFile {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let mut markers = vec![None; lines.len()];
+        let mut mark = |nr: u32, marker: Marker| {
+            let i = (nr as usize).saturating_sub(1);
+            if let Some(m) = markers.get_mut(min(i, lines.len().max(1) - 1)) {
+                if m.is_none() || marker != Marker::Removed {
+                    *m = Some(marker);
+                }
+            }
+        };
+        for row in &diff.split {
+            match row {
+                SplitLine::Pair(Some(old), Some(new)) if old != new => {
+                    if let DiffLine::Add(m) = &diff.line_meta[*new] {
+                        mark(m.new, Marker::Changed);
+                    }
+                }
+                SplitLine::Pair(None, Some(new)) => {
+                    if let DiffLine::Add(m) = &diff.line_meta[*new] {
+                        mark(m.new, Marker::Added);
+                    }
+                }
+                SplitLine::Pair(Some(old), None) => {
+                    if let DiffLine::Del(m) = &diff.line_meta[*old] {
+                        mark(m.new, Marker::Removed);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut numbered = diff
+            .line_meta
+            .iter()
+            .enumerate()
+            .filter_map(|(i, meta)| match meta {
+                DiffLine::Add(m) | DiffLine::Same(m) => {
+                    Some((m.new as usize, i))
+                }
+                _ => None,
+            })
+            .peekable();
+        let diff_lines = (1..=lines.len())
+            .map(|nr| {
+                while numbered.next_if(|(n, _)| *n < nr).is_some() {}
+                numbered.peek().map_or(diff.lines.len(), |(_, i)| *i)
+            })
+            .collect();
+
+        FullFile {
+            lines,
+            markers,
+            diff_lines,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diff {
+    height: usize,
+    offset: usize,
+    diff: FileDiff,
+    range: DiffAction,
+    /// the files changed in the diffed range
+    stats: Vec<Stat>,
+    /// the file being shown
+    index: usize,
+    search: Option<String>,
+    /// the parsed search, or why it couldn't be parsed
+    pattern: Option<Result<SearchPattern, String>>,
+    show_line_numbers: bool,
+    selection: Option<Selection>,
+    split: bool,
+    /// the number of columns of line content scrolled out of view
+    hscroll: usize,
+    wrap: bool,
+    opts: GitDiffOpts,
+    folded: HashSet<usize>,
+    /// every changed file is shown, one after another
+    all_files: bool,
+    /// the collapsed files, when all files are shown
+    folded_files: HashSet<usize>,
+    /// the order of the hunks, when all files are shown
+    hunk_order: HunkOrder,
+    rows: Vec<Row>,
+    /// the two sides of a binary file
+    binary: Option<BinaryFile>,
+    /// a preview of a binary image file
+    image: Option<InlineImage>,
+    /// the full file, while it's being shown instead of the diff
+    full_file: Option<FullFile>,
+    /// the commit the target's branch has moved to since the diff was made
+    moved: Option<GitRef>,
+    /// mark whitespace problems on added lines
+    whitespace_errors: bool,
+    row_cache: RowCache,
+}
+
+/// The two sides of a binary file, with their sizes; a side is None if the
+/// file doesn't exist there
+#[derive(Debug, Clone)]
+struct BinaryFile {
+    old: Option<(FileVersion, Option<u64>)>,
+    new: Option<(FileVersion, Option<u64>)>,
+}
+
+impl Diff {
+    /// Create a diff of one of the files changed in a range; the other files
+    /// can be switched to from the view
+    pub fn new(
+        stats: &[Stat],
+        index: usize,
+        range: &DiffAction,
+        opts: GitDiffOpts,
+    ) -> Diff {
+        Diff::create(stats, index, range, opts, false)
+    }
+
+    /// Create a diff of a file in the changes a commit made, which can be
+    /// given by its path before or after the change
+    pub fn of_commit(
+        commit: &GitRef,
+        path: &str,
+        opts: GitDiffOpts,
+    ) -> Result<Diff, String> {
+        Diff::of_file(
+            &DiffAction::show(Target::Ref(commit.clone())),
+            path,
+            opts,
+        )
+    }
+
+    /// Create a diff of a file in the changes of a range, which can be given
+    /// by its path before or after the change
+    pub fn of_file(
+        range: &DiffAction,
+        path: &str,
+        opts: GitDiffOpts,
+    ) -> Result<Diff, String> {
+        let stats = git_diff_stat(range, Some(opts));
+        let index = stats
+            .iter()
+            .position(|s| s.new_path() == path || s.path == path)
+            .ok_or_else(|| {
+                format!("{} isn't in the diff of {}", path, range.describe())
+            })?;
+        Ok(Diff::new(&stats, index, range, opts))
+    }
+
+    /// Create a diff of every file changed in a range, shown one after another
+    pub fn all_files(range: &DiffAction, opts: GitDiffOpts) -> Diff {
+        Diff::create(&[], 0, range, opts, true)
+    }
+
+    fn create(
+        stats: &[Stat],
+        index: usize,
+        range: &DiffAction,
+        opts: GitDiffOpts,
+        all_files: bool,
+    ) -> Diff {
+        let mut diff = Diff {
+            diff: FileDiff::new(""),
+            height: 0,
+            offset: 0,
+            stats: stats.to_vec(),
+            index,
+            range: range.clone(),
+            search: None,
+            pattern: None,
+            show_line_numbers: false,
+            selection: None,
+            split: false,
+            hscroll: 0,
+            wrap: false,
+            opts,
+            folded: HashSet::new(),
+            all_files,
+            folded_files: HashSet::new(),
+            hunk_order: HunkOrder::default(),
+            rows: vec![],
+            binary: None,
+            image: None,
+            full_file: None,
+            moved: None,
+            whitespace_errors: true,
+            row_cache: RowCache::default(),
+        };
+        diff.refresh();
+        if all_files {
+            diff.folded_files = (0..diff.diff.files.len())
+                .filter(|f| diff.diff.files[*f].len() > LARGE_FILE_LINES)
+                .collect();
+            diff.update_rows();
+        }
+        diff
+    }
+
+    /// Return the file being shown, or the file at the top of the view when
+    /// all files are shown
+    fn stat(&self) -> &Stat {
+        if !self.all_files {
+            return &self.stats[self.index];
+        }
+        let file = self.file_at(self.line_at(self.offset));
+        self.stats
+            .get(file)
+            .or(self.stats.last())
+            .unwrap_or(&NO_STAT)
+    }
+
+    /// Return the changes to the file being shown, which are the changes of
+    /// the commit that made them when several commits are shown together
+    fn file_range(&self) -> DiffAction {
+        match &self.stat().commit {
+            Some(commit) => DiffAction::show(Target::Ref(commit.clone())),
+            None => self.range.clone(),
+        }
+    }
+
+    /// Return the commit whose changes to the file are shown, if they're
+    /// committed
+    fn commit(&self) -> Option<&GitRef> {
+        match (&self.stat().commit, &self.range.target) {
+            (Some(commit), _) | (None, Target::Ref(commit)) => Some(commit),
+            _ => None,
+        }
+    }
+
+    /// Return the index of the file that a unified diff line belongs to
+    fn file_at(&self, line: usize) -> usize {
+        self.diff
+            .files
+            .iter()
+            .rposition(|f| f.start <= line)
+            .unwrap_or(0)
+    }
+
+    /// Return the file whose header is shown in a row, if all files are shown
+    fn file_header_at(&self, row: usize) -> Option<usize> {
+        if !self.all_files {
+            return None;
+        }
+        if let Some(Row::FileFold(f)) = self.rows.get(row) {
+            return Some(*f);
+        }
+        let line = self.line_at(row);
+        let f = self.file_at(line);
+        let file = self.diff.files.get(f)?;
+        let header_end = self
+            .diff
+            .hunk_at(file.start)
+            .map_or(file.end, |h| min(h.lines.start, file.end));
+        (line >= file.start && line < header_end).then_some(f)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.stat().worktree_path()
+    }
+
+    /// Re-diff the file; call this when the underlying file may have changed
+    ///
+    /// The hunks may be different afterwards, so any folded hunks are
+    /// unfolded.
+    pub fn refresh(&mut self) {
+        // collapsed files stay collapsed if they're still in the diff
+        let folded_paths: Vec<String> = self
+            .folded_files
+            .iter()
+            .filter_map(|f| self.stats.get(*f))
+            .map(|s| s.path.clone())
+            .collect();
+
+        self.diff = if self.all_files {
+            self.stats = git_diff_stat(&self.range, Some(self.opts));
+            git_diff_all(&self.range, Some(self.opts))
+        } else {
+            git_diff_file(
+                &self.stats[self.index].path,
+                &self.stats[self.index].old_path,
+                &self.file_range(),
+                Some(self.opts),
+            )
+        };
+        self.folded.clear();
+        self.folded_files = (0..self.stats.len())
+            .filter(|f| folded_paths.contains(&self.stats[*f].path))
+            .filter(|f| *f < self.diff.files.len())
+            .collect();
+        if self.full_file.is_some() {
+            self.full_file = self.load_full_file();
+        }
+        self.update_rows();
+        self.update_binary();
+    }
+
+    /// Diff up to the new tip of the target's branch, if it has moved; returns
+    /// false if it hasn't
+    pub fn follow_branch(&mut self) -> Result<bool, String> {
+        let Some(tip) = self.moved.clone() else {
+            return Ok(false);
+        };
+        let range = self.range.moved_to(tip.clone());
+        if !self.set_range(range) {
+            return Err(if self.all_files {
+                format!("Nothing is changed at {}", tip)
+            } else {
+                format!("{} isn't changed at {}", self.stat().path, tip)
+            });
+        }
+        self.moved = None;
+        Ok(true)
+    }
+
+    /// Show a different range, keeping the file that's being shown; returns
+    /// false if the range doesn't change the file, or anything at all when
+    /// all files are shown
+    fn set_range(&mut self, range: DiffAction) -> bool {
+        let stats = git_diff_stat(&range, Some(self.opts));
+        let index = if self.all_files {
+            (!stats.is_empty()).then_some(0)
+        } else {
+            stats.iter().position(|s| s.path == self.stat().path)
+        };
+        let Some(index) = index else {
+            return false;
+        };
+
+        self.range = range;
+        self.stats = stats;
+        self.index = index;
+        self.selection = None;
+        self.refresh();
+        self.offset = min(self.offset, self.list_count().saturating_sub(1));
+        true
+    }
+
+    /// Diff the commit being shown from another revision
+    pub fn anchor(&mut self, rev: &str) -> Result<String, String> {
+        let range = git_anchor(&self.range, rev)?;
+        if !self.set_range(range) {
+            return Err(if self.all_files {
+                format!("Nothing is different in {}", rev)
+            } else {
+                format!("{} is the same in {}", self.stat().path, rev)
+            });
+        }
+        self.moved = None;
+        Ok(self.range.describe())
+    }
+
+    /// Switch between the staged changes, the unstaged changes, and both
+    /// together, skipping any that don't change the file; returns a
+    /// description of the changes being shown
+    pub fn cycle_worktree_side(&mut self) -> Result<String, String> {
+        let Some(mut range) = self.range.next_worktree_side() else {
+            return Err("Only uncommitted changes can be switched".into());
+        };
+
+        // there are three sides, so at most two others to try
+        for _ in 0..2 {
+            let next = range.next_worktree_side().unwrap();
+            if self.set_range(range) {
+                return Ok(self.range.describe());
+            }
+            range = next;
+        }
+
+        Err(if self.all_files {
+            "There are no other uncommitted changes".into()
+        } else {
+            format!("{} has no other uncommitted changes", self.stat().path)
+        })
+    }
+
+    /// Compare the next pair of versions of a conflicted file: its base,
+    /// ours, and theirs stages, and the working tree, skipping any pairs that
+    /// are the same; returns a description of the versions being compared
+    pub fn cycle_conflict_sides(&mut self) -> Result<String, String> {
+        let path = self.stat().path.clone();
+        if !git_conflicts().contains(&path) {
+            return Err(format!("{} isn't conflicted", path));
+        }
+
+        let mut range = self.range.next_conflict_sides(&path);
+        // there are six pairs, one of which may be the one being shown
+        let others = if self.range.conflict_sides().is_some() {
+            5
+        } else {
+            6
+        };
+        for _ in 0..others {
+            let next = range.next_conflict_sides(&path);
+            if self.set_range(range) {
+                return Ok(self.range.describe());
+            }
+            range = next;
+        }
+        Err(format!("{} has no other versions that differ", path))
+    }
+
+    /// Read the file as it is after the change; returns None if the file is
+    /// binary or was deleted
+    fn load_full_file(&self) -> Option<FullFile> {
+        if self.diff.binary {
+            return None;
+        }
+        let stat = self.stat();
+        let (_, new) =
+            git_file_versions(&stat.path, &stat.old_path, &self.file_range());
+        new?.text().map(|text| FullFile::new(&text, &self.diff))
+    }
+
+    /// Switch between showing the diff and showing the whole file with its
+    /// changed lines marked, keeping the same part of the file in view
+    pub fn toggle_full_file(&mut self) -> Result<bool, &'static str> {
+        if self.full_file.is_some() {
+            let line = self.line_at(self.offset);
+            self.full_file = None;
+            self.update_rows();
+            self.offset = self.row_of(line);
+            return Ok(false);
+        }
+
+        if self.all_files {
+            return Err("Open a single file to show it in full");
+        }
+        if self.diff.binary {
+            return Err("Binary files can't be shown in full");
+        }
+        let line = self.edit_line();
+        let Some(file) = self.load_full_file() else {
+            return Err("The file doesn't exist after the change");
+        };
+        self.full_file = Some(file);
+        self.selection = None;
+        self.hscroll = 0;
+        self.update_rows();
+        self.offset = min(
+            line.saturating_sub(1) as usize,
+            self.list_count().saturating_sub(1),
+        );
+        Ok(true)
+    }
+
+    /// Look up the versions of the file if it's binary
+    fn update_binary(&mut self) {
+        self.image = None;
+        // binary files are summed up by git's own line when all files are
+        // shown
+        self.binary = if self.diff.binary && !self.all_files {
+            let stat = self.stat();
+            let (old, new) = git_file_versions(
+                &stat.path,
+                &stat.old_path,
+                &self.file_range(),
+            );
+            let with_size = |v: FileVersion| {
+                let size = v.size();
+                (v, size)
+            };
+            Some(BinaryFile {
+                old: old.map(with_size),
+                new: new.map(with_size),
+            })
+        } else {
+            None
+        };
+    }
+
+    /// Load a preview of a binary image file, if the protocol can show it
+    fn update_image(&mut self, area: Rect, protocol: ImageProtocol) {
+        if let Some(image) = &mut self.image {
+            image.area = area;
+            return;
+        }
+
+        let Some(format) = ImageFormat::from_path(&self.stat().path) else {
+            return;
+        };
+        if !protocol.supports(format) {
+            return;
+        }
+        let Some(binary) = &self.binary else {
+            return;
+        };
+        // show the image as it is after the change, unless it was deleted
+        let Some((version, _)) = binary.new.as_ref().or(binary.old.as_ref())
+        else {
+            return;
+        };
+
+        self.image = version.base64().map(|data| InlineImage {
+            area,
+            id: version.id(),
+            data,
+        });
+    }
+
+    /// Rebuild the rows of the view from the diff, the layout, and the
+    /// folded hunks
+    fn update_rows(&mut self) {
+        self.row_cache.clear();
+        if let Some(file) = &self.full_file {
+            self.rows = (0..file.lines.len()).map(Row::File).collect();
+            return;
+        }
+
+        let mut fold_of: Vec<Option<usize>> = vec![None; self.diff.lines.len()];
+        for h in &self.folded {
+            for line in self.diff.hunks[*h].lines.clone() {
+                fold_of[line] = Some(*h);
+            }
+        }
+        let mut file_fold_of: Vec<Option<usize>> =
+            vec![None; self.diff.lines.len()];
+        for f in &self.folded_files {
+            for line in self.diff.files[*f].clone() {
+                file_fold_of[line] = Some(*f);
+            }
+        }
+
+        let rows: Vec<(Row, Option<usize>)> = if self.split {
+            self.diff
+                .split
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let line = row.lines().first().copied();
+                    (Row::Split(i), line)
+                })
+                .collect()
+        } else {
+            (0..self.diff.lines.len())
+                .map(|i| (Row::Line(i), Some(i)))
+                .collect()
+        };
+        let rows = match self.hunk_order {
+            HunkOrder::Diff => rows,
+            _ => self.order_hunks(rows),
+        };
+
+        self.rows = vec![];
+        // a collapsed file's hunks may be spread out when they're reordered,
+        // but the file is only shown once
+        let mut shown_file_folds = HashSet::new();
+        for (row, line) in rows {
+            if let Some(f) = line.and_then(|l| file_fold_of[l]) {
+                if shown_file_folds.insert(f) {
+                    self.rows.push(Row::FileFold(f));
+                }
+                continue;
+            }
+            match line.and_then(|l| fold_of[l]) {
+                Some(h) => {
+                    if !matches!(self.rows.last(), Some(Row::Fold(f)) if *f == h)
+                    {
+                        self.rows.push(Row::Fold(h));
+                    }
+                }
+                None => self.rows.push(row),
+            }
+        }
+    }
+
+    /// Rearrange the rows of the diff, each with the line it starts with, so
+    /// the hunks are in the chosen order; each hunk is headed by its file's
+    /// heading, unless it follows another hunk of the file, and the files
+    /// without hunks come last
+    fn order_hunks(
+        &self,
+        rows: Vec<(Row, Option<usize>)>,
+    ) -> Vec<(Row, Option<usize>)> {
+        let hunks = &self.diff.hunks;
+        let mut hunk_of: Vec<Option<usize>> = vec![None; self.diff.lines.len()];
+        for (h, hunk) in hunks.iter().enumerate() {
+            for line in hunk.lines.clone() {
+                hunk_of[line] = Some(h);
+            }
+        }
+        let mut file_of: Vec<Option<usize>> = vec![None; self.diff.lines.len()];
+        for (f, file) in self.diff.files.iter().enumerate() {
+            for line in file.clone() {
+                file_of[line] = Some(f);
+            }
+        }
+
+        let mut hunk_rows: Vec<Vec<(Row, Option<usize>)>> =
+            vec![vec![]; hunks.len()];
+        let mut file_rows: Vec<Vec<(Row, Option<usize>)>> =
+            vec![vec![]; self.diff.files.len()];
+        let mut headings: HashMap<usize, (Row, Option<usize>)> = HashMap::new();
+        for (row, line) in rows {
+            let Some(l) = line else {
+                continue;
+            };
+            match (hunk_of[l], file_of[l]) {
+                (Some(h), _) => hunk_rows[h].push((row, line)),
+                (None, Some(f)) => {
+                    if matches!(self.diff.line_meta[l], DiffLine::Start) {
+                        headings.insert(f, (row.clone(), line));
+                    }
+                    file_rows[f].push((row, line));
+                }
+                _ => {}
+            }
+        }
+
+        let changes: Vec<(usize, usize)> = hunks
+            .iter()
+            .map(|h| {
+                let meta = &self.diff.line_meta[h.lines.clone()];
+                let adds =
+                    meta.iter().filter(|m| matches!(m, DiffLine::Add(_)));
+                let dels =
+                    meta.iter().filter(|m| matches!(m, DiffLine::Del(_)));
+                (adds.count(), dels.count())
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..hunks.len()).collect();
+        order.sort_by(|a, b| self.hunk_order.compare(changes[*a], changes[*b]));
+
+        let mut ordered = vec![];
+        let mut last_file = None;
+        let mut has_hunks = vec![false; self.diff.files.len()];
+        for h in order {
+            let file = file_of[hunks[h].lines.start];
+            if let Some(f) = file {
+                has_hunks[f] = true;
+                if last_file != Some(f) {
+                    ordered.extend(headings.get(&f).cloned());
+                }
+            }
+            last_file = file;
+            ordered.append(&mut hunk_rows[h]);
+        }
+        for (f, rows) in file_rows.into_iter().enumerate() {
+            if !has_hunks[f] {
+                ordered.extend(rows);
+            }
+        }
+        ordered
+    }
+
+    /// Show the hunks of a diff of all files in the next order, keeping the
+    /// line at the top of the view in view; returns None if only one file is
+    /// shown
+    pub fn cycle_hunk_order(&mut self) -> Option<HunkOrder> {
+        if !self.all_files {
+            return None;
+        }
+        let line = self.line_at(self.offset);
+        self.hunk_order = self.hunk_order.next();
+        self.selection = None;
+        self.update_rows();
+        self.offset = self.row_of(line);
+        Some(self.hunk_order)
+    }
+
+    /// Return the unified diff lines shown in a row of the view
+    fn row_lines(&self, row: usize) -> Range<usize> {
+        match self.rows.get(row) {
+            Some(Row::Line(i)) => *i..*i + 1,
+            Some(Row::Split(i)) => {
+                let lines = self.diff.split[*i].lines();
+                match (lines.iter().min(), lines.iter().max()) {
+                    (Some(first), Some(last)) => *first..*last + 1,
+                    _ => row..row,
+                }
+            }
+            Some(Row::Fold(h)) => self.diff.hunks[*h].lines.clone(),
+            Some(Row::FileFold(f)) => self.diff.files[*f].clone(),
+            Some(Row::File(i)) => {
+                let file = self.full_file.as_ref().unwrap();
+                let line = file.diff_lines[*i];
+                match &self.diff.line_meta.get(line) {
+                    Some(DiffLine::Add(m) | DiffLine::Same(m))
+                        if m.new as usize == *i + 1 =>
+                    {
+                        line..line + 1
+                    }
+                    _ => line..line,
+                }
+            }
+            None => row..row,
+        }
+    }
+
+    /// Show the diff of the next file in the range, or the previous one if
+    /// `forward` is false; returns false if there's no file to move to
+    pub fn cycle_file(&mut self, forward: bool) -> bool {
+        if self.all_files {
+            let starts = self.rows_of(self.diff.files.iter().map(|f| f.start));
+            let start = if forward {
+                starts.into_iter().find(|s| *s > self.offset)
+            } else {
+                starts.into_iter().rev().find(|s| *s < self.offset)
+            };
+            let Some(start) = start else {
+                return false;
+            };
+            self.selection = None;
+            self.offset = start;
+            return true;
+        }
+
+        let index = if forward {
+            self.index + 1
+        } else if self.index > 0 {
+            self.index - 1
+        } else {
+            return false;
+        };
+        if index >= self.stats.len() {
+            return false;
+        }
+
+        self.index = index;
+        self.offset = 0;
+        self.selection = None;
+        self.refresh();
+        true
+    }
+
+    /// Scroll to the next hunk, or the previous one if `forward` is false;
+    /// returns false if there's no hunk to move to
+    pub fn cycle_hunk(&mut self, forward: bool) -> bool {
+        if let Some(file) = &self.full_file {
+            // move between runs of marked lines
+            let marked = |i: usize| file.markers[i].is_some();
+            let starts = (0..file.lines.len())
+                .filter(|i| marked(*i) && (*i == 0 || !marked(*i - 1)));
+            let start = if forward {
+                starts.into_iter().find(|s| *s > self.offset)
+            } else {
+                starts.into_iter().rev().find(|s| *s < self.offset)
+            };
+            if let Some(start) = start {
+                self.offset = start;
+            }
+            return start.is_some();
+        }
+
+        let starts =
+            self.rows_of(self.diff.hunks.iter().map(|h| h.lines.start));
+        let start = if forward {
+            starts.into_iter().find(|s| *s > self.offset)
+        } else {
+            starts.into_iter().rev().find(|s| *s < self.offset)
+        };
+
+        match start {
+            Some(start) => {
+                self.selection = None;
+                self.offset = start;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Collapse the hunk at the top of the view to a single line, or expand
+    /// it if it's already collapsed
+    pub fn toggle_fold(&mut self) {
+        if self.full_file.is_some() {
+            return;
+        }
+        if let Some(f) = self.file_header_at(self.offset) {
+            if !self.folded_files.remove(&f) {
+                self.folded_files.insert(f);
+            }
+            self.selection = None;
+            self.update_rows();
+            self.offset = self.row_of(self.diff.files[f].start);
+            return;
+        }
+        let line = self.line_at(self.offset);
+        let Some(h) = self.diff.hunks.iter().position(|h| line < h.lines.end)
+        else {
+            return;
+        };
+        if !self.folded.remove(&h) {
+            self.folded.insert(h);
+        }
+        self.selection = None;
+        self.update_rows();
+        self.offset = self.row_of(self.diff.hunks[h].lines.start);
+    }
+
+    /// Scroll to a line of the file, as numbered after the change or, if it
+    /// was deleted, before it; returns false if the line isn't in the diff
+    pub fn goto_line(&mut self, nr: u32) -> bool {
+        if let Some(file) = &self.full_file {
+            if nr == 0 || nr as usize > file.lines.len() {
+                return false;
+            }
+            self.offset = nr as usize - 1;
+            return true;
+        }
+
+        // when all files are shown, the line is looked for in the file at the
+        // top of the view
+        let file = match self
+            .diff
+            .files
+            .get(self.file_at(self.line_at(self.offset)))
+        {
+            Some(file) if self.all_files => file.clone(),
+            _ => 0..self.diff.lines.len(),
+        };
+        let metas = &self.diff.line_meta[file.clone()];
+        let found = metas
+            .iter()
+            .position(|m| {
+                matches!(m, DiffLine::Add(m) | DiffLine::Same(m) if m.new == nr)
+            })
+            .or_else(|| {
+                metas.iter().position(|m| {
+                    matches!(m, DiffLine::Del(m) | DiffLine::Same(m) if m.old == nr)
+                })
+            });
+        let Some(line) = found.map(|i| i + file.start) else {
+            return false;
+        };
+        self.scroll_to_line(line);
+        true
+    }
+
+    /// Scroll to the first deleted line that contains some text, returning
+    /// false if there isn't one
+    pub fn goto_removed(&mut self, text: &str) -> bool {
+        if self.full_file.is_some() {
+            return false;
+        }
+        let found =
+            self.diff.line_meta.iter().enumerate().position(|(i, m)| {
+                matches!(m, DiffLine::Del(_))
+                    && self.diff.lines[i].contains(text)
+            });
+        let Some(line) = found else {
+            return false;
+        };
+        self.scroll_to_line(line);
+        true
+    }
+
+    /// Scroll a line of the diff to the top of the view, expanding its file
+    /// and hunk if they're collapsed
+    fn scroll_to_line(&mut self, line: usize) {
+        if let Some(f) = self.diff.files.iter().position(|f| f.contains(&line))
+        {
+            if self.folded_files.remove(&f) {
+                self.update_rows();
+            }
+        }
+        if let Some(h) =
+            self.diff.hunks.iter().position(|h| h.lines.contains(&line))
+        {
+            if self.folded.remove(&h) {
+                self.update_rows();
+            }
+        }
+        self.selection = None;
+        self.offset = self.row_of(line);
+    }
+
+    /// Collapse all the hunks, or expand them all, keeping the same part of
+    /// the diff in view
+    pub fn set_all_folded(&mut self, folded: bool) {
+        if self.full_file.is_some() {
+            return;
+        }
+        let line = self.line_at(self.offset);
+        // when all files are shown, whole files are collapsed
+        let (folds, count) = if self.all_files {
+            (&mut self.folded_files, self.diff.files.len())
+        } else {
+            (&mut self.folded, self.diff.hunks.len())
+        };
+        *folds = if folded {
+            (0..count).collect()
+        } else {
+            HashSet::new()
+        };
+        self.selection = None;
+        self.update_rows();
+        self.offset =
+            min(self.row_of(line), self.list_count().saturating_sub(1));
+    }
+
+    /// Return the number of context lines around changes
+    pub fn context(&self) -> u32 {
+        self.opts.context.unwrap_or(DEFAULT_CONTEXT)
+    }
+
+    /// Change the number of context lines around changes, and re-diff the
+    /// file
+    pub fn set_context(&mut self, context: u32) {
+        self.opts.context = Some(context);
+        self.selection = None;
+        let line = self.line_at(self.offset);
+        self.refresh();
+        self.offset =
+            min(self.row_of(line), self.list_count().saturating_sub(1));
+    }
+
+    /// Switch to the next diff algorithm, and re-diff the file
+    pub fn cycle_algorithm(&mut self) -> DiffAlgorithm {
+        self.opts.algorithm = self.opts.algorithm.next();
+        self.selection = None;
+        self.refresh();
+        self.offset = min(self.offset, self.list_count().saturating_sub(1));
+        self.opts.algorithm
+    }
+
+    /// Toggle whether whitespace changes are ignored, and re-diff the file
+    pub fn toggle_ignore_whitespace(&mut self) {
+        self.opts.ignore_whitespace = !self.opts.ignore_whitespace;
+        self.selection = None;
+        self.refresh();
+        self.offset = min(self.offset, self.list_count().saturating_sub(1));
+    }
+
+    /// Toggle marking whitespace problems, returning true if they're marked
+    pub fn toggle_whitespace_errors(&mut self) -> bool {
+        self.whitespace_errors = !self.whitespace_errors;
+        self.whitespace_errors
+    }
+
+    pub fn toggle_show_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
+    }
+
+    /// Switch between unified and side-by-side rendering, keeping the same
+    /// part of the diff in view
+    pub fn toggle_split(&mut self) {
+        if self.full_file.is_some() {
+            return;
+        }
+        let line = self.line_at(self.offset);
+        self.split = !self.split;
+        self.selection = None;
+        self.update_rows();
+        self.offset = self.row_of(line);
+    }
+
+    /// Describe changes to the file's mode, whether it was created or deleted,
+    /// and how similar a renamed or copied file is to the original, from the
+    /// header lines of the file at the top of the view
+    fn file_changes(&self) -> Vec<String> {
+        let file = match self
+            .diff
+            .files
+            .get(self.file_at(self.line_at(self.offset)))
+        {
+            Some(file) => file.clone(),
+            None => 0..self.diff.lines.len(),
+        };
+        let header_end = self
+            .diff
+            .hunk_at(file.start)
+            .map_or(file.end, |h| min(h.lines.start, file.end));
+        let mut changes = vec![];
+        let mut old_mode = None;
+        for line in &self.diff.lines[file.start..header_end] {
+            if let Some(similarity) = line.strip_prefix("similarity index ") {
+                changes.push(format!("{} similar", similarity));
+            } else if let Some(mode) = line.strip_prefix("new file mode ") {
+                changes.push(format!("new file ({})", mode));
+            } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+                changes.push(format!("deleted ({})", mode));
+            } else if let Some(mode) = line.strip_prefix("old mode ") {
+                old_mode = Some(mode);
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                changes.push(format!(
+                    "mode {} → {}",
+                    old_mode.unwrap_or("?"),
+                    mode
+                ));
+            }
+        }
+        changes
+    }
+
+    /// Scroll the content of the lines left or right, keeping line numbers in
+    /// place; long lines are wrapped instead while wrapping is on
+    pub fn scroll_sideways(&mut self, right: bool) {
+        if self.wrap {
+            return;
+        }
+        self.hscroll = if right {
+            let lines = match &self.full_file {
+                Some(file) => &file.lines,
+                None => &self.diff.lines,
+            };
+            let longest =
+                lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+            min(self.hscroll + HSCROLL_STEP, longest.saturating_sub(1))
+        } else {
+            self.hscroll.saturating_sub(HSCROLL_STEP)
+        };
+    }
+
+    /// Toggle whether long lines are wrapped
+    pub fn toggle_wrap(&mut self) -> bool {
+        self.wrap = !self.wrap;
+        self.hscroll = 0;
+        self.wrap
+    }
+
+    /// Return the first unified diff line shown in a row of the view
+    fn line_at(&self, row: usize) -> usize {
+        self.row_lines(row).start
+    }
+
+    /// Return the row of the view that shows a unified diff line, or the first
+    /// row after it
+    fn row_of(&self, line: usize) -> usize {
+        // reordered hunks put rows out of order, so look for the line itself
+        // first
+        (0..self.rows.len())
+            .find(|r| self.row_lines(*r).contains(&line))
+            .or_else(|| {
+                (0..self.rows.len()).find(|r| self.row_lines(*r).end > line)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Return the rows that show each of a set of unified diff lines, in the
+    /// order the rows are shown
+    fn rows_of(&self, lines: impl Iterator<Item = usize>) -> Vec<usize> {
+        let mut row_of_line: Vec<Option<usize>> =
+            vec![None; self.diff.lines.len()];
+        for row in (0..self.rows.len()).rev() {
+            for line in self.row_lines(row) {
+                if let Some(r) = row_of_line.get_mut(line) {
+                    *r = Some(row);
+                }
+            }
+        }
+        let mut rows: Vec<usize> = lines
+            .filter_map(|l| row_of_line.get(l).copied().flatten())
+            .collect();
+        rows.sort();
+        rows.dedup();
+        rows
+    }
+
+    /// Start selecting lines at the top of the view, or stop selecting; lines
+    /// can only be selected in the unified view, with the hunks in the diff's
+    /// order
+    pub fn toggle_selection(&mut self) {
+        if self.split
+            || self.full_file.is_some()
+            || self.hunk_order != HunkOrder::Diff
+        {
+            return;
+        }
+        self.selection = match self.selection {
+            Some(_) => None,
+            None => Some(Selection {
+                anchor: self.offset,
+                cursor: self.offset,
+            }),
+        };
+    }
+
+    pub fn is_selecting(&self) -> bool {
+        self.selection.is_some()
+    }
+
+    /// Extend the selection up a line, scrolling to keep it visible
+    pub fn select_up(&mut self) {
+        if let Some(sel) = &mut self.selection {
+            sel.cursor = sel.cursor.saturating_sub(1);
+            self.offset = min(self.offset, sel.cursor);
+        }
+    }
+
+    /// Extend the selection down a line, scrolling to keep it visible
+    pub fn select_down(&mut self) {
+        let count = self.rows.len();
+        if let Some(sel) = &mut self.selection {
+            sel.cursor = min(sel.cursor + 1, count.saturating_sub(1));
+            if sel.cursor >= self.offset + self.height {
+                self.offset = sel.cursor + 1 - self.height;
+            }
+        }
+    }
+
+    /// Return the selected lines, or the lines of the hunk at the top of the
+    /// view if nothing is selected
+    fn selected_lines(&self) -> Option<Range<usize>> {
+        match &self.selection {
+            Some(sel) => Some(
+                self.row_lines(min(sel.anchor, sel.cursor)).start
+                    ..self.row_lines(max(sel.anchor, sel.cursor)).end,
+            ),
+            None => self
+                .diff
+                .hunk_at(self.line_at(self.offset))
+                .map(|h| h.lines.clone()),
+        }
+    }
+
+    /// Return the line of the changed file that's at the selection cursor or
+    /// the top of the view, for opening the file in an editor
+    pub fn edit_line(&self) -> u32 {
+        let row = match &self.selection {
+            Some(sel) => sel.cursor,
+            None => self.offset,
+        };
+        if self.full_file.is_some() {
+            return row as u32 + 1;
+        }
+        let start = self.line_at(row);
+        self.diff.line_meta[start..]
+            .iter()
+            .find_map(|m| match m {
+                DiffLine::Add(m) | DiffLine::Del(m) | DiffLine::Same(m) => {
+                    Some(m.new.max(1))
+                }
+                _ => None,
+            })
+            .unwrap_or(1)
+    }
+
+    /// Return the text of the deleted line at the selection cursor or the top
+    /// of the view, without its prefix, if there is one there
+    fn removed_text(&self) -> Option<&str> {
+        if self.full_file.is_some() || self.rows.is_empty() {
+            return None;
+        }
+        let row = match &self.selection {
+            Some(sel) => sel.cursor,
+            None => self.offset,
+        };
+        self.row_lines(row)
+            .find(|i| matches!(self.diff.line_meta[*i], DiffLine::Del(_)))
+            .map(|i| self.diff.lines[i].get(1..).unwrap_or_default())
+    }
+
+    /// Find the latest commit that removed some text from a file, looking
+    /// back from a commit or from HEAD, and show its diff scrolled to the
+    /// removed text
+    pub fn find_removal(
+        text: &str,
+        path: &str,
+        commit: Option<&GitRef>,
+        opts: GitDiffOpts,
+    ) -> Result<Diff, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Nothing to look for".into());
+        }
+        let removal =
+            git_find_removal(text, path, commit).ok_or_else(|| {
+                format!("No commit removed \"{}\" from {}", text, path)
+            })?;
+        let mut diff = Diff::of_commit(&removal, path, opts)?;
+        diff.goto_removed(text);
+        Ok(diff)
+    }
+
+    /// Return the text of the selected lines, or of the hunk at the top of
+    /// the view, and the number of lines; without prefixes, only the content
+    /// of the changed and context lines is included
+    pub fn yank(&mut self, prefixes: bool) -> Option<(String, usize)> {
+        let lines = self.selected_lines()?;
+        self.selection = None;
+        let text: Vec<&str> = lines
+            .filter_map(|i| {
+                let line = self.diff.lines[i].as_str();
+                match self.diff.line_meta[i] {
+                    _ if prefixes => Some(line),
+                    DiffLine::Add(_) | DiffLine::Del(_) | DiffLine::Same(_) => {
+                        Some(line.get(1..).unwrap_or_default())
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        Some((text.join("\n") + "\n", text.len()))
+    }
+
+    /// Describe where the selection cursor or the top of the view is: which
+    /// hunk it's in, the line numbers there, and the file's line counts
+    fn position(&self) -> Vec<StatusSegment> {
+        let row = match &self.selection {
+            Some(sel) => sel.cursor,
+            None => self.offset,
+        };
+        let mut position = vec![];
+
+        if self.full_file.is_some() {
+            position.push(format!("line {}", row + 1));
+        } else {
+            let line = self.line_at(row);
+            let hunks = &self.diff.hunks;
+            if let Some(h) = hunks.iter().position(|h| h.lines.contains(&line))
+            {
+                position.push(format!("hunk {}/{}", h + 1, hunks.len()));
+                // on a hunk's header, the numbers are those of its first line
+                let nrs = self.diff.line_meta[line..hunks[h].lines.end]
+                    .iter()
+                    .find_map(|m| match m {
+                        DiffLine::Add(m)
+                        | DiffLine::Del(m)
+                        | DiffLine::Same(m) => Some(m),
+                        _ => None,
+                    });
+                if let Some(nrs) = nrs {
+                    position.push(format!("line {} → {}", nrs.old, nrs.new));
+                }
+            }
+        }
+
+        let stat = self.stat();
+        if !stat.binary {
+            position.push(format!("+{} −{}", stat.adds, stat.deletes));
+        }
+
+        position.into_iter().map(StatusSegment::position).collect()
+    }
+
+    /// True if this is a diff between the index and HEAD or the working tree
+    pub fn can_stage(&self) -> bool {
+        self.range.anchor.is_none()
+            && self.range.conflict_sides().is_none()
+            && matches!(self.range.target, Target::Staged | Target::Unstaged)
+    }
+
+    /// Stage the selected lines, or the hunk at the top of the view; the
+    /// lines are unstaged instead if this is a diff of staged changes
+    pub fn stage(&mut self) -> Result<String, AppError> {
+        let reverse = self.range.target == Target::Staged;
+        let what = match self.selection {
+            Some(_) => "selected lines",
+            None => "hunk",
+        };
+        let patch = self
+            .selected_lines()
+            .and_then(|lines| self.diff.patch(&lines, reverse));
+
+        let Some(patch) = patch else {
+            return Ok("No changes selected".into());
+        };
+        if self.opts.ignore_whitespace {
+            return Ok("Show whitespace changes to stage them".into());
+        }
+        if self.context() == 0 {
+            return Ok("Show context lines to stage changes".into());
+        }
+
+        git_apply_cached(&patch, reverse)?;
+        self.selection = None;
+        self.refresh();
+        self.offset = min(self.offset, self.list_count().saturating_sub(1));
+
+        Ok(format!(
+            "{} {}",
+            if reverse { "Unstaged" } else { "Staged" },
+            what
+        ))
+    }
+}
+
+impl ListInfo for Diff {
+    fn list_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.offset
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.offset = pos;
+    }
+}
+
+impl ListScroll for Diff {
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl Status for Diff {
+    fn status(&self) -> Vec<StatusSegment> {
+        let mut status = vec![StatusSegment::target(format!(
+            "{}: {}",
+            self.range,
+            self.stat().path
+        ))];
+        status.extend(self.position());
+        if let Some(Err(e)) = &self.pattern {
+            status
+                .push(StatusSegment::message(format!("Invalid regex: {}", e)));
+        }
+        if self.opts.ignore_whitespace {
+            status.push(StatusSegment::mode("ignoring whitespace"));
+        }
+        if self.context() != DEFAULT_CONTEXT {
+            status.push(StatusSegment::mode(format!(
+                "context: {}",
+                self.context()
+            )));
+        }
+        if self.hunk_order != HunkOrder::Diff {
+            status.push(StatusSegment::mode(format!(
+                "hunks {}",
+                self.hunk_order.label()
+            )));
+        }
+        if self.full_file.is_some() {
+            status.push(StatusSegment::mode("full file"));
+        } else if self.split {
+            status.push(StatusSegment::mode("split"));
+        }
+        if self.wrap {
+            status.push(StatusSegment::mode("wrap"));
+        } else if self.hscroll > 0 {
+            status.push(StatusSegment::mode(format!(
+                "column {}",
+                self.hscroll + 1
+            )));
+        }
+        if let (Some(branch), Some(_)) = (self.range.branch(), &self.moved) {
+            status.push(StatusSegment::hint(format!(
+                "{} has moved, R reloads",
+                branch
+            )));
+        }
+        if self.is_selecting() {
+            status.push(StatusSegment::mode("selecting"));
+            if self.can_stage() {
+                status.push(StatusSegment::hint(match self.range.target {
+                    Target::Staged => "s unstages the selection",
+                    _ => "s stages the selection",
+                }));
+            }
+        }
+        status
+    }
+}
+
+impl Search for Diff {
+    fn set_search(&mut self, search: Option<String>) {
+        if search != self.search {
+            self.pattern = search.as_deref().map(SearchPattern::new);
+            self.search = search;
+        }
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    /// Lines are matched as they appear in the diff, so a regex search for
+    /// `^\+` finds added lines
+    fn is_match(&self, idx: usize) -> bool {
+        match &self.pattern {
+            Some(Ok(pattern)) => match &self.rows[idx] {
+                Row::Split(i) => self.diff.split[*i]
+                    .lines()
+                    .iter()
+                    .any(|l| pattern.is_match(&self.diff.lines[*l])),
+                Row::File(i) => pattern
+                    .is_match(&self.full_file.as_ref().unwrap().lines[*i]),
+                _ => self.diff.lines[self.row_lines(idx)]
+                    .iter()
+                    .any(|l| pattern.is_match(l)),
+            },
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for Diff {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Char('z' | ']' | '[')] => return KeyResult::Pending,
+
+            // 1G goes to the top, like in other views
+            [Key::Char('1'), Key::Char('G')] => self.scroll_top(),
+            [count @ .., Key::Char('G')] if key_count(count).is_some() => {
+                let nr = key_count(count).unwrap();
+                if !self.goto_line(nr) {
+                    ctx.message(format!("Line {} isn't in the diff", nr));
+                }
+            }
+            keys if key_count(keys).is_some() => return KeyResult::Pending,
+
+            [Key::Char(':')] => {
+                ctx.input =
+                    Some(Input::new("Go to line", InputAction::GotoLine));
+            }
+            [Key::Char(']'), Key::Char(']')] => {
+                if !self.cycle_hunk(true) {
+                    ctx.message("No more hunks");
+                }
+            }
+            [Key::Char('['), Key::Char('[')] => {
+                if !self.cycle_hunk(false) {
+                    ctx.message("No earlier hunks");
+                }
+            }
+            [Key::Char('}')] => {
+                if !self.cycle_file(true) {
+                    ctx.message("This is the last file");
+                }
+            }
+            [Key::Char('{')] => {
+                if !self.cycle_file(false) {
+                    ctx.message("This is the first file");
+                }
+            }
 
-use ratatui::{
-    buffer::Buffer,
-    layout::Rect,
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Paragraph, Widget},
-};
+            [Key::Char('z'), Key::Char('a')] => self.toggle_fold(),
+            [Key::Char('z'), Key::Char('M')] => self.set_all_folded(true),
+            [Key::Char('z'), Key::Char('R')] => self.set_all_folded(false),
 
-use crate::{
-    git::{git_diff_file, DiffAction, DiffLine, FileDiff, Stat},
-    list::{ListInfo, ListScroll},
-    search::Search,
-    ui::highlight_spans,
-    views::statusline::Status,
-};
+            [Key::Escape] if self.is_selecting() => self.toggle_selection(),
+            [Key::Up | Key::Char('k')] if self.is_selecting() => {
+                self.select_up()
+            }
+            [Key::Down | Key::Char('j')] if self.is_selecting() => {
+                self.select_down()
+            }
 
-#[derive(Debug, Clone)]
-pub struct Diff {
-    height: usize,
-    offset: usize,
-    diff: FileDiff,
-    range: DiffAction,
-    stat: Stat,
-    search: Option<String>,
-    show_line_numbers: bool,
-}
+            [Key::Char('v')] => self.toggle_selection(),
 
-impl Diff {
-    pub fn new(stat: &Stat, range: &DiffAction) -> Diff {
-        let diff = git_diff_file(&stat.path, &stat.old_path, range, None);
+            [Key::Char('n')] => self.toggle_show_line_numbers(),
 
-        Diff {
-            diff,
-            height: 0,
-            offset: 0,
-            stat: stat.clone(),
-            range: range.clone(),
-            search: None,
-            show_line_numbers: false,
-        }
-    }
+            [Key::Char('|')] => self.toggle_split(),
+
+            [Key::Char('O')] => match self.cycle_hunk_order() {
+                Some(order) => {
+                    ctx.message(format!("Hunk order: {}", order.label()))
+                }
+                None => ctx.message(
+                    "Hunks can only be reordered when all files are shown",
+                ),
+            },
+
+            [Key::Char('f')] => match self.toggle_full_file() {
+                Ok(true) => ctx.message("Showing the full file"),
+                Ok(false) => ctx.message("Showing the diff"),
+                Err(msg) => ctx.message(msg),
+            },
+
+            [Key::Left | Key::Char('h')] => self.scroll_sideways(false),
+            [Key::Right | Key::Char('l')] => self.scroll_sideways(true),
+
+            [Key::Char('W')] => {
+                ctx.message(if self.toggle_wrap() {
+                    "Wrapping long lines"
+                } else {
+                    "Not wrapping long lines"
+                });
+            }
+
+            [Key::Char('w')] => self.toggle_ignore_whitespace(),
+
+            [Key::Char('R')] => match self.follow_branch() {
+                Ok(true) => {
+                    ctx.message(format!("Showing {}", self.range.describe()))
+                }
+                Ok(false) => ctx.message("The diff is up to date"),
+                Err(msg) => ctx.message(msg),
+            },
+
+            [Key::Char('a')] => {
+                let algorithm = self.cycle_algorithm();
+                ctx.diff_opts.algorithm = algorithm;
+                ctx.message(format!("Diff algorithm: {}", algorithm));
+            }
+
+            [Key::Char(c @ ('+' | '-'))] => {
+                let context = match c {
+                    '+' => self.context() + 1,
+                    _ => self.context().saturating_sub(1),
+                };
+                self.set_context(context);
+                ctx.diff_opts.context = Some(context);
+                ctx.message(format!("Showing {} lines of context", context));
+            }
+
+            [Key::Char('e')] => {
+                let path = self.stat().worktree_path();
+                if path.exists() {
+                    ctx.edit = Some((path, self.edit_line()));
+                } else {
+                    ctx.message("The file isn't in the working tree");
+                }
+            }
+
+            [Key::Char(c @ ('y' | 'Y'))] => match self.yank(*c == 'y') {
+                Some((text, count)) => {
+                    ctx.clipboard = Some(text);
+                    ctx.message(format!(
+                        "Copied {} line{}",
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    ));
+                }
+                None => ctx.message("Nothing to copy"),
+            },
+
+            [Key::Char('A')] => {
+                if self.range.is_commit_show() {
+                    ctx.input = Some(
+                        Input::new("Diff against", InputAction::Anchor)
+                            .with_text("HEAD"),
+                    );
+                } else {
+                    ctx.message(
+                        "Only a single commit can be diffed against another",
+                    );
+                }
+            }
+
+            [Key::Char('c')] => {
+                ctx.message(if self.toggle_whitespace_errors() {
+                    "Marking whitespace errors"
+                } else {
+                    "Not marking whitespace errors"
+                });
+            }
+
+            [Key::Char('L')] => {
+                let path = &self.stat().path;
+                match CommitLog::for_file(path, self.commit()) {
+                    Some(log) => ctx.push(log),
+                    None => ctx.message(format!("No commits changed {}", path)),
+                }
+            }
+
+            [Key::Char('b')] => {
+                // the file is blamed as it is after the change
+                match Blame::new(
+                    self.stat().new_path(),
+                    self.commit(),
+                    self.edit_line(),
+                    self.opts,
+                ) {
+                    Ok(blame) => ctx.push(blame),
+                    Err(e) => ctx.message(e),
+                }
+            }
+
+            [Key::Char('o')] => match self.commit() {
+                Some(commit) => {
+                    match web_url(
+                        commit,
+                        Some((self.stat().new_path(), self.edit_line())),
+                    ) {
+                        Ok(url) => ctx.open_url = Some(url),
+                        Err(e) => ctx.message(e),
+                    }
+                }
+                None => ctx.message("Uncommitted changes aren't on the remote"),
+            },
+
+            [Key::Char('D')] => {
+                let path = self.stat().path.clone();
+                let commit = self.commit().cloned();
+                match self.removed_text() {
+                    // a line deleted in uncommitted changes hasn't been
+                    // removed by any commit yet
+                    Some(_) if commit.is_none() => ctx
+                        .message("The line is removed by uncommitted changes"),
+                    Some(text) => match Diff::find_removal(
+                        text,
+                        &path,
+                        commit.as_ref(),
+                        self.opts,
+                    ) {
+                        Ok(diff) => ctx.push(diff),
+                        Err(e) => ctx.message(e),
+                    },
+                    // text that isn't deleted here may have been removed
+                    // since, so the search starts from HEAD
+                    None => {
+                        ctx.input = Some(Input::new(
+                            "Find the removal of",
+                            InputAction::FindRemoval(path),
+                        ))
+                    }
+                }
+            }
+
+            [Key::Char('S')] => match self.cycle_worktree_side() {
+                Ok(description) => {
+                    ctx.message(format!("Showing {}", description))
+                }
+                Err(e) => ctx.message(e),
+            },
+
+            [Key::Char('M')] => match self.cycle_conflict_sides() {
+                Ok(description) => {
+                    ctx.message(format!("Showing {}", description))
+                }
+                Err(e) => ctx.message(e),
+            },
+
+            [Key::Char('s')] => {
+                if self.can_stage() {
+                    ctx.message(match self.stage() {
+                        Ok(msg) => msg,
+                        Err(e) => format!("Staging failed: {}", e.first_line()),
+                    });
+                    ctx.events.push(AppEvent::RepoChanged);
+                } else {
+                    ctx.message(
+                        "Only staged or unstaged changes can be staged",
+                    );
+                }
+            }
 
-    pub fn path(&self) -> Result<PathBuf, std::io::Error> {
-        self.stat.path()
+            _ => return handle_scroll_keys(self, keys),
+        }
+        KeyResult::Handled
     }
 
-    /// Re-diff the file; call this when the underlying file may have changed
-    pub fn refresh(&mut self) {
-        self.diff = git_diff_file(
-            &self.stat.path,
-            &self.stat.old_path,
-            &self.range,
-            None,
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let title = if self.all_files {
+            format!("Diff — {} files", self.stats.len())
+        } else {
+            format!("Diff — {}", self.stat().path)
+        };
+        let block = opts.block(title);
+        let mut view = DiffView::new(
+            self,
+            Some(DiffViewOpts {
+                tab_width: opts.tab_width,
+                images: opts.images,
+            }),
         );
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
     }
 
-    pub fn toggle_show_line_numbers(&mut self) {
-        self.show_line_numbers = !self.show_line_numbers;
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::FilesChanged(paths)
+                if paths.iter().any(|p| same_path(p, &self.path())) =>
+            {
+                self.refresh()
+            }
+            // a diff of all files isn't watching any one file, so it follows
+            // changes to the index and working tree instead
+            AppEvent::WorktreeChanges(_)
+                if self.all_files
+                    && matches!(
+                        self.range.target,
+                        Target::Staged | Target::Unstaged
+                    ) =>
+            {
+                self.refresh();
+                self.offset =
+                    min(self.offset, self.list_count().saturating_sub(1));
+            }
+            AppEvent::RefsChanged => self.moved = git_branch_moved(&self.range),
+            AppEvent::UseProfile(profile) => {
+                let opts = profile.diff_opts(self.opts);
+                if opts != self.opts {
+                    self.opts = opts;
+                    self.selection = None;
+                    self.refresh();
+                    self.offset =
+                        min(self.offset, self.list_count().saturating_sub(1));
+                }
+            }
+            _ => {}
+        }
     }
-}
 
-impl ListInfo for Diff {
-    fn list_count(&self) -> usize {
-        self.diff.lines.len()
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
     }
 
-    fn list_pos(&self) -> usize {
-        self.offset
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
     }
 
-    fn set_list_pos(&mut self, pos: usize) {
-        self.offset = pos;
+    fn watched_path(&self) -> Option<PathBuf> {
+        // a deleted file can't be watched; its diff is still shown, it just
+        // isn't refreshed
+        let path = self.path();
+        (!self.all_files && path.exists()).then_some(path)
     }
-}
 
-impl ListScroll for Diff {
-    fn height(&self) -> usize {
-        self.height
+    fn patch(&self, _opts: GitDiffOpts) -> Result<String, String> {
+        if self.all_files {
+            return Ok(git_patch(None, &self.range, Some(self.opts)));
+        }
+        let stat = self.stat();
+        Ok(git_patch(
+            Some((&stat.path, &stat.old_path)),
+            &self.file_range(),
+            Some(self.opts),
+        ))
     }
-}
 
-impl Status for Diff {
-    fn status(&self) -> String {
-        format!("{}: {}", self.range, self.stat.path)
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("s", "stage"),
+            ("v", "select"),
+            ("|", "split"),
+            ("f", "full file"),
+            ("n", "numbers"),
+            ("za", "fold"),
+            ("]]", "hunk"),
+            ("}", "file"),
+        ]
     }
-}
 
-impl Search for Diff {
-    fn set_search(&mut self, search: Option<String>) {
-        self.search = search;
+    fn goto_line(&mut self, line: u32) -> bool {
+        Diff::goto_line(self, line)
     }
 
-    fn get_search(&self) -> Option<String> {
-        self.search.clone()
+    fn anchor_to(&mut self, rev: &str) -> Result<String, String> {
+        self.anchor(rev)
     }
 
-    fn is_match(&self, idx: usize) -> bool {
-        match &self.search {
-            Some(search) => self.diff.lines[idx].contains(search),
-            _ => false,
-        }
+    fn inline_image(&self) -> Option<&InlineImage> {
+        self.image.as_ref()
     }
 }
 
 /// The Widget used to render a Diff
 pub struct DiffView<'a> {
     diff: &'a mut Diff,
+    block: Option<Block<'a>>,
     tab_width: u8,
+    images: Option<ImageProtocol>,
 }
 
 pub struct DiffViewOpts {
     pub tab_width: u8,
+    /// the protocol for previewing images, if they should be previewed
+    pub images: Option<ImageProtocol>,
 }
 
 impl<'a> DiffView<'a> {
-    pub fn new(diff: &'a mut Diff, options: Option<DiffViewOpts>) -> DiffView {
+    pub fn new(
+        diff: &'a mut Diff,
+        options: Option<DiffViewOpts>,
+    ) -> DiffView<'a> {
         DiffView {
             diff,
-            tab_width: match options {
+            block: None,
+            tab_width: match &options {
                 Some(opts) => opts.tab_width,
                 _ => 4,
             },
+            images: options.and_then(|opts| opts.images),
         }
     }
+
+    /// Draw the diff inside a block
+    pub fn block(mut self, block: Block<'a>) -> DiffView<'a> {
+        self.block = Some(block);
+        self
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 struct LineRenderer {
     line_nr_width: usize,
     tab_width: usize,
-    search: Option<String>,
+    search: Option<SearchPattern>,
+    /// true if the row holds the current search match
+    current: bool,
+    whitespace_errors: bool,
+    /// the number of columns of content scrolled out of view
+    hscroll: usize,
+    wrap: bool,
 }
 
 impl LineRenderer {
-    fn new(
-        line_nr_width: usize,
-        tab_width: usize,
-        search: Option<String>,
-    ) -> LineRenderer {
-        LineRenderer {
-            line_nr_width,
-            tab_width,
-            search,
-        }
-    }
-
+    /// Render a diff line as line numbers in the given colors, and the line's
+    /// content with its changed parts and any whitespace problems highlighted
     fn render(
         &self,
-        old_color: u8,
-        new_color: u8,
+        numbers: &[(u32, u8)],
         line_color: u8,
-        old: u32,
-        new: u32,
         line: &str,
-    ) -> Vec<Span> {
+        changes: &[Range<usize>],
+        errors: Option<&WhitespaceErrors>,
+    ) -> (Vec<Span<'static>>, Vec<Span<'static>>) {
+        let mut gutter: Vec<Span> = vec![];
         let mut spans: Vec<Span> = vec![];
 
         if self.line_nr_width > 0 {
-            spans.push(Span::styled(
-                format!("{:>width$}", old, width = self.line_nr_width),
-                Style::default().fg(Color::Indexed(old_color)),
+            for (nr, color) in numbers {
+                gutter.push(Span::styled(
+                    format!("{:>width$}", nr, width = self.line_nr_width),
+                    Style::default().fg(Color::Indexed(*color)),
+                ));
+                gutter.push(Span::from(" "));
+            }
+        }
+
+        let content = line.get(1..).unwrap_or_default();
+        let style = Style::default().fg(Color::Indexed(line_color));
+        let changed_style = Style::default()
+            .fg(Color::Indexed(0))
+            .bg(Color::Indexed(line_color));
+        let error_style = Style::default().bg(Color::Indexed(1));
+        let tab = " ".repeat(self.tab_width);
+        let errors = errors.filter(|_| self.whitespace_errors);
+
+        let mut styles = vec![style; content.len()];
+        for change in changes {
+            styles[change.clone()].fill(changed_style);
+        }
+        for range in errors.iter().flat_map(|e| &e.ranges) {
+            styles[range.clone()].fill(error_style);
+        }
+
+        let mut start = 0;
+        for i in 1..=content.len() {
+            if i == content.len() || styles[i] != styles[start] {
+                spans.push(Span::styled(
+                    content[start..i].replace('\t', &tab),
+                    styles[start],
+                ));
+                start = i;
+            }
+        }
+        if content.is_empty() {
+            spans.push(Span::styled("", style));
+        }
+        if errors.is_some_and(|e| e.crlf) {
+            spans.push(Span::styled("^M", error_style));
+        }
+
+        if let Some(search) = &self.search {
+            spans = highlight_matches(spans, search, match_style(self.current));
+        }
+
+        (gutter, spans)
+    }
+
+    /// Lay out line numbers and content in `width` columns; a long line takes
+    /// several rows when lines are wrapped, and otherwise its content is
+    /// scrolled sideways while the numbers stay in place
+    fn layout(
+        &self,
+        gutter: Vec<Span<'static>>,
+        content: Vec<Span<'static>>,
+        width: usize,
+    ) -> Vec<Vec<Span<'static>>> {
+        let mut spans = gutter;
+        if !self.wrap {
+            spans.extend(skip_columns(content, self.hscroll));
+            return vec![spans];
+        }
+
+        let gutter_width: usize =
+            spans.iter().map(|s| s.content.chars().count()).sum();
+        let chunks =
+            wrap_spans(content, width.saturating_sub(gutter_width).max(1));
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut row = if i == 0 {
+                    spans.clone()
+                } else {
+                    vec![Span::from(" ".repeat(gutter_width))]
+                };
+                row.extend(chunk);
+                row
+            })
+            .collect()
+    }
+
+    /// Lay out a line that has no line numbers in `width` columns
+    fn layout_meta(
+        &self,
+        line: Line<'static>,
+        width: usize,
+    ) -> Vec<Line<'static>> {
+        self.layout(vec![], line.spans, width)
+            .into_iter()
+            .map(Line::from)
+            .collect()
+    }
+
+    /// Render one side of a side-by-side row as one or more rows, each
+    /// filling `width` columns
+    fn render_side(
+        &self,
+        diff: &FileDiff,
+        line_nr: Option<usize>,
+        old: bool,
+        width: usize,
+    ) -> Vec<Vec<Span<'static>>> {
+        let rows = match line_nr {
+            Some(i) => {
+                let line = &diff.lines[i];
+                let changes = &diff.word_changes[i];
+                let errors = Some(&diff.whitespace_errors[i]);
+                let (gutter, content) = match &diff.line_meta[i] {
+                    DiffLine::Del(meta) => {
+                        self.render(&[(meta.old, 7)], 1, line, changes, None)
+                    }
+                    DiffLine::Add(meta) => {
+                        self.render(&[(meta.new, 7)], 2, line, changes, errors)
+                    }
+                    DiffLine::Same(meta) => {
+                        let nr = if old { meta.old } else { meta.new };
+                        self.render(&[(nr, 7)], 15, line, changes, None)
+                    }
+                    _ => (vec![], vec![]),
+                };
+                self.layout(gutter, content, width)
+            }
+            None => vec![vec![]],
+        };
+        rows.into_iter().map(|row| fit_spans(row, width)).collect()
+    }
+}
+
+/// Render a line summarizing the change to the file, which stays at the top of
+/// the view
+fn render_header(diff: &Diff, area: Rect, buf: &mut Buffer) {
+    let stat = diff.stat();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    if diff.all_files && diff.stats.is_empty() {
+        let line = Line::from(Span::styled(
+            format!("No changes  {}", diff.range.describe()),
+            Style::default().fg(Color::Indexed(8)),
+        ));
+        Widget::render(Paragraph::new(line), area, buf);
+        return;
+    }
+
+    let mut spans = if stat.old_path.is_empty() {
+        vec![Span::styled(stat.path.clone(), bold)]
+    } else {
+        // for renames, git reports the old path first
+        vec![
+            Span::styled(stat.path.clone(), bold),
+            Span::from(" → "),
+            Span::styled(stat.old_path.clone(), bold),
+        ]
+    };
+
+    if stat.binary {
+        spans.push(Span::styled(
+            " binary",
+            Style::default().fg(Color::Indexed(3)),
+        ));
+    } else {
+        spans.push(Span::styled(
+            format!(" +{}", stat.adds),
+            Style::default().fg(Color::Indexed(2)),
+        ));
+        spans.push(Span::styled(
+            format!(" −{}", stat.deletes),
+            Style::default().fg(Color::Indexed(1)),
+        ));
+    }
+
+    for change in diff.file_changes() {
+        spans.push(Span::styled(
+            format!(", {}", change),
+            Style::default().fg(Color::Indexed(3)),
+        ));
+    }
+
+    // when all files are shown, the header follows the file at the top of
+    // the view
+    let position = if diff.all_files {
+        format!(
+            "file {} of {}, ",
+            min(
+                diff.file_at(diff.line_at(diff.offset)),
+                diff.stats.len() - 1
+            ) + 1,
+            diff.stats.len()
+        )
+    } else {
+        String::new()
+    };
+    spans.push(Span::styled(
+        format!("  {}{}", position, diff.range.describe()),
+        Style::default().fg(Color::Indexed(8)),
+    ));
+
+    Widget::render(Paragraph::new(Line::from(spans)), area, buf);
+}
+
+/// Render a summary of the change to a binary file, with a preview of the
+/// file below it if it's an image that can be shown
+fn render_binary(
+    diff: &mut Diff,
+    area: Rect,
+    buf: &mut Buffer,
+    images: Option<ImageProtocol>,
+) {
+    let Some(binary) = &diff.binary else {
+        return;
+    };
+    let path = &diff.stat().path;
+    let label = Style::default().fg(Color::Indexed(7));
+    let size =
+        |side: &Option<(FileVersion, Option<u64>)>, missing: &str| match side {
+            Some((_, Some(size))) => format_size(*size),
+            Some((_, None)) => "unknown size".into(),
+            None => missing.into(),
+        };
+
+    let mut after = size(&binary.new, "deleted");
+    if let (Some((_, Some(old))), Some((_, Some(new)))) =
+        (&binary.old, &binary.new)
+    {
+        let delta = *new as i64 - *old as i64;
+        if delta != 0 {
+            let sign = if delta > 0 { "+" } else { "-" };
+            after.push_str(&format!(
+                " ({}{})",
+                sign,
+                format_size(delta.unsigned_abs())
             ));
-            spans.push(Span::from(" "));
+        }
+    }
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("Binary file {}", path),
+            Style::default().fg(Color::Indexed(3)),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Type    ", label),
+            Span::from(file_type(path)),
+        ]),
+        Line::from(vec![
+            Span::styled("Before  ", label),
+            Span::from(size(&binary.old, "added")),
+        ]),
+        Line::from(vec![Span::styled("After   ", label), Span::from(after)]),
+    ];
+
+    let preview_top = lines.len() as u16 + 1;
+    Widget::render(Paragraph::new(lines), area, buf);
+
+    match images {
+        Some(protocol) if area.height > preview_top => diff.update_image(
+            Rect {
+                y: area.y + preview_top,
+                height: area.height - preview_top,
+                ..area
+            },
+            protocol,
+        ),
+        _ => diff.image = None,
+    }
+}
+
+/// Describe a number of bytes
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Render a diff line that isn't part of a hunk's content
+fn meta_line<'a>(meta: &DiffLine, line: &str) -> Line<'a> {
+    match meta {
+        DiffLine::Start => Line::from(Span::styled(
+            line.to_string(),
+            Style::default().fg(Color::Indexed(3)),
+        )),
+        DiffLine::Hunk => Line::from(Span::styled(
+            line.to_string(),
+            Style::default().fg(Color::Indexed(6)),
+        )),
+        _ => Line::from(line.to_string()),
+    }
+}
 
-            spans.push(Span::styled(
-                format!("{:>width$}", new, width = self.line_nr_width),
-                Style::default().fg(Color::Indexed(new_color)),
+/// Drop the first `columns` characters from a list of spans
+fn skip_columns(spans: Vec<Span<'_>>, columns: usize) -> Vec<Span<'_>> {
+    let mut remaining = columns;
+    let mut kept: Vec<Span> = vec![];
+
+    for span in spans {
+        let len = span.content.chars().count();
+        if remaining >= len {
+            remaining -= len;
+        } else if remaining > 0 {
+            let content: String =
+                span.content.chars().skip(remaining).collect();
+            kept.push(Span::styled(content, span.style));
+            remaining = 0;
+        } else {
+            kept.push(span);
+        }
+    }
+
+    kept
+}
+
+/// Split a list of spans into rows of at most `width` characters; there's
+/// always at least one row
+fn wrap_spans(spans: Vec<Span<'_>>, width: usize) -> Vec<Vec<Span<'_>>> {
+    let mut rows: Vec<Vec<Span>> = vec![vec![]];
+    let mut remaining = width;
+
+    for span in spans {
+        let mut chars: Vec<char> = span.content.chars().collect();
+        while chars.len() > remaining {
+            let rest = chars.split_off(remaining);
+            if !chars.is_empty() {
+                rows.last_mut().unwrap().push(Span::styled(
+                    chars.into_iter().collect::<String>(),
+                    span.style,
+                ));
+            }
+            rows.push(vec![]);
+            remaining = width;
+            chars = rest;
+        }
+        remaining -= chars.len();
+        if !chars.is_empty() {
+            rows.last_mut().unwrap().push(Span::styled(
+                chars.into_iter().collect::<String>(),
+                span.style,
             ));
-            spans.push(Span::from(" "));
         }
+    }
 
-        let search = if self.search.is_some()
-            && !self.search.clone().unwrap().is_empty()
-        {
-            self.search.clone()
+    rows
+}
+
+/// Truncate or pad spans so that they fill exactly `width` columns
+fn fit_spans(spans: Vec<Span<'_>>, width: usize) -> Vec<Span<'_>> {
+    let mut fitted: Vec<Span> = vec![];
+    let mut remaining = width;
+
+    for span in spans {
+        let len = span.content.chars().count();
+        if len <= remaining {
+            remaining -= len;
+            fitted.push(span);
         } else {
-            None
-        };
+            let content: String =
+                span.content.chars().take(remaining).collect();
+            fitted.push(Span::styled(content, span.style));
+            remaining = 0;
+            break;
+        }
+    }
+
+    if remaining > 0 {
+        fitted.push(Span::from(" ".repeat(remaining)));
+    }
+
+    fitted
+}
+
+/// Return the file whose `diff` line is shown in a row, when all files are
+/// shown
+fn file_start(diff: &Diff, row: &Row) -> Option<usize> {
+    if !diff.all_files {
+        return None;
+    }
+    let line = match row {
+        Row::Line(i) => *i,
+        Row::Split(i) => match diff.diff.split[*i] {
+            SplitLine::Meta(i) => i,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    matches!(diff.diff.line_meta[line], DiffLine::Start)
+        .then(|| diff.file_at(line))
+}
 
-        let line =
-            String::from(&line[1..].replace('\t', &" ".repeat(self.tab_width)));
+/// Render the heading of a file in a diff of all files: its path and how many
+/// lines were added and deleted, and its length if it's collapsed
+fn file_heading(diff: &Diff, f: usize, folded: bool) -> Line<'static> {
+    let file = &diff.diff.files[f];
+    let heading = Style::default()
+        .fg(Color::Indexed(3))
+        .add_modifier(Modifier::BOLD);
+    let mut spans = vec![Span::styled(
+        if folded { "▸ " } else { "▾ " },
+        Style::default().fg(Color::Indexed(8)),
+    )];
+
+    match diff.stats.get(f) {
+        Some(stat) => {
+            if let Some(commit) = &stat.commit {
+                spans.push(Span::styled(
+                    format!("{} ", commit),
+                    Style::default().fg(Color::Indexed(5)),
+                ));
+            }
+            spans.push(Span::styled(stat.path.clone(), heading));
+            if !stat.old_path.is_empty() {
+                // for renames, git reports the old path first
+                spans.push(Span::from(" → "));
+                spans.push(Span::styled(stat.old_path.clone(), heading));
+            }
+            if stat.binary {
+                spans.push(Span::styled(
+                    " binary",
+                    Style::default().fg(Color::Indexed(3)),
+                ));
+            } else {
+                spans.push(Span::styled(
+                    format!(" +{}", stat.adds),
+                    Style::default().fg(Color::Indexed(2)),
+                ));
+                spans.push(Span::styled(
+                    format!(" −{}", stat.deletes),
+                    Style::default().fg(Color::Indexed(1)),
+                ));
+            }
+        }
+        None => spans
+            .push(Span::styled(diff.diff.lines[file.start].clone(), heading)),
+    }
 
+    if folded {
         spans.push(Span::styled(
-            line,
-            Style::default().fg(Color::Indexed(line_color)),
+            format!(" ({} lines)", file.len() - 1),
+            Style::default().fg(Color::Indexed(7)),
         ));
+    }
+    Line::from(spans)
+}
+
+/// Render a row of the diff view as one or more lines, `full_width` columns
+/// wide; each side of a side-by-side row is `width` columns wide
+fn render_row(
+    diff: &Diff,
+    renderer: &LineRenderer,
+    row: &Row,
+    full_width: usize,
+    width: usize,
+) -> Vec<Line<'static>> {
+    if let Some(f) = file_start(diff, row) {
+        return renderer.layout_meta(file_heading(diff, f, false), full_width);
+    }
 
-        if let Some(search) = search {
-            spans = highlight_spans(
-                spans,
-                &search,
-                Style::default().add_modifier(Modifier::REVERSED),
+    match row {
+        Row::Split(i) => match &diff.diff.split[*i] {
+            SplitLine::Meta(i) => renderer.layout_meta(
+                meta_line(&diff.diff.line_meta[*i], &diff.diff.lines[*i]),
+                full_width,
+            ),
+            SplitLine::Pair(old, new) => {
+                let old = renderer.render_side(&diff.diff, *old, true, width);
+                let new = renderer.render_side(&diff.diff, *new, false, width);
+                (0..max(old.len(), new.len()))
+                    .map(|i| {
+                        let mut spans = old
+                            .get(i)
+                            .cloned()
+                            .unwrap_or_else(|| fit_spans(vec![], width));
+                        spans.push(Span::styled(
+                            "│",
+                            Style::default().fg(Color::Indexed(8)),
+                        ));
+                        spans.extend(
+                            new.get(i)
+                                .cloned()
+                                .unwrap_or_else(|| fit_spans(vec![], width)),
+                        );
+                        Line::from(spans)
+                    })
+                    .collect()
+            }
+        },
+        Row::Fold(h) => {
+            let hunk = &diff.diff.hunks[*h].lines;
+            renderer.layout_meta(
+                Line::from(vec![
+                    Span::styled(
+                        diff.diff.lines[hunk.start].clone(),
+                        Style::default().fg(Color::Indexed(6)),
+                    ),
+                    Span::styled(
+                        format!(" ({} lines)", hunk.len() - 1),
+                        Style::default().fg(Color::Indexed(7)),
+                    ),
+                ]),
+                full_width,
+            )
+        }
+        Row::File(i) => {
+            let file = diff.full_file.as_ref().unwrap();
+            let marker = file.markers[*i];
+            let color = match marker {
+                Some(Marker::Added | Marker::Changed) => 2,
+                _ => 15,
+            };
+            let (mut gutter, content) = renderer.render(
+                &[(*i as u32 + 1, 7)],
+                color,
+                &format!(" {}", file.lines[*i]),
+                &[],
+                None,
             );
+            gutter.insert(0, Marker::span(marker));
+            gutter.insert(1, Span::from(" "));
+            renderer
+                .layout(gutter, content, full_width)
+                .into_iter()
+                .map(Line::from)
+                .collect()
+        }
+        Row::FileFold(f) => {
+            renderer.layout_meta(file_heading(diff, *f, true), full_width)
+        }
+        Row::Line(line_nr) => {
+            let line_nr = *line_nr;
+            let line = &diff.diff.lines[line_nr];
+            let changes = &diff.diff.word_changes[line_nr];
+            if line.is_empty() {
+                vec![Line::from("")]
+            } else {
+                let rendered = match &diff.diff.line_meta[line_nr] {
+                    DiffLine::Add(meta) => Some(renderer.render(
+                        &[(meta.old, 16), (meta.new, 7)],
+                        2,
+                        line,
+                        changes,
+                        Some(&diff.diff.whitespace_errors[line_nr]),
+                    )),
+                    DiffLine::Del(meta) => Some(renderer.render(
+                        &[(meta.old, 7), (meta.new, 16)],
+                        1,
+                        line,
+                        changes,
+                        None,
+                    )),
+                    DiffLine::Same(meta) => Some(renderer.render(
+                        &[(meta.old, 7), (meta.new, 7)],
+                        15,
+                        line,
+                        changes,
+                        None,
+                    )),
+                    _ => None,
+                };
+                match rendered {
+                    Some((numbers, content)) => renderer
+                        .layout(numbers, content, full_width)
+                        .into_iter()
+                        .map(Line::from)
+                        .collect(),
+                    None => renderer.layout_meta(
+                        meta_line(&diff.diff.line_meta[line_nr], line),
+                        full_width,
+                    ),
+                }
+            }
         }
-
-        spans
     }
 }
 
 impl<'a> Widget for DiffView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let diff = self.diff;
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        // the header line is dropped if there's no room for anything else
+        let area = if area.height > 1 {
+            render_header(diff, Rect { height: 1, ..area }, buf);
+            Rect {
+                y: area.y + 1,
+                height: area.height - 1,
+                ..area
+            }
+        } else {
+            area
+        };
         diff.height = area.height as usize;
 
+        if diff.binary.is_some() {
+            render_binary(diff, area, buf, self.images);
+            return;
+        }
+
         let line_nr_width = if !diff.show_line_numbers {
             0
+        } else if let Some(file) = &diff.full_file {
+            file.lines.len().to_string().len()
         } else {
             match diff.diff.line_meta.iter().last() {
                 Some(DiffLine::Add(meta))
@@ -215,43 +2630,74 @@ impl<'a> Widget for DiffView<'a> {
                 _ => 0,
             }
         } as usize;
-        let search = diff.search.clone();
-        let renderer =
-            LineRenderer::new(line_nr_width, self.tab_width as usize, search);
+        let search = match &diff.pattern {
+            Some(Ok(pattern)) if !pattern.is_empty() => Some(pattern.clone()),
+            _ => None,
+        };
+        let selected = diff.selection.as_ref().and(diff.selected_lines());
+        let renderer = LineRenderer {
+            line_nr_width,
+            tab_width: self.tab_width as usize,
+            search,
+            current: false,
+            whitespace_errors: diff.whitespace_errors,
+            hscroll: diff.hscroll,
+            wrap: diff.wrap,
+        };
 
-        let lines: Vec<Line> =
-            diff.diff
-                .lines
-                .iter()
-                .enumerate()
-                .map(|(line_nr, line)| {
-                    if !line.is_empty() {
-                        Line::from(match &diff.diff.line_meta[line_nr] {
-                            DiffLine::Add(meta) => renderer
-                                .render(16, 7, 2, meta.old, meta.new, line),
-                            DiffLine::Del(meta) => renderer
-                                .render(7, 16, 1, meta.old, meta.new, line),
-                            DiffLine::Same(meta) => renderer
-                                .render(7, 7, 15, meta.old, meta.new, line),
-                            DiffLine::Start => [Span::styled(
-                                line.clone(),
-                                Style::default().fg(Color::Indexed(3)),
-                            )]
-                            .into(),
-                            DiffLine::Hunk => [Span::styled(
-                                line.clone(),
-                                Style::default().fg(Color::Indexed(6)),
-                            )]
-                            .into(),
-                            _ => [Span::from(line.clone())].into(),
-                        })
-                    } else {
-                        Line::from(vec![Span::from("")])
+        let full_width = area.width as usize;
+        let width = full_width.saturating_sub(1) / 2;
+        let mut lines: Vec<Line> = vec![];
+        for row_nr in diff.offset..diff.rows.len() {
+            if lines.len() >= area.height as usize {
+                break;
+            }
+
+            // n and N scroll the current match to the top of the view, so
+            // the top row is drawn with its own style rather than cached
+            let row_lines =
+                if row_nr == diff.offset && renderer.search.is_some() {
+                    let current = LineRenderer {
+                        current: true,
+                        ..renderer.clone()
+                    };
+                    render_row(
+                        diff,
+                        &current,
+                        &diff.rows[row_nr],
+                        full_width,
+                        width,
+                    )
+                } else {
+                    match diff.row_cache.get(row_nr, &renderer, full_width) {
+                        Some(cached) => cached,
+                        None => {
+                            let rendered = render_row(
+                                diff,
+                                &renderer,
+                                &diff.rows[row_nr],
+                                full_width,
+                                width,
+                            );
+                            diff.row_cache.insert(row_nr, rendered.clone());
+                            rendered
+                        }
                     }
-                })
-                .collect();
+                };
+
+            let is_selected = match &selected {
+                Some(sel) => sel.contains(&diff.row_lines(row_nr).start),
+                None => false,
+            };
+            lines.extend(row_lines.into_iter().map(|line| {
+                if is_selected {
+                    line.patch_style(Style::default().bg(Color::Indexed(8)))
+                } else {
+                    line
+                }
+            }));
+        }
 
-        let view = Paragraph::new(lines).scroll((diff.offset as u16, 0));
-        Widget::render(view, area, buf);
+        Widget::render(Paragraph::new(lines), area, buf);
     }
 }
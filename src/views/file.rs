@@ -0,0 +1,258 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_show_file, GitDiffOpts, GitRef},
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    search::Search,
+    ui::{highlight_spans, match_style},
+    views::{
+        blame::Blame,
+        controller::{
+            handle_cursor_keys, key_count, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        input::{Input, InputAction},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// The contents of a file as it is in a commit
+#[derive(Debug, Clone)]
+pub struct FileContent {
+    list: ListData,
+    path: String,
+    commit: GitRef,
+    lines: Vec<String>,
+    search: Option<String>,
+    opts: GitDiffOpts,
+}
+
+impl FileContent {
+    /// Read a file as it is in a commit; binary files can't be shown
+    pub fn new(
+        path: &str,
+        commit: &GitRef,
+        opts: GitDiffOpts,
+    ) -> Result<FileContent, String> {
+        let text = git_show_file(commit, path)
+            .map_err(|e| e.first_line().to_string())?;
+        if text.contains('\0') {
+            return Err(format!("{} is a binary file", path));
+        }
+        Ok(FileContent {
+            list: ListData::new(),
+            path: path.into(),
+            commit: commit.clone(),
+            lines: text.lines().map(String::from).collect(),
+            search: None,
+            opts,
+        })
+    }
+}
+
+impl ListInfo for FileContent {
+    fn list_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for FileContent {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for FileContent {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for FileContent {
+    fn status(&self) -> Vec<StatusSegment> {
+        vec![StatusSegment::target(format!(
+            "{} at {}",
+            self.path, self.commit
+        ))]
+    }
+}
+
+impl Search for FileContent {
+    fn set_search(&mut self, search: Option<String>) {
+        self.search = search;
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    fn is_match(&self, idx: usize) -> bool {
+        match &self.search {
+            Some(search) => self.lines[idx].contains(search),
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for FileContent {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Char('1'), Key::Char('G')] => self.cursor_to_top(),
+            [count @ .., Key::Char('G')] if key_count(count).is_some() => {
+                let nr = key_count(count).unwrap();
+                if !self.goto_line(nr) {
+                    ctx.message(format!("{} has no line {}", self.path, nr));
+                }
+            }
+            keys if key_count(keys).is_some() => return KeyResult::Pending,
+
+            [Key::Char(':')] => {
+                ctx.input =
+                    Some(Input::new("Go to line", InputAction::GotoLine));
+            }
+
+            [Key::Char('b')] => match Blame::new(
+                &self.path,
+                Some(&self.commit),
+                self.cursor() as u32 + 1,
+                self.opts,
+            ) {
+                Ok(blame) => ctx.push(blame),
+                Err(e) => ctx.message(e),
+            },
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("{} — {}", self.path, self.commit));
+        let mut view = FileContentView::new(self, opts.tab_width);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("b", "blame"),
+            (":", "go to line"),
+            ("/", "search"),
+            ("q", "back"),
+        ]
+    }
+
+    fn goto_line(&mut self, line: u32) -> bool {
+        if line == 0 || line as usize > self.lines.len() {
+            return false;
+        }
+        self.cursor_to(line as usize - 1);
+        true
+    }
+}
+
+/// The Widget used to render a FileContent
+pub struct FileContentView<'a> {
+    file: &'a mut FileContent,
+    block: Option<Block<'a>>,
+    tab_width: u8,
+}
+
+impl<'a> FileContentView<'a> {
+    pub fn new(
+        file: &'a mut FileContent,
+        tab_width: u8,
+    ) -> FileContentView<'a> {
+        FileContentView {
+            file,
+            block: None,
+            tab_width,
+        }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> FileContentView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for FileContentView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.file.list.height = area.height as usize;
+
+        let number_width = self.file.lines.len().to_string().len();
+        let tab = " ".repeat(self.tab_width as usize);
+
+        let cursor = self.file.cursor();
+        let items: Vec<ListItem> = self
+            .file
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut spans = vec![
+                    Span::styled(
+                        format!("{:>width$} ", i + 1, width = number_width),
+                        Style::default().fg(Color::Indexed(8)),
+                    ),
+                    Span::from(line.replace('\t', &tab)),
+                ];
+
+                if let Some(search) = &self.file.search {
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
+                }
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.file.list_state_mut());
+    }
+}
@@ -0,0 +1,119 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_ls_tree, GitDiffOpts, GitRef},
+    list::ListInfo,
+    views::{
+        blame::Blame,
+        controller::{KeyResult, RenderOpts, ViewContext, ViewController},
+        file::FileContent,
+        picker::{Picker, PickerItem, PickerView},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+impl PickerItem for String {
+    fn name(&self) -> &str {
+        self
+    }
+
+    fn line(&self) -> Line<'static> {
+        // the file name stands out from its directory
+        let (dir, name) = match self.rfind('/') {
+            Some(s) => self.split_at(s + 1),
+            None => ("", self.as_str()),
+        };
+        Line::from(vec![
+            Span::styled(
+                dir.to_string(),
+                Style::default().fg(Color::Indexed(8)),
+            ),
+            Span::from(name.to_string()),
+        ])
+    }
+}
+
+/// A list of the files in a commit, narrowed down by typing part of a name
+#[derive(Debug, Clone)]
+pub struct FileFinder {
+    picker: Picker<String>,
+    commit: GitRef,
+    opts: GitDiffOpts,
+}
+
+impl FileFinder {
+    pub fn new(
+        commit: &GitRef,
+        opts: GitDiffOpts,
+    ) -> Result<FileFinder, String> {
+        let files =
+            git_ls_tree(commit).map_err(|e| e.first_line().to_string())?;
+        Ok(FileFinder {
+            picker: Picker::new(files),
+            commit: commit.clone(),
+            opts,
+        })
+    }
+}
+
+impl Status for FileFinder {
+    fn status(&self) -> Vec<StatusSegment> {
+        vec![
+            StatusSegment::target(format!("files at {}", self.commit)),
+            StatusSegment::mode(self.picker.describe_matches()),
+        ]
+    }
+}
+
+impl ViewController for FileFinder {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => {
+                if let Some(path) = self.picker.current() {
+                    match FileContent::new(path, &self.commit, self.opts) {
+                        Ok(file) => ctx.push(file),
+                        Err(e) => ctx.message(e),
+                    }
+                }
+            }
+            [Key::Ctrl('b')] => {
+                if let Some(path) = self.picker.current() {
+                    match Blame::new(path, Some(&self.commit), 1, self.opts) {
+                        Ok(blame) => ctx.push(blame),
+                        Err(e) => ctx.message(e),
+                    }
+                }
+            }
+
+            _ => return self.picker.handle_key(keys, ctx),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("Find file — {}", self.commit));
+        let mut view = PickerView::new(&mut self.picker);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.picker.list_pos(), self.picker.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter", "view"), ("ctrl-b", "blame"), ("esc", "close")]
+    }
+
+    fn takes_text(&self) -> bool {
+        true
+    }
+}
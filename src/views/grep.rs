@@ -0,0 +1,373 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_grep, GitDiffOpts, GitRef, GrepLine},
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    search::Search,
+    ui::{highlight_spans, match_style},
+    views::{
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        file::FileContent,
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// A row of grep results
+#[derive(Debug, Clone, Copy)]
+enum Row {
+    /// the heading of a file's matches, which holds the index of its first
+    /// line
+    File(usize),
+    /// a matching or context line
+    Line(usize),
+    /// a gap between lines of the same file
+    Break,
+}
+
+/// The lines of the files in a commit that match a pattern, grouped by file
+#[derive(Debug, Clone)]
+pub struct Grep {
+    list: ListData,
+    pattern: String,
+    commit: GitRef,
+    lines: Vec<GrepLine>,
+    rows: Vec<Row>,
+    search: Option<String>,
+    opts: GitDiffOpts,
+}
+
+impl Grep {
+    /// Search the files in a commit, failing if nothing matches
+    pub fn new(
+        pattern: &str,
+        commit: &GitRef,
+        opts: GitDiffOpts,
+    ) -> Result<Grep, String> {
+        if pattern.is_empty() {
+            return Err("Nothing to search for".into());
+        }
+        let lines = git_grep(pattern, commit)
+            .map_err(|e| e.first_line().to_string())?;
+        if lines.is_empty() {
+            return Err(format!("No files in {} match {}", commit, pattern));
+        }
+
+        let mut rows = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            match i.checked_sub(1).map(|p| &lines[p]) {
+                Some(prev) if prev.path == line.path => {
+                    if prev.line + 1 != line.line {
+                        rows.push(Row::Break);
+                    }
+                }
+                _ => rows.push(Row::File(i)),
+            }
+            rows.push(Row::Line(i));
+        }
+
+        let mut grep = Grep {
+            list: ListData::new(),
+            pattern: pattern.into(),
+            commit: commit.clone(),
+            lines,
+            rows,
+            search: None,
+            opts,
+        };
+        grep.cursor_to(grep.next_match(0).unwrap_or(0));
+        Ok(grep)
+    }
+
+    /// Return the row of the first match at or after a row
+    fn next_match(&self, from: usize) -> Option<usize> {
+        (from..self.rows.len()).find(
+            |r| matches!(self.rows[*r], Row::Line(i) if self.lines[i].is_match),
+        )
+    }
+
+    /// Return the number of matching lines and the number of files they're in
+    fn counts(&self) -> (usize, usize) {
+        let matches = self.lines.iter().filter(|l| l.is_match).count();
+        let files = self
+            .rows
+            .iter()
+            .filter(|r| matches!(r, Row::File(_)))
+            .count();
+        (matches, files)
+    }
+
+    /// Move to the heading of the next or previous file, returning false if
+    /// there isn't one
+    fn cycle_file(&mut self, forward: bool) -> bool {
+        let cursor = self.cursor();
+        let is_file = |r: &usize| matches!(self.rows[*r], Row::File(_));
+        let row = if forward {
+            (cursor + 1..self.rows.len()).find(is_file)
+        } else {
+            (0..cursor).rev().find(is_file)
+        };
+        match row {
+            Some(row) => {
+                self.cursor_to(row);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open the file at the line at the cursor, or at the first match of the
+    /// file whose heading is at the cursor
+    fn open_file(&self, ctx: &mut ViewContext) {
+        let line = match self.rows.get(self.cursor()) {
+            Some(Row::Line(i)) => &self.lines[*i],
+            Some(Row::File(_)) => match self.next_match(self.cursor()) {
+                Some(row) => match self.rows[row] {
+                    Row::Line(i) => &self.lines[i],
+                    _ => return,
+                },
+                None => return,
+            },
+            _ => return,
+        };
+        match FileContent::new(&line.path, &self.commit, self.opts) {
+            Ok(mut file) => {
+                file.goto_line(line.line);
+                ctx.push(file);
+            }
+            Err(e) => ctx.message(e),
+        }
+    }
+}
+
+impl ListInfo for Grep {
+    fn list_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for Grep {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for Grep {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for Grep {
+    fn status(&self) -> Vec<StatusSegment> {
+        let (matches, files) = self.counts();
+        vec![
+            StatusSegment::target(format!(
+                "grep {} at {}",
+                self.pattern, self.commit
+            )),
+            StatusSegment::mode(format!(
+                "{} match{} in {} file{}",
+                matches,
+                if matches == 1 { "" } else { "es" },
+                files,
+                if files == 1 { "" } else { "s" }
+            )),
+        ]
+    }
+}
+
+impl Search for Grep {
+    fn set_search(&mut self, search: Option<String>) {
+        self.search = search;
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    fn is_match(&self, idx: usize) -> bool {
+        match (&self.search, self.rows[idx]) {
+            (Some(search), Row::File(i)) => self.lines[i].path.contains(search),
+            (Some(search), Row::Line(i)) => self.lines[i].text.contains(search),
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for Grep {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => self.open_file(ctx),
+
+            [Key::Char(c @ ('}' | '{'))] => {
+                if !self.cycle_file(*c == '}') {
+                    ctx.message("No more files");
+                }
+            }
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("Grep — {}", self.pattern));
+        let mut view = GrepView::new(self, opts.tab_width);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("enter", "open file"),
+            ("}", "next file"),
+            ("/", "search"),
+            ("q", "back"),
+        ]
+    }
+}
+
+/// The Widget used to render a Grep
+pub struct GrepView<'a> {
+    grep: &'a mut Grep,
+    block: Option<Block<'a>>,
+    tab_width: u8,
+}
+
+impl<'a> GrepView<'a> {
+    pub fn new(grep: &'a mut Grep, tab_width: u8) -> GrepView<'a> {
+        GrepView {
+            grep,
+            block: None,
+            tab_width,
+        }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> GrepView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for GrepView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.grep.list.height = area.height as usize;
+
+        let number_width = self
+            .grep
+            .lines
+            .iter()
+            .map(|l| l.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let tab = " ".repeat(self.tab_width as usize);
+
+        let cursor = self.grep.cursor();
+        let items: Vec<ListItem> = self
+            .grep
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                let mut spans = match *row {
+                    Row::File(i) => vec![Span::styled(
+                        self.grep.lines[i].path.clone(),
+                        Style::default()
+                            .fg(Color::Indexed(6))
+                            .add_modifier(Modifier::BOLD),
+                    )],
+                    Row::Line(i) => {
+                        let line = &self.grep.lines[i];
+                        let (number_style, text_style) = if line.is_match {
+                            (
+                                Style::default().fg(Color::Indexed(3)),
+                                Style::default(),
+                            )
+                        } else {
+                            (
+                                Style::default().fg(Color::Indexed(8)),
+                                Style::default().fg(Color::Indexed(8)),
+                            )
+                        };
+                        vec![
+                            Span::styled(
+                                format!(
+                                    "{:>width$} ",
+                                    line.line,
+                                    width = number_width
+                                ),
+                                number_style,
+                            ),
+                            Span::styled(
+                                line.text.replace('\t', &tab),
+                                text_style,
+                            ),
+                        ]
+                    }
+                    Row::Break => vec![Span::styled(
+                        format!("{:>width$}", "⋮", width = number_width),
+                        Style::default().fg(Color::Indexed(8)),
+                    )],
+                };
+
+                if let Some(search) = &self.grep.search {
+                    spans =
+                        highlight_spans(spans, search, match_style(r == cursor))
+                }
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.grep.list_state_mut());
+    }
+}
@@ -0,0 +1,174 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::{events::Key, git::GitRef, ui::centered_rect};
+
+/// What to do with the text of an Input once it's submitted
+#[derive(Debug, Clone)]
+pub enum InputAction {
+    /// Commit the staged changes with the entered message; the commit
+    /// template the message was started from is included
+    Commit(Option<String>),
+    /// Amend the HEAD commit with the staged changes and the entered message;
+    /// the HEAD commit's original message is included
+    Amend(String),
+    /// Scroll the current view to the entered line number
+    GotoLine,
+    /// Move the current view to the commit the entered revision names
+    GotoCommit,
+    /// Diff the commit the current view shows against the entered revision
+    Anchor,
+    /// Show only the commits whose messages match the entered pattern
+    GrepLog,
+    /// Show only the commits that add or remove the entered text
+    PickaxeLog,
+    /// Make the log's commits that changed the entered path stand out
+    HighlightPath,
+    /// Limit every log and diff to the entered pathspecs
+    Pathspecs,
+    /// Switch to the settings of the entered profile
+    Profile,
+    /// Compare the commits of the entered ranges with `git range-diff`
+    RangeDiff,
+    /// Write release notes to the entered file, or copy them to the
+    /// clipboard if no file is entered
+    ReleaseNotes(String),
+    /// Show the diff of the latest commit that removed the entered text from
+    /// a file
+    FindRemoval(String),
+    /// Search the files in a commit for the entered pattern
+    Grep(GitRef),
+    /// Diff a file in a commit against the same file in the entered revision
+    DiffFile { commit: GitRef, path: String },
+}
+
+/// The result of passing a key to an Input
+pub enum InputEvent {
+    /// The input is still being edited
+    Editing,
+    /// The user submitted the input
+    Submit,
+    /// The user cancelled the input
+    Cancel,
+}
+
+/// An editable block of text, entered in a popup
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub title: String,
+    pub text: String,
+    pub action: InputAction,
+    multiline: bool,
+    /// lines shown below the text that aren't part of it
+    details: Vec<Line<'static>>,
+}
+
+impl Input {
+    pub fn new(title: impl Into<String>, action: InputAction) -> Input {
+        Input {
+            title: title.into(),
+            text: String::new(),
+            action,
+            multiline: false,
+            details: vec![],
+        }
+    }
+
+    /// Set the initial text
+    pub fn with_text(mut self, text: impl Into<String>) -> Input {
+        self.text = text.into();
+        self
+    }
+
+    /// Allow the text to contain several lines, entered with Ctrl+J
+    pub fn multiline(mut self) -> Input {
+        self.multiline = true;
+        self
+    }
+
+    /// Show lines below the text, like the changes a commit message describes
+    pub fn with_details(mut self, details: Vec<Line<'static>>) -> Input {
+        self.details = details;
+        self
+    }
+
+    pub fn handle_key(&mut self, key: &Key) -> InputEvent {
+        match key {
+            Key::Enter => return InputEvent::Submit,
+            Key::Escape => return InputEvent::Cancel,
+            Key::Backspace => {
+                self.text.pop();
+            }
+            Key::Ctrl('j') if self.multiline => self.text.push('\n'),
+            Key::Char(c) => self.text.push(*c),
+            _ => {}
+        }
+        InputEvent::Editing
+    }
+}
+
+/// The Widget used to render an Input
+pub struct InputView<'a> {
+    input: &'a Input,
+}
+
+impl<'a> InputView<'a> {
+    pub fn new(input: &'a Input) -> InputView<'a> {
+        InputView { input }
+    }
+}
+
+impl<'a> Widget for InputView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines: Vec<Line> = self
+            .input
+            .text
+            .split('\n')
+            .map(|l| Line::from(l.to_string()))
+            .collect();
+        if let Some(last) = lines.last_mut() {
+            last.spans.push(Span::styled(
+                " ",
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+        }
+
+        let hint = match self.input.action {
+            InputAction::Commit(_) | InputAction::Amend(_) => {
+                "enter: accept  ctrl-j: new line  ctrl-s: sign off  esc: cancel"
+            }
+            _ if self.input.multiline => {
+                "enter: accept  ctrl-j: new line  esc: cancel"
+            }
+            _ => "enter: accept  esc: cancel",
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::styled(hint, Style::default().fg(Color::Indexed(8))));
+
+        if !self.input.details.is_empty() {
+            lines.push(Line::from(""));
+            lines.extend(self.input.details.iter().cloned());
+        }
+
+        let width = area.width.saturating_sub(4).min(76);
+        // long details are cut off at the bottom of the screen
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = centered_rect(width, height, area);
+
+        let input = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(" {} ", self.input.title))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Indexed(2)))
+                .padding(Padding::horizontal(1)),
+        );
+
+        Clear.render(rect, buf);
+        input.render(rect, buf);
+    }
+}
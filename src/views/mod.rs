@@ -1,4 +1,18 @@
+pub mod about;
+pub mod blame;
 pub mod commitlog;
+pub mod confirm;
+pub mod controller;
 pub mod diff;
+pub mod file;
+pub mod finder;
+pub mod grep;
+pub mod input;
+pub mod patchids;
+pub mod picker;
+pub mod popup;
+pub mod rangediff;
+pub mod refs;
 pub mod stats;
 pub mod statusline;
+pub mod tree;
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_patch_ids, DiffAction, GitRef, PatchIdCommit, Target},
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    views::{
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        stats::Stats,
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// The commits on one side of a comparison, since the merge base
+#[derive(Debug, Clone)]
+struct Side {
+    /// the branch the commits are on, or the commit at its tip
+    name: String,
+    commits: Vec<PatchIdCommit>,
+}
+
+impl Side {
+    fn new(range: &DiffAction) -> Side {
+        let (Some(base), Target::Ref(tip)) = (&range.anchor, &range.target)
+        else {
+            return Side {
+                name: range.to_string(),
+                commits: vec![],
+            };
+        };
+        Side {
+            name: range.branch().map_or_else(|| tip.to_string(), String::from),
+            commits: git_patch_ids(base, tip),
+        }
+    }
+}
+
+/// A row of the comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    /// the heading of a side, by index
+    Side(usize),
+    /// a commit, by the index of its side and its index in the side
+    Commit(usize, usize),
+}
+
+/// The commits that two branches made since their merge base, with the ones
+/// that make the same change as a commit on the other branch flagged, like
+/// `git cherry` does, to find fixes that were already backported
+#[derive(Debug, Clone)]
+pub struct PatchIds {
+    list: ListData,
+    sides: [Side; 2],
+    /// the commit on the other side with the same patch-id as each commit,
+    /// by side and index in the side
+    duplicates: HashMap<(usize, usize), GitRef>,
+    rows: Vec<Row>,
+    /// only list the commits that aren't on the other side
+    unique_only: bool,
+}
+
+impl PatchIds {
+    pub fn new(first: &DiffAction, second: &DiffAction) -> PatchIds {
+        let sides = [Side::new(first), Side::new(second)];
+
+        let mut by_id: HashMap<&str, Vec<(usize, &GitRef)>> = HashMap::new();
+        for (s, side) in sides.iter().enumerate() {
+            for c in &side.commits {
+                if let Some(id) = &c.patch_id {
+                    by_id.entry(id).or_default().push((s, &c.commit));
+                }
+            }
+        }
+        let mut duplicates = HashMap::new();
+        for (s, side) in sides.iter().enumerate() {
+            for (i, c) in side.commits.iter().enumerate() {
+                let other = c
+                    .patch_id
+                    .as_deref()
+                    .and_then(|id| by_id.get(id))
+                    .and_then(|commits| commits.iter().find(|(o, _)| *o != s));
+                if let Some((_, commit)) = other {
+                    duplicates.insert((s, i), (*commit).clone());
+                }
+            }
+        }
+
+        let mut view = PatchIds {
+            list: ListData::new(),
+            sides,
+            duplicates,
+            rows: vec![],
+            unique_only: false,
+        };
+        view.update_rows();
+        view
+    }
+
+    fn update_rows(&mut self) {
+        self.rows = vec![];
+        for (s, side) in self.sides.iter().enumerate() {
+            self.rows.push(Row::Side(s));
+            for i in 0..side.commits.len() {
+                if !self.unique_only || !self.duplicates.contains_key(&(s, i)) {
+                    self.rows.push(Row::Commit(s, i));
+                }
+            }
+        }
+        let cursor = self.cursor().min(self.rows.len().saturating_sub(1));
+        self.cursor_to(cursor);
+    }
+
+    fn current_commit(&self) -> Option<&PatchIdCommit> {
+        match self.rows.get(self.cursor()) {
+            Some(Row::Commit(s, i)) => self.sides[*s].commits.get(*i),
+            _ => None,
+        }
+    }
+
+    /// Return the number of commits on a side that are also on the other
+    fn duplicate_count(&self, side: usize) -> usize {
+        self.duplicates.keys().filter(|(s, _)| *s == side).count()
+    }
+}
+
+impl ListInfo for PatchIds {
+    fn list_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for PatchIds {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for PatchIds {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for PatchIds {
+    fn status(&self) -> Vec<StatusSegment> {
+        let mut status = vec![StatusSegment::target(format!(
+            "{} vs {}",
+            self.sides[0].name, self.sides[1].name
+        ))];
+        if self.unique_only {
+            status.push(StatusSegment::mode("unique commits"));
+        }
+        status
+    }
+}
+
+impl ViewController for PatchIds {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Char('u')] => {
+                self.unique_only = !self.unique_only;
+                self.update_rows();
+            }
+
+            [Key::Enter] => {
+                if let Some(c) = self.current_commit() {
+                    ctx.push(Stats::new(
+                        DiffAction::show(Target::Ref(c.commit.clone())),
+                        ctx.diff_opts,
+                    ));
+                }
+            }
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block("Same changes");
+        let mut view = PatchIdsView::new(self);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter", "files"), ("u", "unique only")]
+    }
+}
+
+/// The Widget used to render a PatchIds view
+pub struct PatchIdsView<'a> {
+    view: &'a mut PatchIds,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> PatchIdsView<'a> {
+    pub fn new(view: &'a mut PatchIds) -> PatchIdsView<'a> {
+        PatchIdsView { view, block: None }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> PatchIdsView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for PatchIdsView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.view.list.height = area.height as usize;
+
+        let dim = Style::default().fg(Color::Indexed(8));
+        let view = &*self.view;
+        let items: Vec<ListItem> = view
+            .rows
+            .iter()
+            .map(|row| match *row {
+                Row::Side(s) => {
+                    let side = &view.sides[s];
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            side.name.clone(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            format!(
+                                ": {} commit{}, {} also on {}",
+                                side.commits.len(),
+                                if side.commits.len() == 1 { "" } else { "s" },
+                                view.duplicate_count(s),
+                                view.sides[1 - s].name
+                            ),
+                            dim,
+                        ),
+                    ]))
+                }
+                Row::Commit(s, i) => {
+                    let c = &view.sides[s].commits[i];
+                    let duplicate = view.duplicates.get(&(s, i));
+                    let (sign, style) = match duplicate {
+                        Some(_) => ("= ", dim),
+                        None => ("+ ", Style::default().fg(Color::Indexed(2))),
+                    };
+                    let mut spans = vec![
+                        Span::from("  "),
+                        Span::styled(sign, style),
+                        Span::styled(
+                            format!("{} ", c.commit),
+                            Style::default().fg(Color::Indexed(5)),
+                        ),
+                        Span::styled(
+                            c.subject.clone(),
+                            if duplicate.is_some() {
+                                dim
+                            } else {
+                                Style::default()
+                            },
+                        ),
+                    ];
+                    if let Some(other) = duplicate {
+                        spans.push(Span::styled(
+                            format!(" (same as {})", other),
+                            dim,
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.view.list_state_mut());
+    }
+}
@@ -0,0 +1,237 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+    },
+};
+
+use crate::{
+    events::Key,
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    views::controller::{KeyResult, ViewContext},
+};
+
+/// Names whose last path component matches are listed before names that only
+/// match across directories, by adding this to their scores
+const PATH_MATCH_PENALTY: usize = 10_000;
+
+/// Something a Picker lists
+pub trait PickerItem {
+    /// The text a query is matched against
+    fn name(&self) -> &str;
+
+    /// The line the item is listed as
+    fn line(&self) -> Line<'static>;
+}
+
+/// A list of items narrowed down by typing part of a name, for views that
+/// let the user choose one thing from many
+#[derive(Debug, Clone)]
+pub struct Picker<T> {
+    list: ListData,
+    items: Vec<T>,
+    query: String,
+    /// the items that match the query, best first
+    matches: Vec<usize>,
+}
+
+impl<T: PickerItem> Picker<T> {
+    pub fn new(items: Vec<T>) -> Picker<T> {
+        let mut picker = Picker {
+            list: ListData::new(),
+            items,
+            query: String::new(),
+            matches: vec![],
+        };
+        picker.update_matches();
+        picker
+    }
+
+    /// Find the items that match the query, and move to the best one
+    fn update_matches(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                Some((fuzzy_score(item.name(), &self.query)?, i))
+            })
+            .collect();
+        // ties go to the shorter name, and every item is listed in its
+        // original order until something is typed
+        if !self.query.is_empty() {
+            scored.sort_by_key(|(score, i)| {
+                (*score, self.items[*i].name().len(), *i)
+            });
+        }
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.cursor_to_top();
+    }
+
+    /// Return the item at the cursor
+    pub fn current(&self) -> Option<&T> {
+        self.matches.get(self.cursor()).map(|i| &self.items[*i])
+    }
+
+    /// Describe how many of the items match the query
+    pub fn describe_matches(&self) -> String {
+        format!("{} of {}", self.matches.len(), self.items.len())
+    }
+
+    /// Handle typing, moving through the matches, and closing the picker;
+    /// the view handles choosing an item
+    pub fn handle_key(
+        &mut self,
+        keys: &[Key],
+        ctx: &mut ViewContext,
+    ) -> KeyResult {
+        match keys {
+            [Key::Char(c)] => {
+                self.query.push(*c);
+                self.update_matches();
+            }
+            [Key::Backspace] => {
+                self.query.pop();
+                self.update_matches();
+            }
+            [Key::Escape] => ctx.close = true,
+
+            [Key::Up | Key::Ctrl('p')] => self.cursor_up(),
+            [Key::Down | Key::Ctrl('n')] => self.cursor_down(),
+            [Key::Ctrl('u')] => self.cursor_page_up(),
+            [Key::Ctrl('f')] => self.cursor_page_down(),
+
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+}
+
+/// Score how well a path matches a query whose characters appear in it in
+/// order, ignoring case; lower scores are better, and None means the path
+/// doesn't match
+fn fuzzy_score(path: &str, query: &str) -> Option<usize> {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    subsequence_gaps(&path[name_start..], query).or_else(|| {
+        subsequence_gaps(path, query).map(|gaps| gaps + PATH_MATCH_PENALTY)
+    })
+}
+
+/// Return how many characters of some text are skipped to find a query's
+/// characters in it, in order and ignoring case, if they're all there
+fn subsequence_gaps(text: &str, query: &str) -> Option<usize> {
+    let mut gaps = 0;
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            if text.next()? == q {
+                break;
+            }
+            gaps += 1;
+        }
+    }
+    Some(gaps)
+}
+
+impl<T: PickerItem> ListInfo for Picker<T> {
+    fn list_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl<T: PickerItem> ListScroll for Picker<T> {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl<T: PickerItem> ListCursor for Picker<T> {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+/// The Widget used to render a Picker
+pub struct PickerView<'a, T> {
+    picker: &'a mut Picker<T>,
+    block: Option<Block<'a>>,
+}
+
+impl<'a, T: PickerItem> PickerView<'a, T> {
+    pub fn new(picker: &'a mut Picker<T>) -> PickerView<'a, T> {
+        PickerView {
+            picker,
+            block: None,
+        }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> PickerView<'a, T> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a, T: PickerItem> Widget for PickerView<'a, T> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        if area.height == 0 {
+            return;
+        }
+
+        // the query is typed on the first line
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Indexed(6))),
+            Span::from(self.picker.query.clone()),
+            Span::styled(
+                " ",
+                Style::default().add_modifier(Modifier::REVERSED),
+            ),
+        ]))
+        .render(Rect { height: 1, ..area }, buf);
+        let area = Rect {
+            y: area.y + 1,
+            height: area.height - 1,
+            ..area
+        };
+        self.picker.list.height = area.height as usize;
+
+        let items: Vec<ListItem> = self
+            .picker
+            .matches
+            .iter()
+            .map(|i| ListItem::new(self.picker.items[*i].line()))
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.picker.list_state_mut());
+    }
+}
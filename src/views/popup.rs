@@ -0,0 +1,63 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::ui::centered_rect;
+
+/// A read-only block of information shown over the current view
+#[derive(Debug, Clone)]
+pub struct Popup {
+    pub title: String,
+    pub lines: Vec<Line<'static>>,
+}
+
+impl Popup {
+    pub fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Popup {
+        Popup {
+            title: title.into(),
+            lines,
+        }
+    }
+}
+
+/// The Widget used to render a Popup
+pub struct PopupView<'a> {
+    popup: &'a Popup,
+}
+
+impl<'a> PopupView<'a> {
+    pub fn new(popup: &'a Popup) -> PopupView<'a> {
+        PopupView { popup }
+    }
+}
+
+impl<'a> Widget for PopupView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = self
+            .popup
+            .lines
+            .iter()
+            .map(|l| l.width())
+            .max()
+            .unwrap_or(0)
+            .max(self.popup.title.len() + 2) as u16
+            + 4;
+        let height = self.popup.lines.len() as u16 + 2;
+        let rect = centered_rect(width, height, area);
+
+        let popup = Paragraph::new(self.popup.lines.clone()).block(
+            Block::default()
+                .title(format!(" {} ", self.popup.title))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Indexed(4)))
+                .padding(Padding::horizontal(1)),
+        );
+
+        Clear.render(rect, buf);
+        popup.render(rect, buf);
+    }
+}
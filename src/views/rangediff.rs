@@ -0,0 +1,450 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{
+        git_range_diff, DiffAction, GitRef, RangeDiffPair, RangeDiffStatus,
+        Target,
+    },
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    search::Search,
+    ui::{highlight_spans, match_style},
+    views::{
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        stats::Stats,
+        statusline::{Status, StatusSegment},
+    },
+};
+
+fn status_color(status: RangeDiffStatus) -> Color {
+    match status {
+        RangeDiffStatus::Same => Color::Indexed(8),
+        RangeDiffStatus::Changed => Color::Indexed(3),
+        RangeDiffStatus::Removed => Color::Indexed(1),
+        RangeDiffStatus::Added => Color::Indexed(2),
+    }
+}
+
+/// The commits of two ranges paired up by `git range-diff`, like a branch
+/// before and after a rebase; enter shows how a pair's changes differ
+#[derive(Debug, Clone)]
+pub struct RangeDiff {
+    list: ListData,
+    ranges: String,
+    pairs: Vec<RangeDiffPair>,
+}
+
+impl RangeDiff {
+    pub fn new(ranges: &str) -> Result<RangeDiff, String> {
+        let args: Vec<&str> = ranges.split_whitespace().collect();
+        if args.is_empty() {
+            return Err("Enter two ranges, like old...new".into());
+        }
+        let pairs = git_range_diff(&args).map_err(|e| e.first_line())?;
+        if pairs.is_empty() {
+            return Err(format!("{} has no commits", args.join(" ")));
+        }
+        Ok(RangeDiff {
+            list: ListData::new(),
+            ranges: args.join(" "),
+            pairs,
+        })
+    }
+
+    fn current_pair(&self) -> Option<&RangeDiffPair> {
+        self.pairs.get(self.cursor())
+    }
+
+    /// Return the number of pairs with each status
+    fn count(&self, status: RangeDiffStatus) -> usize {
+        self.pairs.iter().filter(|p| p.status == status).count()
+    }
+}
+
+impl ListInfo for RangeDiff {
+    fn list_count(&self) -> usize {
+        self.pairs.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for RangeDiff {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for RangeDiff {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for RangeDiff {
+    fn status(&self) -> Vec<StatusSegment> {
+        let counts: Vec<String> = [
+            (RangeDiffStatus::Changed, "changed"),
+            (RangeDiffStatus::Removed, "removed"),
+            (RangeDiffStatus::Added, "added"),
+        ]
+        .iter()
+        .map(|(status, name)| (self.count(*status), name))
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect();
+
+        vec![
+            StatusSegment::target(&self.ranges),
+            StatusSegment::mode(if counts.is_empty() {
+                "unchanged".into()
+            } else {
+                counts.join(", ")
+            }),
+        ]
+    }
+}
+
+impl ViewController for RangeDiff {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => {
+                let Some(pair) = self.current_pair() else {
+                    return KeyResult::Handled;
+                };
+                match (pair.status, &pair.old, &pair.new) {
+                    (RangeDiffStatus::Changed, Some(old), Some(new)) => {
+                        ctx.push(RangeDiffPatch::new(pair, old, new))
+                    }
+                    (RangeDiffStatus::Same, ..) => {
+                        ctx.message("Both commits make the same change")
+                    }
+                    // a commit that's in only one range is shown as it is
+                    (_, Some(commit), None) | (_, None, Some(commit)) => ctx
+                        .push(Stats::new(
+                            DiffAction::show(Target::Ref(commit.clone())),
+                            ctx.diff_opts,
+                        )),
+                    _ => {}
+                }
+            }
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("Range-diff {}", self.ranges));
+        let mut view = RangeDiffView::new(self);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter", "differences"), ("q", "back")]
+    }
+}
+
+/// The Widget used to render a RangeDiff
+pub struct RangeDiffView<'a> {
+    view: &'a mut RangeDiff,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> RangeDiffView<'a> {
+    pub fn new(view: &'a mut RangeDiff) -> RangeDiffView<'a> {
+        RangeDiffView { view, block: None }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> RangeDiffView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for RangeDiffView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.view.list.height = area.height as usize;
+
+        let hash_width = self
+            .view
+            .pairs
+            .iter()
+            .flat_map(|p| [&p.old, &p.new])
+            .flatten()
+            .map(|c| c.len())
+            .max()
+            .unwrap_or(0);
+        let hash = |commit: &Option<GitRef>| match commit {
+            Some(c) => Span::styled(
+                format!("{:<width$}", c, width = hash_width),
+                Style::default().fg(Color::Indexed(5)),
+            ),
+            None => Span::styled(
+                "-".repeat(hash_width),
+                Style::default().fg(Color::Indexed(8)),
+            ),
+        };
+
+        let items: Vec<ListItem> = self
+            .view
+            .pairs
+            .iter()
+            .map(|pair| {
+                let color = status_color(pair.status);
+                ListItem::new(Line::from(vec![
+                    hash(&pair.old),
+                    Span::styled(
+                        format!(" {} ", pair.status.symbol()),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                    hash(&pair.new),
+                    Span::from(" "),
+                    Span::styled(
+                        pair.subject.clone(),
+                        if pair.status == RangeDiffStatus::Same {
+                            Style::default().fg(color)
+                        } else {
+                            Style::default()
+                        },
+                    ),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.view.list_state_mut());
+    }
+}
+
+/// How the changes of two commits a range-diff paired up differ, as a diff
+/// of their patches
+#[derive(Debug, Clone)]
+pub struct RangeDiffPatch {
+    list: ListData,
+    old: GitRef,
+    new: GitRef,
+    subject: String,
+    lines: Vec<String>,
+    search: Option<String>,
+}
+
+impl RangeDiffPatch {
+    fn new(pair: &RangeDiffPair, old: &GitRef, new: &GitRef) -> RangeDiffPatch {
+        RangeDiffPatch {
+            list: ListData::new(),
+            old: old.clone(),
+            new: new.clone(),
+            subject: pair.subject.clone(),
+            lines: pair.diff.clone(),
+            search: None,
+        }
+    }
+}
+
+impl ListInfo for RangeDiffPatch {
+    fn list_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for RangeDiffPatch {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for RangeDiffPatch {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for RangeDiffPatch {
+    fn status(&self) -> Vec<StatusSegment> {
+        vec![StatusSegment::target(format!(
+            "{} vs {}: {}",
+            self.old, self.new, self.subject
+        ))]
+    }
+}
+
+impl Search for RangeDiffPatch {
+    fn set_search(&mut self, search: Option<String>) {
+        self.search = search;
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    fn is_match(&self, idx: usize) -> bool {
+        match &self.search {
+            Some(search) => self.lines[idx].contains(search),
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for RangeDiffPatch {
+    fn handle_key(
+        &mut self,
+        keys: &[Key],
+        _ctx: &mut ViewContext,
+    ) -> KeyResult {
+        handle_cursor_keys(self, keys)
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("{} → {}", self.old, self.new));
+        let mut view = RangeDiffPatchView::new(self, opts.tab_width);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("/", "search"), ("q", "back")]
+    }
+}
+
+/// The Widget used to render a RangeDiffPatch
+pub struct RangeDiffPatchView<'a> {
+    patch: &'a mut RangeDiffPatch,
+    block: Option<Block<'a>>,
+    tab_width: u8,
+}
+
+impl<'a> RangeDiffPatchView<'a> {
+    pub fn new(
+        patch: &'a mut RangeDiffPatch,
+        tab_width: u8,
+    ) -> RangeDiffPatchView<'a> {
+        RangeDiffPatchView {
+            patch,
+            block: None,
+            tab_width,
+        }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> RangeDiffPatchView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for RangeDiffPatchView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.patch.list.height = area.height as usize;
+
+        let tab = " ".repeat(self.tab_width as usize);
+
+        let cursor = self.patch.cursor();
+        let items: Vec<ListItem> = self
+            .patch
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                // the first column says how the patches differ, and the rest
+                // of the line is a line of the patches
+                let style = match line.chars().next() {
+                    Some('+') => Style::default().fg(Color::Indexed(2)),
+                    Some('-') => Style::default().fg(Color::Indexed(1)),
+                    Some('@') => Style::default().fg(Color::Indexed(6)),
+                    _ => Style::default(),
+                };
+                let mut spans =
+                    vec![Span::styled(line.replace('\t', &tab), style)];
+
+                if let Some(search) = &self.patch.search {
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
+                }
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.patch.list_state_mut());
+    }
+}
@@ -0,0 +1,117 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_refs, NamedRef, RefKind},
+    list::ListInfo,
+    views::{
+        controller::{KeyResult, RenderOpts, ViewContext, ViewController},
+        picker::{Picker, PickerItem, PickerView},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// Return the color a kind of ref is shown in, matching the log's decorations
+fn ref_color(kind: RefKind) -> Color {
+    match kind {
+        RefKind::Branch => Color::Indexed(6),
+        RefKind::RemoteBranch => Color::Indexed(3),
+        RefKind::Tag => Color::Indexed(5),
+    }
+}
+
+impl PickerItem for NamedRef {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn line(&self) -> Line<'static> {
+        let kind = match self.kind {
+            RefKind::Branch => "branch",
+            RefKind::RemoteBranch => "remote",
+            RefKind::Tag => "tag   ",
+        };
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", kind),
+                Style::default().fg(Color::Indexed(8)),
+            ),
+            Span::styled(
+                self.name.clone(),
+                Style::default().fg(ref_color(self.kind)),
+            ),
+        ])
+    }
+}
+
+/// A list of the branches and tags in the repo, narrowed down by typing part
+/// of a name; choosing one moves the log to its commit
+#[derive(Debug, Clone)]
+pub struct RefPicker {
+    picker: Picker<NamedRef>,
+}
+
+impl RefPicker {
+    pub fn new() -> Result<RefPicker, String> {
+        let refs = git_refs();
+        if refs.is_empty() {
+            return Err("The repo has no branches or tags".into());
+        }
+        Ok(RefPicker {
+            picker: Picker::new(refs),
+        })
+    }
+}
+
+impl Status for RefPicker {
+    fn status(&self) -> Vec<StatusSegment> {
+        vec![
+            StatusSegment::target("branches and tags"),
+            StatusSegment::mode(self.picker.describe_matches()),
+        ]
+    }
+}
+
+impl ViewController for RefPicker {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => {
+                if let Some(r) = self.picker.current() {
+                    // the full name can't be mistaken for another kind of ref
+                    // with the same short name
+                    ctx.goto_commit = Some(r.refname.clone());
+                    ctx.close = true;
+                }
+            }
+
+            _ => return self.picker.handle_key(keys, ctx),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block("Go to branch or tag");
+        let mut view = PickerView::new(&mut self.picker);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.picker.list_pos(), self.picker.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[("enter", "go to"), ("esc", "close")]
+    }
+
+    fn takes_text(&self) -> bool {
+        true
+    }
+}
@@ -1,50 +1,303 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    widgets::{
+        Block, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+    },
+    Frame,
 };
 
 use crate::{
-    git::{git_diff_stat, DiffAction, Stat},
+    events::{AppEvent, Key},
+    git::{
+        git_anchor, git_branch_moved, git_diff_stat, git_patch,
+        git_scan_changes, DiffAction, DiffAlgorithm, GitDiffOpts, GitRef, Stat,
+        Target,
+    },
     list::{ListCursor, ListData, ListInfo, ListScroll},
     search::Search,
-    ui::highlight_spans,
-    views::statusline::Status,
+    ui::{highlight_spans, match_style},
+    views::{
+        confirm::{Confirm, ConfirmAction},
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        diff::Diff,
+        input::{Input, InputAction},
+        statusline::{Status, StatusSegment},
+    },
 };
 
+/// Files changed the same way are grouped when there are at least this many
+const MIN_GROUP_SIZE: usize = 3;
+
+/// A row of the list of files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    /// a file, by index in the stats, and the group it's listed in
+    File { index: usize, group: Option<usize> },
+    /// a group of files, by index in the groups
+    Group(usize),
+}
+
+/// Files whose diffs change the same lines, like a license header updated in
+/// every file or files renamed without changes, which are reviewed as one
+#[derive(Debug, Clone)]
+struct Group {
+    change_id: u64,
+    /// the files, by index in the stats
+    files: Vec<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Stats {
     list: ListData,
     commits: DiffAction,
     stats: Vec<Stat>,
+    groups: Vec<Group>,
+    /// the fingerprints of the changes of the expanded groups
+    expanded: HashSet<u64>,
+    rows: Vec<Row>,
     search: Option<String>,
+    opts: GitDiffOpts,
+    /// the other branch's changes, when comparing two branches with their
+    /// merge base
+    other_side: Option<DiffAction>,
+    /// the commit the target's branch has moved to since the stats were read
+    moved: Option<GitRef>,
 }
 
 impl Stats {
-    pub fn new(range: DiffAction) -> Stats {
-        Stats {
+    pub fn new(range: DiffAction, opts: GitDiffOpts) -> Stats {
+        let mut stats = Stats {
             list: ListData::new(),
-            stats: git_diff_stat(&range, None),
+            stats: load_stats(&range, opts),
+            groups: vec![],
+            expanded: HashSet::new(),
+            rows: vec![],
             commits: range,
             search: None,
+            opts,
+            other_side: None,
+            moved: None,
+        };
+        stats.update_rows();
+        stats
+    }
+
+    /// Compare two branches, each diffed from their merge base; Tab switches
+    /// to the other branch
+    pub fn other_side(mut self, range: DiffAction) -> Stats {
+        self.other_side = Some(range);
+        self
+    }
+
+    /// Show the other branch's changes, when comparing two branches
+    pub fn switch_side(&mut self) -> bool {
+        let Some(other) = self.other_side.take() else {
+            return false;
+        };
+        self.other_side = Some(std::mem::replace(&mut self.commits, other));
+        self.moved = git_branch_moved(&self.commits);
+        self.refresh();
+        self.cursor_to_top();
+        true
+    }
+
+    /// Toggle whether whitespace changes are ignored, and re-read the stats
+    pub fn toggle_ignore_whitespace(&mut self) {
+        self.opts.ignore_whitespace = !self.opts.ignore_whitespace;
+        self.refresh();
+    }
+
+    /// Toggle whether the uncommitted changes inside submodules are listed,
+    /// and re-read the stats; returns false if the stats aren't of the
+    /// working tree, where submodules can have such changes
+    pub fn toggle_submodules(&mut self) -> bool {
+        if !self.commits.diffs_worktree() {
+            return false;
+        }
+        self.opts.submodules = !self.opts.submodules;
+        self.refresh();
+        true
+    }
+
+    /// Switch to the next diff algorithm, and re-read the stats
+    pub fn cycle_algorithm(&mut self) -> DiffAlgorithm {
+        self.opts.algorithm = self.opts.algorithm.next();
+        self.refresh();
+        self.opts.algorithm
+    }
+
+    /// Diff up to the new tip of the target's branch, if it has moved;
+    /// returns false if it hasn't
+    pub fn follow_branch(&mut self) -> bool {
+        let Some(tip) = self.moved.take() else {
+            return false;
+        };
+        self.commits = self.commits.moved_to(tip);
+        self.refresh();
+        true
+    }
+
+    pub fn current_stat(&self) -> Option<&Stat> {
+        match self.rows.get(self.cursor()) {
+            Some(Row::File { index, .. }) => self.stats.get(*index),
+            _ => None,
         }
     }
 
-    pub fn commits(&self) -> &DiffAction {
-        &self.commits
+    /// Re-read the stats; call this when the diffed files may have changed
+    pub fn refresh(&mut self) {
+        let path = self.current_stat().map(|s| s.path.clone());
+        self.stats = load_stats(&self.commits, self.opts);
+        self.update_rows();
+
+        // the cursor stays on the same file if it's still listed
+        let row = path.and_then(|path| {
+            self.rows.iter().position(|r| {
+                matches!(r, Row::File { index, .. }
+                    if self.stats[*index].path == path)
+            })
+        });
+        if let Some(row) = row {
+            self.cursor_to(row);
+        }
     }
 
-    pub fn current_stat(&self) -> &Stat {
-        let cursor = self.cursor();
-        &self.stats[cursor]
+    /// Group the files that were changed the same way, and list the groups
+    /// and the files that aren't in collapsed groups
+    fn update_rows(&mut self) {
+        let mut members: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, stat) in self.stats.iter().enumerate() {
+            if let Some(id) = stat.change_id {
+                members.entry(id).or_default().push(i);
+            }
+        }
+
+        // a group is listed where its first file would be
+        self.groups = vec![];
+        let mut group_of: Vec<Option<usize>> = vec![None; self.stats.len()];
+        for stat in &self.stats {
+            let Some(id) = stat.change_id else {
+                continue;
+            };
+            match members.remove(&id) {
+                Some(files) if files.len() >= MIN_GROUP_SIZE => {
+                    for f in &files {
+                        group_of[*f] = Some(self.groups.len());
+                    }
+                    self.groups.push(Group {
+                        change_id: id,
+                        files,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        self.rows = vec![];
+        for (index, group) in group_of.into_iter().enumerate() {
+            let Some(g) = group else {
+                self.rows.push(Row::File { index, group });
+                continue;
+            };
+            let files = &self.groups[g].files;
+            if files[0] != index {
+                continue;
+            }
+            self.rows.push(Row::Group(g));
+            if self.expanded.contains(&self.groups[g].change_id) {
+                self.rows.extend(
+                    files.iter().map(|f| Row::File { index: *f, group }),
+                );
+            }
+        }
+
+        let cursor = self.cursor().min(self.rows.len().saturating_sub(1));
+        self.cursor_to(cursor);
+    }
+
+    /// Return the group at the cursor, or the group of the file at the cursor
+    fn current_group(&self) -> Option<usize> {
+        match self.rows.get(self.cursor()) {
+            Some(Row::Group(g)) => Some(*g),
+            Some(Row::File { group, .. }) => *group,
+            None => None,
+        }
+    }
+
+    /// Show or hide the files of a group, leaving the cursor on the group
+    fn set_expanded(&mut self, group: usize, expanded: bool) {
+        let id = self.groups[group].change_id;
+        if expanded {
+            self.expanded.insert(id);
+        } else {
+            self.expanded.remove(&id);
+        }
+        self.update_rows();
+        if let Some(row) =
+            self.rows.iter().position(|r| *r == Row::Group(group))
+        {
+            self.cursor_to(row);
+        }
+    }
+
+    /// Sum up the files of a group as if they were one file, named after the
+    /// group
+    fn group_stat(&self, group: usize) -> Stat {
+        let files = &self.groups[group].files;
+        let first = &self.stats[files[0]];
+        let expanded = self.expanded.contains(&self.groups[group].change_id);
+        let change = if first.old_path.is_empty() || first.hunks != Some(0) {
+            "changed the same way"
+        } else {
+            "renamed without changes"
+        };
+        let mut stat = Stat {
+            adds: 0,
+            deletes: 0,
+            hunks: Some(0),
+            path: format!(
+                "{} {} files {}, like {}",
+                if expanded { "▾" } else { "▸" },
+                files.len(),
+                change,
+                first.path
+            ),
+            old_path: String::new(),
+            commit: None,
+            ..first.clone()
+        };
+        for f in files {
+            let s = &self.stats[*f];
+            stat.adds += s.adds;
+            stat.deletes += s.deletes;
+            stat.hunks = stat.hunks.zip(s.hunks).map(|(a, b)| a + b);
+        }
+        stat
     }
 }
 
+/// Read the stats of the files changed in a range, with their hunks counted
+/// and their changes fingerprinted
+fn load_stats(range: &DiffAction, opts: GitDiffOpts) -> Vec<Stat> {
+    let mut stats = git_diff_stat(range, Some(opts));
+    git_scan_changes(&mut stats, range, Some(opts));
+    stats
+}
+
 impl ListInfo for Stats {
     fn list_count(&self) -> usize {
-        self.stats.len()
+        self.rows.len()
     }
 
     fn list_pos(&self) -> usize {
@@ -77,8 +330,26 @@ impl ListCursor for Stats {
 }
 
 impl Status for Stats {
-    fn status(&self) -> String {
-        format!("{}", self.commits)
+    fn status(&self) -> Vec<StatusSegment> {
+        let mut status = vec![StatusSegment::target(self.commits.to_string())];
+        if self.opts.ignore_whitespace {
+            status.push(StatusSegment::mode("ignoring whitespace"));
+        }
+        if self.opts.submodules && self.commits.diffs_worktree() {
+            status.push(StatusSegment::mode("with submodules"));
+        }
+        if let Some(Row::Group(_)) = self.rows.get(self.cursor()) {
+            status.push(StatusSegment::hint("Enter lists the files"));
+        }
+        if let (Some(branch), Some(_)) = (self.commits.branch(), &self.moved) {
+            status.push(StatusSegment::hint(format!(
+                "{} has moved, R reloads",
+                branch
+            )));
+        } else if self.other_side.is_some() {
+            status.push(StatusSegment::hint("Tab shows the other branch"));
+        }
+        status
     }
 }
 
@@ -92,16 +363,237 @@ impl Search for Stats {
     }
 
     fn is_match(&self, idx: usize) -> bool {
-        match &self.search {
-            Some(search) => {
-                let stat = &self.stats[idx];
-                stat.path.contains(search)
-                    || stat.adds.to_string().contains(search)
-                    || stat.deletes.to_string().contains(search)
+        let Some(search) = &self.search else {
+            return false;
+        };
+        let is_match = |stat: &Stat| {
+            stat.path.contains(search)
+                || stat.adds.to_string().contains(search)
+                || stat.deletes.to_string().contains(search)
+        };
+        match self.rows.get(idx) {
+            Some(Row::File { index, .. }) => is_match(&self.stats[*index]),
+            // a group matches if any of its files do
+            Some(Row::Group(g)) => self.groups[*g]
+                .files
+                .iter()
+                .any(|f| is_match(&self.stats[*f])),
+            None => false,
+        }
+    }
+}
+
+impl ViewController for Stats {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Char('w')] => self.toggle_ignore_whitespace(),
+
+            [Key::Char('m')] => {
+                if self.toggle_submodules() {
+                    // new views follow the choice
+                    ctx.diff_opts.submodules = self.opts.submodules;
+                    ctx.message(if self.opts.submodules {
+                        "Listing the changes inside submodules"
+                    } else {
+                        "Listing submodules as single entries"
+                    });
+                } else {
+                    ctx.message(
+                        "Only changes to the working tree include submodules",
+                    );
+                }
+            }
+
+            [Key::Char('a')] => {
+                let algorithm = self.cycle_algorithm();
+                ctx.diff_opts.algorithm = algorithm;
+                ctx.message(format!("Diff algorithm: {}", algorithm));
+            }
+
+            [Key::Char('D') | Key::Char('x')] => match self.current_stat() {
+                Some(stat)
+                    if self.commits.target == Target::Unstaged
+                        && self.commits.anchor.is_none() =>
+                {
+                    ctx.confirm = Some(Confirm::new(
+                        format!("Discard unstaged changes to {}?", stat.path),
+                        ConfirmAction::Discard(stat.path.clone()),
+                    ))
+                }
+                _ => ctx.message("Only unstaged changes can be discarded"),
+            },
+
+            [Key::Tab] if self.switch_side() => {}
+
+            [Key::Char('R')] => {
+                if self.follow_branch() {
+                    ctx.message(format!("Showing {}", self.commits.describe()));
+                } else {
+                    ctx.message("The diff is up to date");
+                }
+            }
+
+            [Key::Char('r')] => match self.commits.swapped() {
+                Some(range) => {
+                    self.commits = range;
+                    self.refresh();
+                    ctx.message(format!("Showing {}", self.commits.describe()));
+                }
+                None => {
+                    ctx.message("Only diffs between commits can be swapped")
+                }
+            },
+
+            [Key::Char('A')] => {
+                if self.commits.is_commit_show() {
+                    ctx.input = Some(
+                        Input::new("Diff against", InputAction::Anchor)
+                            .with_text("HEAD"),
+                    );
+                } else {
+                    ctx.message(
+                        "Only a single commit can be diffed against another",
+                    );
+                }
+            }
+
+            [Key::Char('H')] => match self.rows.get(self.cursor()) {
+                Some(Row::File { index, .. }) => {
+                    let path = self.stats[*index].path.clone();
+                    ctx.message(format!(
+                        "Highlighting the commits that changed {} in the log",
+                        path
+                    ));
+                    ctx.events.push(AppEvent::HighlightPath(Some(path)));
+                }
+                _ => ctx.message("Select a file to highlight its commits"),
+            },
+
+            [Key::Char('e')] => match self.current_stat() {
+                Some(stat) if stat.worktree_path().exists() => {
+                    ctx.edit = Some((stat.worktree_path(), 1));
+                }
+                Some(_) => ctx.message("The file isn't in the working tree"),
+                None => {}
+            },
+
+            [Key::Enter] => match self.rows.get(self.cursor()) {
+                Some(Row::File { index, .. }) => ctx.push(Diff::new(
+                    &self.stats,
+                    *index,
+                    &self.commits,
+                    self.opts,
+                )),
+                Some(Row::Group(g)) => {
+                    let expanded =
+                        self.expanded.contains(&self.groups[*g].change_id);
+                    self.set_expanded(*g, !expanded);
+                }
+                None => {}
+            },
+
+            [Key::Right | Key::Char('l')] => {
+                if let Some(Row::Group(g)) = self.rows.get(self.cursor()) {
+                    self.set_expanded(*g, true);
+                }
+            }
+
+            [Key::Left | Key::Char('h')] => {
+                if let Some(g) = self.current_group() {
+                    self.set_expanded(g, false);
+                }
+            }
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let mut view = StatsView::new(self);
+        if let Some(block) =
+            opts.block(format!("Files — {}", view.stats.stats.len()))
+        {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Refresh => self.refresh(),
+            // a list of staged or unstaged changes follows changes to the
+            // index and working tree
+            AppEvent::WorktreeChanges(_)
+                if matches!(
+                    self.commits.target,
+                    Target::Staged | Target::Unstaged
+                ) =>
+            {
+                self.refresh()
+            }
+            AppEvent::RefsChanged => {
+                self.moved = git_branch_moved(&self.commits)
             }
-            _ => false,
+            AppEvent::UseProfile(profile) => {
+                let opts = profile.diff_opts(self.opts);
+                if opts != self.opts {
+                    self.opts = opts;
+                    self.refresh();
+                }
+            }
+            _ => {}
         }
     }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn anchor_to(&mut self, rev: &str) -> Result<String, String> {
+        self.commits = git_anchor(&self.commits, rev)?;
+        self.moved = None;
+        self.refresh();
+        Ok(self.commits.describe())
+    }
+
+    fn select_file(&mut self, path: &str) -> Result<(), String> {
+        let index = self
+            .stats
+            .iter()
+            .position(|s| s.path == path)
+            .ok_or_else(|| format!("{} isn't changed", path))?;
+        if let Some(g) =
+            self.groups.iter().position(|g| g.files.contains(&index))
+        {
+            self.set_expanded(g, true);
+        }
+        let row = self.rows.iter().position(
+            |r| matches!(r, Row::File { index: i, .. } if *i == index),
+        );
+        if let Some(row) = row {
+            self.cursor_to(row);
+        }
+        Ok(())
+    }
+
+    fn patch(&self, _opts: GitDiffOpts) -> Result<String, String> {
+        Ok(git_patch(None, &self.commits, Some(self.opts)))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("enter", "diff"),
+            ("w", "whitespace"),
+            ("a", "algorithm"),
+            ("x", "discard"),
+            ("q", "back"),
+        ]
+    }
 }
 
 /// The Widget used to render Stats
@@ -111,40 +603,147 @@ pub struct StatsView<'a> {
 }
 
 impl<'a> StatsView<'a> {
-    pub fn new(stats: &'a mut Stats) -> StatsView {
+    pub fn new(stats: &'a mut Stats) -> StatsView<'a> {
         StatsView { stats, block: None }
     }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> StatsView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+/// Return the text for a count of added or deleted lines; binary files don't
+/// have lines
+fn count_text(count: u32, binary: bool) -> String {
+    if binary {
+        "-".into()
+    } else {
+        count.to_string()
+    }
+}
+
+/// Return the text of a file's hunk count, which is blank for binary files
+/// and files whose hunks weren't counted
+fn hunks_text(stat: &Stat) -> String {
+    match stat.hunks {
+        Some(hunks) if !stat.binary => format!("{}@", hunks),
+        _ => String::new(),
+    }
+}
+
+/// Render a line describing the diffed range and summing up its changes,
+/// which stays at the top of the view
+fn render_header(stats: &Stats, area: Rect, buf: &mut Buffer) {
+    let files = stats.stats.len();
+    let hunks: u32 = stats.stats.iter().filter_map(|s| s.hunks).sum();
+    let adds: u32 = stats.stats.iter().map(|s| s.adds).sum();
+    let deletes: u32 = stats.stats.iter().map(|s| s.deletes).sum();
+    let description = match (&stats.other_side, &stats.commits.anchor) {
+        (Some(_), Some(base)) => format!(
+            "changes on {} since merge base {}",
+            stats.commits.target, base
+        ),
+        _ => stats.commits.describe(),
+    };
+    let line = Line::from(vec![
+        Span::styled(
+            description,
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::from(format!(
+            ": {} file{}, {} hunk{}, ",
+            files,
+            if files == 1 { "" } else { "s" },
+            hunks,
+            if hunks == 1 { "" } else { "s" }
+        )),
+        Span::styled(
+            format!("+{}", adds),
+            Style::default().fg(Color::Indexed(2)),
+        ),
+        Span::from(" "),
+        Span::styled(
+            format!("−{}", deletes),
+            Style::default().fg(Color::Indexed(1)),
+        ),
+    ]);
+    Paragraph::new(line).render(area, buf);
 }
 
 impl<'a> Widget for StatsView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        // the header line is dropped if there's no room for anything else
+        let area = if area.height > 1 {
+            render_header(self.stats, Rect { height: 1, ..area }, buf);
+            Rect {
+                y: area.y + 1,
+                height: area.height - 1,
+                ..area
+            }
+        } else {
+            area
+        };
         self.stats.list.height = area.height as usize;
 
-        let adds_width = self
-            .stats
+        // a group is listed like a file with the sums of its files' changes
+        let rows: Vec<(Cow<Stat>, &Row)> = self
             .stats
+            .rows
             .iter()
-            .map(|s| s.adds.to_string().len())
+            .map(|row| match row {
+                Row::File { index, .. } => {
+                    (Cow::Borrowed(&self.stats.stats[*index]), row)
+                }
+                Row::Group(g) => (Cow::Owned(self.stats.group_stat(*g)), row),
+            })
+            .collect();
+
+        let hunks_width = rows
+            .iter()
+            .map(|(s, _)| hunks_text(s).len())
             .max()
             .unwrap_or(0);
-        let dels_width = self
-            .stats
-            .stats
+        let adds_width = rows
             .iter()
-            .map(|s| s.deletes.to_string().len())
+            .map(|(s, _)| count_text(s.adds, s.binary).len())
+            .max()
+            .unwrap_or(0);
+        let dels_width = rows
+            .iter()
+            .map(|(s, _)| count_text(s.deletes, s.binary).len())
             .max()
             .unwrap_or(0);
 
-        let items: Vec<ListItem> = self
-            .stats
-            .stats
+        let cursor = self.stats.cursor();
+        let items: Vec<ListItem> = rows
             .iter()
-            .map(|c| {
+            .enumerate()
+            .map(|(i, (c, row))| {
                 let mut spans = vec![
                     Span::styled(
                         format!(
                             "{:>width$}",
-                            c.adds.to_string(),
+                            hunks_text(c),
+                            width = hunks_width
+                        ),
+                        Style::default().fg(Color::Indexed(4)),
+                    ),
+                    Span::from(" "),
+                    Span::styled(
+                        format!(
+                            "{:>width$}",
+                            count_text(c.adds, c.binary),
                             width = adds_width,
                         ),
                         Style::default().fg(Color::Indexed(2)),
@@ -153,22 +752,34 @@ impl<'a> Widget for StatsView<'a> {
                     Span::styled(
                         format!(
                             "{:>width$}",
-                            c.deletes.to_string(),
+                            count_text(c.deletes, c.binary),
                             width = dels_width,
                         ),
                         Style::default().fg(Color::Indexed(1)),
                     ),
                     Span::from(" "),
-                    Span::from(c.path.clone()),
-                    Span::from(" "),
                 ];
+                if let Row::File { group: Some(_), .. } = row {
+                    spans.push(Span::from("  "));
+                }
+                if let Some(commit) = &c.commit {
+                    spans.push(Span::styled(
+                        format!("{} ", commit),
+                        Style::default().fg(Color::Indexed(5)),
+                    ));
+                }
+                let path_style = match row {
+                    Row::Group(_) => Style::default().fg(Color::Indexed(3)),
+                    Row::File { .. } => Style::default(),
+                };
+                spans.extend([
+                    Span::styled(c.path.clone(), path_style),
+                    Span::from(" "),
+                ]);
 
                 if let Some(search) = &self.stats.search {
-                    spans = highlight_spans(
-                        spans.clone(),
-                        search,
-                        Style::default().add_modifier(Modifier::REVERSED),
-                    )
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
                 }
 
                 let row = ListItem::new(Line::from(spans));
@@ -177,13 +788,9 @@ impl<'a> Widget for StatsView<'a> {
             })
             .collect();
 
-        let mut list = List::new(items)
+        let list = List::new(items)
             .highlight_style(Style::default().bg(Color::Indexed(0)));
 
-        if let Some(b) = self.block {
-            list = list.block(b);
-        }
-
         StatefulWidget::render(list, area, buf, self.stats.list_state_mut());
     }
 }
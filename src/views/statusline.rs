@@ -3,12 +3,96 @@ use std::fmt;
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{Paragraph, Widget},
 };
 
+/// What a status segment describes, which determines how it's styled; when
+/// the statusline is too narrow, segments are dropped in this order, from
+/// last to first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SegmentKind {
+    /// a message for the user, which replaces a view's status
+    Message,
+    /// what the view is showing
+    Target,
+    /// a setting that changes what the view shows
+    Mode,
+    /// where the view is in what it shows
+    Position,
+    /// a suggestion for what to do next
+    Hint,
+}
+
+/// A piece of a view's status
+#[derive(Debug, Clone)]
+pub struct StatusSegment {
+    pub kind: SegmentKind,
+    pub text: String,
+}
+
+impl StatusSegment {
+    pub fn message(text: impl Into<String>) -> StatusSegment {
+        StatusSegment {
+            kind: SegmentKind::Message,
+            text: text.into(),
+        }
+    }
+
+    pub fn target(text: impl Into<String>) -> StatusSegment {
+        StatusSegment {
+            kind: SegmentKind::Target,
+            text: text.into(),
+        }
+    }
+
+    pub fn mode(text: impl Into<String>) -> StatusSegment {
+        StatusSegment {
+            kind: SegmentKind::Mode,
+            text: text.into(),
+        }
+    }
+
+    pub fn position(text: impl Into<String>) -> StatusSegment {
+        StatusSegment {
+            kind: SegmentKind::Position,
+            text: text.into(),
+        }
+    }
+
+    pub fn hint(text: impl Into<String>) -> StatusSegment {
+        StatusSegment {
+            kind: SegmentKind::Hint,
+            text: text.into(),
+        }
+    }
+
+    fn style(&self) -> Style {
+        match self.kind {
+            SegmentKind::Message => Style::default().fg(Color::Indexed(15)),
+            SegmentKind::Target => {
+                Style::default().add_modifier(Modifier::BOLD)
+            }
+            SegmentKind::Mode => Style::default().fg(Color::Indexed(3)),
+            SegmentKind::Position => Style::default().fg(Color::Indexed(7)),
+            SegmentKind::Hint => Style::default()
+                .fg(Color::Indexed(7))
+                .add_modifier(Modifier::ITALIC),
+        }
+    }
+
+    /// Return the text of the segment as it's displayed
+    fn display(&self) -> String {
+        match self.kind {
+            SegmentKind::Mode => format!("[{}]", self.text),
+            _ => self.text.clone(),
+        }
+    }
+}
+
 pub trait Status {
-    fn status(&self) -> String;
+    fn status(&self) -> Vec<StatusSegment>;
 }
 
 #[derive(Debug, Clone)]
@@ -30,22 +114,29 @@ impl fmt::Display for Location {
 }
 
 pub struct StatusLine {
-    status: String,
+    status: Vec<StatusSegment>,
     location: Option<Location>,
 }
 
 impl StatusLine {
-    pub fn new(status: String, location: Option<Location>) -> StatusLine {
+    pub fn new(
+        status: Vec<StatusSegment>,
+        location: Option<Location>,
+    ) -> StatusLine {
         StatusLine { status, location }
     }
 
-    pub fn set_status(&mut self, status: String) {
+    pub fn set_status(&mut self, status: Vec<StatusSegment>) {
         self.status = status;
     }
 
     pub fn set_location(&mut self, pos: usize, total: usize) {
         self.location = Some(Location { pos, total });
     }
+
+    pub fn clear_location(&mut self) {
+        self.location = None;
+    }
 }
 
 pub struct StatusLineView<'a> {
@@ -65,8 +156,9 @@ impl<'a> Widget for StatusLineView<'a> {
             _ => area.width,
         };
 
-        let status = Paragraph::new(self.statusline.status.to_string())
-            .style(Style::default().bg(Color::Indexed(8)));
+        let status =
+            Paragraph::new(fit_segments(&self.statusline.status, status_width))
+                .style(Style::default().bg(Color::Indexed(8)));
         Widget::render(
             status,
             Rect {
@@ -96,3 +188,43 @@ impl<'a> Widget for StatusLineView<'a> {
         }
     }
 }
+
+/// Lay out status segments in the given width, dropping the least important
+/// segments and then truncating the rest until they fit
+fn fit_segments(segments: &[StatusSegment], width: u16) -> Line<'static> {
+    let width = width as usize;
+    let mut shown: Vec<&StatusSegment> = segments.iter().collect();
+    let len = |shown: &[&StatusSegment]| {
+        shown
+            .iter()
+            .map(|s| s.display().chars().count() + 1)
+            .sum::<usize>()
+    };
+
+    while len(&shown) > width && shown.len() > 1 {
+        let least = shown.iter().map(|s| s.kind).max().unwrap();
+        let i = shown.iter().rposition(|s| s.kind == least).unwrap();
+        shown.remove(i);
+    }
+
+    let mut remaining = width;
+    let mut spans: Vec<Span> = vec![];
+    for segment in shown {
+        if remaining == 0 {
+            break;
+        }
+        let mut text = segment.display();
+        if text.chars().count() >= remaining {
+            text = text.chars().take(remaining.saturating_sub(1)).collect();
+            text.push('…');
+        }
+        remaining -= text.chars().count().min(remaining);
+        spans.push(Span::styled(text, segment.style()));
+        if remaining > 0 {
+            spans.push(Span::from(" "));
+            remaining -= 1;
+        }
+    }
+
+    Line::from(spans)
+}
@@ -0,0 +1,382 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+    Frame,
+};
+
+use crate::{
+    events::Key,
+    git::{git_ls_tree, GitDiffOpts, GitRef},
+    list::{ListCursor, ListData, ListInfo, ListScroll},
+    search::Search,
+    ui::{highlight_spans, match_style},
+    views::{
+        controller::{
+            handle_cursor_keys, KeyResult, RenderOpts, ViewContext,
+            ViewController,
+        },
+        file::FileContent,
+        input::{Input, InputAction},
+        statusline::{Status, StatusSegment},
+    },
+};
+
+/// A directory or file shown in a Tree
+#[derive(Debug, Clone)]
+struct Entry {
+    /// the path from the repo root
+    path: String,
+    /// how many directories the entry is in
+    depth: usize,
+    dir: bool,
+}
+
+impl Entry {
+    /// Return the last part of the entry's path
+    fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Every file in a commit, in directories that can be expanded and collapsed
+#[derive(Debug, Clone)]
+pub struct Tree {
+    list: ListData,
+    commit: GitRef,
+    /// the path of every file in the commit, in the order they're listed
+    files: Vec<String>,
+    /// the paths of the expanded directories
+    expanded: HashSet<String>,
+    /// the visible directories and files
+    entries: Vec<Entry>,
+    search: Option<String>,
+    opts: GitDiffOpts,
+}
+
+impl Tree {
+    /// List the files in a commit, with every directory collapsed
+    pub fn new(commit: &GitRef, opts: GitDiffOpts) -> Result<Tree, String> {
+        let mut files =
+            git_ls_tree(commit).map_err(|e| e.first_line().to_string())?;
+        files.sort_by(|a, b| tree_order(a, b));
+        let mut tree = Tree {
+            list: ListData::new(),
+            commit: commit.clone(),
+            files,
+            expanded: HashSet::new(),
+            entries: vec![],
+            search: None,
+            opts,
+        };
+        tree.update_entries();
+        Ok(tree)
+    }
+
+    /// Rebuild the visible entries from the files and the expanded
+    /// directories
+    fn update_entries(&mut self) {
+        let mut entries = vec![];
+        let mut prev_dirs: Vec<&str> = vec![];
+
+        for file in &self.files {
+            let parts: Vec<&str> = file.split('/').collect();
+            let dirs = &parts[..parts.len() - 1];
+            // the directories shared with the previous file have been listed
+            let shared = dirs
+                .iter()
+                .zip(&prev_dirs)
+                .take_while(|(a, b)| a == b)
+                .count();
+            // a directory is visible if every directory it's in is expanded
+            let visible = (0..dirs.len())
+                .find(|d| !self.expanded.contains(&dirs[..=*d].join("/")))
+                .unwrap_or(dirs.len());
+
+            for depth in shared..dirs.len().min(visible + 1) {
+                entries.push(Entry {
+                    path: dirs[..=depth].join("/"),
+                    depth,
+                    dir: true,
+                });
+            }
+            if visible == dirs.len() {
+                entries.push(Entry {
+                    path: file.clone(),
+                    depth: dirs.len(),
+                    dir: false,
+                });
+            }
+            prev_dirs = dirs.to_vec();
+        }
+
+        self.entries = entries;
+        let cursor = self.cursor().min(self.entries.len().saturating_sub(1));
+        self.cursor_to(cursor);
+    }
+
+    fn current_entry(&self) -> Option<&Entry> {
+        self.entries.get(self.cursor())
+    }
+
+    /// Expand or collapse the directory at the cursor
+    fn toggle_dir(&mut self) {
+        let Some(entry) = self.current_entry() else {
+            return;
+        };
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.update_entries();
+    }
+
+    /// Collapse the directory at the cursor, or if it isn't an expanded
+    /// directory, move to the directory it's in
+    fn collapse(&mut self) {
+        let Some(entry) = self.current_entry() else {
+            return;
+        };
+        if entry.dir && self.expanded.contains(&entry.path) {
+            self.toggle_dir();
+        } else if let Some((parent, _)) = entry.path.rsplit_once('/') {
+            let parent = parent.to_string();
+            if let Some(i) = self.entries.iter().position(|e| e.path == parent)
+            {
+                self.cursor_to(i);
+            }
+        }
+    }
+}
+
+/// Order paths the way they're listed in a tree: each directory's
+/// subdirectories come before its files, and each group is sorted by name
+fn tree_order(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('/').peekable();
+    let mut b_parts = b.split('/').peekable();
+    while let (Some(a_part), Some(b_part)) = (a_parts.next(), b_parts.next()) {
+        // a part followed by more parts is a directory
+        let a_dir = a_parts.peek().is_some();
+        let b_dir = b_parts.peek().is_some();
+        let order = b_dir.cmp(&a_dir).then_with(|| a_part.cmp(b_part));
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    Ordering::Equal
+}
+
+impl ListInfo for Tree {
+    fn list_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn list_pos(&self) -> usize {
+        self.cursor()
+    }
+
+    fn set_list_pos(&mut self, pos: usize) {
+        self.cursor_to(pos);
+    }
+}
+
+impl ListScroll for Tree {
+    fn height(&self) -> usize {
+        self.list.height
+    }
+
+    fn scroll_to(&mut self, line: usize) {
+        self.cursor_to(line);
+    }
+}
+
+impl ListCursor for Tree {
+    fn list_state(&self) -> &ListState {
+        &self.list.state
+    }
+
+    fn list_state_mut(&mut self) -> &mut ListState {
+        &mut self.list.state
+    }
+}
+
+impl Status for Tree {
+    fn status(&self) -> Vec<StatusSegment> {
+        let mut status =
+            vec![StatusSegment::target(format!("tree of {}", self.commit))];
+        if let Some(entry) = self.current_entry() {
+            status.push(StatusSegment::mode(entry.path.clone()));
+        }
+        status
+    }
+}
+
+impl Search for Tree {
+    fn set_search(&mut self, search: Option<String>) {
+        self.search = search;
+    }
+
+    fn get_search(&self) -> Option<String> {
+        self.search.clone()
+    }
+
+    fn is_match(&self, idx: usize) -> bool {
+        match &self.search {
+            Some(search) => self.entries[idx].name().contains(search),
+            _ => false,
+        }
+    }
+}
+
+impl ViewController for Tree {
+    fn handle_key(&mut self, keys: &[Key], ctx: &mut ViewContext) -> KeyResult {
+        match keys {
+            [Key::Enter] => match self.current_entry() {
+                Some(entry) if entry.dir => self.toggle_dir(),
+                Some(entry) => {
+                    match FileContent::new(&entry.path, &self.commit, self.opts)
+                    {
+                        Ok(file) => ctx.push(file),
+                        Err(e) => ctx.message(e),
+                    }
+                }
+                None => {}
+            },
+
+            [Key::Right | Key::Char('l')] => {
+                if self
+                    .current_entry()
+                    .is_some_and(|e| e.dir && !self.expanded.contains(&e.path))
+                {
+                    self.toggle_dir();
+                }
+            }
+
+            [Key::Left | Key::Char('h')] => self.collapse(),
+
+            [Key::Char('d')] => match self.current_entry() {
+                Some(entry) if !entry.dir => {
+                    ctx.input = Some(
+                        Input::new(
+                            format!("Diff {} against", entry.name()),
+                            InputAction::DiffFile {
+                                commit: self.commit.clone(),
+                                path: entry.path.clone(),
+                            },
+                        )
+                        .with_text("HEAD"),
+                    )
+                }
+                _ => ctx.message("Only files can be diffed"),
+            },
+
+            _ => return handle_cursor_keys(self, keys),
+        }
+        KeyResult::Handled
+    }
+
+    fn render(&mut self, f: &mut Frame, area: Rect, opts: &RenderOpts) {
+        let block = opts.block(format!("Tree — {}", self.commit));
+        let mut view = TreeView::new(self);
+        if let Some(block) = block {
+            view = view.block(block);
+        }
+        f.render_widget(view, area);
+    }
+
+    fn search(&mut self) -> Option<&mut dyn Search> {
+        Some(self)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        Some((self.list_pos(), self.list_count()))
+    }
+
+    fn key_hints(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("enter", "open"),
+            ("h", "collapse"),
+            ("d", "diff"),
+            ("/", "search"),
+            ("q", "back"),
+        ]
+    }
+}
+
+/// The Widget used to render a Tree
+pub struct TreeView<'a> {
+    tree: &'a mut Tree,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> TreeView<'a> {
+    pub fn new(tree: &'a mut Tree) -> TreeView<'a> {
+        TreeView { tree, block: None }
+    }
+
+    /// Draw the list inside a block
+    pub fn block(mut self, block: Block<'a>) -> TreeView<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for TreeView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(b) => {
+                let inner = b.inner(area);
+                b.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        self.tree.list.height = area.height as usize;
+
+        let cursor = self.tree.cursor();
+        let items: Vec<ListItem> = self
+            .tree
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let indent = "  ".repeat(entry.depth);
+                let mut spans = if entry.dir {
+                    let marker = if self.tree.expanded.contains(&entry.path) {
+                        "▾"
+                    } else {
+                        "▸"
+                    };
+                    vec![
+                        Span::from(format!("{}{} ", indent, marker)),
+                        Span::styled(
+                            format!("{}/", entry.name()),
+                            Style::default().fg(Color::Indexed(4)),
+                        ),
+                    ]
+                } else {
+                    vec![
+                        Span::from(format!("{}  ", indent)),
+                        Span::from(entry.name().to_string()),
+                    ]
+                };
+
+                if let Some(search) = &self.tree.search {
+                    spans =
+                        highlight_spans(spans, search, match_style(i == cursor))
+                }
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(Color::Indexed(0)));
+
+        StatefulWidget::render(list, area, buf, self.tree.list_state_mut());
+    }
+}